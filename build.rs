@@ -0,0 +1,81 @@
+//! Compiles the Java helpers in `java-dependencies` with `javac`, so they can be embedded in the binary.
+//! If no `javac` can be found the helpers are left out, with a warning, rather than failing the build
+
+use std::{env, fs};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const SOURCES: &str = "java-dependencies/src/main/java";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", SOURCES);
+    println!("cargo:rerun-if-env-changed=JAVA_HOME");
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let classes_dir = out_dir.join("java-classes");
+    let _ = fs::remove_dir_all(&classes_dir);
+    fs::create_dir_all(&classes_dir).unwrap();
+
+    let mut sources = Vec::new();
+    find_files(Path::new(SOURCES), "java", &mut sources);
+
+    let classes = if compile(&sources, &classes_dir) {
+        let mut classes = Vec::new();
+        find_files(&classes_dir, "class", &mut classes);
+        classes.sort();
+        classes
+    } else {
+        println!("cargo:warning=Could not compile the Java helpers, the JVM backend, generated annotation builders and interface implementations will not work. Is a JDK installed?");
+        Vec::new()
+    };
+
+    let entries = classes.iter()
+        .map(|path| {
+            let name = path.strip_prefix(&classes_dir).unwrap()
+                .with_extension("")
+                .to_string_lossy()
+                .replace('\\', "/");
+            format!("    ({:?}, include_bytes!({:?})),\n", name, path.display().to_string())
+        })
+        .collect::<String>();
+
+    let code = format!("pub const JAVA_HELPERS: &[(&str, &[u8])] = &[\n{}];\n", entries);
+    fs::write(out_dir.join("java_helpers.rs"), code).unwrap();
+}
+
+/// Compile the sources for Java 8, so the helpers work on any JVM jrsgen supports
+fn compile(sources: &[PathBuf], classes_dir: &Path) -> bool {
+    let javac = env::var_os("JAVA_HOME")
+        .map(|x| Path::new(&x).join("bin").join("javac"))
+        .filter(|x| x.is_file() || x.with_extension("exe").is_file())
+        .unwrap_or_else(|| PathBuf::from("javac"));
+
+    // `--release` requires javac 9 or later, older versions only know `-source` and `-target`
+    let target_flags: [&[&str]; 2] = [&["--release", "8"], &["-source", "8", "-target", "8"]];
+    target_flags.iter().any(|flags| {
+        Command::new(&javac)
+            .args(*flags)
+            .arg("-nowarn")
+            .arg("-d")
+            .arg(classes_dir)
+            .args(sources)
+            .output()
+            .map(|x| x.status.success())
+            .unwrap_or(false)
+    })
+}
+
+fn find_files(directory: &Path, extension: &str, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for path in entries.filter_map(|x| x.ok()).map(|x| x.path()) {
+        if path.is_dir() {
+            find_files(&path, extension, files);
+        } else if path.extension().map(|x| x == extension).unwrap_or(false) {
+            files.push(path);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+package jrsgen;
+
+import java.lang.annotation.Annotation;
+import java.lang.annotation.IncompleteAnnotationException;
+import java.lang.reflect.InvocationHandler;
+import java.lang.reflect.Method;
+import java.lang.reflect.Proxy;
+import java.util.Arrays;
+import java.util.Map;
+
+/**
+ * Creates instances of annotation types from a map of element values,
+ * used by the generated annotation builders.
+ */
+public final class AnnotationProxy implements InvocationHandler {
+    private final Class<? extends Annotation> annotationType;
+    private final Map<String, Object> values;
+
+    private AnnotationProxy(Class<? extends Annotation> annotationType, Map<String, Object> values) {
+        this.annotationType = annotationType;
+        this.values = values;
+    }
+
+    /**
+     * Create an instance of the annotation type. Elements missing from {@code values} use their default value.
+     */
+    public static Annotation create(Class<? extends Annotation> annotationType, Map<String, Object> values) {
+        AnnotationProxy handler = new AnnotationProxy(annotationType, values);
+        return (Annotation) Proxy.newProxyInstance(annotationType.getClassLoader(), new Class<?>[] { annotationType }, handler);
+    }
+
+    @Override
+    public Object invoke(Object proxy, Method method, Object[] args) throws Throwable {
+        switch (method.getName()) {
+            case "annotationType":
+                return annotationType;
+            case "toString":
+                return "@" + annotationType.getName() + values;
+            case "hashCode":
+                return hashCodeImpl();
+            case "equals":
+                if (args != null && args.length == 1) {
+                    return equalsImpl(args[0]);
+                }
+                break;
+            default:
+                break;
+        }
+
+        return getValue(method);
+    }
+
+    private Object getValue(Method element) {
+        Object value = values.get(element.getName());
+        if (value == null) {
+            value = element.getDefaultValue();
+        }
+        if (value == null) {
+            throw new IncompleteAnnotationException(annotationType, element.getName());
+        }
+        return value;
+    }
+
+    /**
+     * Hash code as specified by {@link Annotation#hashCode()}
+     */
+    private int hashCodeImpl() {
+        int hash = 0;
+        for (Method element : annotationType.getDeclaredMethods()) {
+            Object value = getValue(element);
+            int valueHash = value.getClass().isArray() ? Arrays.deepHashCode(new Object[] { value }) - 31 : value.hashCode();
+            hash += (127 * element.getName().hashCode()) ^ valueHash;
+        }
+        return hash;
+    }
+
+    /**
+     * Equality as specified by {@link Annotation#equals(Object)}
+     */
+    private boolean equalsImpl(Object other) {
+        if (!annotationType.isInstance(other)) {
+            return false;
+        }
+
+        try {
+            for (Method element : annotationType.getDeclaredMethods()) {
+                Object otherValue = element.invoke(other);
+                if (!Arrays.deepEquals(new Object[] { getValue(element) }, new Object[] { otherValue })) {
+                    return false;
+                }
+            }
+        } catch (ReflectiveOperationException e) {
+            return false;
+        }
+        return true;
+    }
+}
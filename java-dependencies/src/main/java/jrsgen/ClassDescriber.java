@@ -0,0 +1,462 @@
+package jrsgen;
+
+import java.lang.annotation.Annotation;
+import java.lang.reflect.AnnotatedElement;
+import java.lang.reflect.AnnotatedType;
+import java.lang.reflect.Constructor;
+import java.lang.reflect.Executable;
+import java.lang.reflect.Field;
+import java.lang.reflect.InvocationTargetException;
+import java.lang.reflect.Method;
+import java.lang.reflect.Modifier;
+import java.lang.reflect.Parameter;
+import java.lang.reflect.ParameterizedType;
+import java.lang.reflect.Type;
+import java.lang.reflect.TypeVariable;
+import java.util.ArrayList;
+import java.util.List;
+
+/**
+ * Describes a class as a single JSON document, used by jrsgen to explore a class with one call through JNI
+ * rather than a call for every method, argument and annotation. The keys match the description types
+ * of jrsgen's {@code parser::description} module, which reads it with serde_json. Types are described by their {@link Class#getName()}.
+ * The JSON is written by hand, as the JDK has no JSON API and the helper is defined without its dependencies.
+ */
+public final class ClassDescriber {
+    /** The annotation the Kotlin compiler adds to every class it generates */
+    private static final String KOTLIN_METADATA = "kotlin.Metadata";
+
+    /** Added in Java 9 */
+    private static final Method DEPRECATED_SINCE = findMethod(Deprecated.class, "since");
+    /** Added in Java 9 */
+    private static final Method DEPRECATED_FOR_REMOVAL = findMethod(Deprecated.class, "forRemoval");
+    /** Added in Java 17 */
+    private static final Method GET_PERMITTED_SUBCLASSES = findMethod(Class.class, "getPermittedSubclasses");
+
+    private final StringBuilder json = new StringBuilder();
+    /** Whether the next value is the first of its object or array, i.e. not preceded by a comma */
+    private boolean first = true;
+
+    private ClassDescriber() {
+    }
+
+    /**
+     * Describe a class. Synthetic and bridge methods, Kotlin's {@code $default} overloads and synthetic constructors
+     * are left out, as are members that are not public, or protected if {@code includeProtected} is set.
+     * Members are filtered before they are described, to avoid loading the classes they reference.
+     *
+     * @param includeInheritedMethods whether to describe the public methods inherited from superclasses and interfaces as well
+     */
+    public static String describe(Class<?> c, boolean includeInheritedMethods, boolean includeProtected) {
+        ClassDescriber describer = new ClassDescriber();
+        describer.describeClass(c, includeInheritedMethods, includeProtected);
+        return describer.json.toString();
+    }
+
+    private void describeClass(Class<?> c, boolean includeInheritedMethods, boolean includeProtected) {
+        begin('{');
+
+        key("simple_name");
+        String simpleName;
+        try {
+            simpleName = c.getSimpleName();
+        } catch (InternalError e) {
+            // Java 8 throws for nested classes not named like Java names them, e.g. those of Scala
+            simpleName = null;
+        }
+        string(simpleName);
+
+        key("enclosing_class");
+        Class<?> enclosingClass = c.getEnclosingClass();
+        string(enclosingClass == null ? null : enclosingClass.getName());
+
+        key("is_member_class");
+        value(c.isMemberClass());
+        key("is_interface");
+        value(c.isInterface());
+        key("is_annotation");
+        value(c.isAnnotation());
+        key("is_enum");
+        value(c.isEnum());
+
+        key("deprecation");
+        deprecation(c);
+        key("kotlin_metadata");
+        kotlinMetadata(c);
+
+        key("interfaces");
+        classNames(c.getInterfaces());
+
+        key("type_parameters");
+        typeParameters(c.getTypeParameters());
+
+        key("generic_superclass");
+        Type genericSuperclass = c.getGenericSuperclass();
+        string(genericSuperclass instanceof ParameterizedType ? genericSuperclass.getTypeName() : null);
+
+        key("superclasses");
+        begin('[');
+        for (Class<?> superclass = c.getSuperclass(); superclass != null; superclass = superclass.getSuperclass()) {
+            string(superclass.getName());
+        }
+        end(']');
+
+        key("permitted_subclasses");
+        Class<?>[] permittedSubclasses = GET_PERMITTED_SUBCLASSES == null ? null : (Class<?>[]) invoke(GET_PERMITTED_SUBCLASSES, c);
+        classNames(permittedSubclasses == null ? new Class<?>[0] : permittedSubclasses);
+
+        key("fields");
+        begin('[');
+        for (Field field : c.getDeclaredFields()) {
+            field(field);
+        }
+        end(']');
+
+        // The ordinals of the constants are not necessarily the order of the fields
+        key("enum_constants");
+        begin('[');
+        for (String name : enumConstantNames(c)) {
+            string(name);
+        }
+        end(']');
+
+        key("methods");
+        begin('[');
+        for (Method method : includeInheritedMethods ? c.getMethods() : c.getDeclaredMethods()) {
+            boolean isApiMethod = !method.isSynthetic() && !method.isBridge() && !method.getName().endsWith("$default");
+            if (isApiMethod && isIncluded(method.getModifiers(), includeProtected)) {
+                method(method);
+            }
+        }
+        end(']');
+
+        // The single abstract method of a functional interface may be inherited, regardless of whether inherited methods are included
+        key("interface_methods");
+        begin('[');
+        if (c.isInterface()) {
+            for (Method method : c.getMethods()) {
+                method(method);
+            }
+        }
+        end(']');
+
+        key("constructors");
+        begin('[');
+        for (Constructor<?> constructor : c.getDeclaredConstructors()) {
+            if (!constructor.isSynthetic() && isIncluded(constructor.getModifiers(), includeProtected)) {
+                constructor(constructor);
+            }
+        }
+        end(']');
+
+        key("annotation_elements");
+        begin('[');
+        if (c.isAnnotation()) {
+            for (Method element : c.getDeclaredMethods()) {
+                annotationElement(element);
+            }
+        }
+        end(']');
+
+        end('}');
+    }
+
+    private void field(Field field) {
+        begin('{');
+        key("name");
+        string(field.getName());
+        key("modifiers");
+        value(field.getModifiers());
+        key("is_synthetic");
+        value(field.isSynthetic());
+        key("field_type");
+        string(field.getType().getName());
+        key("generic_type");
+        string(field.getGenericType().getTypeName());
+        key("is_enum_constant");
+        value(field.isEnumConstant());
+        key("deprecation");
+        deprecation(field);
+        end('}');
+    }
+
+    private void method(Method method) {
+        begin('{');
+        key("name");
+        string(method.getName());
+        key("modifiers");
+        value(method.getModifiers());
+        key("is_default");
+        value(method.isDefault());
+        key("deprecation");
+        deprecation(method);
+        executable(method);
+
+        key("return_type");
+        string(method.getReturnType().getName());
+        key("declaring_class");
+        string(method.getDeclaringClass().getName());
+        key("type_parameters");
+        typeParameters(method.getTypeParameters());
+        key("generic_return_type");
+        string(method.getGenericReturnType().getTypeName());
+
+        key("annotations");
+        annotationNames(method.getAnnotations(), method.getAnnotatedReturnType().getAnnotations());
+        end('}');
+    }
+
+    private void constructor(Constructor<?> constructor) {
+        begin('{');
+        key("modifiers");
+        value(constructor.getModifiers());
+        key("deprecation");
+        deprecation(constructor);
+        executable(constructor);
+        end('}');
+    }
+
+    /** The keys shared by methods and constructors */
+    private void executable(Executable executable) {
+        key("arguments");
+        classNames(executable.getParameterTypes());
+
+        key("argument_names");
+        begin('[');
+        for (Parameter parameter : executable.getParameters()) {
+            // Without -parameters, getName() returns synthesized names like arg0
+            string(parameter.isNamePresent() ? parameter.getName() : null);
+        }
+        end(']');
+
+        key("generic_arguments");
+        begin('[');
+        for (Type type : executable.getGenericParameterTypes()) {
+            string(type.getTypeName());
+        }
+        end(']');
+
+        key("exceptions");
+        classNames(executable.getExceptionTypes());
+
+        // Declaration and type-use annotations are reported separately. Implicit arguments,
+        // like the outer instance of inner class constructors, may lack annotation information
+        key("argument_annotations");
+        begin('[');
+        Annotation[][] parameterAnnotations = executable.getParameterAnnotations();
+        AnnotatedType[] annotatedParameterTypes = executable.getAnnotatedParameterTypes();
+        for (int i = 0; i < Math.min(parameterAnnotations.length, annotatedParameterTypes.length); i++) {
+            annotationNames(parameterAnnotations[i], annotatedParameterTypes[i].getAnnotations());
+        }
+        end(']');
+    }
+
+    private void annotationElement(Method element) {
+        begin('{');
+        key("name");
+        string(element.getName());
+        key("element_type");
+        string(element.getReturnType().getName());
+        key("default_value");
+        Object defaultValue = element.getDefaultValue();
+        string(defaultValue == null ? null : String.valueOf(defaultValue));
+        end('}');
+    }
+
+    private void typeParameters(TypeVariable<?>[] typeParameters) {
+        begin('[');
+        for (TypeVariable<?> typeParameter : typeParameters) {
+            begin('{');
+            key("name");
+            string(typeParameter.getName());
+            key("bounds");
+            begin('[');
+            for (Type bound : typeParameter.getBounds()) {
+                // The implicit bound is left out
+                if (bound != Object.class) {
+                    string(bound.getTypeName());
+                }
+            }
+            end(']');
+            end('}');
+        }
+        end(']');
+    }
+
+    private void deprecation(AnnotatedElement element) {
+        Deprecated deprecated = element.getAnnotation(Deprecated.class);
+        if (deprecated == null) {
+            nullValue();
+            return;
+        }
+
+        String since = DEPRECATED_SINCE == null ? "" : (String) invoke(DEPRECATED_SINCE, deprecated);
+        boolean forRemoval = DEPRECATED_FOR_REMOVAL != null && (Boolean) invoke(DEPRECATED_FOR_REMOVAL, deprecated);
+
+        begin('{');
+        key("since");
+        string(since.isEmpty() ? null : since);
+        key("for_removal");
+        value(forRemoval);
+        end('}');
+    }
+
+    /** The annotation is only visible if the Kotlin standard library is on the classpath */
+    private void kotlinMetadata(Class<?> c) {
+        for (Annotation annotation : c.getAnnotations()) {
+            Class<? extends Annotation> annotationType = annotation.annotationType();
+            if (!annotationType.getName().equals(KOTLIN_METADATA)) {
+                continue;
+            }
+
+            begin('{');
+            key("kind");
+            value((Integer) invoke(findMethod(annotationType, "k"), annotation));
+            key("strings");
+            begin('[');
+            for (String string : (String[]) invoke(findMethod(annotationType, "d2"), annotation)) {
+                string(string);
+            }
+            end(']');
+            end('}');
+            return;
+        }
+
+        nullValue();
+    }
+
+    /** The names of the types of both arrays of annotations, as a single array */
+    private void annotationNames(Annotation[] annotations, Annotation[] typeAnnotations) {
+        begin('[');
+        for (Annotation[] array : new Annotation[][] { annotations, typeAnnotations }) {
+            for (Annotation annotation : array) {
+                string(annotation.annotationType().getName());
+            }
+        }
+        end(']');
+    }
+
+    private void classNames(Class<?>[] classes) {
+        begin('[');
+        for (Class<?> c : classes) {
+            string(c.getName());
+        }
+        end(']');
+    }
+
+    /**
+     * The names of the constants of an enum in the order of their ordinals, empty for other classes. This initializes the enum,
+     * if that fails the names are taken from its fields in the order they are declared in
+     */
+    private static List<String> enumConstantNames(Class<?> c) {
+        List<String> names = new ArrayList<>();
+        if (!c.isEnum()) {
+            return names;
+        }
+
+        try {
+            for (Object constant : c.getEnumConstants()) {
+                names.add(((Enum<?>) constant).name());
+            }
+        } catch (LinkageError e) {
+            names.clear();
+            for (Field field : c.getDeclaredFields()) {
+                if (field.isEnumConstant()) {
+                    names.add(field.getName());
+                }
+            }
+        }
+        return names;
+    }
+
+    private static boolean isIncluded(int modifiers, boolean includeProtected) {
+        return Modifier.isPublic(modifiers) || (includeProtected && Modifier.isProtected(modifiers));
+    }
+
+    private static Method findMethod(Class<?> c, String name) {
+        try {
+            return c.getMethod(name);
+        } catch (NoSuchMethodException e) {
+            return null;
+        }
+    }
+
+    /** Invoke a method without arguments. Exceptions thrown by the method are rethrown as-is */
+    private static Object invoke(Method method, Object receiver) {
+        try {
+            return method.invoke(receiver);
+        } catch (InvocationTargetException e) {
+            Throwable cause = e.getCause();
+            if (cause instanceof RuntimeException) {
+                throw (RuntimeException) cause;
+            }
+            if (cause instanceof Error) {
+                throw (Error) cause;
+            }
+            throw new IllegalStateException(cause);
+        } catch (IllegalAccessException e) {
+            throw new IllegalStateException(e);
+        }
+    }
+
+    private void begin(char bracket) {
+        separate();
+        json.append(bracket);
+        first = true;
+    }
+
+    private void end(char bracket) {
+        json.append(bracket);
+        first = false;
+    }
+
+    private void key(String key) {
+        string(key);
+        json.append(':');
+        first = true;
+    }
+
+    private void separate() {
+        if (!first) {
+            json.append(',');
+        }
+        first = false;
+    }
+
+    private void nullValue() {
+        separate();
+        json.append("null");
+    }
+
+    private void value(boolean value) {
+        separate();
+        json.append(value);
+    }
+
+    private void value(int value) {
+        separate();
+        json.append(value);
+    }
+
+    /** Characters outside of printable ASCII are escaped, so the JNI conversion of the document never has to deal with them */
+    private void string(String value) {
+        if (value == null) {
+            nullValue();
+            return;
+        }
+
+        separate();
+        json.append('"');
+        for (int i = 0; i < value.length(); i++) {
+            char c = value.charAt(i);
+            if (c == '"' || c == '\\') {
+                json.append('\\').append(c);
+            } else if (c < 0x20 || c > 0x7e) {
+                json.append(String.format("\\u%04x", (int) c));
+            } else {
+                json.append(c);
+            }
+        }
+        json.append('"');
+    }
+}
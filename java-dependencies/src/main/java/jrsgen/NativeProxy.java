@@ -0,0 +1,199 @@
+package jrsgen;
+
+import java.lang.ref.PhantomReference;
+import java.lang.ref.ReferenceQueue;
+import java.lang.reflect.InvocationHandler;
+import java.lang.reflect.InvocationTargetException;
+import java.lang.reflect.Method;
+import java.lang.reflect.Proxy;
+import java.util.Collections;
+import java.util.Set;
+import java.util.concurrent.ConcurrentHashMap;
+
+/**
+ * Implements interfaces in Rust, used by the generated {@code <Name>Impl} traits.
+ * Calls are dispatched to the Rust implementation identified by a handle, which is released when the proxy is collected.
+ * The native methods are registered by the {@code register_natives} function of the generated crate.
+ */
+public final class NativeProxy implements InvocationHandler {
+    /** {@code java.lang.ref.Cleaner}, added in Java 9. {@code null} on Java 8 */
+    private static final Object CLEANER;
+    /** {@code Cleaner.register(Object, Runnable)} */
+    private static final Method CLEANER_REGISTER;
+
+    static {
+        Object cleaner = null;
+        Method register = null;
+        try {
+            Class<?> cleanerClass = Class.forName("java.lang.ref.Cleaner");
+            cleaner = cleanerClass.getMethod("create").invoke(null);
+            register = cleanerClass.getMethod("register", Object.class, Runnable.class);
+        } catch (ReflectiveOperationException e) {
+            // Java 8, the handles are released by FallbackCleaner instead
+        }
+        CLEANER = cleaner;
+        CLEANER_REGISTER = register;
+    }
+
+    private final long handle;
+
+    private NativeProxy(long handle) {
+        this.handle = handle;
+    }
+
+    /**
+     * Create an instance of the interface, calling into the Rust implementation identified by {@code handle}.
+     * The proxy takes ownership of the handle.
+     */
+    public static Object create(Class<?> interfaceType, long handle) {
+        NativeProxy handler = new NativeProxy(handle);
+        Object proxy = Proxy.newProxyInstance(interfaceType.getClassLoader(), new Class<?>[] { interfaceType }, handler);
+        releaseWhenCollected(proxy, new Release(handle));
+        return proxy;
+    }
+
+    /**
+     * Release the handle once the proxy is collected, through {@code java.lang.ref.Cleaner}.
+     * It is looked up reflectively as the helpers target Java 8, which has no cleaner
+     */
+    private static void releaseWhenCollected(Object proxy, Release release) {
+        if (CLEANER == null) {
+            FallbackCleaner.register(proxy, release);
+            return;
+        }
+
+        try {
+            CLEANER_REGISTER.invoke(CLEANER, proxy, release);
+        } catch (ReflectiveOperationException e) {
+            throw new IllegalStateException("Registering the proxy with the cleaner", e);
+        }
+    }
+
+    @Override
+    public Object invoke(Object proxy, Method method, Object[] args) throws Throwable {
+        if (method.getDeclaringClass() == Object.class) {
+            switch (method.getName()) {
+                case "equals":
+                    return proxy == args[0];
+                case "hashCode":
+                    return System.identityHashCode(proxy);
+                case "toString":
+                    return proxy.getClass().getInterfaces()[0].getName() + "@" + Integer.toHexString(System.identityHashCode(proxy));
+                default:
+                    break;
+            }
+        }
+
+        // Only the abstract methods are implemented in Rust
+        if (method.isDefault()) {
+            return invokeDefault(proxy, method, args);
+        }
+
+        return dispatch(handle, method.getName(), descriptor(method), args == null ? new Object[0] : args);
+    }
+
+    /**
+     * Call the Java implementation of a default method, through {@code InvocationHandler.invokeDefault}.
+     * It was added in Java 16, and is looked up reflectively as the helpers target Java 8
+     */
+    private static Object invokeDefault(Object proxy, Method method, Object[] args) throws Throwable {
+        Method invokeDefault;
+        try {
+            invokeDefault = InvocationHandler.class.getMethod("invokeDefault", Object.class, Method.class, Object[].class);
+        } catch (NoSuchMethodException e) {
+            throw new UnsupportedOperationException("Calling the default method " + method + " of a Rust implementation requires Java 16", e);
+        }
+
+        try {
+            return invokeDefault.invoke(null, proxy, method, args);
+        } catch (InvocationTargetException e) {
+            throw e.getCause();
+        }
+    }
+
+    /**
+     * The JNI signature of the method, e.g. {@code (ILjava/lang/String;)V}
+     */
+    private static String descriptor(Method method) {
+        StringBuilder descriptor = new StringBuilder("(");
+        for (Class<?> type : method.getParameterTypes()) {
+            descriptor.append(descriptor(type));
+        }
+        return descriptor.append(')').append(descriptor(method.getReturnType())).toString();
+    }
+
+    private static String descriptor(Class<?> type) {
+        if (type.isArray()) {
+            return type.getName().replace('.', '/');
+        }
+        if (type == void.class) return "V";
+        if (type == boolean.class) return "Z";
+        if (type == byte.class) return "B";
+        if (type == char.class) return "C";
+        if (type == short.class) return "S";
+        if (type == int.class) return "I";
+        if (type == long.class) return "J";
+        if (type == float.class) return "F";
+        if (type == double.class) return "D";
+        return "L" + type.getName().replace('.', '/') + ";";
+    }
+
+    /** Releases a handle. It must not refer to the proxy, which would then never be collected */
+    private static final class Release implements Runnable {
+        private final long handle;
+
+        Release(long handle) {
+            this.handle = handle;
+        }
+
+        @Override
+        public void run() {
+            release(handle);
+        }
+    }
+
+    /**
+     * Runs the releases of collected proxies on Java 8, on a daemon thread like {@code java.lang.ref.Cleaner} does.
+     * The thread is only started when the class is first used
+     */
+    private static final class FallbackCleaner {
+        private static final ReferenceQueue<Object> COLLECTED = new ReferenceQueue<>();
+        /** The references must stay reachable themselves until they are enqueued */
+        private static final Set<ProxyReference> PENDING = Collections.newSetFromMap(new ConcurrentHashMap<>());
+
+        static {
+            Thread thread = new Thread(FallbackCleaner::run, "jrsgen NativeProxy cleaner");
+            thread.setDaemon(true);
+            thread.start();
+        }
+
+        static void register(Object proxy, Release release) {
+            PENDING.add(new ProxyReference(proxy, release));
+        }
+
+        private static void run() {
+            while (true) {
+                try {
+                    ProxyReference reference = (ProxyReference) COLLECTED.remove();
+                    PENDING.remove(reference);
+                    reference.release.run();
+                } catch (InterruptedException e) {
+                    return;
+                }
+            }
+        }
+
+        private static final class ProxyReference extends PhantomReference<Object> {
+            private final Release release;
+
+            ProxyReference(Object proxy, Release release) {
+                super(proxy, COLLECTED);
+                this.release = release;
+            }
+        }
+    }
+
+    private static native Object dispatch(long handle, String name, String descriptor, Object[] args) throws Throwable;
+
+    private static native void release(long handle);
+}
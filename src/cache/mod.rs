@@ -0,0 +1,104 @@
+//! Caches the class tree between runs, so unchanged classpaths do not have to be inspected again
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use anyhow::anyhow;
+use log::{debug, warn};
+use crate::config::Parser;
+use crate::JResult;
+use crate::parser::class_tree::ClassEntry;
+
+/// Bump when the layout of [ClassEntry] changes, invalidating existing caches
+const FORMAT_VERSION: u32 = 7;
+
+/// The directory jrsgen caches files in, `$XDG_CACHE_HOME/jrsgen` or `~/.cache/jrsgen`
+pub fn cache_directory() -> JResult<PathBuf> {
+    let directory = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|x| Path::new(&x).join(".cache")))
+        .ok_or_else(|| anyhow!("Cannot determine the cache directory, neither XDG_CACHE_HOME nor HOME is set"))?;
+
+    Ok(directory.join("jrsgen"))
+}
+
+/// A cached class tree, keyed by the contents of the classpath, the backend and the parser config
+pub struct TreeCache {
+    path: PathBuf,
+}
+
+impl TreeCache {
+    /// Locate the cache entry for the inputs of a run. This reads every file on the classpath to hash it
+    pub fn new(backend: &str, classpath: &[String], config: &Parser) -> JResult<Self> {
+        let mut hasher = DefaultHasher::new();
+        FORMAT_VERSION.hash(&mut hasher);
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+        backend.hash(&mut hasher);
+        toml::to_string(config)?.hash(&mut hasher);
+        // The JVM backend sees the classes of the JDK as well
+        std::env::var_os("JAVA_HOME").hash(&mut hasher);
+
+        for path in classpath {
+            path.hash(&mut hasher);
+            hash_path(Path::new(path), &mut hasher)?;
+        }
+
+        let path = cache_directory()?
+            .join("trees")
+            .join(format!("{:016x}.bin", hasher.finish()));
+
+        Ok(Self { path })
+    }
+
+    /// Read the cached class tree. Returns `None` if there is none, or if it cannot be read
+    pub fn load(&self) -> Option<Vec<ClassEntry>> {
+        let bytes = fs::read(&self.path).ok()?;
+        match bincode::deserialize(&bytes) {
+            Ok(tree) => {
+                debug!("Using cached class tree {}", self.path.display());
+                Some(tree)
+            },
+            Err(e) => {
+                warn!("Ignoring unreadable class tree cache {}: {}", self.path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Store the class tree in the cache
+    pub fn store(&self, tree: &[ClassEntry]) -> JResult<()> {
+        let bytes = bincode::serialize(tree)?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Write to a temporary file first, so a concurrent run never reads a partial cache
+        let partial = self.path.with_extension("part");
+        fs::write(&partial, bytes)?;
+        fs::rename(&partial, &self.path)?;
+
+        debug!("Stored class tree in {}", self.path.display());
+        Ok(())
+    }
+}
+
+/// Hash the contents of a file, or of all files in a directory recursively
+fn hash_path(path: &Path, hasher: &mut DefaultHasher) -> JResult<()> {
+    if !path.is_dir() {
+        fs::read(path)?.hash(hasher);
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)?
+        .map(|x| Ok(x?.path()))
+        .collect::<JResult<Vec<_>>>()?;
+    entries.sort();
+
+    for entry in entries {
+        entry.file_name().hash(hasher);
+        hash_path(&entry, hasher)?;
+    }
+
+    Ok(())
+}
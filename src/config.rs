@@ -2,25 +2,390 @@ use std::collections::HashMap;
 use std::fs;
 use std::io::{Read, Write};
 use std::path::Path;
+use anyhow::anyhow;
 use log::{debug, trace};
 use serde::{Serialize, Deserialize};
+use crate::class_tree::ClassType;
 use crate::JResult;
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct Config {
-    pub generator: Generator
+    pub generator: Generator,
+    #[serde(default)]
+    pub parser: Parser,
+    #[serde(default)]
+    pub maven: Maven,
+    #[serde(default)]
+    pub jvm: Jvm,
+    /// The types of the classes bindings are generated for by their formatted names, set by the generator.
+    /// Other classes without a mapping are passed as the untyped `crate::Object`. `None` if bindings exist for every class
+    #[serde(skip)]
+    pub classes: Option<HashMap<String, ClassType>>,
 }
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct Jvm {
+    /// The Java home of the JDK to use, e.g. `/usr/lib/jvm/java-17-openjdk-amd64`.
+    /// Defaults to `$JAVA_HOME`, or the Java home of the `java` on the `PATH`
+    #[serde(default)]
+    pub java_home: Option<String>,
+    /// Options passed to the JVM used by the `jvm` backend, e.g. `-Xmx4g`, `-Xcheck:jni` or `-Dfoo=bar`
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Parser {
+    /// The package prefixes to generate bindings for, e.g. `com.itextpdf.`.
+    /// Classes matching multiple prefixes are included once
+    #[serde(default = "default_roots")]
+    pub roots: Vec<String>,
+    /// Regular expressions matched against fully qualified class names, e.g. `^com\.foo\.api\.`.
+    /// If not empty, only classes matching at least one of them are included
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Regular expressions matched against fully qualified class names, e.g. `\.internal\.`.
+    /// Classes matching any of them are excluded, even if they match `include`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Include protected classes and methods, in addition to public ones.
+    /// Private and package-private members are never included
+    #[serde(default)]
+    pub include_protected: bool,
+    /// Include public methods inherited from superclasses and interfaces,
+    /// rather than only the methods a class declares itself
+    #[serde(default)]
+    pub include_inherited_methods: bool,
+    /// Also include classes outside of the roots that are referenced by the signatures of included classes,
+    /// e.g. a `org.slf4j.Logger` return type, as long as they are in the jars on the classpath and not excluded.
+    /// The classes they reference are included as well. Classes of the JDK are never included, as nearly all of it
+    /// is reachable from `java.lang.Object`. They are passed as the untyped `crate::Object`, unless a mapping is configured
+    #[serde(default)]
+    pub include_referenced: bool,
+    /// Fully qualified names of the classes to generate bindings for, e.g. `com.itextpdf.kernel.pdf.PdfDocument`.
+    /// If not empty, only these classes and the classes in the roots reachable from their signatures are included,
+    /// instead of every class in the roots. The entry points themselves are included even if they are excluded
+    #[serde(default)]
+    pub entry_points: Vec<String>,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self {
+            roots: default_roots(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            include_protected: false,
+            include_inherited_methods: false,
+            include_referenced: false,
+            entry_points: Vec::new(),
+        }
+    }
+}
+
+fn default_roots() -> Vec<String> {
+    vec!["com.itextpdf.".to_string()]
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Maven {
+    /// The repositories artifacts are downloaded from, in order of preference
+    #[serde(default = "default_repositories")]
+    pub repositories: Vec<String>,
+    /// The directory downloaded artifacts are stored in.
+    /// Defaults to `$XDG_CACHE_HOME/jrsgen/maven`
+    #[serde(default)]
+    pub cache: Option<String>,
+    /// Also resolve the compile and runtime dependencies of the artifacts
+    #[serde(default)]
+    pub transitive: bool,
+}
+
+impl Default for Maven {
+    fn default() -> Self {
+        Self {
+            repositories: default_repositories(),
+            cache: None,
+            transitive: false,
+        }
+    }
+}
+
+fn default_repositories() -> Vec<String> {
+    vec!["https://repo1.maven.org/maven2".to_string()]
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Generator {
+    /// Emit the type parameters of generic Java classes as
+    /// `PhantomData`-backed type parameters on the generated structs.
+    /// When disabled, the type parameters are only documented.
+    #[serde(default)]
+    pub phantom_type_parameters: bool,
+    /// Escape names colliding with a Rust keyword as raw identifiers, e.g. `r#type`, rather than by appending a suffix,
+    /// e.g. `type_k`. `self`, `Self`, `super` and `crate` cannot be raw identifiers, and are always suffixed
+    #[serde(default)]
+    pub raw_identifiers: bool,
+    /// Appended to names colliding with a Rust keyword, e.g. `impl` becomes `impl_k`. Must not be empty
+    #[serde(default = "default_keyword_suffix")]
+    pub keyword_suffix: String,
+    /// Appended to the module of the classes nested in a class, which is named after that class,
+    /// e.g. `com.foo.Bar$Baz` becomes `com::foo::bar_p::Baz`
+    #[serde(default = "default_subclass_parent_suffix")]
+    pub subclass_parent_suffix: String,
+    /// How overloaded methods, which would otherwise get the same Rust name, are named
+    #[serde(default)]
+    pub overload_naming: OverloadNaming,
+    /// How getters and setters, e.g. `getWidth()` and `setWidth(float)`, are named
+    #[serde(default)]
+    pub accessor_naming: AccessorNaming,
+    /// The nullability assumed for objects returned by methods without a nullability annotation
+    #[serde(default)]
+    pub unannotated_returns: AssumedNullability,
+    /// Methods that never return `null`, though not annotated as such, as `com.foo.Bar#getName`,
+    /// or `com.foo.Bar` for all methods declared in the class
+    #[serde(default)]
+    pub non_null_returns: Vec<String>,
+    /// How arguments and return values of type `java.util.List` and `java.util.Map` are generated
+    #[serde(default)]
+    pub collections: CollectionStyle,
+    /// The Rust crate `java.time` types like `Instant` and `LocalDate` are converted to.
+    /// The conversions are behind a cargo feature of the generated crate named after that crate,
+    /// without it the Java objects are passed
+    #[serde(default)]
+    pub java_time: JavaTime,
+    /// The Rust type `java.math.BigDecimal` is converted to, through its plain string representation
+    #[serde(default)]
+    pub big_decimal: BigDecimalType,
+    /// The Rust type `java.math.BigInteger` is converted to, through its string representation
+    #[serde(default)]
+    pub big_integer: BigIntegerType,
+    /// How the methods of superclasses in the tree are made available on their subclasses
+    #[serde(default)]
+    pub inheritance: Inheritance,
+    /// Implement `PartialEq`, `Eq` and `Hash` for the bound classes through Java's `equals` and `hashCode`,
+    /// and `PartialOrd` and `Ord` for classes implementing `java.lang.Comparable` through `compareTo`.
+    /// These panic if the Java methods throw, as the traits cannot return errors
+    #[serde(default)]
+    pub java_equality: bool,
+    /// The version of the `jni` crate the generated code is written against
+    #[serde(default)]
+    pub jni_version: JniVersion,
+    /// Do not depend on `ejni`. The runtime defines the `Object` and `Class` wrappers itself instead of re-exporting
+    /// those of `ejni`, which are then not interchangeable with `ejni` code. The jni 0.21 code never uses `ejni`
+    #[serde(default)]
+    pub pure_jni: bool,
+    /// Constructors, static methods and `Global::attach` do not take a JNIEnv, but use that of the current thread,
+    /// attaching it to the JavaVM passed to the generated `set_java_vm` if needed. The objects they return are not tied
+    /// to an attachment of the thread, so `set_java_vm` is unsafe: threads must not be detached while they use them.
+    /// Not supported with jni 0.21
+    #[serde(default)]
+    pub implicit_env: bool,
+    /// Generate a `<Class>Builder` for classes with setters returning nothing or `this`, chaining them without
+    /// the `set` prefix, e.g. `LabelBuilder::new(env).text("a").size(2).build()`. The first error is returned by `build`.
+    /// Not generated with jni 0.21
+    #[serde(default)]
+    pub builders: bool,
+    /// The directory the bindings crate is generated in. Defaults to `output` in the directory jrsgen was built in
+    #[serde(default)]
+    pub output_directory: Option<String>,
+    /// The name of the generated crate
+    #[serde(default = "default_crate_name")]
+    pub crate_name: String,
+    /// Rust code injected at the top of `lib.rs`, e.g. `#![allow(clippy::too_many_arguments)]` to apply it to the whole crate
+    #[serde(default)]
+    pub lib_prelude: Option<String>,
+    /// Rust code injected at the top of every module of the bindings, e.g. `use` statements for the types of mappings.
+    /// Classes are placed in the module of their package, so this is in scope for all of them
+    #[serde(default)]
+    pub module_prelude: Option<String>,
+    /// A file with the license of the bindings, e.g. `LICENSE-HEADER.txt`. Its lines are prepended to every generated file as comments
+    #[serde(default)]
+    pub license_file: Option<String>,
+    // TOML requires tables to come after values, so this must be the last field
     /// Mappings from a Java type to a Rust type
     /// E.g. java.lang.String -> ejni::String.
     ///
-    /// The Rust type must impl Into<jni::JValue>
+    /// The Rust type must impl Into<jni::objects::JValue>, and `FromRaw` of the generated crate to be returned.
+    /// That impl can be added through the `lib_prelude`.
+    /// With jni 0.21 the Rust type must instead impl `JavaObject` and `Into<jni::objects::JObject>`, and `FromRaw` to be returned.
+    /// Without a mapping, `java.lang.String` arguments are passed as `impl AsRef<str>` and returned as `String`
+    /// Other classes without a mapping that bindings are not generated for are passed as the untyped `crate::Object`
     pub mappings: HashMap<String, String>,
 }
 
+impl Default for Generator {
+    fn default() -> Self {
+        Self {
+            phantom_type_parameters: false,
+            raw_identifiers: false,
+            keyword_suffix: default_keyword_suffix(),
+            subclass_parent_suffix: default_subclass_parent_suffix(),
+            overload_naming: OverloadNaming::default(),
+            accessor_naming: AccessorNaming::default(),
+            unannotated_returns: AssumedNullability::default(),
+            non_null_returns: Vec::new(),
+            collections: CollectionStyle::default(),
+            java_time: JavaTime::default(),
+            big_decimal: BigDecimalType::default(),
+            big_integer: BigIntegerType::default(),
+            inheritance: Inheritance::default(),
+            java_equality: false,
+            jni_version: JniVersion::default(),
+            pure_jni: false,
+            implicit_env: false,
+            builders: false,
+            output_directory: None,
+            crate_name: default_crate_name(),
+            lib_prelude: None,
+            module_prelude: None,
+            license_file: None,
+            mappings: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OverloadNaming {
+    /// Append the argument types, e.g. `add_float_float` and `add_rectangle`
+    #[default]
+    ArgumentTypes,
+    /// Append the number of the overload, e.g. `add_1` and `add_2`
+    Numbered,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessorNaming {
+    /// Keep the Java names, e.g. `get_width` and `set_width`
+    #[default]
+    Java,
+    /// Strip the `get` and `is` prefixes of getters, e.g. `width` and `set_width`
+    Properties,
+    /// Strip the prefixes of setters as well, e.g. `width` and `width(f)`. As Rust has no overloading,
+    /// this only applies to setters without a getter of the same name
+    Builder,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AssumedNullability {
+    /// Return an `Option`, which is `None` if Java returns `null`
+    #[default]
+    Nullable,
+    /// Return the object directly, trusting Java to never return `null`
+    NonNull,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CollectionStyle {
+    /// Convert lists from and to a `Vec`, and maps from and to a `HashMap`, if their type arguments are known.
+    /// Arguments accept anything iterable, and are copied into a new `java.util.ArrayList` or `java.util.HashMap`
+    #[default]
+    Converted,
+    /// Pass the Java object, wrapped like any other class
+    Raw,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum JavaTime {
+    /// E.g. `chrono::DateTime<Utc>` for `Instant` and `chrono::NaiveDate` for `LocalDate`
+    #[default]
+    Chrono,
+    /// E.g. `time::OffsetDateTime` for `Instant` and `time::Date` for `LocalDate`
+    Time,
+    /// Always pass the Java objects, wrapped like any other class
+    Raw,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BigDecimalType {
+    /// Passed as `impl AsRef<str>` and returned as `String`, e.g. `"1234.50"`
+    #[default]
+    String,
+    /// `rust_decimal::Decimal`. Values with more than 28 significant digits fail to convert
+    RustDecimal,
+    /// `bigdecimal::BigDecimal`
+    BigDecimal,
+    /// Pass the Java objects, wrapped like any other class
+    Raw,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BigIntegerType {
+    /// Passed as `impl AsRef<str>` and returned as `String`, e.g. `"-1234"`
+    #[default]
+    String,
+    /// `num_bigint::BigInt`
+    NumBigint,
+    /// Pass the Java objects, wrapped like any other class
+    Raw,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Inheritance {
+    /// Only through the `as_<superclass>` upcasts and `From` impls, e.g. `bar.as_foo().baz()`
+    #[default]
+    Upcast,
+    /// Additionally `Deref` to the nearest superclass in the tree, so its methods are reachable through auto-deref
+    Deref,
+    /// Generate the methods of the superclasses in the tree on the subclass as well, unless it overrides them
+    Duplicate,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JniVersion {
+    /// `jni` 0.19 with `ejni`. The wrappers hold the `&'a JNIEnv<'a>` they were created with
+    #[default]
+    #[serde(rename = "0.19")]
+    V0_19,
+    /// `jni` 0.21. The wrappers only hold the `JObject<'a>`, and methods take a `&mut JNIEnv<'a>`.
+    /// Interfaces cannot be implemented in Rust, and there are no `_async` variants, `iter` methods,
+    /// annotation builders, `java_equality` or `builders`
+    #[serde(rename = "0.21")]
+    V0_21,
+}
+
+fn default_crate_name() -> String {
+    "bindings".to_string()
+}
+
+fn default_keyword_suffix() -> String {
+    "_k".to_string()
+}
+
+fn default_subclass_parent_suffix() -> String {
+    "_p".to_string()
+}
+
 impl Config {
+    /// Whether bindings are generated for a class, given its formatted name, e.g. `com.foo.bar_p.Baz`
+    pub fn has_bindings(&self, formatted_name: &str) -> bool {
+        self.classes.as_ref().is_none_or(|classes| classes.contains_key(formatted_name))
+    }
+
+    /// Whether bindings are generated for an interface, given its formatted name. Its objects are passed as `<Name>Wrapper`
+    pub fn is_interface(&self, formatted_name: &str) -> bool {
+        self.class_type(formatted_name) == Some(ClassType::Interface)
+    }
+
+    /// Whether bindings are generated for an enum, given its formatted name. Its constants are passed as the Rust enum
+    pub fn is_enum(&self, formatted_name: &str) -> bool {
+        self.class_type(formatted_name) == Some(ClassType::Enum)
+    }
+
+    fn class_type(&self, formatted_name: &str) -> Option<ClassType> {
+        self.classes.as_ref()
+            .and_then(|classes| classes.get(formatted_name))
+            .copied()
+    }
+
     /// Create a new Config instance. Read the configuration from `./config.toml`,
     /// creates it if it does not already exist.
     ///
@@ -53,6 +418,10 @@ impl Config {
 
         trace!("Deserrializing config");
         let this: Self = toml::from_slice(&buf)?;
+        if this.generator.keyword_suffix.is_empty() {
+            return Err(anyhow!("generator.keyword_suffix must not be empty, keywords would not be renamed"));
+        }
+
         Ok(this)
     }
 }
\ No newline at end of file
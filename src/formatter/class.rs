@@ -1,143 +1,448 @@
-use clap::arg;
 use convert_case::{Case, Casing};
-use crate::class_tree::{ArgumentType, ClassEntry, ClassType, MethodEntry};
-use crate::config::Config;
-use crate::formatter::{escape_keywords, rename_class_fq};
+use crate::class_tree::{SCALA_MODULE_FIELD, AnnotationElement, ArgumentType, ClassEntry, ClassType, ConstantEntry, ConstantValue, ConstructorEntry, Deprecation, FieldEntry, MethodEntry, Nullability, TypeParameter};
+use crate::config::{AssumedNullability, Config, Generator};
+use crate::formatter::{escape_keywords, format_argument_names, format_javadoc, rename_accessors, rename_class_fq, rename_constant, rename_enum_constant, rename_overloads, rename_properties};
+
+/// The module of the generated crate the bindings are placed in
+const BINDINGS_MODULE: &str = "crate::bindings::";
+/// Appended to the name of an interface's trait to name the struct wrapping its objects, e.g. `ShapeWrapper` for `Shape`
+pub const INTERFACE_WRAPPER_SUFFIX: &str = "Wrapper";
 
 #[derive(Debug)]
 pub struct FormattedClassEntry {
     pub name: String,
+    /// The fully qualified name of the class in Java, e.g. `com.foo.Bar$Baz`
+    pub java_name: String,
+    /// The name as declared in the source. Empty for anonymous classes
+    pub simple_name: String,
+    /// The enclosing class of a nested class
+    #[allow(dead_code)]
+    pub enclosing_class: Option<String>,
+    /// The nested classes in the tree declared in this class
+    pub nested_classes: Vec<String>,
     pub methods: Vec<FormattedMethodEntry>,
     pub class_type: ClassType,
+    pub is_abstract: bool,
+    pub deprecation: Option<Deprecation>,
     pub interfaces: Vec<String>,
+    pub enum_constants: Vec<FormattedEnumConstant>,
+    /// Type parameters, these are erased in the generated code
+    pub type_parameters: Vec<TypeParameter>,
+    /// The parameterized superclass in Java notation, if any
+    pub generic_superclass: Option<String>,
+    /// The superclass chain, starting at the direct superclass
+    pub superclasses: Vec<String>,
+    /// The superclasses in the tree, which the class can be upcast to. Set by `format_classes`, as it depends on the tree
+    pub upcasts: Vec<FormattedUpcast>,
+    /// The subclasses permitted by a sealed class or interface
+    pub permitted_subclasses: Vec<String>,
+    pub annotation_elements: Vec<FormattedAnnotationElement>,
+    /// Whether the class requires an instance of the enclosing class to be instantiated
+    pub is_inner_class: bool,
+    pub constructors: Vec<FormattedConstructorEntry>,
+    /// The static field holding the only instance of the class, e.g. `INSTANCE` of a Kotlin `object`
+    pub instance_field: Option<SingletonField>,
+    /// The name of the static field holding the Kotlin companion object, e.g. `Companion`.
+    /// `format_classes` adds the methods of the companion to the class
+    pub companion_field: Option<String>,
+    pub constants: Vec<FormattedConstant>,
+    pub fields: Vec<FormattedFieldEntry>,
+    /// The lines of the Rust doc comment converted from the Javadoc
+    pub javadoc: Vec<String>,
 }
 
-impl From<ClassEntry> for FormattedClassEntry {
-    fn from(original: ClassEntry) -> Self {
-        let name = rename_class_fq(&original.name);
-        let methods = original.methods.into_iter()
-            .map(FormattedMethodEntry::from)
+impl FormattedClassEntry {
+    pub fn new(original: ClassEntry, config: &Generator) -> Self {
+        let name = rename_class_fq(&original.name, config);
+        let mut methods = original.methods.into_iter()
+            .map(|x| FormattedMethodEntry::new(x, config))
+            .collect::<Vec<_>>();
+
+        let names = rename_accessors(&methods, config);
+        for (method, name) in methods.iter_mut().zip(names) {
+            method.rust_name = name;
+        }
+
+        let names = rename_properties(&methods, &original.properties, config);
+        for (method, name) in methods.iter_mut().zip(names) {
+            method.rust_name = name;
+        }
+
+        let overloads = methods.iter()
+            .map(|x| (x.rust_name.as_str(), x.arguments.as_slice()))
             .collect::<Vec<_>>();
+        let names = rename_overloads(&overloads, config);
+        for (method, name) in methods.iter_mut().zip(names) {
+            method.rust_name = name;
+        }
 
         let interfaces = original.interfaces.into_iter()
-            .map(|x| rename_class_fq(&x))
+            .map(|x| rename_class_fq(&x, config))
+            .collect::<Vec<_>>();
+
+        let superclasses = original.superclasses.into_iter()
+            .map(|x| rename_class_fq(&x, config))
+            .collect::<Vec<_>>();
+
+        let permitted_subclasses = original.permitted_subclasses.into_iter()
+            .map(|x| rename_class_fq(&x, config))
+            .collect::<Vec<_>>();
+
+        let annotation_elements = original.annotation_elements.into_iter()
+            .map(|x| FormattedAnnotationElement::new(x, config))
+            .collect::<Vec<_>>();
+
+        let mut constructors = original.constructors.into_iter()
+            .map(|x| FormattedConstructorEntry::new(x, config))
+            .collect::<Vec<_>>();
+
+        // Constructors of inner classes take the enclosing instance, so they are named differently.
+        // The enclosing instance is left out when telling overloads apart
+        let (constructor_name, implicit_arguments) = if original.is_inner_class { ("new_inner", 1) } else { ("new", 0) };
+        let overloads = constructors.iter()
+            .map(|x| (constructor_name, x.arguments.get(implicit_arguments..).unwrap_or_default()))
+            .collect::<Vec<_>>();
+        let names = rename_overloads(&overloads, config);
+        for (constructor, name) in constructors.iter_mut().zip(names) {
+            constructor.rust_name = name;
+        }
+
+        let enum_constants = original.enum_constants.into_iter()
+            .enumerate()
+            .map(|(ordinal, java_name)| FormattedEnumConstant {
+                rust_name: rename_enum_constant(&java_name, config),
+                java_name,
+                ordinal: ordinal as i32,
+            })
+            .collect::<Vec<_>>();
+
+        let constants = original.constants.into_iter()
+            .map(FormattedConstant::from)
             .collect::<Vec<_>>();
 
+        let fields = original.fields.into_iter()
+            .map(|x| FormattedFieldEntry::new(x, config))
+            .collect::<Vec<_>>();
+
+        let nested_classes = original.nested_classes.iter()
+            .map(|x| rename_class_fq(x, config))
+            .collect::<Vec<_>>();
+
+        let instance_field = original.instance_field.map(|field| SingletonField {
+            class: original.name.clone(),
+            name: field,
+            jni_signature: format!("L{};", original.name.replace('.', "/")),
+        });
+
+        // The methods of a Scala `object` are instance methods of its class, called on the instance in `MODULE$`
+        if let Some(module_field) = instance_field.as_ref().filter(|x| x.name == SCALA_MODULE_FIELD) {
+            for method in methods.iter_mut().filter(|x| !x.is_static) {
+                method.singleton = Some(module_field.clone());
+            }
+        }
+
         Self {
             name,
+            java_name: original.name,
+            simple_name: original.simple_name,
+            enclosing_class: original.enclosing_class.as_deref().map(|x| rename_class_fq(x, config)),
+            nested_classes,
             methods,
             class_type: original.class_type,
-            interfaces
+            is_abstract: original.is_abstract,
+            deprecation: original.deprecation,
+            interfaces,
+            enum_constants,
+            type_parameters: original.type_parameters,
+            generic_superclass: original.generic_superclass,
+            superclasses,
+            upcasts: Vec::new(),
+            permitted_subclasses,
+            annotation_elements,
+            is_inner_class: original.is_inner_class,
+            constructors,
+            instance_field,
+            companion_field: original.companion_field,
+            constants,
+            fields,
+            javadoc: original.javadoc.as_deref().map(format_javadoc).unwrap_or_default(),
+        }
+    }
+
+    /// The formatted names of the classes the generated code for this class may refer to,
+    /// e.g. through method arguments or implemented interfaces. Includes classes outside of the tree
+    pub fn referenced_classes(&self, config: &Generator) -> Vec<String> {
+        let argument_types = self.methods.iter()
+            .flat_map(|x| x.arguments.iter().chain(x.return_type.iter()))
+            .chain(self.constructors.iter().flat_map(|x| x.arguments.iter()))
+            .chain(self.fields.iter().map(|x| &x.field_type))
+            .chain(self.annotation_elements.iter().map(|x| &x.element_type));
+
+        let mut classes = argument_types
+            .filter_map(ArgumentType::class_name)
+            .map(|x| rename_class_fq(&x, config))
+            .chain(self.interfaces.iter().cloned())
+            .chain(self.superclasses.iter().cloned())
+            .chain(self.permitted_subclasses.iter().cloned())
+            .collect::<Vec<_>>();
+        classes.sort();
+        classes.dedup();
+        classes
+    }
+}
+
+#[derive(Debug)]
+pub struct FormattedConstant {
+    /// The name of the Rust constant
+    pub rust_name: String,
+    /// The name of the field in Java
+    pub java_name: String,
+    pub value: ConstantValue,
+    pub deprecation: Option<Deprecation>,
+}
+
+impl From<ConstantEntry> for FormattedConstant {
+    fn from(original: ConstantEntry) -> Self {
+        Self {
+            rust_name: rename_constant(&original.name),
+            java_name: original.name,
+            value: original.value,
+            deprecation: original.deprecation,
+        }
+    }
+}
+
+/// A static field holding an object the methods of another class are called on,
+/// like the instance of a Kotlin or Scala `object`, or a Kotlin companion object
+#[derive(Debug, Clone)]
+pub struct SingletonField {
+    /// The Java name of the class declaring the field
+    pub class: String,
+    pub name: String,
+    pub jni_signature: String,
+}
+
+#[derive(Debug)]
+pub struct FormattedConstructorEntry {
+    /// `new`, or `new_inner` for inner classes, disambiguated like overloaded methods
+    pub rust_name: String,
+    pub deprecation: Option<Deprecation>,
+    /// The arguments as passed through JNI, including the enclosing instance for inner classes
+    pub arguments: Vec<ArgumentType>,
+    /// The Rust names of the arguments, in the same order as `arguments`
+    pub argument_names: Vec<String>,
+    pub jni_signature: String,
+    pub generic_arguments: Vec<String>,
+    pub exceptions: Vec<String>,
+    pub argument_nullability: Vec<Nullability>,
+}
+
+impl FormattedConstructorEntry {
+    pub fn new(original: ConstructorEntry, config: &Generator) -> Self {
+        let jni_signature = format!("({})V", ArgumentType::to_jni_signature(&original.arguments));
+        let argument_names = format_argument_names(&original.argument_names, config);
+
+        Self {
+            rust_name: "new".to_string(),
+            deprecation: original.deprecation,
+            arguments: original.arguments,
+            argument_names,
+            jni_signature,
+            generic_arguments: original.generic_arguments,
+            exceptions: original.exceptions,
+            argument_nullability: original.argument_nullability,
         }
     }
 }
 
 #[derive(Debug)]
+pub struct FormattedFieldEntry {
+    /// The name of the field in snake case
+    pub rust_name: String,
+    pub java_name: String,
+    #[allow(dead_code)]
+    pub jni_signature: String,
+    pub field_type: ArgumentType,
+    /// The type in Java notation, including type arguments
+    #[allow(dead_code)]
+    pub generic_type: String,
+    #[allow(dead_code)]
+    pub is_static: bool,
+    #[allow(dead_code)]
+    pub is_final: bool,
+    #[allow(dead_code)]
+    pub deprecation: Option<Deprecation>,
+}
+
+impl FormattedFieldEntry {
+    pub fn new(original: FieldEntry, config: &Generator) -> Self {
+        let name_cased = original.name.to_case(Case::Snake);
+        let rust_name = escape_keywords(&name_cased, config).to_string();
+        let jni_signature = ArgumentType::to_jni_signature(std::slice::from_ref(&original.field_type));
+
+        Self {
+            rust_name,
+            java_name: original.name,
+            jni_signature,
+            field_type: original.field_type,
+            generic_type: original.generic_type,
+            is_static: original.is_static,
+            is_final: original.is_final,
+            deprecation: original.deprecation,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FormattedAnnotationElement {
+    /// The name of the builder method setting the element
+    pub rust_name: String,
+    /// The name of the element in Java
+    pub java_name: String,
+    pub element_type: ArgumentType,
+    pub default_value: Option<String>,
+}
+
+impl FormattedAnnotationElement {
+    pub fn new(original: AnnotationElement, config: &Generator) -> Self {
+        let name_cased = original.name.to_case(Case::Snake);
+        let rust_name = escape_keywords(&name_cased, config).to_string();
+
+        Self {
+            rust_name,
+            java_name: original.name,
+            element_type: original.element_type,
+            default_value: original.default_value,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FormattedUpcast {
+    /// The name of the Rust method upcasting to the superclass, e.g. `as_bar` for `com.foo.Bar`
+    pub rust_name: String,
+    /// The formatted name of the superclass
+    pub class: String,
+    /// The name of the superclass in Java
+    pub java_name: String,
+}
+
+#[derive(Debug)]
+pub struct FormattedEnumConstant {
+    /// The name of the Rust enum variant
+    pub rust_name: String,
+    /// The name of the constant in Java
+    pub java_name: String,
+    /// The value Java's `Enum.ordinal()` returns for this constant
+    pub ordinal: i32,
+}
+
+#[derive(Debug, Clone)]
 pub struct FormattedMethodEntry {
     pub rust_name: String,
     pub java_name: String,
     pub is_static: bool,
+    pub is_abstract: bool,
+    pub is_default: bool,
+    pub deprecation: Option<Deprecation>,
     pub arguments: Vec<ArgumentType>,
+    /// The Rust names of the arguments, in the same order as `arguments`
+    pub argument_names: Vec<String>,
     pub jni_signature: String,
     pub return_type: Option<ArgumentType>,
+    #[allow(dead_code)]
     pub declaring_class_rust: String,
     pub declaring_class_java: String,
+    /// Type parameters declared by the method, these are erased in the generated code
+    pub type_parameters: Vec<TypeParameter>,
+    /// The arguments in Java notation, including type arguments
+    pub generic_arguments: Vec<String>,
+    /// The return type in Java notation, including type arguments
+    pub generic_return_type: Option<String>,
+    /// The checked exceptions declared by the method, fully qualified Java names
+    pub exceptions: Vec<String>,
+    pub argument_nullability: Vec<Nullability>,
+    pub return_nullability: Nullability,
+    /// The lines of the Rust doc comment converted from the Javadoc
+    pub javadoc: Vec<String>,
+    /// For the instance methods of a singleton, the static field holding the object they are called on.
+    /// These are generated as associated functions, as there is only one object to call them on
+    pub singleton: Option<SingletonField>,
 }
 
-impl From<MethodEntry> for FormattedMethodEntry {
-    fn from(original: MethodEntry) -> Self {
+impl FormattedMethodEntry {
+    pub fn new(original: MethodEntry, config: &Generator) -> Self {
         // Casing and keywords are the only thing in need of adjustment
         let name_cased = original.name.to_case(Case::Snake);
-        let rust_name = escape_keywords(&name_cased).to_string();
+        let rust_name = escape_keywords(&name_cased, config).to_string();
 
-        let declaring_class_rust = rename_class_fq(&original.declaring_class);
+        let declaring_class_rust = rename_class_fq(&original.declaring_class, config);
 
         let jni_args = ArgumentType::to_jni_signature(&original.arguments);
-        let jni_ret = original.return_type.as_ref().map(|x| ArgumentType::to_jni_signature(&[x.clone()]));
+        let jni_ret = original.return_type.as_ref().map(|x| ArgumentType::to_jni_signature(std::slice::from_ref(x)));
         let jni_signature = format!("({}){}", jni_args, jni_ret.unwrap_or("V".to_string()));
 
-        let arguments = original.arguments.into_iter()
-            .map(|x| x.format_to_rust())
-            .collect::<Vec<_>>();
-        let return_type = original.return_type.map(|x| x.format_to_rust());
+        let argument_names = format_argument_names(&original.argument_names, config);
+        let return_nullability = return_nullability(&original.declaring_class, &original.name, original.return_nullability, config);
 
         Self {
             rust_name,
             java_name: original.name,
             is_static: original.is_static,
-            arguments,
-            return_type,
+            is_abstract: original.is_abstract,
+            is_default: original.is_default,
+            deprecation: original.deprecation,
+            arguments: original.arguments,
+            argument_names,
+            return_type: original.return_type,
             jni_signature,
             declaring_class_rust,
             declaring_class_java: original.declaring_class,
+            type_parameters: original.type_parameters,
+            generic_arguments: original.generic_arguments,
+            generic_return_type: original.generic_return_type,
+            exceptions: original.exceptions,
+            argument_nullability: original.argument_nullability,
+            return_nullability,
+            javadoc: original.javadoc.as_deref().map(format_javadoc).unwrap_or_default(),
+            singleton: None,
         }
     }
 }
 
-impl ArgumentType {
-    fn format_to_rust(self) -> Self {
-        match self {
-            ArgumentType::Array(argument_type) => {
-                match argument_type {
-                    ArgumentType::Object(mut class_fq) => {
-                        if class_fq.starts_with("[L") && class_fq.ends_with(';') {
-                            class_fq.remove(0); // Remove the '['
-                            class_fq.remove(0); // Remove the 'L'
-                            class_fq.pop();
-                        }
-
-                        if class_fq.starts_with('[') {
-                            class_fq.remove(0);
-                        }
-
-                        ArgumentType::Array(Box::new(ArgumentType::Object(class_fq)))
-                    },
-                    _ => ArgumentType::Array(argument_type)
-                }
-            },
-            _ => self
-        }
+/// The nullability of the value returned by a method, taking the configured assumptions
+/// into account for methods without a nullability annotation
+fn return_nullability(declaring_class: &str, name: &str, annotated: Nullability, config: &Generator) -> Nullability {
+    if annotated != Nullability::Unknown {
+        return annotated;
+    }
+
+    let is_non_null = config.non_null_returns.iter()
+        .any(|x| match x.split_once('#') {
+            Some((class, method)) => class == declaring_class && method == name,
+            None => x == declaring_class,
+        });
+    if is_non_null {
+        return Nullability::NonNull;
+    }
+
+    match config.unannotated_returns {
+        AssumedNullability::Nullable => Nullability::Nullable,
+        AssumedNullability::NonNull => Nullability::Unknown,
     }
+}
 
+impl ArgumentType {
     pub fn to_jni_signature(this: &[Self]) -> String {
         this.iter()
-            .map(|this| {
-                if this.is_primitive() {
-                    return this.primitive_to_jni_signature();
-                }
-
-                match this {
-                    Self::Object(class_fq) | Self::Array(class_fq) => {
-                        let slashed = class_fq.replace('.', "/");
-                        let mut cleaned = slashed;
-
-                        if cleaned.starts_with("[L") && cleaned.ends_with(';') {
-                            cleaned.remove(0);
-                            cleaned.pop();
-
-                            format!("[L{};", cleaned)
-                        } else if cleaned.starts_with('[') {
-                            cleaned
-                        } else {
-                            unreachable!();
-                        }
-                    },
-                    Self::Array(class_fq) => {
-                        Self::to_jni_signature(&[**class_fq])
-                    },
-                    _ => unreachable!(),
-                }
+            .map(|this| match this {
+                Self::Object(class_fq) => format!("L{};", class_fq.replace('.', "/")),
+                Self::Array(argument_type) => format!("[{}", Self::to_jni_signature(std::slice::from_ref(&**argument_type))),
+                _ => this.primitive_to_jni_signature(),
             })
             .collect::<String>()
     }
 
-    fn is_primitive(&self) -> bool {
-        match self {
-            Self::Object(_) | Self::Array(_) => false,
-            _ => true,
-        }
-    }
-
     fn primitive_to_jni_signature(&self) -> String {
         match self {
             Self::Int => "I".into(),
@@ -153,6 +458,28 @@ impl ArgumentType {
     }
 
     pub fn to_rust_type(&self, config: &Config) -> String {
+        self.rust_type(config, "", "")
+    }
+
+    /// Like `to_rust_type`, but classes without a mapping are referred to by their path in the generated crate,
+    /// e.g. `crate::bindings::com::foo::Bar`
+    pub fn to_generated_rust_type(&self, config: &Config) -> String {
+        self.rust_type(config, BINDINGS_MODULE, "")
+    }
+
+    /// Like `to_generated_rust_type`, but classes in the generated crate have the lifetime `'a` of the JNIEnv,
+    /// e.g. `crate::bindings::com::foo::Bar<'a>`, so returned values are not tied to a borrow of `self`
+    pub fn to_generated_return_type(&self, config: &Config) -> String {
+        self.rust_type(config, BINDINGS_MODULE, "<'a>")
+    }
+
+    /// Whether the type is `java.lang.String` without a configured mapping. These are converted from and to Rust strings
+    pub fn is_string(&self, config: &Config) -> bool {
+        matches!(self, Self::Object(class_fq) if class_fq == "java.lang.String")
+            && !config.generator.mappings.contains_key("java::lang::String")
+    }
+
+    fn rust_type(&self, config: &Config, module: &str, lifetime: &str) -> String {
         match self {
             Self::Int => "i32".into(),
             Self::Byte => "u8".into(),
@@ -162,35 +489,119 @@ impl ArgumentType {
             Self::Char => "u16".into(),
             Self::Boolean => "bool".into(),
             Self::Long => "i64".into(),
-            Self::Object(class_fq) => {
-                // Remove charactes Java puts in
-                let class_fq = class_fq
-                    .replace('[', "")
-                    .replace("[L", "")
-                    .replace(';', "");
-
-                // Name is now rust safe
-                let renamed = rename_class_fq(&class_fq);
-
-                // Convert to a Rust type path
-                let type_path = renamed.replace('.', "::");
-
-                // Try to map the path to a configured mapping
-                let mapped = if let Some(mapping) = config.generator.mappings.get(&type_path) {
-                    mapping.to_owned()
-                } else {
-                    type_path
-                };
-
-                match self {
-                    Self::Object(_) => mapped,
-                    Self::Array(_) => format!("Vec<{}>", mapped),
-                    _ => unreachable!()
-                }
-            },
+            // Name is now rust safe
+            Self::Object(class_fq) => type_path(&rename_class_fq(class_fq, &config.generator), config, module, lifetime),
             Self::Array(argument_type) => {
-                argument_type.to_rust_type(config)
+                format!("Vec<{}>", argument_type.rust_type(config, module, lifetime))
             },
-         }
+        }
+    }
+}
+
+/// The type of a class in the generated crate, given its formatted name, e.g. `crate::bindings::com::foo::bar_p::Baz`
+/// for `com.foo.bar_p.Baz`, or its configured mapping
+pub fn generated_type_path(formatted_name: &str, config: &Config) -> String {
+    type_path(formatted_name, config, BINDINGS_MODULE, "")
+}
+
+/// The trait of an interface in the generated crate, given its formatted name, e.g. `crate::bindings::com::foo::Shape`
+pub fn generated_trait_path(formatted_name: &str) -> String {
+    format!("{BINDINGS_MODULE}{}", formatted_name.replace('.', "::"))
+}
+
+/// `lifetime` is appended to the paths of classes in the generated crate, not to mappings.
+/// Classes without bindings or a mapping, e.g. `java.io.OutputStream`, are the untyped `crate::Object`,
+/// and interfaces the struct wrapping their objects, as their traits are not types. Enums have no lifetime
+fn type_path(formatted_name: &str, config: &Config, module: &str, lifetime: &str) -> String {
+    // Convert to a Rust type path
+    let type_path = formatted_name.replace('.', "::");
+
+    // Try to map the path to a configured mapping
+    if let Some(mapping) = config.generator.mappings.get(&type_path) {
+        mapping.to_owned()
+    } else if config.is_interface(formatted_name) {
+        format!("{module}{type_path}{INTERFACE_WRAPPER_SUFFIX}{lifetime}")
+    } else if config.is_enum(formatted_name) {
+        format!("{module}{type_path}")
+    } else if config.has_bindings(formatted_name) {
+        format!("{module}{type_path}{lifetime}")
+    } else {
+        format!("crate::Object{lifetime}")
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn jni_signature() {
+        let arguments = vec![
+            ArgumentType::Int,
+            ArgumentType::Object("java.lang.String".to_string()),
+            ArgumentType::Array(Box::new(ArgumentType::Array(Box::new(ArgumentType::Float)))),
+            ArgumentType::Array(Box::new(ArgumentType::Object("com.foo.Bar$Baz".to_string()))),
+        ];
+
+        assert_eq!("ILjava/lang/String;[[F[Lcom/foo/Bar$Baz;", ArgumentType::to_jni_signature(&arguments));
+    }
+
+    #[test]
+    fn rust_type() {
+        let config = Config::default();
+
+        assert_eq!("Vec<Vec<f32>>", ArgumentType::Array(Box::new(ArgumentType::Array(Box::new(ArgumentType::Float)))).to_rust_type(&config));
+        assert_eq!("Vec<com::foo::bar_p::Baz>", ArgumentType::Array(Box::new(ArgumentType::Object("com.foo.Bar$Baz".to_string()))).to_rust_type(&config));
+        assert_eq!("crate::bindings::com::foo::Bar", ArgumentType::Object("com.foo.Bar".to_string()).to_generated_rust_type(&config));
+        assert_eq!("Vec<crate::bindings::com::foo::Bar<'a>>", ArgumentType::Array(Box::new(ArgumentType::Object("com.foo.Bar".to_string()))).to_generated_return_type(&config));
+    }
+
+    #[test]
+    fn rust_type_without_bindings() {
+        let mut config = Config {
+            classes: Some([
+                ("com.foo.bar_p.Baz".to_string(), ClassType::Class),
+                ("com.foo.Shape".to_string(), ClassType::Interface),
+                ("com.foo.Color".to_string(), ClassType::Enum),
+            ].into_iter().collect()),
+            ..Config::default()
+        };
+        config.generator.mappings.insert("java::util::UUID".to_string(), "crate::Uuid".to_string());
+
+        assert_eq!("crate::bindings::com::foo::bar_p::Baz<'a>", ArgumentType::Object("com.foo.Bar$Baz".to_string()).to_generated_return_type(&config));
+        assert_eq!("crate::bindings::com::foo::ShapeWrapper<'a>", ArgumentType::Object("com.foo.Shape".to_string()).to_generated_return_type(&config));
+        assert_eq!("crate::bindings::com::foo::Color", ArgumentType::Object("com.foo.Color".to_string()).to_generated_return_type(&config));
+        assert_eq!("crate::Object<'a>", ArgumentType::Object("java.io.OutputStream".to_string()).to_generated_return_type(&config));
+        assert_eq!("crate::Object", ArgumentType::Object("java.lang.Object".to_string()).to_generated_rust_type(&config));
+        assert_eq!("crate::Uuid", ArgumentType::Object("java.util.UUID".to_string()).to_generated_rust_type(&config));
+    }
+
+    #[test]
+    fn string() {
+        let mut config = Config::default();
+        let string = ArgumentType::Object("java.lang.String".to_string());
+
+        assert!(string.is_string(&config));
+        assert!(!ArgumentType::Object("java.lang.StringBuilder".to_string()).is_string(&config));
+
+        config.generator.mappings.insert("java::lang::String".to_string(), "crate::JavaString".to_string());
+        assert!(!string.is_string(&config));
+    }
+
+    #[test]
+    fn assumed_return_nullability() {
+        let mut config = Generator {
+            non_null_returns: vec!["com.foo.Bar#getName".to_string(), "com.foo.Baz".to_string()],
+            ..Generator::default()
+        };
+
+        assert_eq!(Nullability::Nullable, return_nullability("com.foo.Bar", "getParent", Nullability::Unknown, &config));
+        assert_eq!(Nullability::NonNull, return_nullability("com.foo.Bar", "getName", Nullability::Unknown, &config));
+        assert_eq!(Nullability::NonNull, return_nullability("com.foo.Baz", "getParent", Nullability::Unknown, &config));
+        // Annotations win
+        assert_eq!(Nullability::Nullable, return_nullability("com.foo.Baz", "getParent", Nullability::Nullable, &config));
+
+        config.unannotated_returns = AssumedNullability::NonNull;
+        assert_eq!(Nullability::Unknown, return_nullability("com.foo.Bar", "getParent", Nullability::Unknown, &config));
+    }
+}
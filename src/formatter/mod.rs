@@ -1,60 +1,488 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use convert_case::{Case, Casing};
-use const_format::formatcp;
+use log::warn;
+use crate::class_tree::{ArgumentType, ClassEntry, TypeParameter};
+use crate::parser::kotlin::Property;
+use crate::config::{AccessorNaming, Generator, Inheritance, OverloadNaming};
 
 mod class;
+mod names;
 
 pub use class::*;
+pub use names::*;
 
-const KEYWORD_SUFFIX: &str = "_k";
-const SUBCLASS_PARENT_SUFFIX: &str = "_p";
+/// Appended to the class of a Scala `object`, which is named after the object with a `$` appended
+const SCALA_OBJECT_SUFFIX: &str = "Object";
 
-/// Rename keywords. This will add the `KEYWORD_SUFFIX` if a keyword is used.
+/// The keywords of Rust 2021, including those reserved for future use.
+/// Identifiers equal to one of these do not compile
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn",
+    "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self",
+    "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+    "abstract", "become", "box", "do", "final", "macro", "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+/// Keywords that cannot be used as raw identifiers
+const NON_RAW_KEYWORDS: &[&str] = &["self", "Self", "super", "crate"];
+
+/// Rename keywords. This will add the configured `keyword_suffix` if a keyword is used, or make it a raw identifier,
+/// e.g. `r#type`, if `raw_identifiers` is enabled and the keyword can be one. Characters that are not valid
+/// in an identifier are transliterated first.
+/// This function should be called on individual class name components. E.g. `com`
+pub fn escape_keywords<'a>(x: &'a str, config: &Generator) -> Cow<'a, str> {
+    let transliterated = transliterate(x);
+    if !KEYWORDS.contains(&transliterated.as_ref()) {
+        transliterated
+    } else if config.raw_identifiers && !NON_RAW_KEYWORDS.contains(&x) {
+        Cow::Owned(format!("r#{x}"))
+    } else {
+        Cow::Owned(format!("{x}{}", config.keyword_suffix))
+    }
+}
+
+/// Transliterate the characters that are not valid in an identifier, e.g. those of obfuscated or non-English names.
+/// Only ASCII is kept, so the generated code does not trigger lints like `uncommon_codepoints`. A character is replaced
+/// by its code point, as `_uXXXX`, or `_UXXXXXX` outside the Basic Multilingual Plane. E.g. `café` becomes `caf_u00e9`.
+///
+/// An underscore that would start such a sequence is escaped as well, so distinct names never transliterate to the same name
+pub fn transliterate(x: &str) -> Cow<'_, str> {
+    let needs_escape = |idx: usize, c: char| !(c.is_ascii_alphanumeric() || c == '_') || (c == '_' && is_code_point_escape(&x[idx..]));
+    if !x.char_indices().any(|(idx, c)| needs_escape(idx, c)) {
+        return Cow::Borrowed(x);
+    }
+
+    let mut transliterated = String::with_capacity(x.len());
+    for (idx, c) in x.char_indices() {
+        if !needs_escape(idx, c) {
+            transliterated.push(c);
+        } else if (c as u32) <= 0xFFFF {
+            transliterated.push_str(&format!("_u{:04x}", c as u32));
+        } else {
+            transliterated.push_str(&format!("_U{:06x}", c as u32));
+        }
+    }
+
+    Cow::Owned(transliterated)
+}
+
+/// Whether `x` starts with a code point escape produced by `transliterate`
+fn is_code_point_escape(x: &str) -> bool {
+    let is_hex = |digits: &str, len: usize| digits.len() >= len && digits.bytes().take(len).all(|x| x.is_ascii_hexdigit());
+    match x.strip_prefix("_u") {
+        Some(digits) => is_hex(digits, 4),
+        None => x.strip_prefix("_U").map(|digits| is_hex(digits, 6)).unwrap_or(false),
+    }
+}
+
+/// Crates that can be referred to from any module: those of the extern prelude and those the generated code uses.
+/// A module named after one of these makes paths starting with that name ambiguous, e.g. `std::vec::Vec`
+const RESERVED_MODULE_NAMES: &[&str] = &["std", "core", "alloc", "jni", "ejni"];
+
+/// Rename a module name. Besides keywords, this renames names reserved for crates, which get the `keyword_suffix`.
 /// This function should be called on individual class name components. E.g. `com`
-pub fn escape_keywords(x: &str) -> &str {
-    match x {
-        "impl" => formatcp!("impl{}", KEYWORD_SUFFIX),
-        "move" => formatcp!("move{}", KEYWORD_SUFFIX),
-        "in" => formatcp!("in{}", KEYWORD_SUFFIX),
-        _ => x
+fn escape_module_name<'a>(x: &'a str, config: &Generator) -> Cow<'a, str> {
+    if starts_with_digit(x) {
+        Cow::Owned(escape_leading_digit(&transliterate(x)))
+    } else if RESERVED_MODULE_NAMES.contains(&x) {
+        Cow::Owned(format!("{x}{}", config.keyword_suffix))
+    } else {
+        escape_keywords(x, config)
     }
 }
 
+/// Whether a name component starts with a digit, e.g. `1` of the anonymous class `Foo$1` or `1Local` of a local class.
+/// The JVM allows these, but Rust identifiers cannot start with a digit
+fn starts_with_digit(x: &str) -> bool {
+    x.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Prefix a name component starting with a digit with an underscore, e.g. `_1`
+fn escape_leading_digit(x: &str) -> String {
+    format!("_{x}")
+}
+
+/// Format the classes of the tree. Anonymous classes, e.g. `Foo$1`, cannot be referred to by name
+/// in Java either, so they are skipped with a warning
+pub fn format_classes(classes: Vec<ClassEntry>, config: &Generator) -> Vec<FormattedClassEntry> {
+    let (anonymous, classes): (Vec<_>, Vec<_>) = classes.into_iter()
+        .partition(|x| x.simple_name.is_empty());
+
+    if !anonymous.is_empty() {
+        warn!("Skipped {} anonymous classes:", anonymous.len());
+        for class in &anonymous {
+            warn!("    {}", class.name);
+        }
+    }
+
+    let anonymous = anonymous.iter()
+        .map(|x| x.name.as_str())
+        .collect::<HashSet<_>>();
+
+    let classes = match config.inheritance {
+        Inheritance::Duplicate => inherit_methods(classes),
+        Inheritance::Upcast | Inheritance::Deref => classes,
+    };
+
+    let mut classes = classes.into_iter()
+        .map(|mut x| {
+            x.nested_classes.retain(|nested| !anonymous.contains(nested.as_str()));
+            FormattedClassEntry::new(x, config)
+        })
+        .collect::<Vec<_>>();
+
+    let java_names = classes.iter()
+        .map(|x| (x.name.clone(), x.java_name.clone()))
+        .collect::<HashMap<_, _>>();
+    for class in &mut classes {
+        class.upcasts = format_upcasts(&class.superclasses, &java_names);
+    }
+
+    add_companion_methods(&mut classes);
+
+    classes
+}
+
+/// Add the instance methods of the superclasses in the tree to their subclasses, unless a subclass or
+/// a nearer superclass overrides them. Calls are dispatched by the JVM, so they reach overrides outside of the tree as well
+fn inherit_methods(classes: Vec<ClassEntry>) -> Vec<ClassEntry> {
+    let methods = classes.iter()
+        .map(|x| {
+            let methods = x.methods.iter()
+                .filter(|x| !x.is_static)
+                .cloned()
+                .collect::<Vec<_>>();
+            (x.name.clone(), methods)
+        })
+        .collect::<HashMap<_, _>>();
+
+    classes.into_iter()
+        .map(|mut class| {
+            // The superclasses start at the direct superclass, so the nearest override is added first
+            let inherited = class.superclasses.iter()
+                .flat_map(|x| methods.get(x))
+                .flatten();
+            for method in inherited {
+                if !class.methods.iter().any(|x| x.name == method.name && x.arguments == method.arguments) {
+                    class.methods.push(method.clone());
+                }
+            }
+
+            class
+        })
+        .collect()
+}
+
+/// Add the instance methods of Kotlin companion objects in the tree to the classes declaring them, called through
+/// the static field holding the companion, like Kotlin calls them on the class. Methods named like a method of the class
+/// itself are left out, e.g. those annotated with `@JvmStatic`, for which Kotlin generates a static method in the class
+fn add_companion_methods(classes: &mut [FormattedClassEntry]) {
+    let companion_methods = classes.iter()
+        .enumerate()
+        .filter_map(|(idx, class)| {
+            let field = class.companion_field.as_ref()?;
+            let companion_name = format!("{}${field}", class.java_name);
+            let companion = classes.iter().find(|x| x.java_name == companion_name)?;
+
+            let singleton = SingletonField {
+                class: class.java_name.clone(),
+                name: field.clone(),
+                jni_signature: format!("L{};", companion_name.replace('.', "/")),
+            };
+            let methods = companion.methods.iter()
+                .filter(|x| !x.is_static)
+                .map(|x| FormattedMethodEntry {
+                    singleton: Some(singleton.clone()),
+                    ..x.clone()
+                })
+                .collect::<Vec<_>>();
+            Some((idx, methods))
+        })
+        .collect::<Vec<_>>();
+
+    for (idx, methods) in companion_methods {
+        let class = &mut classes[idx];
+        for method in methods {
+            if !class.methods.iter().any(|x| x.rust_name == method.rust_name) {
+                class.methods.push(method);
+            }
+        }
+    }
+}
+
+/// Format the upcasts to the superclasses which are in the tree, given the Java names of the classes in the tree by their formatted name.
+/// The methods are named after the simple name of the superclass, e.g. `as_bar` for `com.foo.Bar`
+fn format_upcasts(superclasses: &[String], java_names: &HashMap<String, String>) -> Vec<FormattedUpcast> {
+    superclasses.iter()
+        .filter_map(|class| {
+            let java_name = java_names.get(class)?;
+            let simple_name = class.rsplit('.').next().unwrap_or_default();
+            Some(FormattedUpcast {
+                rust_name: format!("as_{}", simple_name.to_case(Case::Snake)),
+                class: class.clone(),
+                java_name: java_name.clone(),
+            })
+        })
+        .collect()
+}
+
 /// Rename the parent class of a subclass. This will:
-/// - Rename keywords
 /// - Convert to snake case
 /// - Append the `SUBCLASS_PARENT_SUFFIX` suffix
+/// - Rename keywords
 ///
 /// This function should be called on individual class name components. E.g. `com`
-fn rename_parent_class(input: &str) -> String {
-    let keyword_renamed = escape_keywords(input);
-    let case_adjusted = keyword_renamed.to_string().to_case(Case::Snake);
+fn rename_parent_class(input: &str, config: &Generator) -> String {
+    let case_adjusted = input.to_case(Case::Snake);
+    escape_module_name(&format!("{case_adjusted}{}", config.subclass_parent_suffix), config).to_string()
+}
+
+/// Names used for local variables in generated method bodies, arguments may not shadow these
+const GENERATED_LOCALS: &[&str] = &["env", "jvalue", "value", "obj", "outer"];
+
+/// Format the argument names of a method. This will:
+/// - Convert to snake case
+/// - Rename keywords and names clashing with locals of the generated code
+/// - Fall back to `argN` if the name is not known
+/// - Append the index if names clash after renaming
+pub fn format_argument_names(names: &[Option<String>], config: &Generator) -> Vec<String> {
+    let formatted = names.iter()
+        .enumerate()
+        .map(|(idx, name)| match name {
+            Some(name) => {
+                let case_adjusted = name.to_case(Case::Snake);
+                if GENERATED_LOCALS.contains(&case_adjusted.as_str()) {
+                    format!("{case_adjusted}{}", config.keyword_suffix)
+                } else {
+                    escape_keywords(&case_adjusted, config).to_string()
+                }
+            },
+            None => format!("arg{idx}"),
+        })
+        .collect::<Vec<_>>();
+
+    formatted.iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            let is_duplicate = formatted.iter().filter(|x| *x == name).count() > 1;
+            if is_duplicate {
+                format!("{name}{idx}")
+            } else {
+                name.clone()
+            }
+        })
+        .collect()
+}
+
+/// Rename the getters and setters among the methods of a class according to `accessor_naming`, e.g. `getWidth()` becomes
+/// `width()`. Getters are methods without arguments named `getX`, or `isX` if they return a `boolean`; setters are methods
+/// with one argument named `setX`. A method keeps its name if the new name collides with the name of another method,
+/// where getters take precedence over setters.
+///
+/// Returns the new names, in the same order as `methods`
+pub fn rename_accessors(methods: &[FormattedMethodEntry], config: &Generator) -> Vec<String> {
+    let mut names = methods.iter()
+        .map(|x| x.rust_name.clone())
+        .collect::<Vec<_>>();
+
+    let getter_names = methods.iter()
+        .map(|x| match config.accessor_naming {
+            AccessorNaming::Java => None,
+            AccessorNaming::Properties | AccessorNaming::Builder => getter_property(x),
+        })
+        .collect::<Vec<_>>();
+    rename_unique(&mut names, &getter_names, config);
+
+    let setter_names = methods.iter()
+        .map(|x| match config.accessor_naming {
+            AccessorNaming::Java | AccessorNaming::Properties => None,
+            AccessorNaming::Builder => setter_property(x),
+        })
+        .collect::<Vec<_>>();
+    rename_unique(&mut names, &setter_names, config);
+
+    names
+}
+
+/// Rename the getters and setters of Kotlin properties after the property, e.g. `getName()` becomes `name()` and
+/// `setName(String)` becomes `set_name(String)`, like `accessor_naming = "properties"` does for all getters.
+/// Unlike it, only the accessors the Kotlin metadata declares are renamed, and `isEnabled()` keeps its name,
+/// as it is the name of the property. A method keeps its name if the new name is taken, like in `rename_accessors`.
+///
+/// Returns the new names, in the same order as `methods`
+pub fn rename_properties(methods: &[FormattedMethodEntry], properties: &[Property], config: &Generator) -> Vec<String> {
+    let mut names = methods.iter()
+        .map(|x| x.rust_name.clone())
+        .collect::<Vec<_>>();
+
+    let property = |method: &FormattedMethodEntry, accessor: fn(&Property) -> Option<&String>, argument_count: usize| {
+        properties.iter()
+            .find(|x| !method.is_static && method.arguments.len() == argument_count && accessor(x) == Some(&method.java_name))
+    };
+
+    let getter_names = methods.iter()
+        .map(|x| property(x, |x| Some(&x.getter), 0).map(|x| x.name.clone()))
+        .collect::<Vec<_>>();
+    rename_unique(&mut names, &getter_names.iter().map(Option::as_deref).collect::<Vec<_>>(), config);
 
-    format!("{case_adjusted}{SUBCLASS_PARENT_SUFFIX}")
+    let setter_names = methods.iter()
+        .map(|x| property(x, |x| x.setter.as_ref(), 1).map(|x| format!("set_{}", x.name)))
+        .collect::<Vec<_>>();
+    rename_unique(&mut names, &setter_names.iter().map(Option::as_deref).collect::<Vec<_>>(), config);
+
+    names
+}
+
+/// The property of a getter, e.g. `URL` for `getURL()`
+fn getter_property(method: &FormattedMethodEntry) -> Option<&str> {
+    if method.is_static || !method.arguments.is_empty() {
+        return None;
+    }
+
+    let property = match method.return_type {
+        Some(ArgumentType::Boolean) => method.java_name.strip_prefix("is").or_else(|| method.java_name.strip_prefix("get")),
+        Some(_) => method.java_name.strip_prefix("get"),
+        None => None,
+    }?;
+    property.starts_with(char::is_uppercase).then_some(property)
+}
+
+/// The property of a setter, e.g. `Width` for `setWidth(float)`
+fn setter_property(method: &FormattedMethodEntry) -> Option<&str> {
+    if method.is_static || method.arguments.len() != 1 {
+        return None;
+    }
+
+    method.java_name.strip_prefix("set").filter(|x| x.starts_with(char::is_uppercase))
+}
+
+/// Rename the methods for which a property is present to the property name, unless that name is taken by any other method
+fn rename_unique(names: &mut [String], properties: &[Option<&str>], config: &Generator) {
+    let renamed = properties.iter()
+        .map(|x| x.map(|property| escape_keywords(&property.to_case(Case::Snake), config).to_string()))
+        .collect::<Vec<_>>();
+
+    for (idx, new_name) in renamed.iter().enumerate() {
+        let new_name = match new_name {
+            Some(new_name) => new_name,
+            None => continue,
+        };
+
+        let is_taken = names.iter().enumerate().any(|(other, name)| other != idx && name == new_name)
+            || renamed.iter().enumerate().any(|(other, name)| other != idx && name.as_ref() == Some(new_name));
+        if !is_taken {
+            names[idx] = new_name.clone();
+        }
+    }
+}
+
+/// Give overloaded methods distinct names. `methods` are the Rust names and argument types of the methods of a class,
+/// where overloads share a Rust name. The overload with the fewest arguments keeps its name, the others are renamed
+/// according to `overload_naming`. Names that still clash after renaming get the index of the method appended.
+///
+/// Returns the new names, in the same order as `methods`
+pub fn rename_overloads(methods: &[(&str, &[ArgumentType])], config: &Generator) -> Vec<String> {
+    let mut renamed = methods.iter()
+        .map(|(name, _)| name.to_string())
+        .collect::<Vec<_>>();
+
+    let mut names = methods.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+    names.sort_unstable();
+    names.dedup();
+
+    for name in names {
+        // Order overloads by their signature, rather than the order the JVM reports them in, so names are stable
+        let mut overloads = methods.iter()
+            .enumerate()
+            .filter(|(_, (x, _))| *x == name)
+            .map(|(idx, (_, arguments))| (idx, *arguments))
+            .collect::<Vec<_>>();
+        if overloads.len() < 2 {
+            continue;
+        }
+        overloads.sort_by_key(|(_, arguments)| (arguments.len(), ArgumentType::to_jni_signature(arguments)));
+
+        let keeps_name = overloads[0].1.len() < overloads[1].1.len();
+        let renamed_overloads = if keeps_name { &overloads[1..] } else { &overloads[..] };
+        for (number, (idx, arguments)) in renamed_overloads.iter().enumerate() {
+            renamed[*idx] = match config.overload_naming {
+                OverloadNaming::ArgumentTypes if arguments.is_empty() => name.to_string(),
+                OverloadNaming::ArgumentTypes => {
+                    let types = arguments.iter().map(argument_type_name).collect::<Vec<_>>();
+                    format!("{name}_{}", types.join("_"))
+                },
+                OverloadNaming::Numbered => format!("{name}_{}", number + 1),
+            };
+        }
+    }
+
+    renamed.iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            let is_duplicate = renamed.iter().filter(|x| *x == name).count() > 1;
+            if is_duplicate {
+                format!("{name}_{idx}")
+            } else {
+                name.clone()
+            }
+        })
+        .collect()
+}
+
+/// The name of an argument type in the name of an overload, e.g. `string` for `java.lang.String` or `float_array` for `float[]`
+fn argument_type_name(argument_type: &ArgumentType) -> String {
+    match argument_type {
+        ArgumentType::Boolean => "boolean".to_string(),
+        ArgumentType::Byte => "byte".to_string(),
+        ArgumentType::Char => "char".to_string(),
+        ArgumentType::Short => "short".to_string(),
+        ArgumentType::Int => "int".to_string(),
+        ArgumentType::Long => "long".to_string(),
+        ArgumentType::Float => "float".to_string(),
+        ArgumentType::Double => "double".to_string(),
+        ArgumentType::Object(name) => name.rsplit(['.', '$']).next().unwrap_or_default().to_case(Case::Snake),
+        ArgumentType::Array(element) => format!("{}_array", argument_type_name(element)),
+    }
+}
+
+/// Rename a Java enum constant to a Rust enum variant name. This will:
+/// - Convert to upper camel case, e.g. `FOO_BAR` becomes `FooBar`
+/// - Rename keywords
+pub fn rename_enum_constant(input: &str, config: &Generator) -> String {
+    let case_adjusted = input.to_case(Case::Pascal);
+    escape_keywords(&case_adjusted, config).to_string()
+}
+
+/// Rename a constant field to the Rust convention for constants, e.g. `serialVersionUID` becomes `SERIAL_VERSION_UID`.
+/// Upper case names never collide with keywords
+pub fn rename_constant(input: &str) -> String {
+    transliterate(&input.to_case(Case::UpperSnake)).to_string()
 }
 
 /// Rename a fully qualified class name. This will:
 /// - Fix casing on prefixing name components
 /// - Correctly fix subclasses
-/// - Adjust for keywords
+/// - Adjust for keywords, and packages named after crates like `std`
+/// - Append the `SCALA_OBJECT_SUFFIX` to the class of a Scala `object`, e.g. `Bar$` becomes `BarObject`
 ///
 /// This function should be called only for the entire class name path. E.g. `com.foo.Example`
-pub fn rename_class_fq(input: &str) -> String {
+pub fn rename_class_fq(input: &str, config: &Generator) -> String {
     // Input format: com.foo.Bar$Baz
+    if let Some(object) = input.strip_suffix('$') {
+        return format!("{}{SCALA_OBJECT_SUFFIX}", rename_class_fq(object, config));
+    }
 
-    // First, rename all components containing keywords
-    let mut components = input.split('.').into_iter()
-        .map(escape_keywords)
-        .map(|x| x.to_string())
+    let mut components = input.split('.')
+        .map(str::to_string)
         .collect::<Vec<_>>();
     let class_name = components.pop().unwrap();
 
-    // Correct the casing of the class name components that are not the class name itself
-    let case_adjusted_components = components.into_iter()
-        .map(|x| x.to_case(Case::Snake))
+    // Correct the casing of the class name components that are not the class name itself, then rename keywords
+    let mut class_fully_qualified = components.into_iter()
+        .map(|x| escape_module_name(&x.to_case(Case::Snake), config).to_string())
         .collect::<Vec<_>>();
 
-    let mut class_fully_qualified = case_adjusted_components;
-
     // Handle subclasses
     let mut tmp = class_name;
     while tmp.contains('$') {
@@ -62,7 +490,7 @@ pub fn rename_class_fq(input: &str) -> String {
         let (prefix, suffix) = tmp.split_once('$').unwrap();
 
         let parent_class = prefix;
-        class_fully_qualified.push(rename_parent_class(parent_class));
+        class_fully_qualified.push(rename_parent_class(parent_class, config));
 
         // Could be multiple, if multiple layers of nesting are present
         let child_classes = suffix;
@@ -70,19 +498,168 @@ pub fn rename_class_fq(input: &str) -> String {
     }
 
     // tmp is now the name of the final subclass
-    class_fully_qualified.push(tmp);
+    if starts_with_digit(&tmp) {
+        class_fully_qualified.push(escape_leading_digit(&transliterate(&tmp)));
+    } else {
+        class_fully_qualified.push(escape_keywords(&tmp, config).to_string());
+    }
 
     class_fully_qualified.join(".")
 }
 
+/// Format a Javadoc comment as lines of a Rust doc comment. This will:
+/// - Remove the leading `*` and the common indentation of the lines
+/// - Remove leading and trailing empty lines
+/// - Convert `{@code ...}`, `{@link ...}` and `{@linkplain ...}` to inline code
+///
+/// Every line starts with a space, so it can be used in `#[doc = ...]` directly
+pub fn format_javadoc(javadoc: &str) -> Vec<String> {
+    let lines = javadoc.lines()
+        .map(|x| {
+            let trimmed = x.trim_start();
+            match trimmed.strip_prefix('*') {
+                Some(stripped) => stripped.trim_end(),
+                None => trimmed.trim_end(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let indentation = lines.iter()
+        .filter(|x| !x.trim().is_empty())
+        .map(|x| x.len() - x.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    let mut lines = lines.into_iter()
+        .map(|x| x.get(indentation..).unwrap_or(""))
+        .map(convert_inline_tags)
+        .skip_while(|x| x.is_empty())
+        .collect::<Vec<_>>();
+
+    while lines.last().map(|x| x.is_empty()).unwrap_or(false) {
+        lines.pop();
+    }
+
+    lines.into_iter()
+        .map(|x| format!(" {x}"))
+        .collect()
+}
+
+/// Convert the Javadoc inline tags `{@code ...}`, `{@link ...}` and `{@linkplain ...}` to inline code
+fn convert_inline_tags(line: &str) -> String {
+    let mut converted = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find("{@") {
+        converted.push_str(&rest[..start]);
+        let tag = &rest[start + 2..];
+
+        // Braces may be nested inside the tag, e.g. `{@code new int[] {}}`
+        let mut depth = 1;
+        let end = tag.char_indices()
+            .find(|(_, c)| {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => {},
+                }
+                depth == 0
+            })
+            .map(|(idx, _)| idx);
+
+        let end = match end {
+            Some(end) => end,
+            None => {
+                converted.push_str(&rest[start..]);
+                return converted;
+            }
+        };
+
+        let (name, content) = tag[..end].split_once(char::is_whitespace).unwrap_or((&tag[..end], ""));
+        let content = content.trim();
+        match name {
+            "code" | "literal" => converted.push_str(&format!("`{content}`")),
+            "link" | "linkplain" => {
+                // Use the label if there is one, otherwise the reference
+                let (reference, label) = content.split_once(char::is_whitespace).unwrap_or((content, ""));
+                let text = if label.trim().is_empty() { reference.replace('#', ".") } else { label.trim().to_string() };
+                converted.push_str(&format!("`{text}`"));
+            },
+            _ => converted.push_str(content),
+        }
+
+        rest = &tag[end + 1..];
+    }
+
+    converted.push_str(rest);
+    converted
+}
+
+/// Format type parameters in Java notation, without the surrounding angle brackets.
+/// E.g. `K, V extends java.lang.Comparable<V>`
+pub fn format_type_parameters(type_parameters: &[TypeParameter]) -> String {
+    type_parameters.iter()
+        .map(|x| if x.bounds.is_empty() {
+            x.name.clone()
+        } else {
+            format!("{} extends {}", x.name, x.bounds.join(" & "))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Format the generic signature of a method in Java notation.
+/// E.g. `<T extends java.lang.Number> java.util.List<T> foo(java.lang.Class<T>)`
+pub fn format_generic_signature(name: &str, type_parameters: &[TypeParameter], generic_arguments: &[String], generic_return_type: Option<&str>) -> String {
+    let type_parameters = if type_parameters.is_empty() {
+        String::new()
+    } else {
+        format!("<{}> ", format_type_parameters(type_parameters))
+    };
+
+    let return_type = generic_return_type.unwrap_or("void");
+    let arguments = generic_arguments.join(", ");
+
+    format!("{type_parameters}{return_type} {name}({arguments})")
+}
+
+/// Name the variants of the exception enum for the provided fully qualified exception classes.
+/// Variants are named after the simple class name, e.g. `java.io.IOException` becomes `IoException`.
+/// If multiple exceptions share a simple name, the fully qualified name is used for those instead.
+///
+/// Returns `(java name, variant name)` pairs, sorted by Java name and without duplicates
+pub fn rename_exception_variants(exceptions: &[String]) -> Vec<(String, String)> {
+    let mut exceptions = exceptions.to_vec();
+    exceptions.sort();
+    exceptions.dedup();
+
+    let simple_name = |x: &str| x.rsplit(['.', '$']).next().unwrap().to_case(Case::Pascal);
+    exceptions.iter()
+        .map(|exception| {
+            let simple = simple_name(exception);
+            let is_ambiguous = exceptions.iter()
+                .filter(|other| simple_name(other) == simple)
+                .count() > 1;
+
+            let variant = if is_ambiguous {
+                exception.replace(['.', '$'], "_").to_case(Case::Pascal)
+            } else {
+                simple
+            };
+
+            (exception.clone(), transliterate(&variant).to_string())
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::class_tree::{ClassType, MethodEntry, Nullability, Visibility};
 
     #[test]
     fn simple_class() {
         let input = "com.foo.example.Bar";
-        let output = rename_class_fq(input);
+        let output = rename_class_fq(input, &Generator::default());
 
         assert_eq!("com.foo.example.Bar", &output);
     }
@@ -90,41 +667,414 @@ mod test {
     #[test]
     fn with_keywords() {
         let input = "com.foo.impl.Bar";
-        let output = rename_class_fq(input);
+        let output = rename_class_fq(input, &Generator::default());
 
-        assert_eq!(format!("com.foo.impl{KEYWORD_SUFFIX}.Bar"), output);
+        assert_eq!("com.foo.impl_k.Bar", output);
+    }
+
+    #[test]
+    fn all_keywords() {
+        assert_eq!("com.foo.type_k.Bar", rename_class_fq("com.foo.type.Bar", &Generator::default()));
+        assert_eq!("match_k", escape_keywords("match", &Generator::default()));
+        assert_eq!("crate_k", escape_keywords("crate", &Generator::default()));
+        assert_eq!("yield_k", escape_keywords("yield", &Generator::default()));
+        assert_eq!("matches", escape_keywords("matches", &Generator::default()));
+    }
+
+    #[test]
+    fn reserved_modules() {
+        let config = Generator {
+            raw_identifiers: true,
+            ..Generator::default()
+        };
+
+        assert_eq!("org.std_k.self_k.super_k.crate_k.Foo", rename_class_fq("org.std.self.super.crate.Foo", &config));
+        assert_eq!("com.jni_k.core_k.Std", rename_class_fq("com.jni.core.Std", &config));
+    }
+
+    #[test]
+    fn leading_digits() {
+        let config = Generator::default();
+        assert_eq!("com.foo.bar_p._1", rename_class_fq("com.foo.Bar$1", &config));
+        assert_eq!("com.foo.bar_p._1_local_p._2", rename_class_fq("com.foo.Bar$1Local$2", &config));
+        assert_eq!("com._2_d.Shape", rename_class_fq("com.2d.Shape", &config));
+    }
+
+    #[test]
+    fn non_ascii() {
+        assert_eq!("get_url", transliterate("get_url"));
+        assert_eq!("caf_u00e9", transliterate("café"));
+        assert_eq!("plus_u0024", transliterate("plus$"));
+        assert_eq!("_U01f600", transliterate("\u{1F600}"));
+        // A literal escape sequence is escaped itself, so it does not collide with the transliterated character
+        assert_eq!("caf_u005fu00e9", transliterate("caf_u00e9"));
+        assert_eq!("_u00e9_u005fu00e9", transliterate("é_u00e9"));
+        assert_eq!("caf_u00", transliterate("caf_u00"));
+
+        assert_eq!("com.caf_u00e9.Na_u00efve", rename_class_fq("com.café.Naïve", &Generator::default()));
+        assert_eq!("NA_u00cfVE", rename_constant("naïve"));
+    }
+
+    #[test]
+    fn raw_identifiers() {
+        let config = Generator {
+            raw_identifiers: true,
+            ..Generator::default()
+        };
+
+        assert_eq!("com.foo.r#type.Bar", rename_class_fq("com.foo.type.Bar", &config));
+        assert_eq!("r#match", escape_keywords("match", &config));
+        assert_eq!("self_k", escape_keywords("self", &config));
+        assert_eq!("Self_k", rename_enum_constant("SELF", &config));
+        assert_eq!(vec!["r#in".to_string(), "env_k".to_string()], format_argument_names(&[Some("in".to_string()), Some("env".to_string())], &config));
+    }
+
+    #[test]
+    fn custom_suffixes() {
+        let config = Generator {
+            keyword_suffix: "_".to_string(),
+            subclass_parent_suffix: String::new(),
+            ..Generator::default()
+        };
+
+        assert_eq!("com.impl_.bar.baz.Quix", rename_class_fq("com.impl.Bar$Baz$Quix", &config));
+        // Without a suffix, the module of a parent class may collide with a keyword itself
+        assert_eq!("com.foo.type_.Bar", rename_class_fq("com.foo.Type$Bar", &config));
     }
 
     #[test]
     fn one_subclass() {
         let input = "com.foo.example.Bar$Baz";
-        let output = rename_class_fq(input);
+        let output = rename_class_fq(input, &Generator::default());
 
-        assert_eq!(format!("com.foo.example.bar{SUBCLASS_PARENT_SUFFIX}.Baz"), output);
+        assert_eq!("com.foo.example.bar_p.Baz", output);
     }
 
     #[test]
     fn two_subclasses() {
         let input = "com.foo.example.Bar$Baz$Quix";
-        let output = rename_class_fq(input);
+        let output = rename_class_fq(input, &Generator::default());
 
-        assert_eq!(format!("com.foo.example.bar{SUBCLASS_PARENT_SUFFIX}.baz{SUBCLASS_PARENT_SUFFIX}.Quix"), output);
+        assert_eq!("com.foo.example.bar_p.baz_p.Quix", output);
     }
 
     #[test]
     fn three_subclasses() {
         let input = "com.foo.example.Bar$Baz$Quix$Example";
-        let output = rename_class_fq(input);
+        let output = rename_class_fq(input, &Generator::default());
 
-        assert_eq!(format!("com.foo.example.bar{SUBCLASS_PARENT_SUFFIX}.baz{SUBCLASS_PARENT_SUFFIX}.quix{SUBCLASS_PARENT_SUFFIX}.Example"), output);
+        assert_eq!("com.foo.example.bar_p.baz_p.quix_p.Example", output);
+    }
+
+    #[test]
+    fn scala_object() {
+        assert_eq!(format!("com.foo.Bar{SCALA_OBJECT_SUFFIX}"), rename_class_fq("com.foo.Bar$", &Generator::default()));
+        assert_eq!(format!("com.foo.bar_p.Baz{SCALA_OBJECT_SUFFIX}"), rename_class_fq("com.foo.Bar$Baz$", &Generator::default()));
     }
 
     #[test]
     fn case_adjusting() {
         let input = "com.Foo.example.Bar";
-        let output = rename_class_fq(input);
+        let output = rename_class_fq(input, &Generator::default());
 
         assert_eq!("com.foo.example.Bar", &output);
     }
 
+    #[test]
+    fn constant() {
+        assert_eq!("MAX_VALUE", rename_constant("MAX_VALUE"));
+        assert_eq!("SERIAL_VERSION_UID", rename_constant("serialVersionUID"));
+    }
+
+    #[test]
+    fn enum_constant() {
+        assert_eq!("FooBar", rename_enum_constant("FOO_BAR", &Generator::default()));
+        assert_eq!("Foo", rename_enum_constant("foo", &Generator::default()));
+        assert_eq!("Self_k", rename_enum_constant("SELF", &Generator::default()));
+    }
+
+    #[test]
+    fn generic_signature() {
+        let type_parameters = vec![TypeParameter {
+            name: "T".to_string(),
+            bounds: vec!["java.lang.Number".to_string()],
+        }];
+        let arguments = vec!["java.lang.Class<T>".to_string(), "int".to_string()];
+        let output = format_generic_signature("foo", &type_parameters, &arguments, Some("java.util.List<T>"));
+
+        assert_eq!("<T extends java.lang.Number> java.util.List<T> foo(java.lang.Class<T>, int)", output);
+        assert_eq!("void bar()", format_generic_signature("bar", &[], &[], None));
+    }
+
+    #[test]
+    fn argument_names() {
+        let input = vec![
+            Some("fontSize".to_string()),
+            None,
+            Some("env".to_string()),
+            Some("in".to_string()),
+        ];
+        let output = format_argument_names(&input, &Generator::default());
+
+        assert_eq!(vec![
+            "font_size".to_string(),
+            "arg1".to_string(),
+            "env_k".to_string(),
+            "in_k".to_string(),
+        ], output);
+    }
+
+    #[test]
+    fn overloads() {
+        let string = ArgumentType::Object("java.lang.String".to_string());
+        let rectangle = [ArgumentType::Object("com.itextpdf.kernel.geom.Rectangle".to_string())];
+        let floats = [ArgumentType::Float, ArgumentType::Float];
+        let strings = [ArgumentType::Array(Box::new(string.clone()))];
+        let methods = [
+            ("add", &floats[..]),
+            ("size", &[][..]),
+            ("add", &rectangle[..]),
+            ("add_string_array", &[][..]),
+            ("add", &strings[..]),
+        ];
+
+        let config = Generator::default();
+        assert_eq!(vec!["add_float_float", "size", "add_rectangle", "add_string_array_3", "add_string_array_4"], rename_overloads(&methods, &config));
+
+        let config = Generator {
+            overload_naming: OverloadNaming::Numbered,
+            ..Generator::default()
+        };
+        assert_eq!(vec!["add_3", "size", "add_1", "add_string_array", "add_2"], rename_overloads(&methods, &config));
+
+        let methods = [("of", &[][..]), ("of", &[string][..])];
+        assert_eq!(vec!["of", "of_1"], rename_overloads(&methods, &config));
+    }
+
+    fn method(name: &str, arguments: Vec<ArgumentType>, return_type: Option<ArgumentType>) -> FormattedMethodEntry {
+        let method = MethodEntry {
+            name: name.to_string(),
+            is_static: false,
+            visibility: Visibility::Public,
+            is_abstract: false,
+            is_default: false,
+            deprecation: None,
+            argument_names: vec![None; arguments.len()],
+            argument_nullability: vec![Nullability::Unknown; arguments.len()],
+            arguments,
+            return_type,
+            declaring_class: "com.foo.Bar".to_string(),
+            type_parameters: Vec::new(),
+            generic_arguments: Vec::new(),
+            generic_return_type: None,
+            exceptions: Vec::new(),
+            return_nullability: Nullability::Unknown,
+            javadoc: None,
+        };
+
+        FormattedMethodEntry::new(method, &Generator::default())
+    }
+
+    #[test]
+    fn accessors() {
+        let string = ArgumentType::Object("java.lang.String".to_string());
+        let methods = vec![
+            method("getWidth", Vec::new(), Some(ArgumentType::Float)),
+            method("setWidth", vec![ArgumentType::Float], None),
+            method("isEnabled", Vec::new(), Some(ArgumentType::Boolean)),
+            method("isOpen", Vec::new(), Some(string.clone())),
+            method("setName", vec![string.clone()], None),
+            method("getType", Vec::new(), Some(string.clone())),
+            method("getHeight", Vec::new(), Some(ArgumentType::Float)),
+            method("height", Vec::new(), Some(ArgumentType::Float)),
+            method("getter", Vec::new(), Some(string)),
+        ];
+
+        let config = Generator::default();
+        assert_eq!(methods.iter().map(|x| x.rust_name.clone()).collect::<Vec<_>>(), rename_accessors(&methods, &config));
+
+        let config = Generator {
+            accessor_naming: AccessorNaming::Properties,
+            ..Generator::default()
+        };
+        assert_eq!(
+            vec!["width", "set_width", "enabled", "is_open", "set_name", "type_k", "get_height", "height", "getter"],
+            rename_accessors(&methods, &config)
+        );
+
+        let config = Generator {
+            accessor_naming: AccessorNaming::Builder,
+            ..Generator::default()
+        };
+        assert_eq!(
+            vec!["width", "set_width", "enabled", "is_open", "name", "type_k", "get_height", "height", "getter"],
+            rename_accessors(&methods, &config)
+        );
+    }
+
+    #[test]
+    fn kotlin_properties() {
+        let string = ArgumentType::Object("java.lang.String".to_string());
+        let methods = vec![
+            method("getName", Vec::new(), Some(string.clone())),
+            method("setName", vec![string.clone()], None),
+            method("isEnabled", Vec::new(), Some(ArgumentType::Boolean)),
+            method("getOther", Vec::new(), Some(string.clone())),
+            method("type", Vec::new(), Some(string.clone())),
+            method("getType", Vec::new(), Some(string)),
+        ];
+        let property = |name: &str, getter: &str, setter: Option<&str>| Property {
+            name: name.to_string(),
+            getter: getter.to_string(),
+            setter: setter.map(str::to_string),
+        };
+        let properties = [
+            property("name", "getName", Some("setName")),
+            property("isEnabled", "isEnabled", None),
+            property("type", "getType", None),
+        ];
+
+        assert_eq!(
+            vec!["name", "set_name", "is_enabled", "get_other", "type_k", "get_type"],
+            rename_properties(&methods, &properties, &Generator::default())
+        );
+    }
+
+    fn class(name: &str, simple_name: &str, methods: Vec<MethodEntry>, companion_field: Option<&str>, instance_field: Option<&str>) -> ClassEntry {
+        ClassEntry {
+            name: name.to_string(),
+            simple_name: simple_name.to_string(),
+            enclosing_class: None,
+            nested_classes: Vec::new(),
+            class_type: ClassType::Class,
+            visibility: Visibility::Public,
+            is_abstract: false,
+            deprecation: None,
+            methods,
+            interfaces: Vec::new(),
+            enum_constants: Vec::new(),
+            type_parameters: Vec::new(),
+            generic_superclass: None,
+            superclasses: vec!["java.lang.Object".to_string()],
+            permitted_subclasses: Vec::new(),
+            annotation_elements: Vec::new(),
+            is_inner_class: false,
+            constructors: Vec::new(),
+            instance_field: instance_field.map(str::to_string),
+            companion_field: companion_field.map(str::to_string),
+            properties: Vec::new(),
+            constants: Vec::new(),
+            fields: Vec::new(),
+            functional_method: None,
+            javadoc: None,
+        }
+    }
+
+    fn class_method(name: &str, is_static: bool, declaring_class: &str) -> MethodEntry {
+        MethodEntry {
+            name: name.to_string(),
+            is_static,
+            visibility: Visibility::Public,
+            is_abstract: false,
+            is_default: false,
+            deprecation: None,
+            argument_names: Vec::new(),
+            argument_nullability: Vec::new(),
+            arguments: Vec::new(),
+            return_type: Some(ArgumentType::Int),
+            declaring_class: declaring_class.to_string(),
+            type_parameters: Vec::new(),
+            generic_arguments: Vec::new(),
+            generic_return_type: None,
+            exceptions: Vec::new(),
+            return_nullability: Nullability::Unknown,
+            javadoc: None,
+        }
+    }
+
+    #[test]
+    fn kotlin_companion() {
+        let classes = vec![
+            class("com.k.Bar", "Bar", vec![class_method("shared", true, "com.k.Bar")], Some("Companion"), None),
+            class("com.k.Bar$Companion", "Companion", vec![
+                class_method("create", false, "com.k.Bar$Companion"),
+                class_method("shared", false, "com.k.Bar$Companion"),
+            ], None, None),
+        ];
+        let classes = format_classes(classes, &Generator::default());
+
+        let bar = classes.iter().find(|x| x.java_name == "com.k.Bar").unwrap();
+        assert_eq!(vec!["shared", "create"], bar.methods.iter().map(|x| x.rust_name.as_str()).collect::<Vec<_>>());
+        assert!(bar.methods[0].singleton.is_none());
+
+        let singleton = bar.methods[1].singleton.as_ref().unwrap();
+        assert_eq!(("com.k.Bar", "Companion", "Lcom/k/Bar$Companion;"), (singleton.class.as_str(), singleton.name.as_str(), singleton.jni_signature.as_str()));
+        assert_eq!("com.k.Bar$Companion", bar.methods[1].declaring_class_java);
+    }
+
+    #[test]
+    fn scala_object_methods() {
+        let classes = vec![
+            class("com.s.Foo$", "Foo$", vec![class_method("answer", false, "com.s.Foo$"), class_method("main", true, "com.s.Foo$")], None, Some("MODULE$")),
+        ];
+        let classes = format_classes(classes, &Generator::default());
+
+        let foo = &classes[0];
+        let instance_field = foo.instance_field.as_ref().unwrap();
+        assert_eq!(("com.s.Foo$", "MODULE$", "Lcom/s/Foo$;"), (instance_field.class.as_str(), instance_field.name.as_str(), instance_field.jni_signature.as_str()));
+        assert_eq!(Some("MODULE$"), foo.methods[0].singleton.as_ref().map(|x| x.name.as_str()));
+        assert!(foo.methods[1].singleton.is_none());
+    }
+
+    #[test]
+    fn argument_names_clashing() {
+        let input = vec![Some("aB".to_string()), Some("a_b".to_string())];
+        let output = format_argument_names(&input, &Generator::default());
+
+        assert_eq!(vec!["a_b0".to_string(), "a_b1".to_string()], output);
+    }
+
+    #[test]
+    fn javadoc() {
+        let javadoc = "\n     * Get the {@code Map<K, V>} of {@link java.util.List#size() sizes}.\n     *\n     *   Indented {@link Foo#bar}\n     ";
+        assert_eq!(
+            vec![
+                " Get the `Map<K, V>` of `sizes`.",
+                " ",
+                "   Indented `Foo.bar`",
+            ],
+            format_javadoc(javadoc)
+        );
+        assert_eq!(vec![" Single line"], format_javadoc(" Single line "));
+    }
+
+    #[test]
+    fn exception_variants() {
+        let input = vec![
+            "java.io.IOException".to_string(),
+            "com.foo.Exception".to_string(),
+            "java.lang.Exception".to_string(),
+            "java.io.IOException".to_string(),
+        ];
+        let output = rename_exception_variants(&input);
+
+        assert_eq!(vec![
+            ("com.foo.Exception".to_string(), "ComFooException".to_string()),
+            ("java.io.IOException".to_string(), "IoException".to_string()),
+            ("java.lang.Exception".to_string(), "JavaLangException".to_string()),
+        ], output);
+    }
+
+    #[test]
+    fn upcasts() {
+        let java_names = HashMap::from([
+            ("com.foo.PdfObject".to_string(), "com.foo.PdfObject".to_string()),
+            ("com.foo.bar_p.Baz".to_string(), "com.foo.Bar$Baz".to_string()),
+        ]);
+        let superclasses = ["com.foo.bar_p.Baz".to_string(), "com.foo.PdfObject".to_string(), "java.lang.Object".to_string()];
+        let upcasts = format_upcasts(&superclasses, &java_names);
+
+        assert_eq!(vec!["as_baz", "as_pdf_object"], upcasts.iter().map(|x| x.rust_name.as_str()).collect::<Vec<_>>());
+        assert_eq!("com.foo.Bar$Baz", upcasts[0].java_name);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,150 @@
+//! The mapping from Java names to the paths of the generated Rust items, written as `names.json` next to the generated code.
+//! Classes are keyed by their fully qualified name, members by their name, with the JNI signature for methods and
+//! constructors to tell overloads apart, e.g. `add(FF)V`. Constructors are named `<init>`, like in the class file
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use serde::Serialize;
+use crate::formatter::FormattedClassEntry;
+use crate::JResult;
+
+/// The generated Rust items of a class
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct ClassNames {
+    /// The path of the generated type, e.g. `com::foo::bar_p::Baz`
+    pub rust_path: String,
+    /// The paths of the generated members, keyed by their Java name
+    pub members: BTreeMap<String, String>,
+}
+
+impl ClassNames {
+    pub fn new(class: &FormattedClassEntry) -> Self {
+        let rust_path = class.name.replace('.', "::");
+        let member_path = |rust_name: &str| format!("{rust_path}::{rust_name}");
+
+        let mut members = BTreeMap::new();
+        for constructor in &class.constructors {
+            members.insert(format!("<init>{}", constructor.jni_signature), member_path(&constructor.rust_name));
+        }
+
+        for method in &class.methods {
+            members.insert(format!("{}{}", method.java_name, method.jni_signature), member_path(&method.rust_name));
+        }
+
+        for field in &class.fields {
+            members.insert(field.java_name.clone(), member_path(&field.rust_name));
+        }
+
+        for constant in &class.constants {
+            members.insert(constant.java_name.clone(), member_path(&constant.rust_name));
+        }
+
+        for constant in &class.enum_constants {
+            members.insert(constant.java_name.clone(), member_path(&constant.rust_name));
+        }
+
+        Self {
+            rust_path,
+            members,
+        }
+    }
+}
+
+/// Write the name mapping of the classes to `path` as JSON, sorted by Java name so the output is stable
+pub fn write_names(classes: &[FormattedClassEntry], path: &Path) -> JResult<()> {
+    let names = classes.iter()
+        .map(|x| (x.java_name.as_str(), ClassNames::new(x)))
+        .collect::<BTreeMap<_, _>>();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut json = serde_json::to_string_pretty(&names)?;
+    json.push('\n');
+    fs::write(path, json)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::class_tree::{ArgumentType, ClassEntry, ClassType, ConstructorEntry, FieldEntry, MethodEntry, Nullability, Visibility};
+    use crate::config::Generator;
+
+    #[test]
+    fn names() {
+        let method = |name: &str, arguments: Vec<ArgumentType>| MethodEntry {
+            name: name.to_string(),
+            is_static: false,
+            visibility: Visibility::Public,
+            is_abstract: false,
+            is_default: false,
+            deprecation: None,
+            argument_names: vec![None; arguments.len()],
+            argument_nullability: vec![Nullability::Unknown; arguments.len()],
+            arguments,
+            return_type: None,
+            declaring_class: "com.foo.Bar$Baz".to_string(),
+            type_parameters: Vec::new(),
+            generic_arguments: Vec::new(),
+            generic_return_type: None,
+            exceptions: Vec::new(),
+            return_nullability: Nullability::Unknown,
+            javadoc: None,
+        };
+
+        let class = ClassEntry {
+            name: "com.foo.Bar$Baz".to_string(),
+            simple_name: "Baz".to_string(),
+            enclosing_class: Some("com.foo.Bar".to_string()),
+            nested_classes: Vec::new(),
+            class_type: ClassType::Class,
+            visibility: Visibility::Public,
+            is_abstract: false,
+            deprecation: None,
+            methods: vec![method("add", vec![ArgumentType::Int]), method("add", vec![ArgumentType::Float])],
+            interfaces: Vec::new(),
+            enum_constants: Vec::new(),
+            type_parameters: Vec::new(),
+            generic_superclass: None,
+            superclasses: Vec::new(),
+            permitted_subclasses: Vec::new(),
+            annotation_elements: Vec::new(),
+            is_inner_class: false,
+            constructors: vec![ConstructorEntry {
+                visibility: Visibility::Public,
+                deprecation: None,
+                arguments: Vec::new(),
+                argument_names: Vec::new(),
+                generic_arguments: Vec::new(),
+                exceptions: Vec::new(),
+                argument_nullability: Vec::new(),
+            }],
+            instance_field: None,
+            companion_field: None,
+            properties: Vec::new(),
+            constants: Vec::new(),
+            fields: vec![FieldEntry {
+                name: "maxSize".to_string(),
+                is_static: true,
+                is_final: false,
+                visibility: Visibility::Public,
+                field_type: ArgumentType::Int,
+                generic_type: "int".to_string(),
+                deprecation: None,
+            }],
+            functional_method: None,
+            javadoc: None,
+        };
+
+        let class = FormattedClassEntry::new(class, &Generator::default());
+        let names = ClassNames::new(&class);
+        assert_eq!("com::foo::bar_p::Baz", names.rust_path);
+        assert_eq!(Some("com::foo::bar_p::Baz::new"), names.members.get("<init>()V").map(String::as_str));
+        assert_eq!(Some("com::foo::bar_p::Baz::add_int"), names.members.get("add(I)V").map(String::as_str));
+        assert_eq!(Some("com::foo::bar_p::Baz::add_float"), names.members.get("add(F)V").map(String::as_str));
+        assert_eq!(Some("com::foo::bar_p::Baz::max_size"), names.members.get("maxSize").map(String::as_str));
+    }
+}
@@ -0,0 +1,202 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use crate::class_tree::{ArgumentType, ClassType};
+use crate::config::Config;
+use crate::formatter::{escape_keywords, FormattedClassEntry, FormattedMethodEntry};
+use crate::generator::class::{class_ident, generate_type_parameters};
+use crate::generator::method::{generate_argument_idents, generate_rust_arguments, with_java_time_feature};
+
+/// Names of the builder's own functions, which setters are not chained as
+const RESERVED_NAMES: &[&str] = &["new", "build"];
+
+/// Generate `<Class>Builder` for a class with fluent setters, see `generator.builders`. It holds the result of the
+/// constructor and the setters called so far, so they can be chained without handling the error of each of them
+pub fn generate_builder(class: &FormattedClassEntry, config: &Config) -> TokenStream {
+    if !config.generator.builders || !matches!(class.class_type, ClassType::Class) {
+        return quote! {};
+    }
+
+    // The types `this` is returned as by fluent setters
+    let self_types = std::iter::once(&class.java_name)
+        .chain(&class.superclasses)
+        .chain(&class.interfaces)
+        .filter(|x| *x != "java.lang.Object")
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let type_variables = class.type_parameters.iter()
+        .map(|x| x.name.as_str())
+        .collect::<Vec<_>>();
+    let setters = class.methods.iter()
+        .filter_map(|method| builder_name(method, &self_types, &type_variables, config).map(|name| (method, name)))
+        .collect::<Vec<_>>();
+    if setters.is_empty() {
+        return quote! {};
+    }
+
+    let class_ident = class_ident(class);
+    let builder_ident = format_ident!("{}Builder", class_ident);
+    let type_parameters = generate_type_parameters(class, config);
+
+    let chained = setters.iter()
+        .map(|(method, name)| {
+            with_java_time_feature(method, config, |config| {
+                let name_ident = format_ident!("{}", name);
+                let setter_ident = format_ident!("{}", method.rust_name);
+                let arguments = generate_rust_arguments(method, config);
+                let argument_idents = generate_argument_idents(method);
+                let doc = format!(" Call [`{class_ident}::{}`], unless a previous call failed.", method.rust_name);
+
+                quote! {
+                    #[doc = #doc]
+                    pub fn #name_ident(self, #arguments) -> Self {
+                        Self {
+                            result: self.result.and_then(|obj| {
+                                obj.#setter_ident(#(#argument_idents),*)?;
+                                Ok(obj)
+                            }),
+                        }
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Only classes with a constructor without arguments can be built from scratch
+    let new = class.constructors.iter()
+        .find(|x| x.arguments.is_empty() && !class.is_abstract && !class.is_inner_class)
+        .map(|constructor| {
+            let constructor_ident = format_ident!("{}", constructor.rust_name);
+            let doc = format!(" Build a new object, created through [`{class_ident}::{}`].", constructor.rust_name);
+            if config.generator.implicit_env {
+                quote! {
+                    #[doc = #doc]
+                    pub fn new() -> Self {
+                        Self {
+                            result: #class_ident::#constructor_ident(),
+                        }
+                    }
+                }
+            } else {
+                quote! {
+                    #[doc = #doc]
+                    pub fn new(env: &'a jni::JNIEnv<'a>) -> Self {
+                        Self {
+                            result: #class_ident::#constructor_ident(env),
+                        }
+                    }
+                }
+            }
+        });
+
+    let doc = format!(" Builder chaining the setters of [`{class_ident}`], e.g. `{}`.", setters.iter()
+        .map(|(_, name)| format!(".{name}(..)"))
+        .take(2)
+        .collect::<String>());
+
+    quote! {
+        #[doc = #doc]
+        /// Once a setter fails the later ones are not called, and `build` returns its error.
+        /// Created through `new` or `From` an existing object
+        pub struct #builder_ident<'a, #(#type_parameters),*> {
+            result: crate::JResult<#class_ident<'a, #(#type_parameters),*>>,
+        }
+
+        impl<'a, #(#type_parameters),*> #builder_ident<'a, #(#type_parameters),*> {
+            #new
+
+            #(#chained)*
+
+            /// The object the setters were called on, or the first error
+            pub fn build(self) -> crate::JResult<#class_ident<'a, #(#type_parameters),*>> {
+                self.result
+            }
+        }
+
+        impl<'a, #(#type_parameters),*> From<#class_ident<'a, #(#type_parameters),*>> for #builder_ident<'a, #(#type_parameters),*> {
+            fn from(obj: #class_ident<'a, #(#type_parameters),*>) -> Self {
+                Self {
+                    result: Ok(obj),
+                }
+            }
+        }
+    }
+}
+
+/// The name a setter is chained as, e.g. `width` for `setWidth(float)`. Setters are instance methods with one argument,
+/// named `set` followed by an uppercase letter, that return nothing or `this`: one of `self_types`, or one of the
+/// `type_variables` of the class, like the `T` of iText's `ElementPropertyContainer<T>`, which is erased to its bound
+fn builder_name(method: &FormattedMethodEntry, self_types: &[&str], type_variables: &[&str], config: &Config) -> Option<String> {
+    let is_setter = !method.is_static && method.singleton.is_none() && method.arguments.len() == 1
+        && method.java_name.strip_prefix("set").is_some_and(|x| x.starts_with(char::is_uppercase));
+    let returns_self = match &method.return_type {
+        None => true,
+        Some(ArgumentType::Object(return_class)) => self_types.contains(&return_class.as_str())
+            || method.generic_return_type.as_deref().is_some_and(|x| type_variables.contains(&x)),
+        Some(_) => false,
+    };
+    if !is_setter || !returns_self {
+        return None;
+    }
+
+    // Renamed setters, e.g. with `accessor_naming = "builder"`, have no prefix left to strip
+    let name = method.rust_name.strip_prefix("set_").unwrap_or(&method.rust_name);
+    let name = escape_keywords(name, &config.generator).to_string();
+    (!RESERVED_NAMES.contains(&name.as_str())).then_some(name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::class_tree::Nullability;
+
+    fn setter(java_name: &str, rust_name: &str, return_type: Option<ArgumentType>) -> FormattedMethodEntry {
+        FormattedMethodEntry {
+            rust_name: rust_name.to_string(),
+            java_name: java_name.to_string(),
+            is_static: false,
+            is_abstract: false,
+            is_default: false,
+            deprecation: None,
+            arguments: vec![ArgumentType::Int],
+            argument_names: vec!["arg0".to_string()],
+            jni_signature: String::new(),
+            return_type,
+            declaring_class_rust: "com::foo::Bar".to_string(),
+            declaring_class_java: "com.foo.Bar".to_string(),
+            type_parameters: Vec::new(),
+            generic_arguments: vec!["int".to_string()],
+            generic_return_type: None,
+            exceptions: Vec::new(),
+            argument_nullability: vec![Nullability::Unknown],
+            return_nullability: Nullability::Unknown,
+            javadoc: Vec::new(),
+            singleton: None,
+        }
+    }
+
+    #[test]
+    fn builder_names() {
+        let config = Config::default();
+        let self_types = ["com.foo.Bar", "com.foo.Base", "com.foo.Sized"];
+        let builder_name = |method: &FormattedMethodEntry| builder_name(method, &self_types, &["T"], &config);
+
+        let object = |class_fq: &str| Some(ArgumentType::Object(class_fq.to_string()));
+        assert_eq!(Some("width".to_string()), builder_name(&setter("setWidth", "set_width", None)));
+        assert_eq!(Some("width".to_string()), builder_name(&setter("setWidth", "width", object("com.foo.Bar"))));
+        assert_eq!(Some("width".to_string()), builder_name(&setter("setWidth", "set_width", object("com.foo.Base"))));
+        assert_eq!(Some("width".to_string()), builder_name(&setter("setWidth", "set_width", object("com.foo.Sized"))));
+        assert_eq!(Some("type_k".to_string()), builder_name(&setter("setType", "set_type", None)));
+
+        // Not returning `this`, or not a setter
+        assert_eq!(None, builder_name(&setter("setWidth", "set_width", Some(ArgumentType::Boolean))));
+        assert_eq!(None, builder_name(&setter("setWidth", "set_width", object("java.lang.Object"))));
+        assert_eq!(None, builder_name(&setter("setWidth", "set_width", object("com.foo.Other"))));
+        assert_eq!(None, builder_name(&setter("setup", "setup", None)));
+        assert_eq!(None, builder_name(&setter("setBuild", "set_build", None)));
+
+        let mut generic = setter("setWidth", "set_width", object("com.foo.PropertyContainer"));
+        assert_eq!(None, builder_name(&generic));
+        generic.generic_return_type = Some("T".to_string());
+        assert_eq!(Some("width".to_string()), builder_name(&generic));
+    }
+}
@@ -1,85 +1,1086 @@
-use convert_case::{Case, Casing};
+use std::str::FromStr;
 use proc_macro2::{Ident, TokenStream};
 use quote::{format_ident, quote};
-use crate::class_tree::ClassEntry;
-use crate::formatter::rename;
+use crate::class_tree::{ArgumentType, ConstantValue};
+use crate::config::{Config, Inheritance};
+use crate::formatter::{format_generic_signature, format_type_parameters, generated_trait_path, generated_type_path, FormattedClassEntry, FormattedMethodEntry, INTERFACE_WRAPPER_SUFFIX};
+use crate::generator::{generate_deprecated, generate_javadoc};
+use crate::generator::method::{generate_argument_type, generate_free_function, generate_trait_method};
 
-pub fn generate_interface(class: &ClassEntry) -> (TokenStream, Ident) {
-    let name_ident = format_ident!("{}", class.name.split(".").last().unwrap());
+/// The public instance methods of `java.lang.Object` interfaces may declare, by name and JNI signature
+pub(super) const OBJECT_METHODS: &[(&str, &str)] = &[
+    ("equals", "(Ljava/lang/Object;)Z"),
+    ("hashCode", "()I"),
+    ("toString", "()Ljava/lang/String;"),
+];
+
+/// The identifier of the type generated for a class, the last component of its Rust name
+pub(super) fn class_ident(class: &FormattedClassEntry) -> Ident {
+    format_ident!("{}", class.name.rsplit('.').next().unwrap())
+}
+
+pub fn generate_interface(class: &FormattedClassEntry, config: &Config) -> (TokenStream, Ident) {
+    let name_ident = class_ident(class);
+
+    // Interfaces may redeclare the methods of `java.lang.Object`, which `JavaObject` provides
+    let instance_methods = class.methods.iter()
+        .filter(|x| !x.is_static && !OBJECT_METHODS.contains(&(x.java_name.as_str(), x.jni_signature.as_str())))
+        .collect::<Vec<_>>();
+    let trait_methods = instance_methods.iter()
+        .map(|x| generate_trait_method(x, config))
+        .collect::<Vec<_>>();
+    let implementation = generate_implementation(class, &instance_methods, config);
+
+    // Superinterfaces become supertraits, implementors provide the object through `JavaObject`
+    let supertraits = class.interfaces.iter()
+        .filter(|x| config.has_bindings(x))
+        .map(|x| generate_trait_path(x))
+        .collect::<Vec<_>>();
+
+    let static_methods = class.methods.iter()
+        .filter(|x| x.is_static)
+        .map(|x| generate_free_function(x, config))
+        .collect::<Vec<_>>();
+
+    let deprecated = generate_deprecated(&class.deprecation);
+    let constants = generate_constants(class);
+
+    let sealed_kind = generate_sealed_kind(class, &name_ident, config);
+    let javadoc = generate_javadoc(&class.javadoc);
+    let wrapper = generate_interface_wrapper(class, config);
 
     let tokens = quote! {
-        pub trait #name_ident {}
+        #sealed_kind
+
+        #(#constants)*
+
+        #javadoc
+        #deprecated
+        pub trait #name_ident<'a>: crate::JavaObject<'a> #(+ #supertraits<'a>)* {
+            #(#trait_methods)*
+        }
+
+        #wrapper
+
+        #implementation
+
+        #(#static_methods)*
     };
 
     (tokens, name_ident)
 }
 
-fn generate_interface_impl(name_ident: &Ident, interface: &Ident) -> TokenStream {
+/// Generate `<Name>Wrapper`, the struct objects of the interface are passed and returned as, as its trait is not a type.
+/// It implements the trait, and converts to the wrappers of the superinterfaces in the tree
+fn generate_interface_wrapper(class: &FormattedClassEntry, config: &Config) -> TokenStream {
+    let wrapper_ident = interface_wrapper_ident(class);
+    let fully_qualified_class_path = class.java_name.replace('.', "/");
+    let type_parameters = generate_type_parameters(class, config);
+    let deprecated = generate_deprecated(&class.deprecation);
+    let doc = format!(" An object implementing `{}`, its methods are those of the trait [`{}`].", class.java_name, class_ident(class));
+
+    let gen_struct = generate_struct(&wrapper_ident, &type_parameters, &deprecated, None);
+    let trait_impls = generate_struct_trait_impls(&wrapper_ident, &type_parameters, &fully_qualified_class_path, None);
+    let interface_impl = generate_interface_impl(&wrapper_ident, &type_parameters, &class.name);
+    let superinterfaces = class.interfaces.iter()
+        .filter(|x| config.has_bindings(x))
+        .map(|x| generate_interface_conversion(&wrapper_ident, &type_parameters, x, quote! { crate::FromRaw::from_raw(value.env, value.obj) }, config))
+        .collect::<Vec<_>>();
+
     quote! {
-        impl<'a> #interface for #name_ident<'a> {}
+        #[doc = #doc]
+        #gen_struct
+
+        #trait_impls
+
+        #interface_impl
+
+        #(#superinterfaces)*
     }
 }
 
-fn generate_struct(name_ident: &Ident) -> TokenStream {
+/// Generate the `<Name>Impl` trait, through which the interface is implemented in Rust. Its `into_java` creates a
+/// `java.lang.reflect.Proxy` calling into the implementation through the `jrsgen.NativeProxy` helper, which must be on
+/// the classpath with its natives registered by `crate::register_natives`. Only the abstract methods are implemented
+/// in Rust, default methods keep their Java implementation. Objects are passed as `JObject`, as the conversions only
+/// exist for the other direction. The superinterfaces' `<Name>Impl` traits become supertraits
+fn generate_implementation(class: &FormattedClassEntry, instance_methods: &[&FormattedMethodEntry], config: &Config) -> TokenStream {
+    let name_ident = format_ident!("{}Impl", class_ident(class));
+    let fully_qualified_class_path = class.java_name.replace('.', "/");
+
+    let supertraits = class.interfaces.iter()
+        .filter(|x| config.has_bindings(x))
+        .map(|x| TokenStream::from_str(&format!("{}Impl", generated_trait_path(x))).expect("Formatted class names are valid paths"))
+        .collect::<Vec<_>>();
+
+    let abstract_methods = instance_methods.iter()
+        .filter(|x| x.is_abstract && !x.is_default)
+        .collect::<Vec<_>>();
+
+    let declarations = abstract_methods.iter()
+        .map(|method| {
+            let name_ident = format_ident!("{}", method.rust_name);
+            let argument_idents = method.argument_names.iter().map(|x| format_ident!("{}", x));
+            let argument_types = method.arguments.iter().map(|x| match primitive_wrapper(x) {
+                Some(_) => generate_argument_type(x, config),
+                None => quote! { jni::objects::JObject<'a> },
+            });
+            let return_type = match &method.return_type {
+                Some(x) if primitive_wrapper(x).is_some() => generate_argument_type(x, config),
+                Some(_) => quote! { jni::objects::JObject<'a> },
+                None => quote! { () },
+            };
+            let signature = format_generic_signature(&method.java_name, &method.type_parameters, &method.generic_arguments, method.generic_return_type.as_deref());
+            let doc = format!(" Java: `{signature}`");
+
+            quote! {
+                #[doc = #doc]
+                fn #name_ident<'a>(&self, env: &'a jni::JNIEnv<'a>, #(#argument_idents: #argument_types),*) -> crate::JResult<#return_type>;
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let dispatch_arms = abstract_methods.iter()
+        .map(|method| {
+            let name_ident = format_ident!("{}", method.rust_name);
+            let java_name = &method.java_name;
+            let signature = &method.jni_signature;
+            let arguments = method.arguments.iter().enumerate()
+                .map(|(idx, argument)| match primitive_wrapper(argument) {
+                    Some((_, descriptor, getter)) => {
+                        let signature = format!("(){descriptor}");
+                        let from_jvalue = match argument {
+                            ArgumentType::Boolean => quote! { .z()? },
+                            // Bytes are unsigned in Rust
+                            ArgumentType::Byte => quote! { .b()? as u8 },
+                            ArgumentType::Char => quote! { .c()? },
+                            ArgumentType::Short => quote! { .s()? },
+                            ArgumentType::Int => quote! { .i()? },
+                            ArgumentType::Long => quote! { .j()? },
+                            ArgumentType::Float => quote! { .f()? },
+                            _ => quote! { .d()? },
+                        };
+                        quote! { crate::unbox(env, args[#idx], #getter, #signature)?#from_jvalue }
+                    },
+                    None => quote! { args[#idx] },
+                })
+                .collect::<Vec<_>>();
+
+            let result = match method.return_type.as_ref().map(|x| (x, primitive_wrapper(x))) {
+                Some((return_type, Some((wrapper, descriptor, _)))) => {
+                    let signature = format!("({descriptor})L{wrapper};");
+                    let to_jvalue = match return_type {
+                        ArgumentType::Boolean => quote! { jni::objects::JValue::Bool(value as u8) },
+                        ArgumentType::Byte => quote! { jni::objects::JValue::Byte(value as i8) },
+                        ArgumentType::Char => quote! { jni::objects::JValue::Char(value) },
+                        ArgumentType::Short => quote! { jni::objects::JValue::Short(value) },
+                        ArgumentType::Int => quote! { jni::objects::JValue::Int(value) },
+                        ArgumentType::Long => quote! { jni::objects::JValue::Long(value) },
+                        ArgumentType::Float => quote! { jni::objects::JValue::Float(value) },
+                        _ => quote! { jni::objects::JValue::Double(value) },
+                    };
+                    quote! { crate::box_primitive(env, #wrapper, #signature, #to_jvalue) }
+                },
+                Some((_, None)) => quote! { Ok(value) },
+                None => quote! { Ok(jni::objects::JObject::null()) },
+            };
+
+            quote! {
+                (#java_name, #signature) => Some((|| -> crate::JResult<jni::objects::JObject<'a>> {
+                    let value = self.#name_ident(env, #(#arguments),*)?;
+                    #result
+                })()),
+            }
+        })
+        .collect::<Vec<_>>();
+
     quote! {
+        /// Implement the interface in Rust. `into_java` creates the Java object calling into the implementation
+        pub trait #name_ident: Send + Sync + 'static #(+ #supertraits)* {
+            #(#declarations)*
+
+            /// Call the implementation of a method invoked from Java, given its name and JNI signature.
+            /// `None` if the method is not one of this interface
+            #[doc(hidden)]
+            #[allow(unused_variables)]
+            fn dispatch_java<'a>(&self, env: &'a jni::JNIEnv<'a>, name: &str, signature: &str, args: &[jni::objects::JObject<'a>]) -> Option<crate::JResult<jni::objects::JObject<'a>>> {
+                match (name, signature) {
+                    #(#dispatch_arms)*
+                    _ => None #(.or_else(|| <Self as #supertraits>::dispatch_java(self, env, name, signature, args)))*,
+                }
+            }
+
+            /// Create a Java object implementing the interface by calling into this implementation
+            fn into_java<'a>(self, env: &'a jni::JNIEnv<'a>) -> crate::JResult<crate::Object<'a>>
+            where
+                Self: Sized,
+            {
+                let handler: crate::ProxyHandler = Box::new(move |env, name, signature, args| {
+                    <Self as #name_ident>::dispatch_java(&self, env, name, signature, &args)
+                        .unwrap_or_else(|| Err(crate::Error::Conversion(format!("{}{} is not implemented in Rust", name, signature))))
+                });
+                static CLASS: crate::ClassRef = crate::ClassRef::new(#fully_qualified_class_path);
+                let obj = crate::new_proxy(env, #fully_qualified_class_path, handler)?;
+                Ok(crate::Object::new(env, obj, CLASS.get(env)?))
+            }
+        }
+    }
+}
+
+/// The wrapper class in the notation of JNI, descriptor and unboxing getter of a primitive, `None` for objects
+fn primitive_wrapper(argument_type: &ArgumentType) -> Option<(&'static str, &'static str, &'static str)> {
+    match argument_type {
+        ArgumentType::Boolean => Some(("java/lang/Boolean", "Z", "booleanValue")),
+        ArgumentType::Byte => Some(("java/lang/Byte", "B", "byteValue")),
+        ArgumentType::Char => Some(("java/lang/Character", "C", "charValue")),
+        ArgumentType::Short => Some(("java/lang/Short", "S", "shortValue")),
+        ArgumentType::Int => Some(("java/lang/Integer", "I", "intValue")),
+        ArgumentType::Long => Some(("java/lang/Long", "J", "longValue")),
+        ArgumentType::Float => Some(("java/lang/Float", "F", "floatValue")),
+        ArgumentType::Double => Some(("java/lang/Double", "D", "doubleValue")),
+        ArgumentType::Object(_) | ArgumentType::Array(_) => None,
+    }
+}
+
+/// Generate a `pub const` for every compile-time constant of the class, next to the type in its module
+pub(super) fn generate_constants(class: &FormattedClassEntry) -> Vec<TokenStream> {
+    class.constants.iter()
+        .map(|constant| {
+            let ident = format_ident!("{}", constant.rust_name);
+            let doc = format!(" `{}.{}`", class.java_name, constant.java_name);
+            let deprecated = generate_deprecated(&constant.deprecation);
+
+            let (rust_type, value) = match &constant.value {
+                ConstantValue::Boolean(value) => (quote!(bool), quote!(#value)),
+                // Java bytes are bound as `u8`
+                ConstantValue::Byte(value) => {
+                    let value = *value as u8;
+                    (quote!(u8), quote!(#value))
+                },
+                ConstantValue::Char(value) => (quote!(u16), quote!(#value)),
+                ConstantValue::Short(value) => (quote!(i16), quote!(#value)),
+                ConstantValue::Int(value) => (quote!(i32), quote!(#value)),
+                ConstantValue::Long(value) => (quote!(i64), quote!(#value)),
+                ConstantValue::Float(value) => (quote!(f32), generate_float(*value as f64, quote!(f32))),
+                ConstantValue::Double(value) => (quote!(f64), generate_float(*value, quote!(f64))),
+                ConstantValue::String(value) => (quote!(&str), quote!(#value)),
+            };
+
+            quote! {
+                #[doc = #doc]
+                #deprecated
+                pub const #ident: #rust_type = #value;
+            }
+        })
+        .collect()
+}
+
+/// Generate a float literal. Literals cannot be infinite or NaN, these use the associated constants of `float_type` instead
+fn generate_float(value: f64, float_type: TokenStream) -> TokenStream {
+    if value.is_nan() {
+        quote!(#float_type::NAN)
+    } else if value == f64::INFINITY {
+        quote!(#float_type::INFINITY)
+    } else if value == f64::NEG_INFINITY {
+        quote!(#float_type::NEG_INFINITY)
+    } else {
+        let literal = proc_macro2::Literal::f64_unsuffixed(value);
+        quote!(#literal)
+    }
+}
+
+pub fn generate_enum(class: &FormattedClassEntry) -> (TokenStream, Ident) {
+    let name_ident = class_ident(class);
+    let fully_qualified_class_path = class.java_name.replace('.', "/");
+    let field_signature = format!("L{};", fully_qualified_class_path);
+    let enum_type = generate_enum_type(class, &name_ident);
+
+    let tokens = quote! {
+        #enum_type
+
+        impl #name_ident {
+            /// Get the Java object of this constant. Enum constants are not tied to a JNIEnv,
+            /// so this is used instead of `Into<jni::objects::JValue>`
+            pub fn into_jvalue<'a>(self, env: &'a jni::JNIEnv<'a>) -> crate::JResult<jni::objects::JValue<'a>> {
+                let value = crate::catch_exception(env, env.get_static_field(#fully_qualified_class_path, self.name(), #field_signature))?;
+                Ok(value)
+            }
+        }
+
+        impl<'a> crate::JavaConvert<'a> for #name_ident {
+            /// Convert a constant. Fails if it is unknown, e.g. because it was added after the bindings were generated
+            fn from_java(env: &'a jni::JNIEnv<'a>, obj: jni::objects::JObject<'a>) -> crate::JResult<Self> {
+                let ordinal = crate::catch_exception(env, env.call_method(obj, "ordinal", "()I", &[]))?.i()?;
+                Self::from_ordinal(ordinal)
+                    .ok_or_else(|| crate::Error::Conversion(format!("{} is not a known ordinal of {}", ordinal, #fully_qualified_class_path)))
+            }
+
+            fn to_java(&self, env: &'a jni::JNIEnv<'a>) -> crate::JResult<jni::objects::JObject<'a>> {
+                Ok(self.into_jvalue(env)?.l()?)
+            }
+        }
+    };
+
+    (tokens, name_ident)
+}
+
+/// Generate the Rust enum of an enum class, with its conversions from and to the names and ordinals of the constants,
+/// and its `crate::ClassName` impl. These do not depend on the `jni` version
+pub(super) fn generate_enum_type(class: &FormattedClassEntry, name_ident: &Ident) -> TokenStream {
+    let fully_qualified_class_path = class.java_name.replace('.', "/");
+
+    let variants = class.enum_constants.iter()
+        .map(|x| format_ident!("{}", x.rust_name))
+        .collect::<Vec<_>>();
+    let java_names = class.enum_constants.iter()
+        .map(|x| x.java_name.as_str())
+        .collect::<Vec<_>>();
+    let ordinals = class.enum_constants.iter()
+        .map(|x| x.ordinal)
+        .collect::<Vec<_>>();
+
+    let deprecated = generate_deprecated(&class.deprecation);
+    let javadoc = generate_javadoc(&class.javadoc);
+
+    quote! {
+        #javadoc
+        #deprecated
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum #name_ident {
+            #(#variants),*
+        }
+
+        impl #name_ident {
+            /// The value Java's `Enum.ordinal()` returns for this constant
+            pub fn ordinal(&self) -> i32 {
+                match self {
+                    #(Self::#variants => #ordinals),*
+                }
+            }
+
+            /// The name of the constant in Java
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(Self::#variants => #java_names),*
+                }
+            }
+
+            /// Get the constant with the provided Java name, like Java's `valueOf`
+            pub fn value_of(name: &str) -> Option<Self> {
+                match name {
+                    #(#java_names => Some(Self::#variants),)*
+                    _ => None,
+                }
+            }
+
+            /// Get the constant with the provided ordinal
+            pub fn from_ordinal(ordinal: i32) -> Option<Self> {
+                match ordinal {
+                    #(#ordinals => Some(Self::#variants),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl crate::ClassName for #name_ident {
+            fn class_name() -> &'static str {
+                #fully_qualified_class_path
+            }
+
+            fn class_ref() -> &'static crate::ClassRef {
+                static CLASS: crate::ClassRef = crate::ClassRef::new(#fully_qualified_class_path);
+                &CLASS
+            }
+        }
+    }
+}
+
+/// Generate an enum of the permitted subclasses of a sealed class or interface, named `<Name>Kind`,
+/// with a `classify` function to find out which subclass an object is
+pub fn generate_sealed_kind(class: &FormattedClassEntry, name_ident: &Ident, config: &Config) -> TokenStream {
+    // The permitted subclasses are formatted already, e.g. `com.foo.shape_p.Circle`
+    let kind_ident = format_ident!("{}Kind", name_ident);
+    let ((variants, types), values): ((Vec<_>, Vec<_>), Vec<_>) = class.permitted_subclasses.iter()
+        .filter(|x| config.has_bindings(x))
+        .map(|subclass| {
+            let variant = format_ident!("{}", subclass.rsplit('.').next().unwrap());
+            let path = generate_bindings_path(subclass, config);
+            // Enum constants are converted to the Rust enum, which has no lifetime
+            if config.is_enum(subclass) {
+                ((variant, path.clone()), quote! { <#path as crate::JavaConvert>::from_java(env, obj.inner)? })
+            } else {
+                ((variant, quote! { #path<'a> }), quote! { crate::FromRaw::from_raw(env, obj) })
+            }
+        })
+        .unzip();
+
+    // Subclasses outside of the tree cannot be told apart from other objects
+    if variants.is_empty() {
+        return quote! {};
+    }
+
+    quote! {
+        /// The subclasses permitted by the sealed Java type
+        pub enum #kind_ident<'a> {
+            #(#variants(#types),)*
+        }
+
+        impl<'a> #kind_ident<'a> {
+            /// Find out which permitted subclass `obj` is an instance of.
+            /// Returns `None` if it is not an instance of any of them, e.g. if it is `null`
+            pub fn classify(env: &'a jni::JNIEnv<'a>, obj: crate::Object<'a>) -> crate::JResult<Option<Self>> {
+                #(
+                    if crate::catch_exception(env, env.is_instance_of(obj.inner, <#types as crate::ClassName>::class_name()))? {
+                        return Ok(Some(Self::#variants(#values)));
+                    }
+                )*
+
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Generate a builder for an annotation type, named `<Name>Builder`. Annotation instances are created
+/// through `java.lang.reflect.Proxy` by the `jrsgen.AnnotationProxy` helper, which must be on the classpath.
+/// Its class file is written to `output/java` alongside the bindings
+pub fn generate_annotation(class: &FormattedClassEntry, config: &Config) -> (TokenStream, Ident) {
+    let name_ident = format_ident!("{}Builder", class_ident(class));
+    let fully_qualified_class_path = class.java_name.replace('.', "/");
+
+    let setters = class.annotation_elements.iter()
+        .map(|element| {
+            let java_name = &element.java_name;
+            let setter_ident = format_ident!("{}", element.rust_name);
+            let doc = match &element.default_value {
+                Some(default_value) => format!(" Set `{java_name}`. Defaults to `{default_value}`"),
+                None => format!(" Set `{java_name}`. This element has no default and must be set"),
+            };
+
+            // Strings are converted to Java strings when the annotation is built, where creating them may fail
+            if element.element_type.is_string(config) {
+                return quote! {
+                    #[doc = #doc]
+                    pub fn #setter_ident(mut self, value: impl AsRef<str>) -> Self {
+                        self.strings.push((#java_name, value.as_ref().to_string()));
+                        self
+                    }
+                };
+            }
+
+            let ty = match &element.element_type {
+                ArgumentType::Object(_) | ArgumentType::Array(_) => quote! { crate::Object<'a> },
+                primitive => generate_argument_type(primitive, config),
+            };
+            let to_jvalue = match &element.element_type {
+                ArgumentType::Boolean => quote! { jni::objects::JValue::Bool(value as u8) },
+                ArgumentType::Byte => quote! { jni::objects::JValue::Byte(value as i8) },
+                _ => quote! { value.into() },
+            };
+
+            quote! {
+                #[doc = #doc]
+                pub fn #setter_ident(mut self, value: #ty) -> Self {
+                    self.values.push((#java_name, #to_jvalue));
+                    self
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let tokens = quote! {
+        /// Builder for instances of the annotation type
         pub struct #name_ident<'a> {
             env: &'a jni::JNIEnv<'a>,
-            obj: ejni::Object<'a>,
+            values: Vec<(&'static str, jni::objects::JValue<'a>)>,
+            strings: Vec<(&'static str, String)>,
+        }
+
+        impl<'a> #name_ident<'a> {
+            pub fn new(env: &'a jni::JNIEnv<'a>) -> Self {
+                Self {
+                    env,
+                    values: Vec::new(),
+                    strings: Vec::new(),
+                }
+            }
+
+            #(#setters)*
+
+            /// Create the annotation instance. Elements that were not set use their default value
+            pub fn build(self) -> crate::JResult<crate::Object<'a>> {
+                let env = self.env;
+                // Any of the calls may throw, e.g. if an element without a default value was not set
+                let build = || -> jni::errors::Result<crate::Object<'a>> {
+                    let map = env.new_object("java/util/HashMap", "()V", &[])?;
+                    let strings = self.strings.into_iter()
+                        .map(|(name, value)| Ok((name, jni::objects::JValue::Object(env.new_string(value)?.into()))))
+                        .collect::<jni::errors::Result<Vec<_>>>()?;
+
+                    for (name, value) in self.values.into_iter().chain(strings) {
+                        // Primitives are stored boxed
+                        let value = match value {
+                            jni::objects::JValue::Object(obj) => obj,
+                            jni::objects::JValue::Bool(_) => env.call_static_method("java/lang/Boolean", "valueOf", "(Z)Ljava/lang/Boolean;", &[value])?.l()?,
+                            jni::objects::JValue::Byte(_) => env.call_static_method("java/lang/Byte", "valueOf", "(B)Ljava/lang/Byte;", &[value])?.l()?,
+                            jni::objects::JValue::Char(_) => env.call_static_method("java/lang/Character", "valueOf", "(C)Ljava/lang/Character;", &[value])?.l()?,
+                            jni::objects::JValue::Short(_) => env.call_static_method("java/lang/Short", "valueOf", "(S)Ljava/lang/Short;", &[value])?.l()?,
+                            jni::objects::JValue::Int(_) => env.call_static_method("java/lang/Integer", "valueOf", "(I)Ljava/lang/Integer;", &[value])?.l()?,
+                            jni::objects::JValue::Long(_) => env.call_static_method("java/lang/Long", "valueOf", "(J)Ljava/lang/Long;", &[value])?.l()?,
+                            jni::objects::JValue::Float(_) => env.call_static_method("java/lang/Float", "valueOf", "(F)Ljava/lang/Float;", &[value])?.l()?,
+                            jni::objects::JValue::Double(_) => env.call_static_method("java/lang/Double", "valueOf", "(D)Ljava/lang/Double;", &[value])?.l()?,
+                            jni::objects::JValue::Void => unreachable!(),
+                        };
+
+                        let key = env.new_string(name)?;
+                        env.call_method(map, "put", "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;", &[key.into(), value.into()])?;
+                    }
+
+                    let class = env.find_class(#fully_qualified_class_path)?;
+                    let annotation = env.call_static_method(
+                        "jrsgen/AnnotationProxy",
+                        "create",
+                        "(Ljava/lang/Class;Ljava/util/Map;)Ljava/lang/annotation/Annotation;",
+                        &[jni::objects::JValue::Object(class.into()), jni::objects::JValue::Object(map)]
+                    )?.l()?;
+
+                    Ok(crate::Object::new(env, annotation, crate::Class::new(env, class)))
+                };
+
+                crate::catch_exception(env, build())
+            }
+        }
+    };
+
+    (tokens, name_ident)
+}
+
+/// The path of a formatted class in the generated crate, e.g. `crate::bindings::com::foo::Bar` for `com.foo.Bar`
+pub(super) fn generate_bindings_path(formatted_name: &str, config: &Config) -> TokenStream {
+    TokenStream::from_str(&generated_type_path(formatted_name, config)).expect("Formatted class names are valid paths")
+}
+
+pub(super) fn generate_trait_path(formatted_name: &str) -> TokenStream {
+    TokenStream::from_str(&generated_trait_path(formatted_name)).expect("Formatted class names are valid paths")
+}
+
+/// The name of the struct wrapping the objects of an interface, e.g. `ShapeWrapper`
+pub(super) fn interface_wrapper_ident(class: &FormattedClassEntry) -> Ident {
+    format_ident!("{}{INTERFACE_WRAPPER_SUFFIX}", class_ident(class))
+}
+
+pub(super) fn generate_interface_impl(name_ident: &Ident, type_parameters: &[Ident], interface: &str) -> TokenStream {
+    let interface = generate_trait_path(interface);
+    quote! {
+        impl<'a, #(#type_parameters),*> #interface<'a> for #name_ident<'a, #(#type_parameters),*> {}
+    }
+}
+
+/// Implement the trait of an interface for a class, and `From` the class for the struct wrapping the interface's objects.
+/// `into_wrapper` converts the class, `value`, to the wrapper
+pub(super) fn generate_interface_conversion(name_ident: &Ident, type_parameters: &[Ident], interface: &str, into_wrapper: TokenStream, config: &Config) -> TokenStream {
+    let interface_impl = generate_interface_impl(name_ident, type_parameters, interface);
+    let wrapper = generate_bindings_path(interface, config);
+    quote! {
+        #interface_impl
+
+        impl<'a, #(#type_parameters),*> From<#name_ident<'a, #(#type_parameters),*>> for #wrapper<'a> {
+            fn from(value: #name_ident<'a, #(#type_parameters),*>) -> Self {
+                #into_wrapper
+            }
+        }
+    }
+}
+
+/// Implement `PartialEq`, `Eq` and `Hash` through `equals` and `hashCode`, which Java requires to be consistent.
+/// If the class is `comparable`, implement `PartialOrd` and `Ord` through `compareTo` as well.
+/// These panic if the Java methods throw
+fn generate_equality(name_ident: &Ident, type_parameters: &[Ident], comparable: bool) -> TokenStream {
+    let ordering = if comparable {
+        quote! {
+            /// Ordered through Java's `compareTo`, which may be inconsistent with `equals`, e.g. for `BigDecimal`.
+            /// Panics if it throws
+            impl<'a, #(#type_parameters),*> Ord for #name_ident<'a, #(#type_parameters),*> {
+                fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                    crate::java_compare(self.env, self.obj.inner, other.obj.inner)
+                }
+            }
+
+            impl<'a, #(#type_parameters),*> PartialOrd for #name_ident<'a, #(#type_parameters),*> {
+                fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                    Some(self.cmp(other))
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        #ordering
+
+        /// Compared through Java's `equals`. Panics if it throws
+        impl<'a, #(#type_parameters),*> PartialEq for #name_ident<'a, #(#type_parameters),*> {
+            fn eq(&self, other: &Self) -> bool {
+                crate::java_equals(self.env, self.obj.inner, other.obj.inner)
+            }
+        }
+
+        impl<'a, #(#type_parameters),*> Eq for #name_ident<'a, #(#type_parameters),*> {}
+
+        /// Hashed through Java's `hashCode`. Panics if it throws
+        impl<'a, #(#type_parameters),*> std::hash::Hash for #name_ident<'a, #(#type_parameters),*> {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                state.write_i32(crate::java_hash_code(self.env, self.obj.inner));
+            }
+        }
+    }
+}
+
+/// Whether the class implements one of the `names`, or extends one of them. Its `interfaces` include those
+/// inherited from its superclasses and superinterfaces in the tree, which `resolve_interfaces` added.
+/// The interfaces of superclasses outside of the tree are not known, so those superclasses are matched by name
+fn implements(class: &FormattedClassEntry, names: &[&str]) -> bool {
+    class.interfaces.iter()
+        .chain(class.superclasses.iter())
+        .any(|x| names.contains(&x.as_str()))
+}
+
+/// The interfaces and classes from outside of the tree whose implementors and subclasses are a `java.lang.Iterable`.
+/// The bytecode backend ends the superclass chain at the first class outside of the classpath,
+/// so the commonly extended collections are listed besides their abstract bases
+const ITERABLES: &[&str] = &[
+    "java.lang.Iterable",
+    "java.util.Collection",
+    "java.util.List",
+    "java.util.Set",
+    "java.util.SortedSet",
+    "java.util.NavigableSet",
+    "java.util.Queue",
+    "java.util.Deque",
+    "java.util.AbstractCollection",
+    "java.util.AbstractList",
+    "java.util.AbstractSequentialList",
+    "java.util.AbstractSet",
+    "java.util.AbstractQueue",
+    "java.util.ArrayList",
+    "java.util.LinkedList",
+    "java.util.Vector",
+    "java.util.HashSet",
+    "java.util.LinkedHashSet",
+    "java.util.TreeSet",
+    "java.util.ArrayDeque",
+    "java.util.PriorityQueue",
+];
+
+/// The interfaces from outside of the tree whose implementors are a `java.util.Iterator`
+const ITERATORS: &[&str] = &[
+    "java.util.Iterator",
+    "java.util.ListIterator",
+];
+
+/// Generate an `iter` method for classes implementing `java.lang.Iterable` or `java.util.Iterator`, returning
+/// a Rust `Iterator` over the elements. The element type is erased, so the caller picks it.
+/// The method is left out if the class has a method of the same name
+fn generate_iteration(class: &FormattedClassEntry, name_ident: &Ident, type_parameters: &[Ident]) -> TokenStream {
+    let (iterator, doc) = if implements(class, ITERABLES) {
+        (
+            quote! { crate::catch_exception(self.env, self.env.call_method(self.obj.inner, "iterator", "()Ljava/util/Iterator;", &[]))?.l()? },
+            " Iterate over the elements through a new `java.util.Iterator`, wrapping them as `T`",
+        )
+    } else if implements(class, ITERATORS) {
+        (
+            quote! { self.obj.inner },
+            " Iterate over the remaining elements, wrapping them as `T`. This advances the Java iterator",
+        )
+    } else {
+        return quote! {};
+    };
+
+    if class.methods.iter().any(|x| x.rust_name == "iter") {
+        return quote! {};
+    }
+
+    quote! {
+        impl<'a, #(#type_parameters),*> #name_ident<'a, #(#type_parameters),*> {
+            #[doc = #doc]
+            pub fn iter<T: crate::ClassName + crate::FromRaw<'a>>(&self) -> crate::JResult<crate::JavaIter<'a, T>> {
+                crate::JavaIter::new(self.env, #iterator)
+            }
+        }
+    }
+}
+
+/// Generate `<Name>Global`, a handle to the object owning a global reference. Unlike the wrapper it is not tied to
+/// a `JNIEnv`, so it is `Send + Sync` and can be stored. `to_global` creates it, `attach` gets the wrapper back.
+/// `to_global` is left out if the class has a method of the same name
+fn generate_global(class: &FormattedClassEntry, name_ident: &Ident, type_parameters: &[Ident], deprecated: &TokenStream, config: &Config) -> TokenStream {
+    let global_ident = format_ident!("{}Global", name_ident);
+    let doc = format!(" A handle to a `{}` not tied to a `JNIEnv`, so it can be sent to other threads and stored.", class.java_name);
+
+    let to_global = if class.methods.iter().any(|x| x.rust_name == "to_global") {
+        quote! {}
+    } else {
+        let doc = format!(" Create a handle to the object not tied to the `JNIEnv`, see [`{global_ident}`]");
+        quote! {
+            impl<'a, #(#type_parameters),*> #name_ident<'a, #(#type_parameters),*> {
+                #[doc = #doc]
+                pub fn to_global(&self) -> crate::JResult<#global_ident<#(#type_parameters),*>> {
+                    Ok(#global_ident {
+                        obj: crate::catch_exception(self.env, self.env.new_global_ref(self.obj.inner))?,
+                        _marker: std::marker::PhantomData,
+                    })
+                }
+            }
+        }
+    };
+
+    let attach = if config.generator.implicit_env {
+        quote! {
+            /// Get the wrapper for use on the current thread, see `crate::env`
+            pub fn attach<'a>(&self) -> crate::JResult<#name_ident<'a, #(#type_parameters),*>> {
+                crate::attach_global(crate::env()?, &self.obj)
+            }
+        }
+    } else {
+        quote! {
+            /// Get the wrapper for use with `env`, which must belong to the current thread
+            pub fn attach<'a>(&self, env: &'a jni::JNIEnv<'a>) -> crate::JResult<#name_ident<'a, #(#type_parameters),*>> {
+                crate::attach_global(env, &self.obj)
+            }
+        }
+    };
+
+    quote! {
+        #[doc = #doc]
+        /// The object is kept alive until the handle is dropped. `attach` gets the wrapper back
+        #deprecated
+        pub struct #global_ident<#(#type_parameters),*> {
+            obj: jni::objects::GlobalRef,
+            // Only marks the Java type parameters, so the handle is `Send + Sync` regardless of them
+            _marker: std::marker::PhantomData<fn() -> (#(#type_parameters,)*)>,
+        }
+
+        impl<#(#type_parameters),*> #global_ident<#(#type_parameters),*> {
+            #attach
+
+            /// The global reference to the object
+            pub fn as_global_ref(&self) -> &jni::objects::GlobalRef {
+                &self.obj
+            }
+        }
+
+        /// Clones share the global reference, which is deleted when the last of them is dropped
+        impl<#(#type_parameters),*> Clone for #global_ident<#(#type_parameters),*> {
+            fn clone(&self) -> Self {
+                Self {
+                    obj: self.obj.clone(),
+                    _marker: std::marker::PhantomData,
+                }
+            }
+        }
+
+        #to_global
+    }
+}
+
+/// The interfaces and classes from outside of the tree whose implementors and subclasses are a `java.lang.AutoCloseable`.
+/// Like for `ITERABLES`, the commonly extended streams are listed besides their abstract bases
+const CLOSEABLES: &[&str] = &[
+    "java.lang.AutoCloseable",
+    "java.io.Closeable",
+    "java.nio.channels.Channel",
+    "java.io.InputStream",
+    "java.io.OutputStream",
+    "java.io.Reader",
+    "java.io.Writer",
+    "java.io.FilterInputStream",
+    "java.io.FilterOutputStream",
+    "java.io.FilterReader",
+    "java.io.FilterWriter",
+    "java.io.BufferedInputStream",
+    "java.io.BufferedOutputStream",
+    "java.io.BufferedReader",
+    "java.io.BufferedWriter",
+    "java.io.PrintStream",
+    "java.io.PrintWriter",
+];
+
+/// Whether the class implements `java.lang.AutoCloseable`, directly or through a superclass
+pub fn is_closeable(class: &FormattedClassEntry) -> bool {
+    implements(class, CLOSEABLES)
+}
+
+/// Whether the method is `close()`, generated as the consuming `close` for closeable classes
+pub fn is_close_method(method: &FormattedMethodEntry) -> bool {
+    !method.is_static && method.java_name == "close" && method.jni_signature == "()V"
+}
+
+/// Generate a consuming `close`, as the object may not be used after being closed,
+/// and `close_on_drop` for a guard closing it at the end of its scope
+fn generate_closing(class: &FormattedClassEntry, name_ident: &Ident, type_parameters: &[Ident]) -> TokenStream {
+    if !is_closeable(class) {
+        return quote! {};
+    }
+
+    let close_on_drop = if class.methods.iter().any(|x| x.rust_name == "close_on_drop") {
+        quote! {}
+    } else {
+        quote! {
+            /// Close the object when the returned guard is dropped. Failures are logged
+            pub fn close_on_drop(self) -> crate::CloseGuard<'a, Self> {
+                crate::CloseGuard::new(self)
+            }
+        }
+    };
+
+    quote! {
+        impl<'a, #(#type_parameters),*> #name_ident<'a, #(#type_parameters),*> {
+            /// Java: `void close()`. Consumes the object, as it may not be used after being closed
+            pub fn close(self) -> crate::JResult<()> {
+                crate::close(self.env, self.obj.inner)
+            }
+
+            #close_on_drop
+        }
+    }
+}
+
+/// Generate an `as_<superclass>` method and a `From` impl for every superclass in the tree, so instances can be
+/// passed where a superclass is expected. The method is left out if the class has a method of the same name
+fn generate_upcasts(class: &FormattedClassEntry, name_ident: &Ident, type_parameters: &[Ident], config: &Config) -> TokenStream {
+    if class.upcasts.is_empty() {
+        return quote! {};
+    }
+
+    let (methods, impls): (Vec<_>, Vec<_>) = class.upcasts.iter()
+        .map(|upcast| {
+            let superclass = generate_bindings_path(&upcast.class, config);
+            let method = if class.methods.iter().any(|x| x.rust_name == upcast.rust_name) {
+                quote! {}
+            } else {
+                let method_ident = format_ident!("{}", upcast.rust_name);
+                let doc = format!(" Upcast to the superclass `{}`", upcast.java_name);
+                quote! {
+                    #[doc = #doc]
+                    pub fn #method_ident(&self) -> #superclass<'a> {
+                        crate::FromRaw::from_raw(self.env, self.obj.clone())
+                    }
+                }
+            };
+
+            let from_impl = quote! {
+                impl<'a, #(#type_parameters),*> From<#name_ident<'a, #(#type_parameters),*>> for #superclass<'a> {
+                    fn from(value: #name_ident<'a, #(#type_parameters),*>) -> Self {
+                        crate::FromRaw::from_raw(value.env, value.obj)
+                    }
+                }
+            };
+
+            (method, from_impl)
+        })
+        .unzip();
+
+    quote! {
+        impl<'a, #(#type_parameters),*> #name_ident<'a, #(#type_parameters),*> {
+            #(#methods)*
+        }
+
+        #(#impls)*
+    }
+}
+
+/// Get the type parameters to emit on the generated struct for `class`.
+/// Empty unless `phantom_type_parameters` is enabled in the config
+pub fn generate_type_parameters(class: &FormattedClassEntry, config: &Config) -> Vec<Ident> {
+    if !config.generator.phantom_type_parameters {
+        return Vec::new();
+    }
+
+    class.type_parameters.iter()
+        .map(|x| format_ident!("{}", x.name))
+        .collect()
+}
+
+/// Document the Java generics, abstractness and superclass of `class`, as these are not expressed in the generated struct
+pub(super) fn generate_class_doc(class: &FormattedClassEntry) -> TokenStream {
+    let mut doc = Vec::new();
+    if !class.type_parameters.is_empty() {
+        let type_parameters = format_type_parameters(&class.type_parameters);
+        doc.push(format!(" Java type parameters: `<{type_parameters}>`."));
+        doc.push(" These are erased, arguments and return values using them are passed as `java.lang.Object` or their bound.".to_string());
+    }
+
+    if class.is_abstract {
+        doc.push(" Abstract in Java, instances can only be obtained from subclasses.".to_string());
+    }
+
+    let superclass = class.generic_superclass.as_ref().or_else(|| class.superclasses.first());
+    if let Some(superclass) = superclass {
+        doc.push(format!(" Extends `{superclass}`."));
+    }
+
+    let javadoc = generate_javadoc(&class.javadoc);
+
+    quote! {
+        #javadoc
+        #(#[doc = #doc])*
+    }
+}
+
+/// `parent` is the superclass the struct derefs to, which it then holds as well
+fn generate_struct(name_ident: &Ident, type_parameters: &[Ident], deprecated: &TokenStream, parent: Option<&TokenStream>) -> TokenStream {
+    let parent_field = parent.map(|parent| quote! {
+        parent: #parent<'a>,
+    });
+
+    quote! {
+        #deprecated
+        pub struct #name_ident<'a, #(#type_parameters),*> {
+            pub(crate) env: &'a jni::JNIEnv<'a>,
+            pub(crate) obj: crate::Object<'a>,
+            #parent_field
+            _marker: std::marker::PhantomData<(#(#type_parameters,)*)>,
         }
     }
 }
 
-fn generate_struct_trait_impls(name_ident: &Ident, fully_qualified_class_name: &str) -> TokenStream {
+fn generate_struct_trait_impls(name_ident: &Ident, type_parameters: &[Ident], fully_qualified_class_name: &str, parent: Option<&TokenStream>) -> TokenStream {
+    let (parent_field, deref) = match parent {
+        Some(parent) => (
+            quote! {
+                parent: crate::FromRaw::from_raw(env, obj.clone()),
+            },
+            quote! {
+                impl<'a, #(#type_parameters),*> std::ops::Deref for #name_ident<'a, #(#type_parameters),*> {
+                    type Target = #parent<'a>;
+
+                    fn deref(&self) -> &Self::Target {
+                        &self.parent
+                    }
+                }
+            },
+        ),
+        None => (quote! {}, quote! {}),
+    };
+    let name = name_ident.to_string();
+
     quote! {
-        impl<'a> crate::ClassName for #name_ident<'a> {
+        #deref
+
+        /// Formatted through Java's `toString()`
+        impl<'a, #(#type_parameters),*> std::fmt::Display for #name_ident<'a, #(#type_parameters),*> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                crate::fmt_to_string(self.env, self.obj.inner, f)
+            }
+        }
+
+        /// Clones the wrapper with a new local reference to the object, rather than sharing the reference.
+        /// Panics if creating it fails
+        impl<'a, #(#type_parameters),*> Clone for #name_ident<'a, #(#type_parameters),*> {
+            fn clone(&self) -> Self {
+                crate::FromRaw::from_raw(self.env, crate::clone_local_ref(self.env, &self.obj))
+            }
+        }
+
+        /// Shows the runtime class and identity hash code of the object
+        impl<'a, #(#type_parameters),*> std::fmt::Debug for #name_ident<'a, #(#type_parameters),*> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                crate::fmt_identity(self.env, self.obj.inner, #name, f)
+            }
+        }
+
+        impl<'a, #(#type_parameters),*> crate::ClassName for #name_ident<'a, #(#type_parameters),*> {
             fn class_name() -> &'static str {
                 #fully_qualified_class_name
             }
+
+            fn class_ref() -> &'static crate::ClassRef {
+                static CLASS: crate::ClassRef = crate::ClassRef::new(#fully_qualified_class_name);
+                &CLASS
+            }
         }
 
-        impl<'a> crate::FromRaw<'a> for #name_ident<'a> {
-            fn from_raw(env: &'a jni::JNIEnv<'a>, obj: ejni::Object<'a>) -> Self {
+        impl<'a, #(#type_parameters),*> crate::FromRaw<'a> for #name_ident<'a, #(#type_parameters),*> {
+            fn from_raw(env: &'a jni::JNIEnv<'a>, obj: crate::Object<'a>) -> Self {
                 Self {
                     env,
-                    obj
+                    #parent_field
+                    obj,
+                    _marker: std::marker::PhantomData,
                 }
             }
         }
 
-        impl<'a> Into<jni::JValue<'a>> for #name_ident<'a> {
-            fn into(self) -> jni::JValue<'a> {
+        impl<'a, #(#type_parameters),*> crate::JavaObject<'a> for #name_ident<'a, #(#type_parameters),*> {
+            fn env(&self) -> &'a jni::JNIEnv<'a> {
+                self.env
+            }
+
+            fn obj(&self) -> &crate::Object<'a> {
+                &self.obj
+            }
+        }
+
+        impl<'a, #(#type_parameters),*> Into<jni::objects::JValue<'a>> for #name_ident<'a, #(#type_parameters),*> {
+            fn into(self) -> jni::objects::JValue<'a> {
                 self.obj.into()
             }
         }
     }
 }
 
-pub fn generate_class(class: &ClassEntry) -> (TokenStream, Ident) {
-    println!("{}", class.name);
-
-    let compatible_name = rename(&class.name);
-
-    let name_ident = format_ident!("{}", compatible_name.split('.').last().unwrap());
-    let fully_qualified_class_path = class.name.replace('.', "/");
+pub fn generate_class(class: &FormattedClassEntry, config: &Config) -> (TokenStream, Ident) {
+    let name_ident = class_ident(class);
+    let fully_qualified_class_path = class.java_name.replace('.', "/");
 
-    let gen_struct = generate_struct(&name_ident);
-    let trait_impls = generate_struct_trait_impls(&name_ident, &fully_qualified_class_path);
+    let type_parameters = generate_type_parameters(class, config);
+    let class_doc = generate_class_doc(class);
+    let deprecated = generate_deprecated(&class.deprecation);
+    // The upcasts start at the nearest superclass in the tree
+    let parent = class.upcasts.first()
+        .filter(|_| config.generator.inheritance == Inheritance::Deref)
+        .map(|x| generate_bindings_path(&x.class, config));
+    let gen_struct = generate_struct(&name_ident, &type_parameters, &deprecated, parent.as_ref());
+    let trait_impls = generate_struct_trait_impls(&name_ident, &type_parameters, &fully_qualified_class_path, parent.as_ref());
+    // Interfaces outside of the tree have no trait to implement
     let interfaces = class.interfaces.iter()
-        .map(|x| {
-            let name_compatible = rename(x);
-            let mut name = name_compatible.split('.').last().unwrap();
-
-            format_ident!("{}", name)
-        })
-        .map(|x| generate_interface_impl(&name_ident, &x))
+        .filter(|x| config.has_bindings(x))
+        .map(|x| generate_interface_conversion(&name_ident, &type_parameters, x, quote! { crate::FromRaw::from_raw(value.env, value.obj) }, config))
         .collect::<Vec<_>>();
 
+    let sealed_kind = generate_sealed_kind(class, &name_ident, config);
+    let upcasts = generate_upcasts(class, &name_ident, &type_parameters, config);
+    let equality = if config.generator.java_equality {
+        let comparable = implements(class, &["java.lang.Comparable"]);
+        generate_equality(&name_ident, &type_parameters, comparable)
+    } else {
+        quote! {}
+    };
+
+    let iteration = generate_iteration(class, &name_ident, &type_parameters);
+    let closing = generate_closing(class, &name_ident, &type_parameters);
+    let global = generate_global(class, &name_ident, &type_parameters, &deprecated, config);
+
     let tokens = quote! {
+        #class_doc
         #gen_struct
 
+        #sealed_kind
+
         #trait_impls
 
         #(#interfaces)*
+
+        #upcasts
+
+        #equality
+
+        #iteration
+
+        #closing
+
+        #global
     };
 
     (tokens, name_ident)
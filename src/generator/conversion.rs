@@ -0,0 +1,712 @@
+use std::str::FromStr;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use crate::class_tree::ArgumentType;
+use crate::config::{BigDecimalType, BigIntegerType, CollectionStyle, Config, JavaTime};
+use crate::formatter::rename_class_fq;
+
+/// How an object is converted between Java and Rust in the generated code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conversion {
+    /// `java.lang.String`, passed as `impl AsRef<str>` and returned as `String`
+    String,
+    /// `java.util.List` with a known element type, passed as `impl IntoIterator` and returned as `Vec`
+    List(Box<Conversion>),
+    /// `java.util.Map` with known key and value types, passed as `impl IntoIterator` of pairs and returned as `HashMap`.
+    /// Only keys which are hashable in Rust are converted
+    Map(Box<Conversion>, Box<Conversion>),
+    /// `java.util.Optional` with a known type argument, passed and returned as `Option`. A `null` optional is returned as `None`
+    Optional(Box<Conversion>),
+    /// `java.util.OptionalInt`, `OptionalLong` or `OptionalDouble`, passed and returned as an `Option` of the primitive
+    OptionalPrimitive(ArgumentType),
+    /// A `java.time` type, converted to the type of the configured time crate through its `crate::JavaConvert` impl.
+    /// Holds its fully qualified Java name
+    JavaTime(String),
+    /// `java.math.BigDecimal` or `BigInteger`, converted through their string representation to the configured type.
+    /// Holds its fully qualified Java name and the Rust type
+    BigNumber(String, &'static str),
+    /// An array of a primitive, e.g. `int[]`, passed as a slice and returned as a `Vec`.
+    /// Copied as a whole through `crate::ArrayElement`. Holds the type of the elements
+    PrimitiveArray(ArgumentType),
+    /// An array of objects or arrays, passed as `impl IntoIterator` and returned as `Vec`, e.g. `Vec<Vec<f32>>` for `float[][]`.
+    /// Holds the conversion of the elements and their class in the notation of JNI, e.g. `[F`
+    Array(Box<Conversion>, String),
+    /// An enum in the generated crate, passed and returned as the Rust enum through its `crate::JavaConvert` impl.
+    /// Holds its fully qualified Java name
+    Enum(String),
+    /// Any other class, wrapped in its generated type or configured mapping. Holds its fully qualified Java name
+    Object(String),
+}
+
+impl Conversion {
+    /// The conversion for an argument or return value, given its type in Java notation including type arguments.
+    /// `None` for primitives
+    pub fn new(argument_type: &ArgumentType, generic_type: Option<&str>, config: &Config) -> Option<Self> {
+        let class_fq = match argument_type {
+            ArgumentType::Object(class_fq) => class_fq,
+            ArgumentType::Array(element) => return Some(Self::array(element, config)),
+            _ => return None,
+        };
+
+        // The generic type is only used if it describes the same class, the source may e.g. omit implicit arguments
+        let conversion = generic_type
+            .map(parse_generic_type)
+            .filter(|(erased, _)| erased == class_fq)
+            .map(|(erased, type_arguments)| Self::from_generic(&erased, &type_arguments, config))
+            .unwrap_or_else(|| Self::erased(argument_type, config));
+
+        Some(conversion)
+    }
+
+    /// The conversion for an array, given the type of its elements. The type arguments of the elements are unknown
+    fn array(element: &ArgumentType, config: &Config) -> Self {
+        let element_class = match element {
+            ArgumentType::Object(class_fq) => class_fq.replace('.', "/"),
+            ArgumentType::Array(_) => ArgumentType::to_jni_signature(std::slice::from_ref(element)),
+            primitive => return Self::PrimitiveArray(primitive.clone()),
+        };
+
+        let element = Self::new(element, None, config).expect("Objects and arrays are converted");
+        Self::Array(Box::new(element), element_class)
+    }
+
+    /// The conversion for a class when its type arguments are unknown
+    fn erased(argument_type: &ArgumentType, config: &Config) -> Self {
+        let class_fq = match argument_type {
+            ArgumentType::Object(class_fq) => class_fq,
+            _ => unreachable!("Only objects are converted"),
+        };
+
+        match class_fq.as_str() {
+            _ if argument_type.is_string(config) => Self::String,
+            _ if is_mapped(class_fq, config) => Self::Object(class_fq.clone()),
+            _ if is_enum(class_fq, config) => Self::Enum(class_fq.clone()),
+            "java.util.OptionalInt" => Self::OptionalPrimitive(ArgumentType::Int),
+            "java.util.OptionalLong" => Self::OptionalPrimitive(ArgumentType::Long),
+            "java.util.OptionalDouble" => Self::OptionalPrimitive(ArgumentType::Double),
+            _ if java_time_type(class_fq, config.generator.java_time).is_some() => Self::JavaTime(class_fq.clone()),
+            _ => match big_number_type(class_fq, config) {
+                Some(rust_type) => Self::BigNumber(class_fq.clone(), rust_type),
+                None => Self::Object(class_fq.clone()),
+            },
+        }
+    }
+
+    fn from_generic(class_fq: &str, type_arguments: &[String], config: &Config) -> Self {
+        let argument_type = ArgumentType::Object(class_fq.to_string());
+        let collections = config.generator.collections == CollectionStyle::Converted;
+
+        let converted = match (class_fq, type_arguments) {
+            _ if is_mapped(class_fq, config) => None,
+            ("java.util.List", [element]) if collections => Self::type_argument(element, config)
+                .map(|element| Self::List(Box::new(element))),
+            ("java.util.Map", [key, value]) if collections => Self::type_argument(key, config)
+                .filter(Self::is_hashable)
+                .zip(Self::type_argument(value, config))
+                .map(|(key, value)| Self::Map(Box::new(key), Box::new(value))),
+            ("java.util.Optional", [value]) => Self::type_argument(value, config)
+                .map(|value| Self::Optional(Box::new(value))),
+            _ => None,
+        };
+
+        converted.unwrap_or_else(|| Self::erased(&argument_type, config))
+    }
+
+    /// Whether the Rust type implements `Hash` and `Eq`, so it can be the key of a `HashMap`
+    fn is_hashable(&self) -> bool {
+        match self {
+            Self::String => true,
+            Self::List(element) | Self::Optional(element) | Self::Array(element, _) => element.is_hashable(),
+            // `f64` is not `Eq`
+            Self::OptionalPrimitive(primitive) => *primitive != ArgumentType::Double,
+            Self::JavaTime(_) | Self::BigNumber(..) | Self::Enum(_) => true,
+            Self::PrimitiveArray(element) => !matches!(element, ArgumentType::Float | ArgumentType::Double),
+            Self::Map(..) | Self::Object(_) => false,
+        }
+    }
+
+    /// Whether a `java.time` type is converted, which requires the feature of the time crate
+    pub fn uses_java_time(&self) -> bool {
+        match self {
+            Self::JavaTime(_) => true,
+            Self::List(element) | Self::Optional(element) | Self::Array(element, _) => element.uses_java_time(),
+            Self::Map(key, value) => key.uses_java_time() || value.uses_java_time(),
+            Self::String | Self::OptionalPrimitive(_) | Self::BigNumber(..) | Self::PrimitiveArray(_) | Self::Enum(_) | Self::Object(_) => false,
+        }
+    }
+
+    /// Whether the Rust value holds references to Java objects, i.e. it contains wrapped objects.
+    /// Otherwise the references used converting it can be deleted
+    pub fn holds_references(&self) -> bool {
+        match self {
+            Self::Object(_) => true,
+            Self::List(element) | Self::Optional(element) | Self::Array(element, _) => element.holds_references(),
+            Self::Map(key, value) => key.holds_references() || value.holds_references(),
+            Self::String | Self::OptionalPrimitive(_) | Self::JavaTime(_) | Self::BigNumber(..) | Self::PrimitiveArray(_) | Self::Enum(_) => false,
+        }
+    }
+
+    /// Whether the conversion handles `null` itself, so the value is not wrapped in another `Option`
+    pub fn is_optional(&self) -> bool {
+        matches!(self, Self::Optional(_) | Self::OptionalPrimitive(_))
+    }
+
+    /// The conversion for a type argument. `None` if the class is not known, e.g. for type variables
+    fn type_argument(type_argument: &str, config: &Config) -> Option<Self> {
+        // `? extends Foo` is passed and returned as `Foo`. `? super Foo` could be anything when returned
+        let type_argument = type_argument.strip_prefix("? extends ").unwrap_or(type_argument);
+        if type_argument.starts_with('?') || type_argument.ends_with("[]") {
+            return None;
+        }
+
+        let (erased, type_arguments) = parse_generic_type(type_argument);
+        // Type variables are not qualified
+        if !erased.contains('.') {
+            return None;
+        }
+
+        Some(Self::from_generic(&erased, &type_arguments, config))
+    }
+
+    /// The type of a non-null argument. Uses `impl Trait` where possible, so callers can pass borrowed strings and any iterable
+    pub fn argument_type(&self, config: &Config) -> TokenStream {
+        self.argument_type_in(config, false)
+    }
+
+    /// Objects in collections have the lifetime `'a` of the JNIEnv, as `impl Trait` cannot elide it.
+    /// For the same reason slices in collections are owned or passed as `impl AsRef`
+    fn argument_type_in(&self, config: &Config, collection: bool) -> TokenStream {
+        match self {
+            Self::String => quote! { impl AsRef<str> },
+            Self::BigNumber(_, "String") => quote! { impl AsRef<str> },
+            Self::List(element) | Self::Array(element, _) => {
+                let element = element.argument_type_in(config, true);
+                quote! { impl IntoIterator<Item = #element> }
+            },
+            Self::Map(key, value) => {
+                let key = key.argument_type_in(config, true);
+                let value = value.argument_type_in(config, true);
+                quote! { impl IntoIterator<Item = (#key, #value)> }
+            },
+            // Like a nullable argument
+            Self::Optional(value) => {
+                let value = value.nullable_argument_type_in(config, collection);
+                quote! { Option<#value> }
+            },
+            Self::OptionalPrimitive(primitive) => Self::optional_primitive_type(primitive, config),
+            Self::JavaTime(class_fq) => Self::java_time_type(class_fq, config),
+            Self::BigNumber(_, rust_type) => Self::big_number_type(rust_type),
+            Self::PrimitiveArray(element) if collection => {
+                let element = Self::primitive_type(element, config);
+                quote! { impl AsRef<[#element]> }
+            },
+            Self::PrimitiveArray(element) => {
+                let element = Self::primitive_type(element, config);
+                quote! { &[#element] }
+            },
+            Self::Enum(class_fq) | Self::Object(class_fq) => Self::object_type(class_fq, config, collection),
+        }
+    }
+
+    /// The type of a nullable argument, which is wrapped in an `Option`.
+    /// `None` could not be passed for an `Option<impl Trait>` without naming a type
+    pub fn nullable_argument_type(&self, config: &Config) -> TokenStream {
+        self.nullable_argument_type_in(config, false)
+    }
+
+    fn nullable_argument_type_in(&self, config: &Config, collection: bool) -> TokenStream {
+        match self {
+            Self::String => quote! { &str },
+            Self::BigNumber(_, "String") => quote! { &str },
+            Self::List(element) | Self::Array(element, _) => {
+                let element = element.nullable_argument_type_in(config, true);
+                quote! { Vec<#element> }
+            },
+            Self::Map(key, value) => {
+                let key = key.nullable_argument_type_in(config, true);
+                let value = value.nullable_argument_type_in(config, true);
+                quote! { std::collections::HashMap<#key, #value> }
+            },
+            Self::Optional(value) => {
+                let value = value.nullable_argument_type_in(config, collection);
+                quote! { Option<#value> }
+            },
+            Self::OptionalPrimitive(primitive) => Self::optional_primitive_type(primitive, config),
+            Self::JavaTime(class_fq) => Self::java_time_type(class_fq, config),
+            Self::BigNumber(_, rust_type) => Self::big_number_type(rust_type),
+            Self::PrimitiveArray(element) if collection => {
+                let element = Self::primitive_type(element, config);
+                quote! { Vec<#element> }
+            },
+            Self::PrimitiveArray(element) => {
+                let element = Self::primitive_type(element, config);
+                quote! { &[#element] }
+            },
+            Self::Enum(class_fq) | Self::Object(class_fq) => Self::object_type(class_fq, config, collection),
+        }
+    }
+
+    /// The type of a return value, objects have the lifetime `'a` of the JNIEnv
+    pub fn return_type(&self, config: &Config) -> TokenStream {
+        match self {
+            Self::String => quote! { String },
+            Self::List(element) | Self::Array(element, _) => {
+                let element = element.return_type(config);
+                quote! { Vec<#element> }
+            },
+            Self::Map(key, value) => {
+                let key = key.return_type(config);
+                let value = value.return_type(config);
+                quote! { std::collections::HashMap<#key, #value> }
+            },
+            Self::Optional(value) => {
+                let value = value.return_type(config);
+                quote! { Option<#value> }
+            },
+            Self::OptionalPrimitive(primitive) => Self::optional_primitive_type(primitive, config),
+            Self::JavaTime(class_fq) => Self::java_time_type(class_fq, config),
+            Self::BigNumber(_, rust_type) => Self::big_number_type(rust_type),
+            Self::PrimitiveArray(element) => {
+                let element = Self::primitive_type(element, config);
+                quote! { Vec<#element> }
+            },
+            Self::Enum(class_fq) | Self::Object(class_fq) => Self::object_type(class_fq, config, true),
+        }
+    }
+
+    pub(super) fn optional_primitive_type(primitive: &ArgumentType, config: &Config) -> TokenStream {
+        let rust_type = Self::primitive_type(primitive, config);
+        quote! { Option<#rust_type> }
+    }
+
+    pub(super) fn primitive_type(primitive: &ArgumentType, config: &Config) -> TokenStream {
+        TokenStream::from_str(&primitive.to_rust_type(config)).expect("Primitives are valid Rust types")
+    }
+
+    pub(super) fn java_time_type(class_fq: &str, config: &Config) -> TokenStream {
+        let rust_type = java_time_type(class_fq, config.generator.java_time).expect("Converted java.time types are known");
+        TokenStream::from_str(rust_type).expect("Time types are valid Rust types")
+    }
+
+    pub(super) fn big_number_type(rust_type: &str) -> TokenStream {
+        TokenStream::from_str(rust_type).expect("Number types are valid Rust types")
+    }
+
+    /// The type of a class in the generated crate or its mapping, with the lifetime `'a` if `lifetime` is set
+    pub(super) fn object_type(class_fq: &str, config: &Config, lifetime: bool) -> TokenStream {
+        let argument_type = ArgumentType::Object(class_fq.to_string());
+        let rust_type = if lifetime {
+            argument_type.to_generated_return_type(config)
+        } else {
+            argument_type.to_generated_rust_type(config)
+        };
+        TokenStream::from_str(&rust_type).expect("Formatted types are valid Rust types")
+    }
+
+    /// An expression converting the Rust `value` to a `JValue`. May use `?` and `env`
+    pub fn rust_to_jvalue(&self, value: TokenStream) -> TokenStream {
+        match self {
+            Self::Object(_) => quote! { #value.into() },
+            _ => {
+                let object = self.rust_to_java(value);
+                quote! { jni::objects::JValue::Object(#object) }
+            }
+        }
+    }
+
+    /// An expression converting the Rust `value` to a `JObject`. May use `?` and `env`
+    fn rust_to_java(&self, value: TokenStream) -> TokenStream {
+        match self {
+            Self::String => quote! {
+                crate::catch_exception(env, env.new_string(AsRef::<str>::as_ref(&#value)))?.into()
+            },
+            Self::List(element) => {
+                let element = element.rust_to_java(quote! { element });
+                quote! {
+                    crate::new_list(env, #value, |element| Ok(#element))?
+                }
+            },
+            Self::Map(key, value_conversion) => {
+                let key = key.rust_to_java(quote! { key });
+                let value_converted = value_conversion.rust_to_java(quote! { value });
+                quote! {
+                    crate::new_map(env, #value, |key| Ok(#key), |value| Ok(#value_converted))?
+                }
+            },
+            Self::Optional(value_conversion) => {
+                let value_converted = value_conversion.rust_to_java(quote! { value });
+                quote! {
+                    crate::new_optional(env, "java/util/Optional", "(Ljava/lang/Object;)Ljava/util/Optional;", #value, |value| Ok(jni::objects::JValue::Object(#value_converted)))?
+                }
+            },
+            Self::OptionalPrimitive(primitive) => {
+                let (class_name, _, signature) = optional_primitive(primitive);
+                let of_signature = format!("({signature})L{class_name};");
+                quote! {
+                    crate::new_optional(env, #class_name, #of_signature, #value, |value| Ok(value.into()))?
+                }
+            },
+            Self::JavaTime(_) | Self::Enum(_) => quote! {
+                crate::JavaConvert::to_java(&#value, env)?
+            },
+            Self::BigNumber(class_fq, rust_type) => {
+                let class_name = class_fq.replace('.', "/");
+                let value = if *rust_type == "String" {
+                    quote! { AsRef::<str>::as_ref(&#value) }
+                } else {
+                    quote! { &ToString::to_string(&#value) }
+                };
+
+                quote! {
+                    crate::new_big_number(env, #class_name, #value)?
+                }
+            },
+            Self::PrimitiveArray(_) => quote! {
+                crate::new_primitive_array(env, #value)?
+            },
+            Self::Array(element, element_class) => {
+                let element = element.rust_to_java(quote! { element });
+                quote! {
+                    crate::new_object_array(env, #element_class, #value, |element| Ok(#element))?
+                }
+            },
+            Self::Object(_) => quote! {
+                {
+                    let value: jni::objects::JValue = #value.into();
+                    value.l()?
+                }
+            },
+        }
+    }
+
+    /// An expression converting the `JObject` `value` to the Rust return type. May use `?` and `env`
+    pub fn java_to_rust(&self, value: TokenStream, config: &Config) -> TokenStream {
+        match self {
+            Self::String => quote! {
+                crate::get_string(env, #value)?
+            },
+            Self::List(element) => {
+                let element = element.java_to_rust(quote! { element }, config);
+                quote! {
+                    crate::list_to_vec(env, #value, |element| Ok(#element))?
+                }
+            },
+            Self::Map(key, value_conversion) => {
+                let key = key.java_to_rust(quote! { key }, config);
+                let value_converted = value_conversion.java_to_rust(quote! { value }, config);
+                quote! {
+                    crate::map_to_hash_map(env, #value, |key| Ok(#key), |value| Ok(#value_converted))?
+                }
+            },
+            Self::Optional(value_conversion) => {
+                let value_converted = value_conversion.java_to_rust(quote! { value.l()? }, config);
+                quote! {
+                    crate::optional_to_option(env, #value, "get", "()Ljava/lang/Object;", |value| Ok(#value_converted))?
+                }
+            },
+            Self::OptionalPrimitive(primitive) => {
+                let (_, getter, signature) = optional_primitive(primitive);
+                let getter_signature = format!("(){signature}");
+                // The accessors of `JValue` are named after the JNI signature of the primitive
+                let accessor = format_ident!("{}", signature.to_ascii_lowercase());
+                quote! {
+                    crate::optional_to_option(env, #value, #getter, #getter_signature, |value| Ok(value.#accessor()?))?
+                }
+            },
+            Self::JavaTime(class_fq) => {
+                let rust_type = Self::java_time_type(class_fq, config);
+                quote! {
+                    <#rust_type as crate::JavaConvert>::from_java(env, #value)?
+                }
+            },
+            Self::BigNumber(class_fq, rust_type) => {
+                // `toString` may use scientific notation for decimals, which not every Rust type parses
+                let getter = if class_fq == "java.math.BigDecimal" { "toPlainString" } else { "toString" };
+                let rust_type = Self::big_number_type(rust_type);
+                quote! {
+                    crate::parse_big_number::<#rust_type>(env, #value, #getter)?
+                }
+            },
+            Self::PrimitiveArray(element) => {
+                let element = Self::primitive_type(element, config);
+                quote! {
+                    <#element as crate::ArrayElement>::array_to_vec(env, #value)?
+                }
+            },
+            Self::Array(element, _) => {
+                let element = element.java_to_rust(quote! { element }, config);
+                quote! {
+                    crate::object_array_to_vec(env, #value, |element| Ok(#element))?
+                }
+            },
+            Self::Enum(class_fq) => {
+                let rust_type = Self::object_type(class_fq, config, false);
+                quote! {
+                    <#rust_type as crate::JavaConvert>::from_java(env, #value)?
+                }
+            },
+            Self::Object(_) => quote! {
+                crate::wrap(env, #value)?
+            },
+        }
+    }
+}
+
+/// Whether a mapping is configured for the class, which is then passed as is
+fn is_mapped(class_fq: &str, config: &Config) -> bool {
+    let type_path = rename_class_fq(class_fq, &config.generator).replace('.', "::");
+    config.generator.mappings.contains_key(&type_path)
+}
+
+/// Whether bindings are generated for the class and it is an enum
+fn is_enum(class_fq: &str, config: &Config) -> bool {
+    config.is_enum(&rename_class_fq(class_fq, &config.generator))
+}
+
+/// The Rust type a `java.time` class is converted to, `None` if it is not converted
+fn java_time_type(class_fq: &str, java_time: JavaTime) -> Option<&'static str> {
+    let rust_type = match (java_time, class_fq) {
+        (JavaTime::Chrono, "java.time.Instant") => "chrono::DateTime<chrono::Utc>",
+        (JavaTime::Chrono, "java.time.LocalDate") => "chrono::NaiveDate",
+        (JavaTime::Chrono, "java.time.LocalTime") => "chrono::NaiveTime",
+        (JavaTime::Chrono, "java.time.LocalDateTime") => "chrono::NaiveDateTime",
+        (JavaTime::Chrono, "java.time.Duration") => "chrono::TimeDelta",
+        (JavaTime::Time, "java.time.Instant") => "time::OffsetDateTime",
+        (JavaTime::Time, "java.time.LocalDate") => "time::Date",
+        (JavaTime::Time, "java.time.LocalTime") => "time::Time",
+        (JavaTime::Time, "java.time.LocalDateTime") => "time::PrimitiveDateTime",
+        (JavaTime::Time, "java.time.Duration") => "time::Duration",
+        _ => return None,
+    };
+
+    Some(rust_type)
+}
+
+/// The Rust type `java.math.BigDecimal` or `BigInteger` is converted to, `None` if it is not converted
+fn big_number_type(class_fq: &str, config: &Config) -> Option<&'static str> {
+    let rust_type = match class_fq {
+        "java.math.BigDecimal" => match config.generator.big_decimal {
+            BigDecimalType::String => "String",
+            BigDecimalType::RustDecimal => "rust_decimal::Decimal",
+            BigDecimalType::BigDecimal => "bigdecimal::BigDecimal",
+            BigDecimalType::Raw => return None,
+        },
+        "java.math.BigInteger" => match config.generator.big_integer {
+            BigIntegerType::String => "String",
+            BigIntegerType::NumBigint => "num_bigint::BigInt",
+            BigIntegerType::Raw => return None,
+        },
+        _ => return None,
+    };
+
+    Some(rust_type)
+}
+
+/// The JNI name of the optional class for a primitive, the name of its getter, and the JNI signature of the primitive
+pub(super) fn optional_primitive(primitive: &ArgumentType) -> (&'static str, &'static str, char) {
+    match primitive {
+        ArgumentType::Int => ("java/util/OptionalInt", "getAsInt", 'I'),
+        ArgumentType::Long => ("java/util/OptionalLong", "getAsLong", 'J'),
+        ArgumentType::Double => ("java/util/OptionalDouble", "getAsDouble", 'D'),
+        _ => unreachable!("No optional class for {primitive:?}"),
+    }
+}
+
+/// Split a type in Java notation into its erased name and its type arguments,
+/// e.g. `java.util.Map<java.lang.String, java.util.List<T>>` into `java.util.Map` and `[java.lang.String, java.util.List<T>]`.
+/// The type arguments of enclosing classes, as in `com.foo.Outer<T>$Inner`, are dropped
+fn parse_generic_type(generic_type: &str) -> (String, Vec<String>) {
+    let mut erased = String::new();
+    let mut type_arguments = Vec::new();
+    let mut depth = 0;
+
+    for c in generic_type.chars() {
+        match c {
+            '<' => {
+                depth += 1;
+                if depth == 1 {
+                    type_arguments.clear();
+                    type_arguments.push(String::new());
+                    continue;
+                }
+            },
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    continue;
+                }
+            },
+            ',' if depth == 1 => {
+                type_arguments.push(String::new());
+                continue;
+            },
+            '$' if depth == 0 => type_arguments.clear(),
+            _ => {},
+        }
+
+        match type_arguments.last_mut() {
+            Some(type_argument) if depth > 0 => type_argument.push(c),
+            _ => erased.push(c),
+        }
+    }
+
+    let type_arguments = type_arguments.iter()
+        .map(|x| x.trim().to_string())
+        .collect();
+    (erased, type_arguments)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generic_type() {
+        assert_eq!(("java.lang.String".to_string(), Vec::<String>::new()), parse_generic_type("java.lang.String"));
+        assert_eq!(
+            ("java.util.Map".to_string(), vec!["java.lang.String".to_string(), "java.util.List<? extends T>".to_string()]),
+            parse_generic_type("java.util.Map<java.lang.String, java.util.List<? extends T>>"),
+        );
+        assert_eq!(("com.foo.Outer$Inner".to_string(), Vec::<String>::new()), parse_generic_type("com.foo.Outer<T>$Inner"));
+    }
+
+    #[test]
+    fn list() {
+        let mut config = Config::default();
+        let list = ArgumentType::Object("java.util.List".to_string());
+        let string = Box::new(Conversion::String);
+
+        assert_eq!(Some(Conversion::List(string.clone())), Conversion::new(&list, Some("java.util.List<java.lang.String>"), &config));
+        assert_eq!(
+            Some(Conversion::List(Box::new(Conversion::List(string)))),
+            Conversion::new(&list, Some("java.util.List<? extends java.util.List<java.lang.String>>"), &config),
+        );
+        assert_eq!(
+            Some(Conversion::List(Box::new(Conversion::Object("com.foo.Bar$Baz".to_string())))),
+            Conversion::new(&list, Some("java.util.List<com.foo.Bar<T>$Baz>"), &config),
+        );
+
+        // Unknown element types
+        let raw = Some(Conversion::Object("java.util.List".to_string()));
+        assert_eq!(raw, Conversion::new(&list, Some("java.util.List<T>"), &config));
+        assert_eq!(raw, Conversion::new(&list, Some("java.util.List<? super java.lang.String>"), &config));
+        assert_eq!(raw, Conversion::new(&list, Some("java.util.List"), &config));
+        assert_eq!(raw, Conversion::new(&list, None, &config));
+
+        config.generator.collections = CollectionStyle::Raw;
+        assert_eq!(raw, Conversion::new(&list, Some("java.util.List<java.lang.String>"), &config));
+        assert_eq!(None, Conversion::new(&ArgumentType::Int, Some("int"), &config));
+    }
+
+    #[test]
+    fn optional() {
+        let mut config = Config::default();
+        let optional = ArgumentType::Object("java.util.Optional".to_string());
+
+        assert_eq!(
+            Some(Conversion::Optional(Box::new(Conversion::List(Box::new(Conversion::String))))),
+            Conversion::new(&optional, Some("java.util.Optional<java.util.List<java.lang.String>>"), &config),
+        );
+        assert_eq!(
+            Some(Conversion::OptionalPrimitive(ArgumentType::Long)),
+            Conversion::new(&ArgumentType::Object("java.util.OptionalLong".to_string()), Some("java.util.OptionalLong"), &config),
+        );
+        assert_eq!(Some(Conversion::Object("java.util.Optional".to_string())), Conversion::new(&optional, Some("java.util.Optional<T>"), &config));
+
+        // Not a collection
+        config.generator.collections = CollectionStyle::Raw;
+        assert_eq!(
+            Some(Conversion::Optional(Box::new(Conversion::String))),
+            Conversion::new(&optional, Some("java.util.Optional<java.lang.String>"), &config),
+        );
+
+        config.generator.mappings.insert("java::util::Optional".to_string(), "crate::Optional".to_string());
+        assert_eq!(Some(Conversion::Object("java.util.Optional".to_string())), Conversion::new(&optional, Some("java.util.Optional<java.lang.String>"), &config));
+    }
+
+    #[test]
+    fn java_time() {
+        let mut config = Config::default();
+        let instant = ArgumentType::Object("java.time.Instant".to_string());
+        let list = ArgumentType::Object("java.util.List".to_string());
+
+        let conversion = Conversion::new(&list, Some("java.util.List<java.time.Instant>"), &config).unwrap();
+        assert!(conversion.uses_java_time());
+        assert_eq!("Vec < chrono :: DateTime < chrono :: Utc > >", conversion.return_type(&config).to_string());
+
+        config.generator.java_time = JavaTime::Time;
+        assert_eq!("time :: OffsetDateTime", Conversion::new(&instant, None, &config).unwrap().return_type(&config).to_string());
+
+        config.generator.java_time = JavaTime::Raw;
+        assert_eq!(Some(Conversion::Object("java.time.Instant".to_string())), Conversion::new(&instant, None, &config));
+        assert!(!Conversion::new(&list, Some("java.util.List<java.time.Instant>"), &config).unwrap().uses_java_time());
+    }
+
+    #[test]
+    fn big_number() {
+        let mut config = Config::default();
+        let big_decimal = ArgumentType::Object("java.math.BigDecimal".to_string());
+
+        let conversion = Conversion::new(&big_decimal, None, &config).unwrap();
+        assert_eq!("impl AsRef < str >", conversion.argument_type(&config).to_string());
+        assert_eq!("String", conversion.return_type(&config).to_string());
+
+        config.generator.big_decimal = BigDecimalType::RustDecimal;
+        let conversion = Conversion::new(&big_decimal, None, &config).unwrap();
+        assert_eq!("rust_decimal :: Decimal", conversion.argument_type(&config).to_string());
+
+        config.generator.big_decimal = BigDecimalType::Raw;
+        assert_eq!(Some(Conversion::Object("java.math.BigDecimal".to_string())), Conversion::new(&big_decimal, None, &config));
+    }
+
+    #[test]
+    fn primitive_array() {
+        let config = Config::default();
+        let bytes = ArgumentType::Array(Box::new(ArgumentType::Byte));
+        let conversion = Conversion::new(&bytes, None, &config).unwrap();
+        assert_eq!(Conversion::PrimitiveArray(ArgumentType::Byte), conversion);
+        assert_eq!("& [u8]", conversion.argument_type(&config).to_string());
+        assert_eq!("Vec < u8 >", conversion.return_type(&config).to_string());
+
+        let chars = ArgumentType::Array(Box::new(ArgumentType::Char));
+        assert_eq!("Vec < u16 >", Conversion::new(&chars, None, &config).unwrap().return_type(&config).to_string());
+
+        let doubles = Conversion::new(&ArgumentType::Array(Box::new(ArgumentType::Double)), None, &config).unwrap();
+        assert!(!doubles.is_hashable());
+    }
+
+    #[test]
+    fn array() {
+        let config = Config::default();
+        let matrix = ArgumentType::Array(Box::new(ArgumentType::Array(Box::new(ArgumentType::Float))));
+        let conversion = Conversion::new(&matrix, None, &config).unwrap();
+        assert_eq!(Conversion::Array(Box::new(Conversion::PrimitiveArray(ArgumentType::Float)), "[F".to_string()), conversion);
+        assert_eq!("impl IntoIterator < Item = impl AsRef < [f32] > >", conversion.argument_type(&config).to_string());
+        assert_eq!("Vec < Vec < f32 > >", conversion.nullable_argument_type(&config).to_string());
+        assert_eq!("Vec < Vec < f32 > >", conversion.return_type(&config).to_string());
+
+        let strings = ArgumentType::Array(Box::new(ArgumentType::Object("java.lang.String".to_string())));
+        let conversion = Conversion::new(&strings, None, &config).unwrap();
+        assert_eq!(Conversion::Array(Box::new(Conversion::String), "java/lang/String".to_string()), conversion);
+        assert_eq!("Vec < String >", conversion.return_type(&config).to_string());
+    }
+
+    #[test]
+    fn map() {
+        let mut config = Config::default();
+        let map = ArgumentType::Object("java.util.Map".to_string());
+
+        assert_eq!(
+            Some(Conversion::Map(Box::new(Conversion::String), Box::new(Conversion::List(Box::new(Conversion::Object("com.foo.Bar".to_string())))))),
+            Conversion::new(&map, Some("java.util.Map<java.lang.String, java.util.List<com.foo.Bar>>"), &config),
+        );
+
+        // Keys must be hashable, and values known
+        let raw = Some(Conversion::Object("java.util.Map".to_string()));
+        assert_eq!(raw, Conversion::new(&map, Some("java.util.Map<com.foo.Bar, java.lang.String>"), &config));
+        assert_eq!(raw, Conversion::new(&map, Some("java.util.Map<java.lang.String, V>"), &config));
+
+        config.generator.collections = CollectionStyle::Raw;
+        assert_eq!(raw, Conversion::new(&map, Some("java.util.Map<java.lang.String, java.lang.String>"), &config));
+    }
+}
@@ -0,0 +1,404 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use crate::config::{Config, Inheritance};
+use crate::formatter::FormattedClassEntry;
+use crate::generator::generate_deprecated;
+use crate::generator::class::{
+    class_ident, generate_bindings_path, generate_class_doc, generate_constants, generate_enum_type, generate_interface_conversion,
+    generate_interface_impl, generate_trait_path, generate_type_parameters, interface_wrapper_ident, is_closeable, OBJECT_METHODS,
+};
+use crate::generator::generate_javadoc;
+use crate::generator::jni21::method::{generate_free_function, generate_new_unchecked, generate_trait_method};
+
+/// Generate the trait of an interface. Unlike with jni 0.19 there is no `<Name>Impl` trait,
+/// interfaces cannot be implemented in Rust
+pub fn generate_interface(class: &FormattedClassEntry, config: &Config) -> TokenStream {
+    let name_ident = class_ident(class);
+
+    // Interfaces may redeclare the methods of `java.lang.Object`, which `JavaObject` provides
+    let trait_methods = class.methods.iter()
+        .filter(|x| !x.is_static && !OBJECT_METHODS.contains(&(x.java_name.as_str(), x.jni_signature.as_str())))
+        .map(|x| generate_trait_method(x, config))
+        .collect::<Vec<_>>();
+
+    // Superinterfaces become supertraits, implementors provide the object through `JavaObject`
+    let supertraits = class.interfaces.iter()
+        .filter(|x| config.has_bindings(x))
+        .map(|x| generate_trait_path(x))
+        .collect::<Vec<_>>();
+
+    let static_methods = class.methods.iter()
+        .filter(|x| x.is_static)
+        .map(|x| generate_free_function(x, config))
+        .collect::<Vec<_>>();
+
+    let deprecated = generate_deprecated(&class.deprecation);
+    let constants = generate_constants(class);
+    let sealed_kind = generate_sealed_kind(class, &name_ident, config);
+    let javadoc = generate_javadoc(&class.javadoc);
+    let wrapper = generate_interface_wrapper(class, config);
+
+    quote! {
+        #sealed_kind
+
+        #(#constants)*
+
+        #javadoc
+        #deprecated
+        pub trait #name_ident<'a>: crate::JavaObject<'a> #(+ #supertraits<'a>)* {
+            #(#trait_methods)*
+        }
+
+        #wrapper
+
+        #(#static_methods)*
+    }
+}
+
+/// Generate `<Name>Wrapper`, see `class::generate_interface_wrapper`
+fn generate_interface_wrapper(class: &FormattedClassEntry, config: &Config) -> TokenStream {
+    let wrapper_ident = interface_wrapper_ident(class);
+    let fully_qualified_class_path = class.java_name.replace('.', "/");
+    let type_parameters = generate_type_parameters(class, config);
+    let deprecated = generate_deprecated(&class.deprecation);
+    let doc = format!(" An object implementing `{}`, its methods are those of the trait [`{}`].", class.java_name, class_ident(class));
+
+    let gen_struct = generate_struct(&wrapper_ident, &type_parameters, &deprecated);
+    let trait_impls = generate_struct_trait_impls(&wrapper_ident, &type_parameters, &fully_qualified_class_path, None);
+    let new_unchecked = generate_new_unchecked(class);
+    let interface_impl = generate_interface_impl(&wrapper_ident, &type_parameters, &class.name);
+    let superinterfaces = class.interfaces.iter()
+        .filter(|x| config.has_bindings(x))
+        .map(|x| generate_interface_conversion(&wrapper_ident, &type_parameters, x, quote! { Self::new_unchecked(value.obj) }, config))
+        .collect::<Vec<_>>();
+
+    quote! {
+        #[doc = #doc]
+        #gen_struct
+
+        #trait_impls
+
+        impl<'a, #(#type_parameters),*> #wrapper_ident<'a, #(#type_parameters),*> {
+            #new_unchecked
+        }
+
+        #interface_impl
+
+        #(#superinterfaces)*
+    }
+}
+
+pub fn generate_enum(class: &FormattedClassEntry) -> TokenStream {
+    let name_ident = class_ident(class);
+    let fully_qualified_class_path = class.java_name.replace('.', "/");
+    let field_signature = format!("L{};", fully_qualified_class_path);
+    let enum_type = generate_enum_type(class, &name_ident);
+
+    quote! {
+        #enum_type
+
+        impl #name_ident {
+            /// Get the Java object of this constant
+            pub fn to_java<'a>(self, env: &mut jni::JNIEnv<'a>) -> crate::JResult<jni::objects::JObject<'a>> {
+                let value = crate::catch_exception(env, |env| env.get_static_field(#fully_qualified_class_path, self.name(), #field_signature))?;
+                Ok(value.l()?)
+            }
+        }
+
+        impl<'a> crate::JavaConvert<'a> for #name_ident {
+            /// Convert a constant. Fails if it is unknown, e.g. because it was added after the bindings were generated
+            fn from_java(env: &mut jni::JNIEnv<'a>, obj: jni::objects::JObject<'a>) -> crate::JResult<Self> {
+                let obj = env.auto_local(obj);
+                let ordinal = crate::catch_exception(env, |env| env.call_method(&obj, "ordinal", "()I", &[]))?.i()?;
+                Self::from_ordinal(ordinal)
+                    .ok_or_else(|| crate::Error::Conversion(format!("{} is not a known ordinal of {}", ordinal, #fully_qualified_class_path)))
+            }
+
+            fn to_java(&self, env: &mut jni::JNIEnv<'a>) -> crate::JResult<jni::objects::JObject<'a>> {
+                #name_ident::to_java(*self, env)
+            }
+        }
+    }
+}
+
+/// Generate the `<Name>Kind` enum of a sealed class or interface, see `class::generate_sealed_kind`
+fn generate_sealed_kind(class: &FormattedClassEntry, name_ident: &Ident, config: &Config) -> TokenStream {
+    let kind_ident = format_ident!("{}Kind", name_ident);
+    let ((variants, types), values): ((Vec<_>, Vec<_>), Vec<_>) = class.permitted_subclasses.iter()
+        .filter(|x| config.has_bindings(x))
+        .map(|subclass| {
+            let variant = format_ident!("{}", subclass.rsplit('.').next().unwrap());
+            let path = generate_bindings_path(subclass, config);
+            // Enum constants are converted to the Rust enum, which has no lifetime
+            if config.is_enum(subclass) {
+                ((variant, path.clone()), quote! { <#path as crate::JavaConvert>::from_java(env, obj)? })
+            } else {
+                ((variant, quote! { #path<'a> }), quote! { crate::FromRaw::from_raw(env, obj) })
+            }
+        })
+        .unzip();
+
+    // Subclasses outside of the tree cannot be told apart from other objects
+    if variants.is_empty() {
+        return quote! {};
+    }
+
+    quote! {
+        /// The subclasses permitted by the sealed Java type
+        pub enum #kind_ident<'a> {
+            #(#variants(#types),)*
+        }
+
+        impl<'a> #kind_ident<'a> {
+            /// Find out which permitted subclass `obj` is an instance of.
+            /// Returns `None` if it is not an instance of any of them, e.g. if it is `null`
+            pub fn classify(env: &mut jni::JNIEnv<'a>, obj: jni::objects::JObject<'a>) -> crate::JResult<Option<Self>> {
+                #(
+                    let class = <#types as crate::ClassName>::class_ref().get(env)?;
+                    if crate::catch_exception(env, |env| env.is_instance_of(&obj, class))? {
+                        return Ok(Some(Self::#variants(#values)));
+                    }
+                )*
+
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Generate the struct of a class, which only holds the object. Methods take the `JNIEnv` as their first argument
+fn generate_struct(name_ident: &Ident, type_parameters: &[Ident], deprecated: &TokenStream) -> TokenStream {
+    quote! {
+        #deprecated
+        #[repr(transparent)]
+        pub struct #name_ident<'a, #(#type_parameters),*> {
+            pub(crate) obj: jni::objects::JObject<'a>,
+            _marker: std::marker::PhantomData<(#(#type_parameters,)*)>,
+        }
+    }
+}
+
+/// `parent` is the superclass the struct derefs to
+fn generate_struct_trait_impls(name_ident: &Ident, type_parameters: &[Ident], fully_qualified_class_name: &str, parent: Option<&TokenStream>) -> TokenStream {
+    let deref = parent.map(|parent| quote! {
+        impl<'a, #(#type_parameters),*> std::ops::Deref for #name_ident<'a, #(#type_parameters),*> {
+            type Target = #parent<'a>;
+
+            fn deref(&self) -> &Self::Target {
+                // Both are `repr(transparent)` wrappers of the `JObject`, and the object is an instance of the superclass
+                unsafe { &*(self as *const Self as *const #parent<'a>) }
+            }
+        }
+    });
+    let name = name_ident.to_string();
+
+    quote! {
+        #deref
+
+        /// Shows the raw reference, as the runtime class cannot be looked up without a `JNIEnv`
+        impl<'a, #(#type_parameters),*> std::fmt::Debug for #name_ident<'a, #(#type_parameters),*> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(#name)
+                    .field("obj", &self.obj.as_raw())
+                    .finish()
+            }
+        }
+
+        impl<'a, #(#type_parameters),*> crate::ClassName for #name_ident<'a, #(#type_parameters),*> {
+            fn class_name() -> &'static str {
+                #fully_qualified_class_name
+            }
+
+            fn class_ref() -> &'static crate::ClassRef {
+                static CLASS: crate::ClassRef = crate::ClassRef::new(#fully_qualified_class_name);
+                &CLASS
+            }
+        }
+
+        impl<'a, #(#type_parameters),*> crate::FromRaw<'a> for #name_ident<'a, #(#type_parameters),*> {
+            fn from_raw(_env: &mut jni::JNIEnv<'a>, obj: jni::objects::JObject<'a>) -> Self {
+                Self::new_unchecked(obj)
+            }
+        }
+
+        impl<'a, #(#type_parameters),*> crate::JavaObject<'a> for #name_ident<'a, #(#type_parameters),*> {
+            fn obj(&self) -> &jni::objects::JObject<'a> {
+                &self.obj
+            }
+        }
+
+        impl<'a, #(#type_parameters),*> AsRef<jni::objects::JObject<'a>> for #name_ident<'a, #(#type_parameters),*> {
+            fn as_ref(&self) -> &jni::objects::JObject<'a> {
+                &self.obj
+            }
+        }
+
+        impl<'a, #(#type_parameters),*> From<#name_ident<'a, #(#type_parameters),*>> for jni::objects::JObject<'a> {
+            fn from(value: #name_ident<'a, #(#type_parameters),*>) -> Self {
+                value.obj
+            }
+        }
+    }
+}
+
+/// Generate an `as_<superclass>` method and a `From` impl for every superclass in the tree, see `class::generate_upcasts`.
+/// The method borrows the wrapper as its superclass
+fn generate_upcasts(class: &FormattedClassEntry, name_ident: &Ident, type_parameters: &[Ident], config: &Config) -> TokenStream {
+    if class.upcasts.is_empty() {
+        return quote! {};
+    }
+
+    let (methods, impls): (Vec<_>, Vec<_>) = class.upcasts.iter()
+        .map(|upcast| {
+            let superclass = generate_bindings_path(&upcast.class, config);
+            let method = if class.methods.iter().any(|x| x.rust_name == upcast.rust_name) {
+                quote! {}
+            } else {
+                let method_ident = format_ident!("{}", upcast.rust_name);
+                let doc = format!(" Upcast to the superclass `{}`", upcast.java_name);
+                quote! {
+                    #[doc = #doc]
+                    pub fn #method_ident(&self) -> &#superclass<'a> {
+                        // Both are `repr(transparent)` wrappers of the `JObject`, and the object is an instance of the superclass
+                        unsafe { &*(self as *const Self as *const #superclass<'a>) }
+                    }
+                }
+            };
+
+            let from_impl = quote! {
+                impl<'a, #(#type_parameters),*> From<#name_ident<'a, #(#type_parameters),*>> for #superclass<'a> {
+                    fn from(value: #name_ident<'a, #(#type_parameters),*>) -> Self {
+                        Self::new_unchecked(value.obj)
+                    }
+                }
+            };
+
+            (method, from_impl)
+        })
+        .unzip();
+
+    quote! {
+        impl<'a, #(#type_parameters),*> #name_ident<'a, #(#type_parameters),*> {
+            #(#methods)*
+        }
+
+        #(#impls)*
+    }
+}
+
+/// Generate `<Name>Global`, see `class::generate_global`
+fn generate_global(class: &FormattedClassEntry, name_ident: &Ident, type_parameters: &[Ident], deprecated: &TokenStream) -> TokenStream {
+    let global_ident = format_ident!("{}Global", name_ident);
+    let doc = format!(" A handle to a `{}` not tied to a `JNIEnv`, so it can be sent to other threads and stored.", class.java_name);
+
+    let to_global = if class.methods.iter().any(|x| x.rust_name == "to_global") {
+        quote! {}
+    } else {
+        let doc = format!(" Create a handle to the object not tied to the `JNIEnv`, see [`{global_ident}`]");
+        quote! {
+            impl<'a, #(#type_parameters),*> #name_ident<'a, #(#type_parameters),*> {
+                #[doc = #doc]
+                pub fn to_global(&self, env: &mut jni::JNIEnv<'a>) -> crate::JResult<#global_ident<#(#type_parameters),*>> {
+                    Ok(#global_ident {
+                        obj: crate::catch_exception(env, |env| env.new_global_ref(&self.obj))?,
+                        _marker: std::marker::PhantomData,
+                    })
+                }
+            }
+        }
+    };
+
+    quote! {
+        #[doc = #doc]
+        /// The object is kept alive until the handle is dropped. `attach` gets the wrapper back
+        #deprecated
+        pub struct #global_ident<#(#type_parameters),*> {
+            obj: jni::objects::GlobalRef,
+            // Only marks the Java type parameters, so the handle is `Send + Sync` regardless of them
+            _marker: std::marker::PhantomData<fn() -> (#(#type_parameters,)*)>,
+        }
+
+        impl<#(#type_parameters),*> #global_ident<#(#type_parameters),*> {
+            /// Get the wrapper for use with `env`, which must belong to the current thread
+            pub fn attach<'a>(&self, env: &mut jni::JNIEnv<'a>) -> crate::JResult<#name_ident<'a, #(#type_parameters),*>> {
+                crate::attach_global(env, &self.obj)
+            }
+
+            /// The global reference to the object
+            pub fn as_global_ref(&self) -> &jni::objects::GlobalRef {
+                &self.obj
+            }
+        }
+
+        /// Clones share the global reference, which is deleted when the last of them is dropped
+        impl<#(#type_parameters),*> Clone for #global_ident<#(#type_parameters),*> {
+            fn clone(&self) -> Self {
+                Self {
+                    obj: self.obj.clone(),
+                    _marker: std::marker::PhantomData,
+                }
+            }
+        }
+
+        #to_global
+    }
+}
+
+/// Generate a consuming `close`, as the object may not be used after being closed
+fn generate_closing(class: &FormattedClassEntry, name_ident: &Ident, type_parameters: &[Ident]) -> TokenStream {
+    if !is_closeable(class) {
+        return quote! {};
+    }
+
+    quote! {
+        impl<'a, #(#type_parameters),*> #name_ident<'a, #(#type_parameters),*> {
+            /// Java: `void close()`. Consumes the object, as it may not be used after being closed
+            pub fn close(self, env: &mut jni::JNIEnv<'a>) -> crate::JResult<()> {
+                crate::close(env, self.obj)
+            }
+        }
+    }
+}
+
+pub fn generate_class(class: &FormattedClassEntry, config: &Config) -> (TokenStream, Ident) {
+    let name_ident = class_ident(class);
+    let fully_qualified_class_path = class.java_name.replace('.', "/");
+
+    let type_parameters = generate_type_parameters(class, config);
+    let class_doc = generate_class_doc(class);
+    let deprecated = generate_deprecated(&class.deprecation);
+    // The upcasts start at the nearest superclass in the tree
+    let parent = class.upcasts.first()
+        .filter(|_| config.generator.inheritance == Inheritance::Deref)
+        .map(|x| generate_bindings_path(&x.class, config));
+    let gen_struct = generate_struct(&name_ident, &type_parameters, &deprecated);
+    let trait_impls = generate_struct_trait_impls(&name_ident, &type_parameters, &fully_qualified_class_path, parent.as_ref());
+    let interfaces = class.interfaces.iter()
+        .filter(|x| config.has_bindings(x))
+        .map(|x| generate_interface_conversion(&name_ident, &type_parameters, x, quote! { Self::new_unchecked(value.obj) }, config))
+        .collect::<Vec<_>>();
+
+    let sealed_kind = generate_sealed_kind(class, &name_ident, config);
+    let upcasts = generate_upcasts(class, &name_ident, &type_parameters, config);
+    let closing = generate_closing(class, &name_ident, &type_parameters);
+    let global = generate_global(class, &name_ident, &type_parameters, &deprecated);
+
+    let tokens = quote! {
+        #class_doc
+        #gen_struct
+
+        #sealed_kind
+
+        #trait_impls
+
+        #(#interfaces)*
+
+        #upcasts
+
+        #closing
+
+        #global
+    };
+
+    (tokens, name_ident)
+}
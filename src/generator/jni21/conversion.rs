@@ -0,0 +1,245 @@
+//! The code `Conversion`s generate for jni 0.21. Converting takes the `&mut JNIEnv` in scope as `env`,
+//! and converting to Rust consumes the reference, so the helpers delete the references they are done with
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use crate::config::Config;
+use crate::generator::conversion::{optional_primitive, Conversion};
+
+/// The type of an argument. Objects are borrowed at the top level, as the wrappers are not `Copy`, and owned with
+/// the lifetime `'a` of the JNIEnv in collections and optionals, whose references are consumed converting them
+pub fn argument_type(conversion: &Conversion, config: &Config, nullable: bool) -> TokenStream {
+    match conversion {
+        Conversion::Object(class_fq) => {
+            let rust_type = Conversion::object_type(class_fq, config, false);
+            if nullable {
+                quote! { Option<&#rust_type> }
+            } else {
+                quote! { &#rust_type }
+            }
+        },
+        _ if nullable => {
+            let rust_type = argument_type_in(conversion, config, true, false);
+            quote! { Option<#rust_type> }
+        },
+        _ => argument_type_in(conversion, config, false, false),
+    }
+}
+
+/// Like `Conversion::argument_type_in` and `Conversion::nullable_argument_type_in`, with owned objects
+fn argument_type_in(conversion: &Conversion, config: &Config, nullable: bool, collection: bool) -> TokenStream {
+    match conversion {
+        Conversion::String | Conversion::BigNumber(_, "String") if nullable => quote! { &str },
+        Conversion::String | Conversion::BigNumber(_, "String") => quote! { impl AsRef<str> },
+        Conversion::List(element) | Conversion::Array(element, _) => {
+            let element = argument_type_in(element, config, nullable, true);
+            if nullable {
+                quote! { Vec<#element> }
+            } else {
+                quote! { impl IntoIterator<Item = #element> }
+            }
+        },
+        Conversion::Map(key, value) => {
+            let key = argument_type_in(key, config, nullable, true);
+            let value = argument_type_in(value, config, nullable, true);
+            if nullable {
+                quote! { std::collections::HashMap<#key, #value> }
+            } else {
+                quote! { impl IntoIterator<Item = (#key, #value)> }
+            }
+        },
+        // Like a nullable argument
+        Conversion::Optional(value) => {
+            let value = argument_type_in(value, config, true, collection);
+            quote! { Option<#value> }
+        },
+        Conversion::OptionalPrimitive(primitive) => Conversion::optional_primitive_type(primitive, config),
+        Conversion::JavaTime(class_fq) => Conversion::java_time_type(class_fq, config),
+        Conversion::BigNumber(_, rust_type) => Conversion::big_number_type(rust_type),
+        Conversion::PrimitiveArray(element) if collection && nullable => {
+            let element = Conversion::primitive_type(element, config);
+            quote! { Vec<#element> }
+        },
+        Conversion::PrimitiveArray(element) if collection => {
+            let element = Conversion::primitive_type(element, config);
+            quote! { impl AsRef<[#element]> }
+        },
+        Conversion::PrimitiveArray(element) => {
+            let element = Conversion::primitive_type(element, config);
+            quote! { &[#element] }
+        },
+        Conversion::Enum(class_fq) | Conversion::Object(class_fq) => Conversion::object_type(class_fq, config, true),
+    }
+}
+
+/// An expression converting the owned Rust `value` to a `JObject`. May use `?` and `env`
+pub fn rust_to_java(conversion: &Conversion, value: TokenStream) -> TokenStream {
+    match conversion {
+        Conversion::String => quote! {
+            crate::new_string(env, AsRef::<str>::as_ref(&#value))?
+        },
+        Conversion::List(element) => {
+            let env_param = env_param(element);
+            let element = rust_to_java(element, quote! { element });
+            quote! {
+                crate::new_list(env, #value, |#env_param, element| Ok(#element))?
+            }
+        },
+        Conversion::Map(key, value_conversion) => {
+            let (key_env_param, value_env_param) = (env_param(key), env_param(value_conversion));
+            let key = rust_to_java(key, quote! { key });
+            let value_converted = rust_to_java(value_conversion, quote! { value });
+            quote! {
+                crate::new_map(env, #value, |#key_env_param, key| Ok(#key), |#value_env_param, value| Ok(#value_converted))?
+            }
+        },
+        Conversion::Optional(value_conversion) => {
+            let env_param = env_param(value_conversion);
+            let value_converted = rust_to_java(value_conversion, quote! { value });
+            quote! {
+                crate::new_optional(env, "java/util/Optional", "(Ljava/lang/Object;)Ljava/util/Optional;", #value, |#env_param, value| Ok(jni::objects::JValueOwned::Object(#value_converted)))?
+            }
+        },
+        Conversion::OptionalPrimitive(primitive) => {
+            let (class_name, _, signature) = optional_primitive(primitive);
+            let of_signature = format!("({signature})L{class_name};");
+            quote! {
+                crate::new_optional(env, #class_name, #of_signature, #value, |_, value| Ok(value.into()))?
+            }
+        },
+        Conversion::JavaTime(_) | Conversion::Enum(_) => quote! {
+            crate::JavaConvert::to_java(&#value, env)?
+        },
+        Conversion::BigNumber(class_fq, rust_type) => {
+            let class_name = class_fq.replace('.', "/");
+            let value = if *rust_type == "String" {
+                quote! { AsRef::<str>::as_ref(&#value) }
+            } else {
+                quote! { &ToString::to_string(&#value) }
+            };
+
+            quote! {
+                crate::new_big_number(env, #class_name, #value)?
+            }
+        },
+        Conversion::PrimitiveArray(_) => quote! {
+            crate::new_primitive_array(env, #value)?
+        },
+        Conversion::Array(element, element_class) => {
+            let env_param = env_param(element);
+            let element = rust_to_java(element, quote! { element });
+            quote! {
+                crate::new_object_array(env, #element_class, #value, |#env_param, element| Ok(#element))?
+            }
+        },
+        Conversion::Object(_) => quote! {
+            jni::objects::JObject::from(#value)
+        },
+    }
+}
+
+/// The name of the `env` parameter of a closure converting to Java, `_` if the conversion does not use it
+fn env_param(conversion: &Conversion) -> TokenStream {
+    match conversion {
+        Conversion::Object(_) => quote! { _ },
+        _ => quote! { env },
+    }
+}
+
+/// An expression converting the `JObject` `value` to the Rust return type, consuming it. May use `?` and `env`
+pub fn java_to_rust(conversion: &Conversion, value: TokenStream, config: &Config) -> TokenStream {
+    match conversion {
+        Conversion::String => quote! {
+            crate::get_string(env, #value)?
+        },
+        Conversion::List(element) => {
+            let element = java_to_rust(element, quote! { element }, config);
+            quote! {
+                crate::list_to_vec(env, #value, |env, element| Ok(#element))?
+            }
+        },
+        Conversion::Map(key, value_conversion) => {
+            let key = java_to_rust(key, quote! { key }, config);
+            let value_converted = java_to_rust(value_conversion, quote! { value }, config);
+            quote! {
+                crate::map_to_hash_map(env, #value, |env, key| Ok(#key), |env, value| Ok(#value_converted))?
+            }
+        },
+        Conversion::Optional(value_conversion) => {
+            let value_converted = java_to_rust(value_conversion, quote! { value.l()? }, config);
+            quote! {
+                crate::optional_to_option(env, #value, "get", "()Ljava/lang/Object;", |env, value| Ok(#value_converted))?
+            }
+        },
+        Conversion::OptionalPrimitive(primitive) => {
+            let (_, getter, signature) = optional_primitive(primitive);
+            let getter_signature = format!("(){signature}");
+            // The accessors of `JValueOwned` are named after the JNI signature of the primitive
+            let accessor = format_ident!("{}", signature.to_ascii_lowercase());
+            quote! {
+                crate::optional_to_option(env, #value, #getter, #getter_signature, |_, value| Ok(value.#accessor()?))?
+            }
+        },
+        Conversion::JavaTime(class_fq) => {
+            let rust_type = Conversion::java_time_type(class_fq, config);
+            quote! {
+                <#rust_type as crate::JavaConvert>::from_java(env, #value)?
+            }
+        },
+        Conversion::BigNumber(class_fq, rust_type) => {
+            // `toString` may use scientific notation for decimals, which not every Rust type parses
+            let getter = if class_fq == "java.math.BigDecimal" { "toPlainString" } else { "toString" };
+            let rust_type = Conversion::big_number_type(rust_type);
+            quote! {
+                crate::parse_big_number::<#rust_type>(env, #value, #getter)?
+            }
+        },
+        Conversion::PrimitiveArray(element) => {
+            let element = Conversion::primitive_type(element, config);
+            quote! {
+                <#element as crate::ArrayElement>::array_to_vec(env, #value)?
+            }
+        },
+        Conversion::Array(element, _) => {
+            let element = java_to_rust(element, quote! { element }, config);
+            quote! {
+                crate::object_array_to_vec(env, #value, |env, element| Ok(#element))?
+            }
+        },
+        Conversion::Enum(class_fq) => {
+            let rust_type = Conversion::object_type(class_fq, config, false);
+            quote! {
+                <#rust_type as crate::JavaConvert>::from_java(env, #value)?
+            }
+        },
+        Conversion::Object(_) => quote! {
+            crate::FromRaw::from_raw(env, #value)
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::class_tree::ArgumentType;
+
+    /// Compare ignoring whitespace, as types parsed from strings are spaced differently than `quote!`d ones
+    fn assert_tokens(expected: TokenStream, actual: TokenStream) {
+        let strip = |tokens: TokenStream| tokens.to_string().replace(' ', "");
+        assert_eq!(strip(expected), strip(actual));
+    }
+
+    #[test]
+    fn object_arguments() {
+        let config = Config::default();
+        let object = Conversion::new(&ArgumentType::Object("com.foo.Bar".to_string()), None, &config).unwrap();
+        assert_tokens(quote! { &crate::bindings::com::foo::Bar }, argument_type(&object, &config, false));
+        assert_tokens(quote! { Option<&crate::bindings::com::foo::Bar> }, argument_type(&object, &config, true));
+
+        // Objects in collections and optionals are owned, as converting them consumes the references
+        let list = Conversion::new(&ArgumentType::Object("java.util.List".to_string()), Some("java.util.List<com.foo.Bar>"), &config).unwrap();
+        assert_tokens(quote! { impl IntoIterator<Item = crate::bindings::com::foo::Bar<'a>> }, argument_type(&list, &config, false));
+        let optional = Conversion::new(&ArgumentType::Object("java.util.Optional".to_string()), Some("java.util.Optional<com.foo.Bar>"), &config).unwrap();
+        assert_tokens(quote! { Option<crate::bindings::com::foo::Bar<'a>> }, argument_type(&optional, &config, false));
+    }
+}
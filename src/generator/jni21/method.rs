@@ -0,0 +1,433 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use crate::class_tree::ArgumentType;
+use crate::config::Config;
+use crate::formatter::{FormattedClassEntry, FormattedConstructorEntry, FormattedMethodEntry, SingletonField};
+use crate::generator::conversion::Conversion;
+use crate::generator::jni21::conversion;
+use crate::generator::method::{
+    argument_conversion, constructor_as_method, generate_argument_idents, generate_argument_type, generate_constructor_doc,
+    generate_return_type, generate_return_value_type, generate_signature_doc, is_nullable_argument, is_nullable_return,
+    return_conversion, with_java_time_feature,
+};
+
+pub fn generate_method(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    with_java_time_feature(method, config, |config| {
+        if let Some(singleton) = &method.singleton {
+            generate_singleton(method, singleton, config)
+        } else if method.is_static {
+            generate_static(method, false, config)
+        } else {
+            generate_associated(method, config)
+        }
+    })
+}
+
+/// Generate a module-level function for a static method.
+/// Used for static interface methods, as they cannot be called through an implementing type
+pub fn generate_free_function(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    with_java_time_feature(method, config, |config| generate_static(method, true, config))
+}
+
+/// Generate a trait method with a default implementation for an instance method of an interface, abstract or not.
+/// The call is dispatched by the JVM. The object is provided by `crate::JavaObject`
+pub fn generate_trait_method(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    with_java_time_feature(method, config, |config| {
+        let name_ident = format_ident!("{}", method.rust_name);
+        let arguments = generate_rust_arguments(method, config);
+        let return_type = generate_return_type(method, config);
+
+        let call = generate_method_call(method, Call::Method(quote! { self.obj() }), config);
+        let return_handler = generate_return_handler(method, config);
+        let doc = generate_signature_doc(method);
+
+        quote! {
+            #doc
+            fn #name_ident(&self, env: &mut jni::JNIEnv<'a>, #arguments) -> #return_type {
+                let jvalue = #call;
+                #return_handler
+            }
+        }
+    })
+}
+
+/// Generate the constructors of a class that is not an inner class, see `method::generate_constructors`
+pub fn generate_constructors(class: &FormattedClassEntry, config: &Config) -> Vec<TokenStream> {
+    class.constructors.iter()
+        .map(|constructor| {
+            let method = constructor_as_method(class, constructor, 0);
+            with_java_time_feature(&method, config, |config| generate_constructor(class, constructor, config))
+        })
+        .collect()
+}
+
+fn generate_constructor(class: &FormattedClassEntry, constructor: &FormattedConstructorEntry, config: &Config) -> TokenStream {
+    let name_ident = format_ident!("{}", constructor.rust_name);
+    let method = constructor_as_method(class, constructor, 0);
+
+    let arguments = generate_rust_arguments(&method, config);
+    let argument_idents = generate_argument_idents(&method);
+
+    let class_name = class.java_name.replace('.', "/");
+    let call = generate_call(&method, &class_name, "<init>", &constructor.jni_signature, Call::Constructor(quote! { &[#(#argument_idents),*] }), config);
+    let doc = generate_constructor_doc(&method, &format!("new {}({})", class.simple_name, method.generic_arguments.join(", ")));
+
+    quote! {
+        #doc
+        pub fn #name_ident(env: &mut jni::JNIEnv<'a>, #arguments) -> crate::JResult<Self> {
+            let obj = #call.l()?;
+            Ok(crate::FromRaw::from_raw(env, obj))
+        }
+    }
+}
+
+/// Generate `new_unchecked`, wrapping an object obtained elsewhere, e.g. through raw JNI calls.
+/// The object is not checked to be an instance of the class
+pub fn generate_new_unchecked(class: &FormattedClassEntry) -> TokenStream {
+    let doc = format!(" Wrap `obj` without checking that it is an instance of `{}`, or that it is not `null`.", class.java_name);
+
+    quote! {
+        #[doc = #doc]
+        /// The object must be an instance of the class, as methods are called through the IDs of the declaring classes.
+        pub fn new_unchecked(obj: jni::objects::JObject<'a>) -> Self {
+            Self {
+                obj,
+                _marker: std::marker::PhantomData,
+            }
+        }
+    }
+}
+
+/// Generate the constructors of a non-static inner class, see `method::generate_inner_constructors`
+pub fn generate_inner_constructors(class: &FormattedClassEntry, config: &Config) -> Vec<TokenStream> {
+    class.constructors.iter()
+        .map(|constructor| {
+            let method = constructor_as_method(class, constructor, 1);
+            with_java_time_feature(&method, config, |config| generate_inner_constructor(class, constructor, config))
+        })
+        .collect()
+}
+
+fn generate_inner_constructor(class: &FormattedClassEntry, constructor: &FormattedConstructorEntry, config: &Config) -> TokenStream {
+    let name_ident = format_ident!("{}", constructor.rust_name);
+
+    let outer_type = constructor.arguments.first()
+        .expect("Inner class constructor without enclosing instance");
+    let outer_type = generate_argument_type(outer_type, config);
+
+    // The explicit arguments are handled like those of a static method
+    let method = constructor_as_method(class, constructor, 1);
+    let arguments = generate_rust_arguments(&method, config);
+    let argument_idents = generate_argument_idents(&method);
+
+    let class_name = class.java_name.replace('.', "/");
+    let jvalue_array = quote! { &[jni::objects::JValue::Object(crate::JavaObject::obj(outer)).as_jni(), #(#argument_idents),*] };
+    let call = generate_call(&method, &class_name, "<init>", &constructor.jni_signature, Call::Constructor(jvalue_array), config);
+    let doc = generate_constructor_doc(&method, &format!("outer.new {}({})", class.simple_name, method.generic_arguments.join(", ")));
+
+    quote! {
+        #doc
+        pub fn #name_ident(env: &mut jni::JNIEnv<'a>, outer: &#outer_type, #arguments) -> crate::JResult<Self> {
+            let obj = #call.l()?;
+            Ok(crate::FromRaw::from_raw(env, obj))
+        }
+    }
+}
+
+/// Generate a static method. If `free_function` is set, the function declares its own lifetime,
+/// rather than using the one of the surrounding `impl` block
+fn generate_static(method: &FormattedMethodEntry, free_function: bool, config: &Config) -> TokenStream {
+    let name_ident = format_ident!("{}", method.rust_name);
+    let arguments = generate_rust_arguments(method, config);
+    let return_type = generate_return_type(method, config);
+
+    let call = generate_method_call(method, Call::Static, config);
+    let return_handler = generate_return_handler(method, config);
+    let doc = generate_signature_doc(method);
+    let generics = if free_function {
+        quote! { <'a> }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        #doc
+        pub fn #name_ident #generics(env: &mut jni::JNIEnv<'a>, #arguments) -> #return_type {
+            let jvalue = #call;
+            #return_handler
+        }
+    }
+}
+
+fn generate_associated(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    let name_ident = format_ident!("{}", method.rust_name);
+    let arguments = generate_rust_arguments(method, config);
+    let return_type = generate_return_type(method, config);
+
+    let call = generate_method_call(method, Call::Method(quote! { &self.obj }), config);
+    let return_handler = generate_return_handler(method, config);
+    let doc = generate_signature_doc(method);
+
+    quote! {
+        #doc
+        pub fn #name_ident(&self, env: &mut jni::JNIEnv<'a>, #arguments) -> #return_type {
+            let jvalue = #call;
+            #return_handler
+        }
+    }
+}
+
+/// Generate an associated function for an instance method of a singleton, see `method::generate_singleton`
+fn generate_singleton(method: &FormattedMethodEntry, singleton: &SingletonField, config: &Config) -> TokenStream {
+    let name_ident = format_ident!("{}", method.rust_name);
+    let arguments = generate_rust_arguments(method, config);
+    let return_type = generate_return_type(method, config);
+
+    let singleton = generate_singleton_field(singleton);
+    let call = generate_method_call(method, Call::Method(quote! { &singleton }), config);
+    let return_handler = generate_return_handler(method, config);
+    let doc = generate_signature_doc(method);
+
+    quote! {
+        #doc
+        pub fn #name_ident(env: &mut jni::JNIEnv<'a>, #arguments) -> #return_type {
+            let singleton = #singleton;
+            let jvalue = #call;
+            #return_handler
+        }
+    }
+}
+
+/// Read the static field holding a singleton. Evaluates to the `JObject`
+fn generate_singleton_field(singleton: &SingletonField) -> TokenStream {
+    let class_name = singleton.class.replace('.', "/");
+    let name = &singleton.name;
+    let signature = &singleton.jni_signature;
+    quote! {
+        crate::catch_exception(env, |env| env.get_static_field(#class_name, #name, #signature))?.l()?
+    }
+}
+
+/// Generate `instance`, see `method::generate_instance_accessor`
+pub fn generate_instance_accessor(class: &FormattedClassEntry) -> TokenStream {
+    let Some(instance_field) = &class.instance_field else {
+        return quote! {};
+    };
+    if class.methods.iter().any(|x| x.rust_name == "instance") {
+        return quote! {};
+    }
+
+    let singleton = generate_singleton_field(instance_field);
+    let doc = format!(" Java: `{}.{}`", class.java_name, instance_field.name);
+
+    quote! {
+        #[doc = #doc]
+        /// The only instance of the class.
+        pub fn instance(env: &mut jni::JNIEnv<'a>) -> crate::JResult<Self> {
+            let obj = #singleton;
+            Ok(crate::FromRaw::from_raw(env, obj))
+        }
+    }
+}
+
+/// Convert the returned `JValueOwned` to the Rust return type. Objects are converted as described by `Conversion`,
+/// which consumes the returned reference unless the Rust value holds it
+fn generate_return_handler(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    let return_type = match &method.return_type {
+        Some(return_type) => return_type,
+        None => return quote! {
+            jvalue.v()?;
+            Ok(())
+        },
+    };
+
+    let nullable = is_nullable_return(method, config);
+    let value = match return_type {
+        ArgumentType::Boolean => quote! { jvalue.z()? },
+        // Bytes are unsigned in Rust
+        ArgumentType::Byte => quote! { jvalue.b()? as u8 },
+        ArgumentType::Char => quote! { jvalue.c()? },
+        ArgumentType::Short => quote! { jvalue.s()? },
+        ArgumentType::Int => quote! { jvalue.i()? },
+        ArgumentType::Long => quote! { jvalue.j()? },
+        ArgumentType::Float => quote! { jvalue.f()? },
+        ArgumentType::Double => quote! { jvalue.d()? },
+        ArgumentType::Object(_) | ArgumentType::Array(_) => {
+            let rust_type = generate_return_value_type(method, config);
+            let conversion = return_conversion(method, config).expect("Objects and arrays are converted");
+            let value = conversion::java_to_rust(&conversion, quote! { value }, config);
+            let null_check = if nullable {
+                quote! {
+                    if value.is_null() {
+                        return Ok(None);
+                    }
+                }
+            } else {
+                quote! {}
+            };
+
+            quote! {
+                {
+                    let value = jvalue.l()?;
+                    #null_check
+                    let value: #rust_type = #value;
+                    value
+                }
+            }
+        },
+    };
+
+    if nullable {
+        quote! { Ok(Some(#value)) }
+    } else {
+        quote! { Ok(#value) }
+    }
+}
+
+/// How a generated function calls into Java
+enum Call {
+    /// Call an instance method on the `&JObject`
+    Method(TokenStream),
+    Static,
+    /// Call a constructor with the array of arguments, which for inner classes includes the enclosing instance
+    Constructor(TokenStream),
+}
+
+/// Call the method on the class declaring it, see `generate_call`
+fn generate_method_call(method: &FormattedMethodEntry, call: Call, config: &Config) -> TokenStream {
+    let class_name = method.declaring_class_java.replace('.', "/");
+    generate_call(method, &class_name, &method.java_name, &method.jni_signature, call, config)
+}
+
+/// Convert the arguments and make the JNI call in a local frame, like `method::generate_call`.
+/// Evaluates to the returned `JValueOwned`
+fn generate_call(method: &FormattedMethodEntry, class_name: &str, java_name: &str, signature: &str, call: Call, config: &Config) -> TokenStream {
+    let jvalues = generate_jvalue_arguments(method, config);
+    let argument_idents = generate_argument_idents(method);
+    let call = match call {
+        Call::Method(obj) => {
+            let ret = generate_return_kind(method);
+            quote! { METHOD.call(env, #obj, #ret, &[#(#argument_idents),*]) }
+        },
+        Call::Static => {
+            let ret = generate_return_kind(method);
+            quote! { METHOD.call_static(env, #ret, &[#(#argument_idents),*]) }
+        },
+        Call::Constructor(jvalue_array) => quote! { METHOD.new_object(env, #jvalue_array).map(Into::into) },
+    };
+
+    quote! {
+        {
+            static METHOD: crate::MethodId = crate::MethodId::new(#class_name, #java_name, #signature);
+            crate::call_in_frame(env, |env| {
+                #jvalues
+                #call
+            })?
+        }
+    }
+}
+
+/// The `ReturnType` the unchecked JNI calls take to pick the function for the return type
+fn generate_return_kind(method: &FormattedMethodEntry) -> TokenStream {
+    let primitive = match &method.return_type {
+        None => quote! { Void },
+        Some(ArgumentType::Boolean) => quote! { Boolean },
+        Some(ArgumentType::Byte) => quote! { Byte },
+        Some(ArgumentType::Char) => quote! { Char },
+        Some(ArgumentType::Short) => quote! { Short },
+        Some(ArgumentType::Int) => quote! { Int },
+        Some(ArgumentType::Long) => quote! { Long },
+        Some(ArgumentType::Float) => quote! { Float },
+        Some(ArgumentType::Double) => quote! { Double },
+        Some(ArgumentType::Object(_)) => return quote! { jni::signature::ReturnType::Object },
+        Some(ArgumentType::Array(_)) => return quote! { jni::signature::ReturnType::Array },
+    };
+
+    quote! { jni::signature::ReturnType::Primitive(jni::signature::Primitive::#primitive) }
+}
+
+/// Convert the Rust arguments to `jni::sys::jvalue`s. Converted objects are kept in a binding of the same name,
+/// which the `jvalue` then shadows, so they live until the end of the frame
+fn generate_jvalue_arguments(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    let argument_idents = generate_argument_idents(method);
+
+    let tokens = method.arguments.iter().zip(&argument_idents).enumerate()
+        .map(|(idx, (argument_type, ident))| {
+            let jvalue = |variant: TokenStream| quote! {
+                let #ident = jni::objects::JValue::#variant.as_jni();
+            };
+
+            match argument_type {
+                ArgumentType::Boolean => jvalue(quote! { Bool(#ident as u8) }),
+                ArgumentType::Byte => jvalue(quote! { Byte(#ident as i8) }),
+                ArgumentType::Char => jvalue(quote! { Char(#ident) }),
+                ArgumentType::Short => jvalue(quote! { Short(#ident) }),
+                ArgumentType::Int => jvalue(quote! { Int(#ident) }),
+                ArgumentType::Long => jvalue(quote! { Long(#ident) }),
+                ArgumentType::Float => jvalue(quote! { Float(#ident) }),
+                ArgumentType::Double => jvalue(quote! { Double(#ident) }),
+                ArgumentType::Object(_) | ArgumentType::Array(_) => {
+                    let conversion = argument_conversion(method, idx, config).expect("Objects and arrays are converted");
+                    generate_object_argument(&conversion, ident, is_nullable_argument(method, idx, config))
+                },
+            }
+        })
+        .collect::<Vec<_>>();
+
+    quote! {
+        #(#tokens)*
+    }
+}
+
+fn generate_object_argument(conversion: &Conversion, ident: &Ident, nullable: bool) -> TokenStream {
+    match conversion {
+        // Borrowed, so the caller keeps the reference
+        Conversion::Object(_) if nullable => quote! {
+            let #ident = match #ident {
+                Some(value) => jni::objects::JValue::Object(crate::JavaObject::obj(value)).as_jni(),
+                None => jni::objects::JValue::Object(&jni::objects::JObject::null()).as_jni(),
+            };
+        },
+        Conversion::Object(_) => quote! {
+            let #ident = jni::objects::JValue::Object(crate::JavaObject::obj(#ident)).as_jni();
+        },
+        _ => {
+            let object = if nullable {
+                let value = conversion::rust_to_java(conversion, quote! { value });
+                quote! {
+                    match #ident {
+                        Some(value) => #value,
+                        None => jni::objects::JObject::null(),
+                    }
+                }
+            } else {
+                conversion::rust_to_java(conversion, quote! { #ident })
+            };
+
+            quote! {
+                let #ident = #object;
+                let #ident = jni::objects::JValue::Object(&#ident).as_jni();
+            }
+        },
+    }
+}
+
+fn generate_rust_arguments(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    let argument_idents = generate_argument_idents(method);
+    let tokens = method.arguments.iter().zip(&argument_idents).enumerate()
+        .map(|(idx, (argument_type, ident))| {
+            let ty = match argument_conversion(method, idx, config) {
+                Some(conversion) => conversion::argument_type(&conversion, config, is_nullable_argument(method, idx, config)),
+                None => generate_argument_type(argument_type, config),
+            };
+
+            quote! {
+                #ident: #ty
+            }
+        })
+        .collect::<Vec<_>>();
+
+    quote! {
+        #(#tokens),*
+    }
+}
@@ -0,0 +1,106 @@
+//! Code generation for jni 0.21, selected by `generator.jni_version`. Its `JNIEnv` is passed as `&mut` to every call,
+//! so the wrappers only hold the `JObject`, and methods take the env as their first argument after `self`.
+//! The crate layout, conversions and helper names follow those of the jni 0.19 code in the parent module
+
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use crate::class_tree::ClassType;
+use crate::config::Config;
+use crate::formatter::FormattedClassEntry;
+use crate::generator::class::{generate_type_parameters, is_close_method, is_closeable};
+use crate::generator::jni21::class::{generate_class, generate_enum, generate_interface};
+use crate::generator::jni21::method::{generate_constructors, generate_inner_constructors, generate_instance_accessor, generate_method, generate_new_unchecked};
+
+mod class;
+mod conversion;
+mod method;
+pub(super) mod runtime;
+
+/// Generate the code of a class, see `generator::generate_entry`. Annotation types get no builder,
+/// as building them relies on the Java helpers
+pub fn generate_entry(class: &FormattedClassEntry, config: &Config) -> TokenStream {
+    let type_parameters = generate_type_parameters(class, config);
+    let (class_tokens, class_ident) = match class.class_type {
+        ClassType::Class => generate_class(class, config),
+        // Interface methods are generated as part of the trait
+        ClassType::Interface => return generate_interface(class, config),
+        ClassType::Annotation => return quote! {},
+        // Enum constants are fully described by the generated Rust enum
+        ClassType::Enum => return generate_enum(class),
+    };
+
+    // Closeable classes get a consuming `close` instead
+    let closeable = is_closeable(class);
+    let methods = class.methods.iter()
+        .filter(|x| !(closeable && is_close_method(x)))
+        .map(|x| generate_method(x, config))
+        .collect::<Vec<_>>();
+
+    // Only inner classes need an enclosing instance, other classes are constructed through `new`
+    let constructors = match (class.is_abstract, class.is_inner_class) {
+        (true, _) => Vec::new(),
+        (false, true) => generate_inner_constructors(class, config),
+        (false, false) => generate_constructors(class, config),
+    };
+    let new_unchecked = generate_new_unchecked(class);
+    let instance = generate_instance_accessor(class);
+
+    quote! {
+        #class_tokens
+
+        impl<'a, #(#type_parameters),*> #class_ident<'a, #(#type_parameters),*> {
+            #(#constructors)*
+            #new_unchecked
+            #instance
+            #(#methods)*
+        }
+    }
+}
+
+/// Generate `JavaException::from_throwable`, see `generator::generate_exceptions`
+pub fn generate_from_throwable(jni_names: &[String], variants: &[Ident]) -> TokenStream {
+    let variant_count = variants.len();
+
+    quote! {
+        /// Get the variant for a thrown exception. Subclasses of a declared exception map to its variant,
+        /// e.g. a `java.io.FileNotFoundException` to `IoException` if only `java.io.IOException` is declared
+        pub fn from_throwable<'a>(env: &mut jni::JNIEnv<'a>, throwable: &jni::objects::JThrowable<'_>) -> crate::JResult<Self> {
+            let class = env.get_object_class(throwable)?;
+            let class = env.auto_local(class);
+            let name = env.call_method(&class, "getName", "()Ljava/lang/String;", &[])?.l()?;
+            let name = crate::get_string(env, name)?;
+
+            let exact = Self::from_class_name(&name);
+            if !matches!(exact, Self::Other(_)) {
+                return Ok(exact);
+            }
+
+            let candidates: [(&str, Self); #variant_count] = [#((#jni_names, Self::#variants)),*];
+            let mut best: Option<(jni::objects::AutoLocal<'a, jni::objects::JClass<'a>>, Self)> = None;
+            for (jni_name, variant) in candidates {
+                // The exception class might not be on the classpath
+                let candidate = match env.find_class(jni_name) {
+                    Ok(candidate) => env.auto_local(candidate),
+                    Err(_) => {
+                        env.exception_clear()?;
+                        continue;
+                    }
+                };
+
+                if !env.is_instance_of(throwable, &candidate)? {
+                    continue;
+                }
+
+                let replace = match &best {
+                    Some((class, _)) => env.is_assignable_from(&candidate, class)?,
+                    None => true,
+                };
+                if replace {
+                    best = Some((candidate, variant));
+                }
+            }
+
+            Ok(best.map(|(_, variant)| variant).unwrap_or(Self::Other(name)))
+        }
+    }
+}
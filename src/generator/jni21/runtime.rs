@@ -0,0 +1,861 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use crate::config::JavaTime;
+use crate::generator::runtime::OBJECT_MONITOR_FEATURE;
+
+/// Generate `runtime.rs` for jni 0.21. The items match those of the jni 0.19 runtime where the API allows,
+/// but take a `&mut JNIEnv<'a>`, as the wrappers do not hold one. Helpers converting Java objects consume the
+/// `JObject`, deleting the local reference once they are done with it
+pub fn generate_runtime() -> TokenStream {
+    let array_elements = generate_array_elements();
+
+    quote! {
+        use std::fmt;
+        use jni::objects::{JObject, JValueOwned};
+        use jni::JNIEnv;
+
+        /// The result of a call into Java
+        pub type JResult<T> = Result<T, Error>;
+
+        /// An error calling into Java
+        #[derive(Debug)]
+        pub enum Error {
+            /// The Java code threw an exception. It is cleared, so the JNIEnv can be used again
+            Exception {
+                exception: crate::JavaException,
+                /// The fully qualified class name of the thrown exception, which might be a subclass of `exception`
+                class_name: String,
+                message: Option<String>,
+            },
+            /// JNI itself failed, e.g. because a class or method could not be found
+            Jni(jni::errors::Error),
+            /// A value could not be converted between Java and Rust, e.g. because it is out of the range of the Rust type
+            Conversion(String),
+        }
+
+        impl fmt::Display for Error {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    Self::Exception { class_name, message: Some(message), .. } => write!(f, "{}: {}", class_name, message),
+                    Self::Exception { class_name, message: None, .. } => write!(f, "{}", class_name),
+                    Self::Jni(e) => write!(f, "JNI error: {}", e),
+                    Self::Conversion(message) => write!(f, "Conversion error: {}", message),
+                }
+            }
+        }
+
+        impl std::error::Error for Error {
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                match self {
+                    Self::Jni(e) => Some(e),
+                    Self::Exception { .. } | Self::Conversion(_) => None,
+                }
+            }
+        }
+
+        impl From<jni::errors::Error> for Error {
+            fn from(e: jni::errors::Error) -> Self {
+                Self::Jni(e)
+            }
+        }
+
+        /// A type bound to a Java class
+        pub trait ClassName {
+            /// The name of the class in the notation of JNI, e.g. `java/lang/String`
+            fn class_name() -> &'static str;
+
+            /// The class, looked up on first use
+            fn class_ref() -> &'static ClassRef;
+        }
+
+        /// A type converted from a Java object
+        pub trait FromRaw<'a> {
+            /// Wrap or convert an object, which must be an instance of the class the type is bound to
+            fn from_raw(env: &mut JNIEnv<'a>, obj: JObject<'a>) -> Self;
+        }
+
+        /// A type that wraps a Java object, through which the traits of Java interfaces call into Java
+        pub trait JavaObject<'a> {
+            /// The underlying Java object
+            fn obj(&self) -> &JObject<'a>;
+
+            /// Whether the object is an instance of the class `T` is bound to, like Java's `instanceof`
+            fn is_instance_of<T: ClassName>(&self, env: &mut JNIEnv<'a>) -> JResult<bool>
+            where
+                Self: Sized,
+            {
+                let class = T::class_ref().get(env)?;
+                catch_exception(env, |env| env.is_instance_of(self.obj(), class))
+            }
+
+            /// Downcast to a more specific type, e.g. a subclass or an implemented interface's class,
+            /// through a new local reference. Returns `None` if the object is not an instance of its class
+            fn try_cast<T: ClassName + FromRaw<'a>>(&self, env: &mut JNIEnv<'a>) -> JResult<Option<T>>
+            where
+                Self: Sized,
+            {
+                if !self.is_instance_of::<T>(env)? {
+                    return Ok(None);
+                }
+
+                let obj = catch_exception(env, |env| env.new_local_ref(self.obj()))?;
+                Ok(Some(T::from_raw(env, obj)))
+            }
+
+            /// Java: `Class<?> getClass()`. The runtime class of the object
+            fn get_class(&self, env: &mut JNIEnv<'a>) -> JResult<jni::objects::JClass<'a>> {
+                catch_exception(env, |env| env.get_object_class(self.obj()))
+            }
+
+            /// Java: `int hashCode()`
+            fn hash_code(&self, env: &mut JNIEnv<'a>) -> JResult<i32> {
+                Ok(catch_exception(env, |env| env.call_method(self.obj(), "hashCode", "()I", &[]))?.i()?)
+            }
+
+            /// Java: `boolean equals(Object)`
+            fn equals(&self, env: &mut JNIEnv<'a>, other: &dyn JavaObject<'a>) -> JResult<bool> {
+                let other = jni::objects::JValue::Object(other.obj());
+                Ok(catch_exception(env, |env| env.call_method(self.obj(), "equals", "(Ljava/lang/Object;)Z", &[other]))?.z()?)
+            }
+
+            /// Java: `String toString()`. A `null` result is returned as `"null"`
+            fn java_to_string(&self, env: &mut JNIEnv<'a>) -> JResult<String> {
+                let value = catch_exception(env, |env| env.call_method(self.obj(), "toString", "()Ljava/lang/String;", &[]))?.l()?;
+                if value.is_null() {
+                    Ok("null".to_string())
+                } else {
+                    get_string(env, value)
+                }
+            }
+
+            /// Enter the monitor of the object until the guard is dropped, like Java's `synchronized`.
+            /// The monitor must be held for `wait` and `notify`
+            #[cfg(feature = #OBJECT_MONITOR_FEATURE)]
+            fn lock(&self, env: &mut JNIEnv<'a>) -> JResult<jni::MonitorGuard<'a>> {
+                catch_exception(env, |env| env.lock_obj(self.obj()))
+            }
+
+            /// Java: `void wait()`
+            #[cfg(feature = #OBJECT_MONITOR_FEATURE)]
+            fn wait(&self, env: &mut JNIEnv<'a>) -> JResult<()> {
+                catch_exception(env, |env| env.call_method(self.obj(), "wait", "()V", &[]))?;
+                Ok(())
+            }
+
+            /// Java: `void wait(long)`. Java waits indefinitely for a timeout of zero, so it is rounded up to a millisecond
+            #[cfg(feature = #OBJECT_MONITOR_FEATURE)]
+            fn wait_timeout(&self, env: &mut JNIEnv<'a>, timeout: std::time::Duration) -> JResult<()> {
+                let millis = i64::try_from(timeout.as_millis()).unwrap_or(i64::MAX).max(1);
+                catch_exception(env, |env| env.call_method(self.obj(), "wait", "(J)V", &[millis.into()]))?;
+                Ok(())
+            }
+
+            /// Java: `void notify()`
+            #[cfg(feature = #OBJECT_MONITOR_FEATURE)]
+            fn notify(&self, env: &mut JNIEnv<'a>) -> JResult<()> {
+                catch_exception(env, |env| env.call_method(self.obj(), "notify", "()V", &[]))?;
+                Ok(())
+            }
+
+            /// Java: `void notifyAll()`
+            #[cfg(feature = #OBJECT_MONITOR_FEATURE)]
+            fn notify_all(&self, env: &mut JNIEnv<'a>) -> JResult<()> {
+                catch_exception(env, |env| env.call_method(self.obj(), "notifyAll", "()V", &[]))?;
+                Ok(())
+            }
+        }
+
+        /// An object of a class without bindings or a mapping, these are passed untyped
+        #[repr(transparent)]
+        pub struct Object<'a>(pub JObject<'a>);
+
+        impl<'a> ClassName for Object<'a> {
+            fn class_name() -> &'static str {
+                "java/lang/Object"
+            }
+
+            fn class_ref() -> &'static ClassRef {
+                static CLASS: ClassRef = ClassRef::new("java/lang/Object");
+                &CLASS
+            }
+        }
+
+        impl<'a> FromRaw<'a> for Object<'a> {
+            fn from_raw(_env: &mut JNIEnv<'a>, obj: JObject<'a>) -> Self {
+                Self(obj)
+            }
+        }
+
+        impl<'a> JavaObject<'a> for Object<'a> {
+            fn obj(&self) -> &JObject<'a> {
+                &self.0
+            }
+        }
+
+        impl<'a> From<Object<'a>> for JObject<'a> {
+            fn from(value: Object<'a>) -> Self {
+                value.0
+            }
+        }
+
+        /// Create a local reference to the object of a global one, wrapped as `T`. For the `attach` methods of the global handles
+        pub fn attach_global<'a, T: FromRaw<'a>>(env: &mut JNIEnv<'a>, global: &jni::objects::GlobalRef) -> JResult<T> {
+            let obj = catch_exception(env, |env| env.new_local_ref(global))?;
+            Ok(T::from_raw(env, obj))
+        }
+
+        /// A Rust type converted from and to objects of a Java class
+        pub trait JavaConvert<'a>: Sized {
+            /// Convert a Java object, which must not be `null`. Deletes the reference
+            fn from_java(env: &mut JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self>;
+
+            /// Create the Java object for the value
+            fn to_java(&self, env: &mut JNIEnv<'a>) -> JResult<JObject<'a>>;
+        }
+
+        /// A primitive copied from and to Java arrays as a whole, with the `Get/Set<Type>ArrayRegion` functions
+        pub trait ArrayElement: Sized {
+            /// Create a Java array holding a copy of the values
+            fn new_array<'a>(env: &mut JNIEnv<'a>, values: &[Self]) -> JResult<JObject<'a>>;
+
+            /// Copy the elements of a Java array, which must not be `null`. Deletes the reference
+            fn array_to_vec<'a>(env: &mut JNIEnv<'a>, array: JObject<'a>) -> JResult<Vec<Self>>;
+        }
+
+        #array_elements
+
+        /// Copy the values into a new Java array of their primitive type
+        pub fn new_primitive_array<'a, T: ArrayElement>(env: &mut JNIEnv<'a>, values: impl AsRef<[T]>) -> JResult<JObject<'a>> {
+            T::new_array(env, values.as_ref())
+        }
+
+        /// Copy the converted elements into a new Java array of objects.
+        /// `element_class` is the class of the elements in the notation of JNI, e.g. `java/lang/String` or `[F`
+        pub fn new_object_array<'a, T>(
+            env: &mut JNIEnv<'a>,
+            element_class: &str,
+            elements: impl IntoIterator<Item = T>,
+            convert: impl Fn(&mut JNIEnv<'a>, T) -> JResult<JObject<'a>>,
+        ) -> JResult<JObject<'a>> {
+            // The length must be known up front
+            let elements = elements.into_iter()
+                .map(|element| {
+                    let element = convert(env, element)?;
+                    Ok(env.auto_local(element))
+                })
+                .collect::<JResult<Vec<_>>>()?;
+            let length = array_length(&elements)?;
+            let array = catch_exception(env, |env| env.new_object_array(length, element_class, JObject::null()))?;
+            for (idx, element) in elements.iter().enumerate() {
+                catch_exception(env, |env| env.set_object_array_element(&array, idx as jni::sys::jsize, element))?;
+            }
+
+            Ok(array.into())
+        }
+
+        /// Convert the elements of a Java array of objects, which must not be `null`. Deletes the reference
+        pub fn object_array_to_vec<'a, T>(
+            env: &mut JNIEnv<'a>,
+            array: JObject<'a>,
+            convert: impl Fn(&mut JNIEnv<'a>, JObject<'a>) -> JResult<T>,
+        ) -> JResult<Vec<T>> {
+            let array = env.auto_local(jni::objects::JObjectArray::from(array));
+            let length = catch_exception(env, |env| env.get_array_length(&*array))?;
+            (0..length)
+                .map(|idx| {
+                    let element = catch_exception(env, |env| env.get_object_array_element(&*array, idx))?;
+                    convert(env, element)
+                })
+                .collect()
+        }
+
+        /// The length of a Java array with the values
+        fn array_length<T>(values: &[T]) -> JResult<jni::sys::jsize> {
+            jni::sys::jsize::try_from(values.len())
+                .map_err(|_| Error::Conversion(format!("{} elements do not fit in a Java array", values.len())))
+        }
+
+        /// Run a JNI call and turn its result into a `JResult`. If the call threw,
+        /// the pending exception is cleared and returned as `Error::Exception`.
+        /// Otherwise the JVM aborts on the next JNI call
+        pub fn catch_exception<'a, T>(env: &mut JNIEnv<'a>, call: impl FnOnce(&mut JNIEnv<'a>) -> jni::errors::Result<T>) -> JResult<T> {
+            match call(env) {
+                Ok(value) => Ok(value),
+                // Failing JNI functions leave an exception pending as well,
+                // e.g. a `NoSuchMethodError` if a method cannot be found
+                Err(e) if !env.exception_check()? => Err(Error::Jni(e)),
+                Err(_) => {
+                    let throwable = env.exception_occurred()?;
+                    env.exception_clear()?;
+                    Err(exception_error(env, throwable)?)
+                },
+            }
+        }
+
+        /// The `Error::Exception` describing a throwable. Deletes the reference
+        fn exception_error<'a>(env: &mut JNIEnv<'a>, throwable: jni::objects::JThrowable<'a>) -> JResult<Error> {
+            let throwable = env.auto_local(throwable);
+            let class = env.get_object_class(&throwable)?;
+            let class = env.auto_local(class);
+            let name = env.call_method(&class, "getName", "()Ljava/lang/String;", &[])?.l()?;
+            let message = env.call_method(&throwable, "getMessage", "()Ljava/lang/String;", &[])?.l()?;
+
+            Ok(Error::Exception {
+                exception: crate::JavaException::from_throwable(env, &throwable)?,
+                class_name: get_string(env, name)?,
+                message: if message.is_null() {
+                    None
+                } else {
+                    Some(get_string(env, message)?)
+                },
+            })
+        }
+
+        /// Run `f`, converting arguments and calling into Java, in a new frame of local references. The references created
+        /// in it are deleted afterwards, so they do not accumulate when calling in a loop. A returned object is kept,
+        /// as a new reference in the enclosing frame. `f` must not keep any other reference it creates
+        pub(crate) fn call_in_frame<'a>(env: &mut JNIEnv<'a>, f: impl FnOnce(&mut JNIEnv<'a>) -> JResult<JValueOwned<'a>>) -> JResult<JValueOwned<'a>> {
+            catch_exception(env, |env| env.push_local_frame(LOCAL_FRAME_CAPACITY))?;
+            let result = f(env);
+            let kept = match &result {
+                Ok(JValueOwned::Object(obj)) => obj,
+                _ => &JObject::null(),
+            };
+
+            // Only the kept reference is used after the frame is popped
+            let kept = unsafe { env.pop_local_frame(kept) }?;
+            match result {
+                Ok(JValueOwned::Object(_)) => Ok(JValueOwned::Object(kept)),
+                result => result,
+            }
+        }
+
+        /// The number of local references a frame is created for. The JVM grows frames if more are needed
+        const LOCAL_FRAME_CAPACITY: i32 = 16;
+
+        /// A class looked up by `FindClass` on first use, for the statics of the generated code.
+        /// The global reference is never deleted, which keeps the class loaded
+        pub struct ClassRef {
+            name: &'static str,
+            class: once_cell::sync::OnceCell<jni::objects::GlobalRef>,
+        }
+
+        impl ClassRef {
+            /// `name` is in the notation of JNI, e.g. `java/lang/String`
+            pub const fn new(name: &'static str) -> Self {
+                Self {
+                    name,
+                    class: once_cell::sync::OnceCell::new(),
+                }
+            }
+
+            /// Get the class, looking it up the first time
+            pub fn get(&self, env: &mut JNIEnv<'_>) -> JResult<&jni::objects::JClass<'static>> {
+                let class = self.class.get_or_try_init(|| -> JResult<jni::objects::GlobalRef> {
+                    let class = catch_exception(env, |env| env.find_class(self.name))?;
+                    let class = env.auto_local(class);
+                    Ok(catch_exception(env, |env| env.new_global_ref(&class))?)
+                })?;
+
+                Ok(<&jni::objects::JClass>::from(class.as_obj()))
+            }
+        }
+
+        /// The ID of a method or constructor, looked up on the first call and cached, for the statics of the generated methods.
+        /// IDs are looked up on the declaring class, and stay valid as the class is kept loaded
+        pub struct MethodId {
+            class: ClassRef,
+            name: &'static str,
+            signature: &'static str,
+            id: once_cell::sync::OnceCell<jni::objects::JMethodID>,
+        }
+
+        impl MethodId {
+            /// `class` is in the notation of JNI, e.g. `java/lang/String`. The name of constructors is `<init>`
+            pub const fn new(class: &'static str, name: &'static str, signature: &'static str) -> Self {
+                Self {
+                    class: ClassRef::new(class),
+                    name,
+                    signature,
+                    id: once_cell::sync::OnceCell::new(),
+                }
+            }
+
+            /// The ID, which for static methods is the one of a `JStaticMethodID`
+            fn get(&self, env: &mut JNIEnv<'_>, is_static: bool) -> JResult<jni::objects::JMethodID> {
+                let id = self.id.get_or_try_init(|| -> JResult<jni::objects::JMethodID> {
+                    let class = self.class.get(env)?;
+                    if is_static {
+                        let id = catch_exception(env, |env| env.get_static_method_id(class, self.name, self.signature))?;
+                        // Both are `jmethodID`s in JNI
+                        Ok(unsafe { jni::objects::JMethodID::from_raw(id.into_raw()) })
+                    } else {
+                        catch_exception(env, |env| env.get_method_id(class, self.name, self.signature))
+                    }
+                })?;
+
+                Ok(*id)
+            }
+
+            /// Call the instance method on `obj`, which must be an instance of the class.
+            /// `args` must match the signature of the method, and `ret` its return type
+            pub fn call<'a>(&self, env: &mut JNIEnv<'a>, obj: &JObject<'_>, ret: jni::signature::ReturnType, args: &[jni::sys::jvalue]) -> JResult<JValueOwned<'a>> {
+                // The JVM does not check this
+                if obj.is_null() {
+                    return Err(jni::errors::Error::NullPtr("Calling a method on null").into());
+                }
+
+                let id = self.get(env, false)?;
+                // The ID belongs to the class of `obj`, and the generated code passes arguments of the types in the signature
+                catch_exception(env, |env| unsafe { env.call_method_unchecked(obj, id, ret, args) })
+            }
+
+            /// Call the static method. `args` must match the signature of the method, and `ret` its return type
+            pub fn call_static<'a>(&self, env: &mut JNIEnv<'a>, ret: jni::signature::ReturnType, args: &[jni::sys::jvalue]) -> JResult<JValueOwned<'a>> {
+                let id = unsafe { jni::objects::JStaticMethodID::from_raw(self.get(env, true)?.into_raw()) };
+                let class = self.class.get(env)?;
+                // The ID belongs to the class, and the generated code passes arguments of the types in the signature
+                catch_exception(env, |env| unsafe { env.call_static_method_unchecked(class, id, ret, args) })
+            }
+
+            /// Create an instance of the class through the constructor. `args` must match its signature
+            pub fn new_object<'a>(&self, env: &mut JNIEnv<'a>, args: &[jni::sys::jvalue]) -> JResult<JObject<'a>> {
+                let id = self.get(env, false)?;
+                let class = self.class.get(env)?;
+                // The ID is the one of a constructor of the class, and the arguments are of the types in its signature
+                catch_exception(env, |env| unsafe { env.new_object_unchecked(class, id, args) })
+            }
+        }
+
+        /// Copy the converted elements into a new `java.util.ArrayList`
+        pub fn new_list<'a, T>(
+            env: &mut JNIEnv<'a>,
+            elements: impl IntoIterator<Item = T>,
+            convert: impl Fn(&mut JNIEnv<'a>, T) -> JResult<JObject<'a>>,
+        ) -> JResult<JObject<'a>> {
+            let list = catch_exception(env, |env| env.new_object("java/util/ArrayList", "()V", &[]))?;
+            for element in elements {
+                let element = convert(env, element)?;
+                let element = env.auto_local(element);
+                catch_exception(env, |env| env.call_method(&list, "add", "(Ljava/lang/Object;)Z", &[(&element).into()]))?;
+            }
+
+            Ok(list)
+        }
+
+        /// Convert the elements of a `java.util.List`, in the order of its iterator. Deletes the reference
+        pub fn list_to_vec<'a, T>(
+            env: &mut JNIEnv<'a>,
+            list: JObject<'a>,
+            convert: impl Fn(&mut JNIEnv<'a>, JObject<'a>) -> JResult<T>,
+        ) -> JResult<Vec<T>> {
+            let list = env.auto_local(list);
+            let iterator = catch_exception(env, |env| env.call_method(&list, "iterator", "()Ljava/util/Iterator;", &[]))?.l()?;
+            let iterator = env.auto_local(iterator);
+            let mut elements = Vec::new();
+            while catch_exception(env, |env| env.call_method(&iterator, "hasNext", "()Z", &[]))?.z()? {
+                let element = catch_exception(env, |env| env.call_method(&iterator, "next", "()Ljava/lang/Object;", &[]))?.l()?;
+                elements.push(convert(env, element)?);
+            }
+
+            Ok(elements)
+        }
+
+        /// Copy the converted entries into a new `java.util.HashMap`
+        pub fn new_map<'a, K, V>(
+            env: &mut JNIEnv<'a>,
+            entries: impl IntoIterator<Item = (K, V)>,
+            convert_key: impl Fn(&mut JNIEnv<'a>, K) -> JResult<JObject<'a>>,
+            convert_value: impl Fn(&mut JNIEnv<'a>, V) -> JResult<JObject<'a>>,
+        ) -> JResult<JObject<'a>> {
+            let map = catch_exception(env, |env| env.new_object("java/util/HashMap", "()V", &[]))?;
+            for (key, value) in entries {
+                let key = convert_key(env, key)?;
+                let key = env.auto_local(key);
+                let value = convert_value(env, value)?;
+                let value = env.auto_local(value);
+                let previous = catch_exception(env, |env| env.call_method(
+                    &map,
+                    "put",
+                    "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+                    &[(&key).into(), (&value).into()],
+                ))?.l()?;
+                env.delete_local_ref(previous)?;
+            }
+
+            Ok(map)
+        }
+
+        /// Convert the entries of a `java.util.Map`, iterating over its `entrySet`. Deletes the reference
+        pub fn map_to_hash_map<'a, K: std::hash::Hash + Eq, V>(
+            env: &mut JNIEnv<'a>,
+            map: JObject<'a>,
+            convert_key: impl Fn(&mut JNIEnv<'a>, JObject<'a>) -> JResult<K>,
+            convert_value: impl Fn(&mut JNIEnv<'a>, JObject<'a>) -> JResult<V>,
+        ) -> JResult<std::collections::HashMap<K, V>> {
+            let map = env.auto_local(map);
+            let entries = catch_exception(env, |env| env.call_method(&map, "entrySet", "()Ljava/util/Set;", &[]))?.l()?;
+            let entries = env.auto_local(entries);
+            let iterator = catch_exception(env, |env| env.call_method(&entries, "iterator", "()Ljava/util/Iterator;", &[]))?.l()?;
+            let iterator = env.auto_local(iterator);
+            let mut converted = std::collections::HashMap::new();
+            while catch_exception(env, |env| env.call_method(&iterator, "hasNext", "()Z", &[]))?.z()? {
+                let entry = catch_exception(env, |env| env.call_method(&iterator, "next", "()Ljava/lang/Object;", &[]))?.l()?;
+                let entry = env.auto_local(entry);
+                let key = catch_exception(env, |env| env.call_method(&entry, "getKey", "()Ljava/lang/Object;", &[]))?.l()?;
+                let value = catch_exception(env, |env| env.call_method(&entry, "getValue", "()Ljava/lang/Object;", &[]))?.l()?;
+                let key = convert_key(env, key)?;
+                converted.insert(key, convert_value(env, value)?);
+            }
+
+            Ok(converted)
+        }
+
+        /// Call `obj.close()`, for the consuming `close` methods of the `java.lang.AutoCloseable` implementors.
+        /// Deletes the reference
+        pub fn close<'a>(env: &mut JNIEnv<'a>, obj: JObject<'a>) -> JResult<()> {
+            let obj = env.auto_local(obj);
+            catch_exception(env, |env| env.call_method(&obj, "close", "()V", &[]))?;
+            Ok(())
+        }
+
+        /// Create a `java.util.Optional`, or one of its primitive variants, through its static `of` or `empty`.
+        /// `signature` is the signature of `of`
+        pub fn new_optional<'a, T>(
+            env: &mut JNIEnv<'a>,
+            class_name: &str,
+            signature: &str,
+            value: Option<T>,
+            convert: impl Fn(&mut JNIEnv<'a>, T) -> JResult<JValueOwned<'a>>,
+        ) -> JResult<JObject<'a>> {
+            let optional = match value {
+                Some(value) => {
+                    let value = convert(env, value)?;
+                    let optional = catch_exception(env, |env| env.call_static_method(class_name, "of", signature, &[value.borrow()]));
+                    if let JValueOwned::Object(value) = value {
+                        env.delete_local_ref(value)?;
+                    }
+                    optional?
+                },
+                None => catch_exception(env, |env| env.call_static_method(class_name, "empty", format!("()L{class_name};"), &[]))?,
+            };
+
+            Ok(optional.l()?)
+        }
+
+        /// Convert the value of a `java.util.Optional`, or one of its primitive variants, obtained through `getter`.
+        /// `null` is treated like an empty optional. Deletes the reference
+        pub fn optional_to_option<'a, T>(
+            env: &mut JNIEnv<'a>,
+            optional: JObject<'a>,
+            getter: &str,
+            signature: &str,
+            convert: impl Fn(&mut JNIEnv<'a>, JValueOwned<'a>) -> JResult<T>,
+        ) -> JResult<Option<T>> {
+            let optional = env.auto_local(optional);
+            if optional.is_null() || !catch_exception(env, |env| env.call_method(&optional, "isPresent", "()Z", &[]))?.z()? {
+                return Ok(None);
+            }
+
+            let value = catch_exception(env, |env| env.call_method(&optional, getter, signature, &[]))?;
+            Ok(Some(convert(env, value)?))
+        }
+
+        /// Create a `java.math.BigDecimal` or `BigInteger` from its string representation
+        pub fn new_big_number<'a>(env: &mut JNIEnv<'a>, class_name: &str, value: &str) -> JResult<JObject<'a>> {
+            let value = catch_exception(env, |env| env.new_string(value))?;
+            let value = env.auto_local(value);
+            catch_exception(env, |env| env.new_object(class_name, "(Ljava/lang/String;)V", &[(&value).into()]))
+        }
+
+        /// Parse the string representation of a `java.math.BigDecimal` or `BigInteger`, obtained through `getter`.
+        /// Deletes the reference
+        pub fn parse_big_number<'a, T>(env: &mut JNIEnv<'a>, obj: JObject<'a>, getter: &str) -> JResult<T>
+        where
+            T: std::str::FromStr,
+            T::Err: fmt::Display,
+        {
+            let obj = env.auto_local(obj);
+            let value = catch_exception(env, |env| env.call_method(&obj, getter, "()Ljava/lang/String;", &[]))?.l()?;
+            let value = get_string(env, value)?;
+            value.parse().map_err(|e| Error::Conversion(format!("{}: {}", value, e)))
+        }
+
+        /// Create a `java.lang.String`
+        pub fn new_string<'a>(env: &mut JNIEnv<'a>, value: &str) -> JResult<JObject<'a>> {
+            Ok(catch_exception(env, |env| env.new_string(value))?.into())
+        }
+
+        /// Convert a `java.lang.String` to a Rust string. Deletes the reference
+        pub fn get_string<'a>(env: &mut JNIEnv<'a>, obj: JObject<'a>) -> JResult<String> {
+            static STRING: ClassRef = ClassRef::new("java/lang/String");
+            let string = env.auto_local(jni::objects::JString::from(obj));
+            let class = STRING.get(env)?;
+            if !catch_exception(env, |env| env.is_instance_of(&*string, class))? {
+                return Err(Error::Conversion("The object is not a java.lang.String".to_string()));
+            }
+
+            // Checked to be a string above
+            let value = catch_exception(env, |env| unsafe { env.get_string_unchecked(&string) })?;
+            Ok(value.into())
+        }
+    }
+}
+
+/// Implement `ArrayElement` for the Rust types of the primitives. Bytes are unsigned in Rust,
+/// JNI provides copies from and to `u8` slices for them. Booleans are copied through `jboolean`
+fn generate_array_elements() -> TokenStream {
+    let elements = [
+        ("bool", "boolean", "JBooleanArray"),
+        ("u16", "char", "JCharArray"),
+        ("i16", "short", "JShortArray"),
+        ("i32", "int", "JIntArray"),
+        ("i64", "long", "JLongArray"),
+        ("f32", "float", "JFloatArray"),
+        ("f64", "double", "JDoubleArray"),
+    ];
+
+    let impls = elements.iter()
+        .map(|(rust_type, java_type, array_type)| {
+            let rust_type = format_ident!("{}", rust_type);
+            let array_type = format_ident!("{}", array_type);
+            let new_array = format_ident!("new_{}_array", java_type);
+            let set_region = format_ident!("set_{}_array_region", java_type);
+            let get_region = format_ident!("get_{}_array_region", java_type);
+            let (to_jni, from_jni) = if rust_type == "bool" {
+                (
+                    quote! { let values = values.iter().map(|&x| x as jni::sys::jboolean).collect::<Vec<_>>(); },
+                    quote! { .into_iter().map(|x| x != 0).collect() },
+                )
+            } else {
+                (quote! {}, quote! {})
+            };
+
+            quote! {
+                impl ArrayElement for #rust_type {
+                    fn new_array<'a>(env: &mut JNIEnv<'a>, values: &[Self]) -> JResult<JObject<'a>> {
+                        #to_jni
+                        let length = array_length(&values)?;
+                        let array = catch_exception(env, |env| env.#new_array(length))?;
+                        catch_exception(env, |env| env.#set_region(&array, 0, &values))?;
+                        Ok(array.into())
+                    }
+
+                    fn array_to_vec<'a>(env: &mut JNIEnv<'a>, array: JObject<'a>) -> JResult<Vec<Self>> {
+                        let array = env.auto_local(jni::objects::#array_type::from(array));
+                        let length = catch_exception(env, |env| env.get_array_length(&*array))?;
+                        let mut values = vec![Default::default(); length as usize];
+                        catch_exception(env, |env| env.#get_region(&*array, 0, &mut values))?;
+                        Ok(values #from_jni)
+                    }
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    quote! {
+        impl ArrayElement for u8 {
+            fn new_array<'a>(env: &mut JNIEnv<'a>, values: &[Self]) -> JResult<JObject<'a>> {
+                Ok(catch_exception(env, |env| env.byte_array_from_slice(values))?.into())
+            }
+
+            fn array_to_vec<'a>(env: &mut JNIEnv<'a>, array: JObject<'a>) -> JResult<Vec<Self>> {
+                let array = env.auto_local(jni::objects::JByteArray::from(array));
+                catch_exception(env, |env| env.convert_byte_array(&*array))
+            }
+        }
+
+        #(#impls)*
+    }
+}
+
+/// Generate `java_time.rs` for jni 0.21, see `runtime::generate_java_time`
+pub fn generate_java_time(java_time: JavaTime) -> Option<TokenStream> {
+    let conversions = match java_time {
+        JavaTime::Chrono => generate_chrono(),
+        JavaTime::Time => generate_time(),
+        JavaTime::Raw => return None,
+    };
+
+    Some(quote! {
+        use jni::objects::{JObject, JValue, JValueOwned};
+        use jni::JNIEnv;
+        use crate::{catch_exception, Error, JResult, JavaConvert};
+
+        fn call<'a>(env: &mut JNIEnv<'a>, obj: &JObject<'_>, name: &str, signature: &str) -> JResult<JValueOwned<'a>> {
+            catch_exception(env, |env| env.call_method(obj, name, signature, &[]))
+        }
+
+        fn call_static<'a>(env: &mut JNIEnv<'a>, class: &str, name: &str, signature: &str, arguments: &[JValue]) -> JResult<JObject<'a>> {
+            Ok(catch_exception(env, |env| env.call_static_method(class, name, signature, arguments))?.l()?)
+        }
+
+        fn out_of_range(class: &str) -> Error {
+            Error::Conversion(format!("{} is out of the range of the Rust type", class))
+        }
+
+        #conversions
+    })
+}
+
+fn generate_chrono() -> TokenStream {
+    quote! {
+        use chrono::{Datelike, Timelike};
+
+        impl<'a> JavaConvert<'a> for chrono::DateTime<chrono::Utc> {
+            fn from_java(env: &mut JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let obj = env.auto_local(obj);
+                let seconds = call(env, &obj, "getEpochSecond", "()J")?.j()?;
+                let nanos = call(env, &obj, "getNano", "()I")?.i()?;
+                chrono::DateTime::from_timestamp(seconds, nanos as u32).ok_or_else(|| out_of_range("java.time.Instant"))
+            }
+
+            fn to_java(&self, env: &mut JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let nanos = i64::from(self.timestamp_subsec_nanos());
+                call_static(env, "java/time/Instant", "ofEpochSecond", "(JJ)Ljava/time/Instant;", &[self.timestamp().into(), nanos.into()])
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for chrono::NaiveDate {
+            fn from_java(env: &mut JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let obj = env.auto_local(obj);
+                let year = call(env, &obj, "getYear", "()I")?.i()?;
+                let month = call(env, &obj, "getMonthValue", "()I")?.i()?;
+                let day = call(env, &obj, "getDayOfMonth", "()I")?.i()?;
+                chrono::NaiveDate::from_ymd_opt(year, month as u32, day as u32).ok_or_else(|| out_of_range("java.time.LocalDate"))
+            }
+
+            fn to_java(&self, env: &mut JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let arguments = [self.year().into(), (self.month() as i32).into(), (self.day() as i32).into()];
+                call_static(env, "java/time/LocalDate", "of", "(III)Ljava/time/LocalDate;", &arguments)
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for chrono::NaiveTime {
+            fn from_java(env: &mut JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let obj = env.auto_local(obj);
+                let nanos = call(env, &obj, "toNanoOfDay", "()J")?.j()?;
+                chrono::NaiveTime::from_num_seconds_from_midnight_opt((nanos / 1_000_000_000) as u32, (nanos % 1_000_000_000) as u32)
+                    .ok_or_else(|| out_of_range("java.time.LocalTime"))
+            }
+
+            fn to_java(&self, env: &mut JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let nanos = i64::from(self.num_seconds_from_midnight()) * 1_000_000_000 + i64::from(self.nanosecond());
+                call_static(env, "java/time/LocalTime", "ofNanoOfDay", "(J)Ljava/time/LocalTime;", &[nanos.into()])
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for chrono::NaiveDateTime {
+            fn from_java(env: &mut JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let obj = env.auto_local(obj);
+                let date = call(env, &obj, "toLocalDate", "()Ljava/time/LocalDate;")?.l()?;
+                let time = call(env, &obj, "toLocalTime", "()Ljava/time/LocalTime;")?.l()?;
+                Ok(chrono::NaiveDateTime::new(chrono::NaiveDate::from_java(env, date)?, chrono::NaiveTime::from_java(env, time)?))
+            }
+
+            fn to_java(&self, env: &mut JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let date = self.date().to_java(env)?;
+                let date = env.auto_local(date);
+                let time = self.time().to_java(env)?;
+                let time = env.auto_local(time);
+                call_static(env, "java/time/LocalDateTime", "of", "(Ljava/time/LocalDate;Ljava/time/LocalTime;)Ljava/time/LocalDateTime;", &[(&date).into(), (&time).into()])
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for chrono::TimeDelta {
+            fn from_java(env: &mut JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let obj = env.auto_local(obj);
+                let seconds = call(env, &obj, "getSeconds", "()J")?.j()?;
+                let nanos = call(env, &obj, "getNano", "()I")?.i()?;
+                chrono::TimeDelta::new(seconds, nanos as u32).ok_or_else(|| out_of_range("java.time.Duration"))
+            }
+
+            fn to_java(&self, env: &mut JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let nanos = i64::from(self.subsec_nanos());
+                call_static(env, "java/time/Duration", "ofSeconds", "(JJ)Ljava/time/Duration;", &[self.num_seconds().into(), nanos.into()])
+            }
+        }
+    }
+}
+
+fn generate_time() -> TokenStream {
+    quote! {
+        impl<'a> JavaConvert<'a> for time::OffsetDateTime {
+            fn from_java(env: &mut JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let obj = env.auto_local(obj);
+                let seconds = call(env, &obj, "getEpochSecond", "()J")?.j()?;
+                let nanos = call(env, &obj, "getNano", "()I")?.i()?;
+                time::OffsetDateTime::from_unix_timestamp_nanos(i128::from(seconds) * 1_000_000_000 + i128::from(nanos))
+                    .map_err(|_| out_of_range("java.time.Instant"))
+            }
+
+            fn to_java(&self, env: &mut JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let nanos = i64::from(self.nanosecond());
+                call_static(env, "java/time/Instant", "ofEpochSecond", "(JJ)Ljava/time/Instant;", &[self.unix_timestamp().into(), nanos.into()])
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for time::Date {
+            fn from_java(env: &mut JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let obj = env.auto_local(obj);
+                let year = call(env, &obj, "getYear", "()I")?.i()?;
+                let month = call(env, &obj, "getMonthValue", "()I")?.i()?;
+                let day = call(env, &obj, "getDayOfMonth", "()I")?.i()?;
+                let month = time::Month::try_from(month as u8).map_err(|_| out_of_range("java.time.LocalDate"))?;
+                time::Date::from_calendar_date(year, month, day as u8).map_err(|_| out_of_range("java.time.LocalDate"))
+            }
+
+            fn to_java(&self, env: &mut JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let arguments = [self.year().into(), i32::from(u8::from(self.month())).into(), i32::from(self.day()).into()];
+                call_static(env, "java/time/LocalDate", "of", "(III)Ljava/time/LocalDate;", &arguments)
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for time::Time {
+            fn from_java(env: &mut JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let obj = env.auto_local(obj);
+                let nanos = call(env, &obj, "toNanoOfDay", "()J")?.j()?;
+                let seconds = nanos / 1_000_000_000;
+                time::Time::from_hms_nano((seconds / 3600) as u8, (seconds / 60 % 60) as u8, (seconds % 60) as u8, (nanos % 1_000_000_000) as u32)
+                    .map_err(|_| out_of_range("java.time.LocalTime"))
+            }
+
+            fn to_java(&self, env: &mut JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let seconds = i64::from(self.hour()) * 3600 + i64::from(self.minute()) * 60 + i64::from(self.second());
+                let nanos = seconds * 1_000_000_000 + i64::from(self.nanosecond());
+                call_static(env, "java/time/LocalTime", "ofNanoOfDay", "(J)Ljava/time/LocalTime;", &[nanos.into()])
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for time::PrimitiveDateTime {
+            fn from_java(env: &mut JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let obj = env.auto_local(obj);
+                let date = call(env, &obj, "toLocalDate", "()Ljava/time/LocalDate;")?.l()?;
+                let time = call(env, &obj, "toLocalTime", "()Ljava/time/LocalTime;")?.l()?;
+                Ok(time::PrimitiveDateTime::new(time::Date::from_java(env, date)?, time::Time::from_java(env, time)?))
+            }
+
+            fn to_java(&self, env: &mut JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let date = self.date().to_java(env)?;
+                let date = env.auto_local(date);
+                let time = self.time().to_java(env)?;
+                let time = env.auto_local(time);
+                call_static(env, "java/time/LocalDateTime", "of", "(Ljava/time/LocalDate;Ljava/time/LocalTime;)Ljava/time/LocalDateTime;", &[(&date).into(), (&time).into()])
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for time::Duration {
+            fn from_java(env: &mut JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let obj = env.auto_local(obj);
+                let seconds = call(env, &obj, "getSeconds", "()J")?.j()?;
+                let nanos = call(env, &obj, "getNano", "()I")?.i()?;
+                Ok(time::Duration::new(seconds, nanos))
+            }
+
+            fn to_java(&self, env: &mut JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let nanos = i64::from(self.subsec_nanoseconds());
+                call_static(env, "java/time/Duration", "ofSeconds", "(JJ)Ljava/time/Duration;", &[self.whole_seconds().into(), nanos.into()])
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Tracks the files of the generated crate between runs, so unchanged classes are not generated again,
+//! unchanged files are not rewritten, and files no longer generated are deleted
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use log::{debug, warn};
+use crate::JResult;
+
+/// The name of the manifest file in the output directory
+const MANIFEST_FILE: &str = ".jrsgen-manifest";
+
+/// The generated files, with the hash of what they were generated from, keyed by their path relative to the output directory
+pub struct Manifest {
+    output_dir: PathBuf,
+    previous: BTreeMap<String, u64>,
+    current: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Manifest {
+    /// Read the manifest of the previous run. If there is none, or it cannot be read, every file is written
+    pub fn load(output_dir: &Path) -> Self {
+        let previous = match fs::read_to_string(output_dir.join(MANIFEST_FILE)) {
+            Ok(contents) => parse_manifest(&contents).unwrap_or_else(|| {
+                warn!("Ignoring malformed manifest in {}", output_dir.display());
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+
+        Self {
+            output_dir: output_dir.to_path_buf(),
+            previous,
+            current: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Whether `file` was generated from the same input, with hash `hash`, by the previous run and still exists.
+    /// If so, it is kept
+    pub fn is_current(&self, file: &str, hash: u64) -> bool {
+        let is_current = self.previous.get(file) == Some(&hash) && self.output_dir.join(file).exists();
+        if is_current {
+            self.current.lock().unwrap().insert(file.to_string(), hash);
+        }
+
+        is_current
+    }
+
+    /// Write `file`, generated from the input with hash `hash`. The file is left untouched if its contents would not change,
+    /// so its modification time does not invalidate incremental compilation
+    pub fn write(&self, file: &str, hash: u64, contents: &str) -> JResult<()> {
+        let path = self.output_dir.join(file);
+        if fs::read(&path).ok().as_deref() != Some(contents.as_bytes()) {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            fs::write(&path, contents)?;
+        }
+
+        self.current.lock().unwrap().insert(file.to_string(), hash);
+        Ok(())
+    }
+
+    /// Delete the files of the previous run that were not generated this time, and store the manifest
+    pub fn finish(self) -> JResult<()> {
+        let current = self.current.into_inner().unwrap();
+        for file in self.previous.keys().filter(|x| !current.contains_key(*x)) {
+            debug!("Deleting stale file {file}");
+            let path = self.output_dir.join(file);
+            if path.exists() {
+                fs::remove_file(&path)?;
+            }
+
+            // Remove directories left empty, e.g. of a removed package
+            for dir in path.ancestors().skip(1).take_while(|x| *x != self.output_dir) {
+                if fs::remove_dir(dir).is_err() {
+                    break;
+                }
+            }
+        }
+
+        fs::write(self.output_dir.join(MANIFEST_FILE), format_manifest(&current))?;
+        Ok(())
+    }
+}
+
+/// Each line holds the hash in hex and the path of a file, e.g. `00000000075bcd15 src/lib.rs`
+fn format_manifest(files: &BTreeMap<String, u64>) -> String {
+    files.iter()
+        .map(|(file, hash)| format!("{hash:016x} {file}\n"))
+        .collect()
+}
+
+fn parse_manifest(contents: &str) -> Option<BTreeMap<String, u64>> {
+    contents.lines()
+        .map(|line| {
+            let (hash, file) = line.split_once(' ')?;
+            Some((file.to_string(), u64::from_str_radix(hash, 16).ok()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn manifest_format() {
+        let files = BTreeMap::from([
+            ("src/lib.rs".to_string(), 123456789),
+            ("src/bindings/com/foo/Bar Baz.rs".to_string(), u64::MAX),
+        ]);
+
+        let formatted = format_manifest(&files);
+        assert_eq!("ffffffffffffffff src/bindings/com/foo/Bar Baz.rs\n00000000075bcd15 src/lib.rs\n", formatted);
+        assert_eq!(Some(files), parse_manifest(&formatted));
+        assert_eq!(None, parse_manifest("src/lib.rs"));
+    }
+
+    #[test]
+    fn stale_files() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let manifest = Manifest::load(dir.path());
+        manifest.write("src/lib.rs", 1, "lib").unwrap();
+        manifest.write("src/bindings/com/foo/Bar.rs", 2, "bar").unwrap();
+        manifest.finish().unwrap();
+
+        let manifest = Manifest::load(dir.path());
+        assert!(manifest.is_current("src/lib.rs", 1));
+        assert!(!manifest.is_current("src/bindings/com/foo/Bar.rs", 3));
+        manifest.finish().unwrap();
+
+        assert!(dir.path().join("src/lib.rs").exists());
+        assert!(!dir.path().join("src/bindings").exists());
+    }
+}
@@ -1,90 +1,480 @@
 use std::str::FromStr;
-use convert_case::{Case, Casing};
-use proc_macro2::TokenStream;
+use proc_macro2::{Ident, TokenStream};
 use quote::{format_ident, quote};
-use crate::class_tree::{ArgumentType, MethodEntry};
-use crate::formatter::rename;
+use crate::class_tree::{ArgumentType, Nullability};
+use crate::config::{Config, JavaTime};
+use crate::formatter::{format_generic_signature, FormattedClassEntry, FormattedConstructorEntry, FormattedMethodEntry, SingletonField};
+use crate::generator::{generate_deprecated, generate_javadoc};
+use crate::generator::conversion::Conversion;
+use crate::generator::runtime::{java_time_crate, ASYNC_FEATURE};
+
+pub fn generate_method(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    let generated = with_java_time_feature(method, config, |config| {
+        if let Some(singleton) = &method.singleton {
+            generate_singleton(method, singleton, config)
+        } else if method.is_static {
+            generate_static(method, false, config)
+        } else {
+            generate_associated(method, config)
+        }
+    });
+    let async_variant = if returns_future(method) {
+        with_java_time_feature(method, config, |config| generate_async(method, config))
+    } else {
+        quote! {}
+    };
 
-pub fn generate_method(method: &MethodEntry) -> TokenStream {
-    // Filter out lamdas and other things
-    if method.name.contains("lambda$") || method.name.contains('$') {
-        return quote! {};
+    quote! {
+        #generated
+        #async_variant
     }
+}
 
-    for arg in &method.arguments {
-        match arg {
-            ArgumentType::Object(object) => {
-                if object.contains("lambda$") {
-                    return quote! {};
-                }
-            },
-            _ => {}
+/// The classes of the futures methods get an `_async` variant for, see `crate::JavaFuture`
+const FUTURES: &[&str] = &[
+    "java.util.concurrent.CompletableFuture",
+    "java.util.concurrent.CompletionStage",
+    "com.google.common.util.concurrent.ListenableFuture",
+];
+
+fn returns_future(method: &FormattedMethodEntry) -> bool {
+    matches!(&method.return_type, Some(ArgumentType::Object(class_fq)) if FUTURES.contains(&class_fq.as_str()))
+}
+
+/// Generate `<name>_async` for a method returning a future, which returns a `crate::JavaFuture` to await rather than
+/// the Java future. Behind the feature of the async runtime
+fn generate_async(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    let name_ident = format_ident!("{}_async", method.rust_name);
+    let arguments = generate_rust_arguments(method, config);
+
+    let (receiver, env, call) = if let Some(singleton) = &method.singleton {
+        let (env_parameter, env) = generate_env_parameter(config);
+        let singleton = generate_singleton_field(singleton);
+        (env_parameter, quote! { #env let singleton = #singleton; }, Call::Method(quote! { singleton }))
+    } else if method.is_static {
+        let (env_parameter, env) = generate_env_parameter(config);
+        (env_parameter, env, Call::Static)
+    } else {
+        (quote! { &self, }, quote! { let env = self.env; }, Call::Method(quote! { self.obj.inner }))
+    };
+    let call = generate_method_call(method, call, config);
+
+    let doc = format!(" Like [`Self::{}`], returning a Rust future completing with the Java future.", method.rust_name);
+    let deprecated = generate_deprecated(&method.deprecation);
+
+    quote! {
+        #[doc = #doc]
+        #[cfg(feature = #ASYNC_FEATURE)]
+        #deprecated
+        pub fn #name_ident(#receiver #arguments) -> crate::JResult<crate::JavaFuture> {
+            #env
+            let jvalue = #call;
+            crate::JavaFuture::new(env, jvalue.l()?)
+        }
+    }
+}
+
+/// Generate a module-level function for a static method.
+/// Used for static interface methods, as they cannot be called through an implementing type
+pub fn generate_free_function(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    with_java_time_feature(method, config, |config| generate_static(method, true, config))
+}
+
+/// Generate a trait method with a default implementation for an instance method of an interface, abstract or not.
+/// The call is dispatched by the JVM. The JNIEnv and object are provided by `crate::JavaObject`
+pub fn generate_trait_method(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    with_java_time_feature(method, config, |config| generate_trait_method_body(method, config))
+}
+
+/// Generate a method or constructor using `generate`. If it converts `java.time` types, it is generated twice:
+/// with the types of the time crate if its feature is enabled, and with the Java objects if it is not
+pub(super) fn with_java_time_feature(method: &FormattedMethodEntry, config: &Config, generate: impl Fn(&Config) -> TokenStream) -> TokenStream {
+    let uses_java_time = (0..method.arguments.len())
+        .filter_map(|idx| argument_conversion(method, idx, config))
+        .chain(return_conversion(method, config))
+        .any(|x| x.uses_java_time());
+    let feature = match java_time_crate(config.generator.java_time) {
+        Some((feature, _)) if uses_java_time => feature,
+        _ => return generate(config),
+    };
+
+    let mut raw_config = config.clone();
+    raw_config.generator.java_time = JavaTime::Raw;
+
+    let converted = generate(config);
+    let raw = generate(&raw_config);
+    quote! {
+        #[cfg(feature = #feature)]
+        #converted
+
+        #[cfg(not(feature = #feature))]
+        #raw
+    }
+}
+
+fn generate_trait_method_body(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    let name_snake_ident = format_ident!("{}", method.rust_name);
+    let arguments = generate_rust_arguments(method, config);
+    let return_type = generate_return_type(method, config);
+
+    let call = generate_method_call(method, Call::Method(quote! { self.obj().inner }), config);
+    let return_handler = generate_return_handler(method, config);
+    let doc = generate_signature_doc(method);
+
+    quote! {
+        #doc
+        fn #name_snake_ident(&self, #arguments) -> #return_type {
+            let env = self.env();
+            let jvalue = #call;
+            #return_handler
         }
     }
+}
+
+/// Generate the constructors of a class that is not an inner class, named `new` by the formatter,
+/// with the argument types appended for overloads
+pub fn generate_constructors(class: &FormattedClassEntry, config: &Config) -> Vec<TokenStream> {
+    class.constructors.iter()
+        .map(|constructor| {
+            let method = constructor_as_method(class, constructor, 0);
+            with_java_time_feature(&method, config, |config| generate_constructor(class, constructor, config))
+        })
+        .collect()
+}
+
+fn generate_constructor(class: &FormattedClassEntry, constructor: &FormattedConstructorEntry, config: &Config) -> TokenStream {
+    let name_ident = format_ident!("{}", constructor.rust_name);
+    let method = constructor_as_method(class, constructor, 0);
+
+    let arguments = generate_rust_arguments(&method, config);
+    let jvalue_array = generate_jvalue_array(&method);
+
+    let class_name = class.java_name.replace('.', "/");
+    let call = generate_call(&method, &class_name, "<init>", &constructor.jni_signature, Call::Constructor(jvalue_array), config);
+    let doc = generate_constructor_doc(&method, &format!("new {}({})", class.simple_name, method.generic_arguments.join(", ")));
+    let (env_parameter, env) = generate_env_parameter(config);
+
+    quote! {
+        #doc
+        pub fn #name_ident(#env_parameter #arguments) -> crate::JResult<Self> {
+            #env
+            let obj = #call.l()?;
+            crate::wrap(env, obj)
+        }
+    }
+}
+
+/// Generate `new_unchecked`, wrapping an object obtained elsewhere, e.g. through raw JNI calls.
+/// The object is not checked to be an instance of the class
+pub fn generate_new_unchecked(class: &FormattedClassEntry) -> TokenStream {
+    let doc = format!(" Wrap `obj` without checking that it is an instance of `{}`, or that it is not `null`.", class.java_name);
 
-    if method.is_static {
-        generate_static(method)
+    quote! {
+        #[doc = #doc]
+        /// The object must be an instance of the class, as methods are called through the IDs of the declaring classes.
+        pub fn new_unchecked(env: &'a jni::JNIEnv<'a>, obj: jni::objects::JObject<'a>) -> crate::JResult<Self> {
+            crate::wrap(env, obj)
+        }
+    }
+}
+
+/// Generate the constructors of a non-static inner class, named `new_inner` by the formatter, with the argument types
+/// appended for overloads. The enclosing instance is passed to Java as the implicit first constructor argument
+pub fn generate_inner_constructors(class: &FormattedClassEntry, config: &Config) -> Vec<TokenStream> {
+    class.constructors.iter()
+        .map(|constructor| {
+            let method = constructor_as_method(class, constructor, 1);
+            with_java_time_feature(&method, config, |config| generate_inner_constructor(class, constructor, config))
+        })
+        .collect()
+}
+
+fn generate_inner_constructor(class: &FormattedClassEntry, constructor: &FormattedConstructorEntry, config: &Config) -> TokenStream {
+    let name_ident = format_ident!("{}", constructor.rust_name);
+
+    let outer_type = constructor.arguments.first()
+        .expect("Inner class constructor without enclosing instance");
+    let outer_type = generate_argument_type(outer_type, config);
+
+    // The explicit arguments are handled like those of a static method
+    let method = constructor_as_method(class, constructor, 1);
+    let arguments = generate_rust_arguments(&method, config);
+    let argument_idents = generate_argument_idents(&method);
+
+    let class_name = class.java_name.replace('.', "/");
+    let jvalue_array = quote! { &[jni::objects::JValue::Object(outer.obj.inner), #(#argument_idents),*] };
+    let call = generate_call(&method, &class_name, "<init>", &constructor.jni_signature, Call::Constructor(jvalue_array), config);
+    let doc = generate_constructor_doc(&method, &format!("outer.new {}({})", class.simple_name, method.generic_arguments.join(", ")));
+
+    quote! {
+        #doc
+        pub fn #name_ident(outer: &#outer_type<'a>, #arguments) -> crate::JResult<Self> {
+            let env = outer.env;
+            let obj = #call.l()?;
+            crate::wrap(env, obj)
+        }
+    }
+}
+
+/// Describe a constructor as a static method, so its arguments are handled like those of methods.
+/// The first `implicit_arguments` arguments, like the enclosing instance of inner classes, are left out
+pub(super) fn constructor_as_method(class: &FormattedClassEntry, constructor: &FormattedConstructorEntry, implicit_arguments: usize) -> FormattedMethodEntry {
+    // The generic arguments come from the source, which may omit the implicit ones
+    let generic_arguments = if constructor.generic_arguments.len() == constructor.arguments.len() {
+        &constructor.generic_arguments[implicit_arguments..]
     } else {
-        generate_associated(method)
+        &constructor.generic_arguments[..]
+    };
+
+    FormattedMethodEntry {
+        rust_name: constructor.rust_name.clone(),
+        java_name: class.simple_name.clone(),
+        is_static: true,
+        is_abstract: false,
+        is_default: false,
+        deprecation: constructor.deprecation.clone(),
+        arguments: constructor.arguments[implicit_arguments..].to_vec(),
+        argument_names: constructor.argument_names.iter().skip(implicit_arguments).cloned().collect(),
+        jni_signature: constructor.jni_signature.clone(),
+        return_type: None,
+        declaring_class_rust: class.name.clone(),
+        declaring_class_java: class.java_name.clone(),
+        type_parameters: Vec::new(),
+        generic_arguments: generic_arguments.to_vec(),
+        generic_return_type: None,
+        exceptions: constructor.exceptions.clone(),
+        argument_nullability: constructor.argument_nullability.iter().skip(implicit_arguments).copied().collect(),
+        return_nullability: Nullability::Unknown,
+        javadoc: Vec::new(),
+        singleton: None,
     }
 }
 
-fn generate_static(method: &MethodEntry) -> TokenStream {
-    let name_snake = method.name.to_case(Case::Snake);
-    let formatted = rename(&name_snake);
+/// Document the Java expression calling the constructor, e.g. `new Foo(int)`,
+/// and the checked exceptions it may throw. Also marks the constructor deprecated if it is in Java
+pub(super) fn generate_constructor_doc(method: &FormattedMethodEntry, expression: &str) -> TokenStream {
+    let doc = format!(" Java: `{expression}`");
+    let errors = method.exceptions.iter()
+        .map(|x| format!(" - `{x}`"))
+        .collect::<Vec<_>>();
+    let errors_header = if errors.is_empty() {
+        Vec::new()
+    } else {
+        vec![String::new(), " # Errors".to_string(), String::new(), " Throws:".to_string()]
+    };
+    let deprecated = generate_deprecated(&method.deprecation);
 
-    let name_snake_ident = format_ident!("{}", formatted);
-    let arguments = generate_rust_arguments(method);
-    let return_type = generate_return_type(&method.return_type);
-    let jvalues = generate_jvalue_arguments(method, false);
+    quote! {
+        #[doc = #doc]
+        #(#[doc = #errors_header])*
+        #(#[doc = #errors])*
+        #deprecated
+    }
+}
 
-    let java_name = &method.name;
-    let class_name = method.declaring_class.replace('.', "/");
+/// Document the Java declaration of the method, including the type arguments erased in the generated signature,
+/// the checked exceptions it may throw and the class declaring it. Also marks the method deprecated if it is in Java
+pub(super) fn generate_signature_doc(method: &FormattedMethodEntry) -> TokenStream {
+    let modifiers = [(method.is_static, "static "), (method.is_default, "default "), (method.is_abstract, "abstract ")].iter()
+        .filter(|(is_set, _)| *is_set)
+        .map(|(_, modifier)| *modifier)
+        .collect::<String>();
+    let signature = format_generic_signature(&method.java_name, &method.type_parameters, &method.generic_arguments, method.generic_return_type.as_deref());
+    let throws = if method.exceptions.is_empty() {
+        String::new()
+    } else {
+        format!(" throws {}", method.exceptions.join(", "))
+    };
+
+    let doc = format!(" Java: `{modifiers}{signature}{throws}`");
+    let declaring_class = match &method.singleton {
+        Some(singleton) => format!(" Declared in `{}`, called on `{}.{}`.", method.declaring_class_java, singleton.class, singleton.name),
+        None => format!(" Declared in `{}`.", method.declaring_class_java),
+    };
+    let abstract_doc = if method.is_abstract {
+        vec![" Abstract in Java, this calls the implementation of the object's runtime class.".to_string()]
+    } else {
+        Vec::new()
+    };
+
+    let errors = if method.exceptions.is_empty() {
+        Vec::new()
+    } else {
+        let mut errors = vec![
+            String::new(),
+            " # Errors".to_string(),
+            String::new(),
+            " Throws:".to_string(),
+        ];
+        errors.extend(method.exceptions.iter().map(|x| format!(" - `{x}`")));
+        errors
+    };
+
+    let deprecated = generate_deprecated(&method.deprecation);
+    let javadoc = generate_javadoc(&method.javadoc);
+
+    quote! {
+        #javadoc
+        #[doc = #doc]
+        #[doc = #declaring_class]
+        #(#[doc = #abstract_doc])*
+        #(#[doc = #errors])*
+        #deprecated
+    }
+}
 
-    let method_signature = generate_signature(method);
-    let jvalue_array = generate_jvalue_array(method);
-    let return_handler = generate_return_handler(method);
+/// Generate a static method. If `free_function` is set, the function declares its own lifetime,
+/// rather than using the one of the surrounding `impl` block
+fn generate_static(method: &FormattedMethodEntry, free_function: bool, config: &Config) -> TokenStream {
+    let name_snake_ident = format_ident!("{}", method.rust_name);
+    let arguments = generate_rust_arguments(method, config);
+    let return_type = generate_return_type(method, config);
+
+    let call = generate_method_call(method, Call::Static, config);
+    let return_handler = generate_return_handler(method, config);
+    let doc = generate_signature_doc(method);
+    let generics = if free_function {
+        quote! { <'a> }
+    } else {
+        quote! {}
+    };
+    let (env_parameter, env) = generate_env_parameter(config);
 
     quote! {
-        pub fn #name_snake_ident(env: &'a jni::JNIEnv<'a>, #arguments) -> #return_type {
-            #jvalues
-            let jvalue = env.call_static_method(#class_name, #java_name, #method_signature, #jvalue_array)?;
+        #doc
+        pub fn #name_snake_ident #generics(#env_parameter #arguments) -> #return_type {
+            #env
+            let jvalue = #call;
             #return_handler
         }
     }
 }
 
-fn generate_associated(method: &MethodEntry) -> TokenStream {
-    let name_snake = method.name.to_case(Case::Snake);
-    let name_formatted = format_name(&name_snake);
+/// The `env` parameter of constructors and static methods, and the statement binding `env` in their body.
+/// With `implicit_env` there is no parameter, and the JNIEnv of the current thread is used
+pub(super) fn generate_env_parameter(config: &Config) -> (TokenStream, TokenStream) {
+    if config.generator.implicit_env {
+        (quote! {}, quote! { let env: &'a jni::JNIEnv<'a> = crate::env()?; })
+    } else {
+        (quote! { env: &'a jni::JNIEnv<'a>, }, quote! {})
+    }
+}
 
-    let name_snake_ident = format_ident!("{}", name_formatted);
-    let arguments = generate_rust_arguments(method);
-    let return_type = generate_return_type(&method.return_type);
-    let jvalues = generate_jvalue_arguments(method, true);
+fn generate_associated(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    let name_snake_ident = format_ident!("{}", method.rust_name);
+    let arguments = generate_rust_arguments(method, config);
+    let return_type = generate_return_type(method, config);
 
-    let java_name = &method.name;
-    let method_signature = generate_signature(method);
-    let jvalue_array = generate_jvalue_array(method);
-    let return_handler = generate_return_handler(method);
+    let call = generate_method_call(method, Call::Method(quote! { self.obj.inner }), config);
+    let return_handler = generate_return_handler(method, config);
+    let doc = generate_signature_doc(method);
 
     quote! {
+        #doc
         pub fn #name_snake_ident(&self, #arguments) -> #return_type {
-            #jvalues
-            let jvalue = self.env.call_method(self.obj.inner, #java_name, #method_signature, #jvalue_array)?;
+            let env = self.env;
+            let jvalue = #call;
             #return_handler
         }
     }
 }
 
-fn generate_return_handler(method: &MethodEntry) -> TokenStream {
+/// Generate an associated function for an instance method of a singleton, calling it on the object in the static field
+fn generate_singleton(method: &FormattedMethodEntry, singleton: &SingletonField, config: &Config) -> TokenStream {
+    let name_snake_ident = format_ident!("{}", method.rust_name);
+    let arguments = generate_rust_arguments(method, config);
+    let return_type = generate_return_type(method, config);
+
+    let singleton = generate_singleton_field(singleton);
+    let call = generate_method_call(method, Call::Method(quote! { singleton }), config);
+    let return_handler = generate_return_handler(method, config);
+    let doc = generate_signature_doc(method);
+    let (env_parameter, env) = generate_env_parameter(config);
+
+    quote! {
+        #doc
+        pub fn #name_snake_ident(#env_parameter #arguments) -> #return_type {
+            #env
+            let singleton = #singleton;
+            let jvalue = #call;
+            #return_handler
+        }
+    }
+}
+
+/// Read the static field holding a singleton. Evaluates to the `JObject`
+fn generate_singleton_field(singleton: &SingletonField) -> TokenStream {
+    let class_name = singleton.class.replace('.', "/");
+    let name = &singleton.name;
+    let signature = &singleton.jni_signature;
+    quote! {
+        crate::catch_exception(env, env.get_static_field(#class_name, #name, #signature))?.l()?
+    }
+}
+
+/// Generate `instance`, returning the object in the static field holding the only instance of the class,
+/// e.g. `INSTANCE` of a Kotlin `object`. Nothing if the class has no such field, or a method is named `instance`
+pub fn generate_instance_accessor(class: &FormattedClassEntry, config: &Config) -> TokenStream {
+    let Some(instance_field) = &class.instance_field else {
+        return quote! {};
+    };
+    if class.methods.iter().any(|x| x.rust_name == "instance") {
+        return quote! {};
+    }
+
+    let singleton = generate_singleton_field(instance_field);
+    let doc = format!(" Java: `{}.{}`", class.java_name, instance_field.name);
+    let (env_parameter, env) = generate_env_parameter(config);
+
+    quote! {
+        #[doc = #doc]
+        /// The only instance of the class.
+        pub fn instance(#env_parameter) -> crate::JResult<Self> {
+            #env
+            let obj = #singleton;
+            crate::wrap(env, obj)
+        }
+    }
+}
+
+/// Whether the value is an object annotated as nullable, and should thus be an `Option` in Rust
+fn is_nullable_object(argument_type: &ArgumentType, nullability: Nullability) -> bool {
+    nullability == Nullability::Nullable && matches!(argument_type, ArgumentType::Object(_) | ArgumentType::Array(_))
+}
+
+/// Whether the argument at `idx` is nullable. Optionals are already an `Option` in Rust
+pub(super) fn is_nullable_argument(method: &FormattedMethodEntry, idx: usize, config: &Config) -> bool {
+    is_nullable_object(&method.arguments[idx], method.argument_nullability[idx])
+        && !argument_conversion(method, idx, config).is_some_and(|x| x.is_optional())
+}
+
+/// Whether the returned value is nullable. Optionals are already an `Option` in Rust
+pub(super) fn is_nullable_return(method: &FormattedMethodEntry, config: &Config) -> bool {
+    let return_type = method.return_type.as_ref().expect("Method returns a value");
+    is_nullable_object(return_type, method.return_nullability)
+        && !return_conversion(method, config).is_some_and(|x| x.is_optional())
+}
+
+/// Convert the returned `jvalue` to the Rust return type. Objects are converted as described by `Conversion`
+fn generate_return_handler(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
     if let Some(return_type) = &method.return_type {
+        let nullable = is_nullable_return(method, config);
+        let null_check = if nullable {
+            quote! {
+                if jvalue.l()?.is_null() {
+                    return Ok(None);
+                }
+            }
+        } else {
+            quote! {}
+        };
         let value = match return_type {
             ArgumentType::Boolean => quote! {
                 let value = jvalue.z()?;
             },
+            // Bytes are unsigned in Rust
             ArgumentType::Byte => quote! {
-                let value = jvalue.b()?;
+                let value = jvalue.b()? as u8;
             },
             ArgumentType::Char => quote! {
                 let value = jvalue.c()?;
@@ -104,30 +494,58 @@ fn generate_return_handler(method: &MethodEntry) -> TokenStream {
             ArgumentType::Double => quote! {
                 let value = jvalue.d()?;
             },
-            ArgumentType::Object(object) => quote! {
-                let value = jvalue.l()?;
-                let value = object.
-            },
-            ArgumentType::Array(class_name) => quote! {
-                todo!("Array type for class {}", #class_name);
+            ArgumentType::Object(_) | ArgumentType::Array(_) => {
+                let rust_type = generate_return_value_type(method, config);
+                let conversion = return_conversion(method, config).expect("Objects and arrays are converted");
+                if conversion.holds_references() {
+                    let value = conversion.java_to_rust(quote! { value }, config);
+                    quote! {
+                        let value = jvalue.l()?;
+                        let value: #rust_type = #value;
+                    }
+                } else {
+                    // Neither the references used converting it nor the returned one are needed afterwards
+                    let value = conversion.java_to_rust(quote! { obj }, config);
+                    quote! {
+                        let obj = jvalue.l()?;
+                        let value: #rust_type = crate::in_local_frame(env, || Ok(#value))?;
+                        crate::catch_exception(env, env.delete_local_ref(obj))?;
+                    }
+                }
             },
         };
 
+        let value_ret = if nullable {
+            quote! { Some(value) }
+        } else {
+            quote! { value }
+        };
+
         quote! {
+            #null_check
             #value
-            Ok(value)
+            Ok(#value_ret)
         }
     } else {
         quote! {
+            jvalue.v()?;
             Ok(())
         }
     }
 }
 
-fn generate_jvalue_array(method: &MethodEntry) -> TokenStream {
+/// The identifiers of the method's arguments
+pub(super) fn generate_argument_idents(method: &FormattedMethodEntry) -> Vec<Ident> {
+    method.argument_names.iter()
+        .map(|x| format_ident!("{}", x))
+        .collect()
+}
+
+fn generate_jvalue_array(method: &FormattedMethodEntry) -> TokenStream {
+    let argument_idents = generate_argument_idents(method);
     let tokens = method.arguments.iter().enumerate()
         .map(|(idx, _)| {
-            let ident = format_ident!("arg{}", idx);
+            let ident = &argument_idents[idx];
             quote! { #ident }
         })
         .collect::<Vec<_>>();
@@ -137,112 +555,143 @@ fn generate_jvalue_array(method: &MethodEntry) -> TokenStream {
     }
 }
 
-fn argument_type_to_signature(argument_type: &ArgumentType) -> String {
-    match argument_type {
-        ArgumentType::Boolean => "Z".to_string(),
-        ArgumentType::Byte => "B".to_string(),
-        ArgumentType::Char => "C".to_string(),
-        ArgumentType::Short => "S".to_string(),
-        ArgumentType::Int => "I".to_string(),
-        ArgumentType::Long => "J".to_string(),
-        ArgumentType::Float => "F".to_string(),
-        ArgumentType::Double => "D".to_string(),
-        ArgumentType::Object(name) => {
-            let name = name.split('.')
-                .map(format_name)
-                .map(|x| x.to_string())
-                .collect::<Vec<_>>()
-                .join(".");
-
-            let name_slashed = name.replace('.', "/");
-            format!("L{};", name_slashed)
+/// How a generated function calls into Java
+enum Call {
+    /// Call an instance method on the object
+    Method(TokenStream),
+    Static,
+    /// Call a constructor with the array of arguments, which for inner classes includes the enclosing instance
+    Constructor(TokenStream),
+}
+
+/// Call the method on the class declaring it, see `generate_call`
+fn generate_method_call(method: &FormattedMethodEntry, call: Call, config: &Config) -> TokenStream {
+    let class_name = method.declaring_class_java.replace('.', "/");
+    generate_call(method, &class_name, &method.java_name, &method.jni_signature, call, config)
+}
+
+/// Convert the arguments and make the JNI call in a local frame, so the references to the converted arguments are
+/// deleted once it returns. The method ID is looked up on the first call and kept in a static, rather than looking it
+/// up by name on every call. Evaluates to the returned `JValue`
+fn generate_call(method: &FormattedMethodEntry, class_name: &str, java_name: &str, signature: &str, call: Call, config: &Config) -> TokenStream {
+    let jvalues = generate_jvalue_arguments(method, config);
+    let call = match call {
+        Call::Method(obj) => {
+            let jvalue_array = generate_jvalue_array(method);
+            let ret = generate_return_java_type(method);
+            quote! { METHOD.call(env, #obj, #ret, #jvalue_array) }
+        },
+        Call::Static => {
+            let jvalue_array = generate_jvalue_array(method);
+            let ret = generate_return_java_type(method);
+            quote! { METHOD.call_static(env, #ret, #jvalue_array) }
         },
-        ArgumentType::Array(name) => {
-            let name = name.split('.')
-                .into_iter()
-                .map(format_name)
-                .map(|x| x.to_string())
-                .collect::<Vec<_>>()
-                .join(".");
-
-            let name_slashed = name.replace('.', "/");
-            format!("[L{};", name_slashed)
+        Call::Constructor(jvalue_array) => quote! { METHOD.new_object(env, #jvalue_array).map(Into::into) },
+    };
+
+    quote! {
+        {
+            static METHOD: crate::MethodId = crate::MethodId::new(#class_name, #java_name, #signature);
+            crate::call_in_frame(env, || {
+                #jvalues
+                #call
+            })?
         }
     }
 }
 
-fn generate_signature(method: &MethodEntry) -> String {
-    let arguments = method.arguments.iter()
-        .map(argument_type_to_signature)
-        .collect::<String>();
-    let ret = if let Some(ret) = &method.return_type {
-        argument_type_to_signature(ret)
-    } else {
-        "V".to_string()
+/// The `JavaType` the unchecked JNI calls take to pick the function for the return type
+fn generate_return_java_type(method: &FormattedMethodEntry) -> TokenStream {
+    let primitive = match &method.return_type {
+        None => quote! { Void },
+        Some(ArgumentType::Boolean) => quote! { Boolean },
+        Some(ArgumentType::Byte) => quote! { Byte },
+        Some(ArgumentType::Char) => quote! { Char },
+        Some(ArgumentType::Short) => quote! { Short },
+        Some(ArgumentType::Int) => quote! { Int },
+        Some(ArgumentType::Long) => quote! { Long },
+        Some(ArgumentType::Float) => quote! { Float },
+        Some(ArgumentType::Double) => quote! { Double },
+        // Only the kind is used, not the class, so this avoids allocating it for every call
+        Some(ArgumentType::Object(_) | ArgumentType::Array(_)) => return quote! { jni::signature::JavaType::Object(String::new()) },
     };
 
-    format!("({}){}", arguments, ret)
+    quote! { jni::signature::JavaType::Primitive(jni::signature::Primitive::#primitive) }
 }
 
-fn generate_jvalue_arguments(method: &MethodEntry, associated_method: bool) -> TokenStream {
-    let env = if associated_method {
-        quote! {
-            let env = self.env;
-        }
-    } else {
-        quote! {}
-    };
+/// Convert the Rust arguments to `JValue`s, see `Conversion`
+fn generate_jvalue_arguments(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    let argument_idents = generate_argument_idents(method);
 
     let tokens = method.arguments.iter().enumerate()
         .map(|(idx, argument_type)| {
-            let arg_name = format_ident!("arg{}", idx);
+            let arg_name = &argument_idents[idx];
 
             match argument_type {
                 ArgumentType::Byte => quote! {
-                    let #arg_name = jni::JValue::Byte(#arg_name as i8);
+                    let #arg_name = jni::objects::JValue::Byte(#arg_name as i8);
                 },
                 ArgumentType::Boolean => quote! {
-                    let #arg_name = jni::JValue::Bool(if #arg_name { 1 } else { 0 });
+                    let #arg_name = jni::objects::JValue::Bool(if #arg_name { 1 } else { 0 });
                 },
                 ArgumentType::Int => quote! {
-                    let #arg_name = jni::JValue::Int(#arg_name);
+                    let #arg_name = jni::objects::JValue::Int(#arg_name);
                 },
                 ArgumentType::Long => quote! {
-                    let #arg_name = jni::JValue::Long(#arg_name);
+                    let #arg_name = jni::objects::JValue::Long(#arg_name);
                 },
                 ArgumentType::Double => quote! {
-                    let #arg_name = jni::JValue::Double(#arg_name);
+                    let #arg_name = jni::objects::JValue::Double(#arg_name);
                 },
                 ArgumentType::Float => quote! {
-                    let #arg_name = jni::JValue::Float(#arg_name);
+                    let #arg_name = jni::objects::JValue::Float(#arg_name);
                 },
                 ArgumentType::Short => quote! {
-                    let #arg_name = jni::JValue::Short(#arg_name);
+                    let #arg_name = jni::objects::JValue::Short(#arg_name);
                 },
                 ArgumentType::Char => quote! {
-                    let #arg_name = jni::JValue::Char(#arg_name);
+                    let #arg_name = jni::objects::JValue::Char(#arg_name);
                 },
-                ArgumentType::Object(_) => quote! {
-                    let #arg_name = #arg_name.into();
+                ArgumentType::Object(_) | ArgumentType::Array(_) if is_nullable_argument(method, idx, config) => {
+                    let value = argument_conversion(method, idx, config)
+                        .expect("Objects and arrays are converted")
+                        .rust_to_jvalue(quote! { value });
+                    quote! {
+                        let #arg_name = match #arg_name {
+                            Some(value) => #value,
+                            None => jni::objects::JValue::Object(jni::objects::JObject::null()),
+                        };
+                    }
+                },
+                ArgumentType::Object(_) | ArgumentType::Array(_) => {
+                    let value = argument_conversion(method, idx, config)
+                        .expect("Objects and arrays are converted")
+                        .rust_to_jvalue(quote! { #arg_name });
+                    quote! {
+                        let #arg_name = #value;
+                    }
                 },
-                ArgumentType::Array(class_name) => quote! {
-                    todo!("Yet to generate array of {}", #class_name);
-                }
             }
         })
         .collect::<Vec<_>>();
 
     quote! {
-        #env
         #(#tokens)*
     }
 }
 
-fn generate_return_type(return_type: &Option<ArgumentType>) -> TokenStream {
-    if let Some(return_type) = &return_type {
-        let return_type = generate_argument_type(return_type);
-        quote! {
-            crate::JResult<#return_type>
+pub(super) fn generate_return_type(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    if method.return_type.is_some() {
+        let nullable = is_nullable_return(method, config);
+        let return_type = generate_return_value_type(method, config);
+        if nullable {
+            quote! {
+                crate::JResult<Option<#return_type>>
+            }
+        } else {
+            quote! {
+                crate::JResult<#return_type>
+            }
         }
     } else {
         quote! {
@@ -251,108 +700,57 @@ fn generate_return_type(return_type: &Option<ArgumentType>) -> TokenStream {
     }
 }
 
-fn generate_argument_type(argument_type: &ArgumentType) -> TokenStream {
-    match argument_type {
-        ArgumentType::Int => quote!(i32),
-        ArgumentType::Char => quote!(u16),
-        ArgumentType::Double => quote!(f64),
-        ArgumentType::Float => quote!(f32),
-        ArgumentType::Byte => quote!(u8),
-        ArgumentType::Long => quote!(i64),
-        ArgumentType::Short => quote!(i16),
-        ArgumentType::Boolean => quote!(bool),
-        ArgumentType::Array(object) => {
-            match object.as_str() {
-                "[Z" => quote! {
-                    Vec<bool>
-                },
-                "[B" => quote! {
-                    Vec<u8>
-                },
-                "[C" => quote! {
-                    Vec<u16>
-                },
-                "[S" => quote! {
-                    Vec<i16>
-                },
-                "[I" => quote! {
-                    Vec<i32>
-                },
-                "[J" => quote! {
-                    Vec<i64>
-                },
-                "[F" => quote! {
-                    Vec<f32>
-                },
-                "[D" => quote! {
-                    Vec<f64>
-                },
-                _ => {
-                    // We're dealing with a 2D array
-                    if object.contains("[[") {
-                        match object.as_str() {
-                            "[[I" => return quote! {
-                                Vec<Vec<i32>>
-                            },
-                            "[[B" => return quote! {
-                                Vec<Vec<u8>>
-                            },
-                            _ => {}
-                        }
-                    }
-
-                    let object = object
-                        .replace("[L", "")
-                        .replace("[", "")
-                        .replace(';', "")
-                        .replace('.', "::")
-                        .replace('$', "::");
-
-                    let object = object.split("::")
-                        .into_iter()
-                        .map(format_name)
-                        .map(|x| x.to_string())
-                        .collect::<Vec<_>>()
-                        .join("::");
-
-                    println!("{object}");
-                    let tokens = TokenStream::from_str(&object).unwrap();
+/// The type of the returned value, without the `Option` of nullable objects
+pub(super) fn generate_return_value_type(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    let return_type = method.return_type.as_ref().expect("Method returns a value");
+    match return_conversion(method, config) {
+        Some(conversion) => conversion.return_type(config),
+        None => {
+            let rust_type = return_type.to_generated_return_type(config);
+            TokenStream::from_str(&rust_type).expect("Formatted types are valid Rust types")
+        }
+    }
+}
 
-                    let name_as_path = quote! {
-                        crate::bindings::#tokens
-                    };
+/// The conversion of the argument at `idx`, `None` for primitives
+pub(super) fn argument_conversion(method: &FormattedMethodEntry, idx: usize, config: &Config) -> Option<Conversion> {
+    // Without type arguments if the generic arguments do not line up
+    let generic_type = method.generic_arguments.get(idx)
+        .filter(|_| method.generic_arguments.len() == method.arguments.len());
+    Conversion::new(&method.arguments[idx], generic_type.map(String::as_str), config)
+}
 
-                    quote! {
-                        Vec<#name_as_path>
-                    }
-                }
-            }
-        }
-        ArgumentType::Object(object) => {
-            let object = object
-                .split('.')
-                .map(format_name)
-                .collect::<Vec<_>>()
-                .join(".")
-                .replace('.', "::")
-                .replace("$", "::");
-            let tokens = TokenStream::from_str(&object).unwrap();
+/// The conversion of the returned value, `None` for primitives
+pub(super) fn return_conversion(method: &FormattedMethodEntry, config: &Config) -> Option<Conversion> {
+    let return_type = method.return_type.as_ref()?;
+    Conversion::new(return_type, method.generic_return_type.as_deref(), config)
+}
 
-            quote! {
-                crate::bindings::#tokens
-            }
-        }
-    }
+pub fn generate_argument_type(argument_type: &ArgumentType, config: &Config) -> TokenStream {
+    let rust_type = argument_type.to_generated_rust_type(config);
+    TokenStream::from_str(&rust_type).expect("Formatted types are valid Rust types")
 }
 
-fn generate_rust_arguments(method: &MethodEntry) -> TokenStream {
+pub(super) fn generate_rust_arguments(method: &FormattedMethodEntry, config: &Config) -> TokenStream {
+    let argument_idents = generate_argument_idents(method);
     let tokens = method.arguments.iter().enumerate()
         .map(|(idx, arg)| {
-            let ident = format_ident!("arg{}", idx);
-            let ty = generate_argument_type(arg);
-
-            quote! {
-                #ident: #ty
+            let ident = &argument_idents[idx];
+            let nullable = is_nullable_argument(method, idx, config);
+            let ty = match argument_conversion(method, idx, config) {
+                Some(conversion) if nullable => conversion.nullable_argument_type(config),
+                Some(conversion) => conversion.argument_type(config),
+                None => generate_argument_type(arg, config),
+            };
+
+            if nullable {
+                quote! {
+                    #ident: Option<#ty>
+                }
+            } else {
+                quote! {
+                    #ident: #ty
+                }
             }
         })
         .collect::<Vec<_>>();
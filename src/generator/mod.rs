@@ -1,77 +1,224 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::fs::File;
+use std::hash::{Hash, Hasher};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::Path;
 use std::process::{Command, Stdio};
-use convert_case::{Case, Casing};
-use proc_macro2::TokenStream;
-use quote::quote;
+use std::str::FromStr;
+use anyhow::anyhow;
+use log::trace;
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use crate::class_tree::{ClassEntry, ClassType};
-use crate::formatter::rename;
-use crate::generator::class::{generate_class, generate_interface};
-use crate::generator::method::generate_method;
+use crate::class_tree::{ClassType, Deprecation};
+use crate::formatter::{rename_exception_variants, FormattedClassEntry};
+use crate::config::{Config, Generator, JniVersion};
+use crate::generator::manifest::Manifest;
+use crate::generator::class::{generate_annotation, generate_class, generate_enum, generate_interface, generate_type_parameters, is_close_method, is_closeable};
+use crate::generator::method::{generate_constructors, generate_inner_constructors, generate_instance_accessor, generate_method, generate_new_unchecked};
 use crate::JResult;
 
+mod builder;
 mod class;
+mod conversion;
+mod jni21;
+mod manifest;
 mod method;
+mod runtime;
+mod skeleton;
 
-pub fn generate(tree: Vec<ClassEntry>) -> JResult<()> {
-    let base_dir = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/output/src/bindings"));
+/// Marks the files jrsgen generated, followed by its version. `@generated` is recognized by e.g. rustfmt and GitHub
+pub const GENERATED_MARKER: &str = "@generated by jrsgen";
 
-    tree.into_iter()
-        .try_for_each(|mut class| {
-            let mut components = class.name.split('.').into_iter()
-                .map(rename)
-                .collect::<Vec<_>>();
+/// Generate a crate with the bindings for the formatted classes in `output_dir`. Every class is written to its own file,
+/// at the path of its Rust name, e.g. `src/bindings/com/foo/bar_p/Baz.rs` for `com.foo.Bar$Baz`.
+///
+/// Generation is incremental: classes that did not change since the previous run are not generated again,
+/// files whose contents would not change are not rewritten, and files of classes that were removed are deleted.
+///
+/// `backend` describes how the classes were read, e.g. `JVM, Java 17.0.2`. It is stated in the header of every file
+pub fn generate(mut tree: Vec<FormattedClassEntry>, config: &Config, output_dir: &Path, backend: &str) -> JResult<()> {
+    let jni_version = config.generator.jni_version;
+    if jni_version == JniVersion::V0_21 && config.generator.java_equality {
+        return Err(anyhow!("generator.java_equality is not supported with jni 0.21, the wrappers do not hold a JNIEnv"));
+    }
+    if jni_version == JniVersion::V0_21 && config.generator.implicit_env {
+        return Err(anyhow!("generator.implicit_env is not supported with jni 0.21, its JNIEnv is borrowed mutably by every call"));
+    }
 
-            let mut name = components.pop().unwrap().to_string();
-            let mut dir = base_dir.join(components.join("/"));
+    resolve_interfaces(&mut tree);
+    sort_tree(&mut tree);
 
-            println!("Handling: {name}");
+    // Signatures refer to classes outside of the tree as well, these are passed untyped
+    let mut config = config.clone();
+    config.classes = Some(tree.iter().map(|x| (x.name.clone(), x.class_type)).collect());
+    let config = &config;
+    let manifest = Manifest::load(output_dir);
 
-            // We're dealing with a subclass
-            if name.contains('$') {
-                let parts = name.split('$').collect::<Vec<_>>();
+    let features = skeleton::Features::new(&tree, &config.generator);
+    let license = match &config.generator.license_file {
+        Some(path) => Some(fs::read_to_string(path).map_err(|e| anyhow!("Reading license file {path}: {e}"))?),
+        None => None,
+    };
+    let header = Header { license: license.as_deref(), backend };
 
-                let parent_class = parts.first().unwrap().to_string();
-                let parent_class_as_package = format!("{}_d", parent_class.to_case(Case::Snake));
+    let manifest_header = header.generate("#", None);
+    let dependencies = runtime::dependencies(&config.generator);
+    write_source(&manifest, "Cargo.toml", manifest_header + &skeleton::generate_manifest(&config.generator, &features, &dependencies))?;
 
-                dir.push(&parent_class_as_package);
+    let lib_prelude = parse_prelude("lib_prelude", config.generator.lib_prelude.as_deref())?;
+    let module_prelude = parse_prelude("module_prelude", config.generator.module_prelude.as_deref())?;
 
-                // The child class becomes the name
-                name = parts.last().unwrap().to_string();
+    let source_header = header.generate("//", None);
+    let time_feature = runtime::java_time_crate(config.generator.java_time).map(|(name, _)| name);
+    write_source(&manifest, "src/lib.rs", source_header.clone() + &format_tokens(skeleton::generate_lib(&lib_prelude, time_feature).to_string())?)?;
+    let (runtime, java_time) = match jni_version {
+        JniVersion::V0_19 => (runtime::generate_runtime(&config.generator), runtime::generate_java_time(config.generator.java_time)),
+        JniVersion::V0_21 => (jni21::runtime::generate_runtime(), jni21::runtime::generate_java_time(config.generator.java_time)),
+    };
+    write_source(&manifest, "src/runtime.rs", source_header.clone() + &format_tokens(runtime.to_string())?)?;
+    write_source(&manifest, "src/exceptions.rs", source_header.clone() + &format_tokens(generate_exceptions(&tree, jni_version).to_string())?)?;
+    if let Some(java_time) = java_time {
+        write_source(&manifest, "src/java_time.rs", source_header.clone() + &format_tokens(java_time.to_string())?)?;
+    }
+    // The generated code relies on jrsgen's Java helpers, which the user puts on the classpath.
+    // The jni 0.21 code does not implement interfaces or build annotations, which are what they are for
+    if jni_version == JniVersion::V0_19 {
+        crate::java_helpers::write_to(&output_dir.join("java"))?;
+    }
 
-                // TODO this needs to be handled better
-                // This is to quickly avoid nested Enums,
-                // they cause trouble
-                if name.parse::<i32>().is_ok() {
-                    return Ok(());
-                }
+    let mut modules = skeleton::ModuleTree::default();
+    for class in &tree {
+        modules.add_class(&class.name);
+    }
 
-                // Fix the ident in the Class too
-                class.name = class.name
-                    .replace(&parent_class, &parent_class_as_package)
-                    .replace('$', ".");
-            }
+    for (path, tokens) in modules.generate(&features, &module_prelude) {
+        let file = path.iter().fold("src/bindings".to_string(), |file, x| format!("{file}/{x}"));
+        write_source(&manifest, &format!("{file}/mod.rs"), source_header.clone() + &format_tokens(tokens.to_string())?)?;
+    }
 
-            if !dir.exists() {
-                fs::create_dir_all(&dir)?;
-            }
+    let config_hash = generator_config_hash(config, &header)?;
+    let class_count = tree.len();
+    let mut errors = tree.into_par_iter()
+        .filter_map(|class| {
+            generate_class_file(&class, config, config_hash, &manifest, &header)
+                .err()
+                .map(|e| (class.java_name, e))
+        })
+        .collect::<Vec<_>>();
+
+    // Files of classes that failed are not in the manifest, so their outdated versions are deleted
+    manifest.finish()?;
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    errors.sort_by(|a, b| a.0.cmp(&b.0));
+    let report = errors.iter()
+        .map(|(class, e)| format!("\n  {class}: {e:#}"))
+        .collect::<String>();
+    Err(anyhow!("Failed to generate {} of {} classes:{}", errors.len(), class_count, report))
+}
+
+/// Generate the file of a class, unless it did not change since the previous run.
+/// E.g. `src/bindings/com/foo/bar_p/Baz.rs` for `com.foo.bar_p.Baz`,
+/// nested classes are already placed in the module of their enclosing class
+fn generate_class_file(class: &FormattedClassEntry, config: &Config, config_hash: u64, manifest: &Manifest, header: &Header) -> JResult<()> {
+    let file = format!("src/bindings/{}.rs", class.name.replace('.', "/"));
+    let hash = class_hash(class, config_hash);
+    if manifest.is_current(&file, hash) {
+        trace!("Skipping unchanged {}", class.java_name);
+        return Ok(());
+    }
+
+    trace!("Generating {}", class.java_name);
+    let tokens = match config.generator.jni_version {
+        JniVersion::V0_19 => generate_entry(class, config),
+        JniVersion::V0_21 => jni21::generate_entry(class, config),
+    };
+    let header = header.generate("//", Some(&class.java_name));
+    manifest.write(&file, hash, &(header + &format_tokens(tokens.to_string())?))
+}
+
+/// Write a file that is cheap to generate, keyed by the hash of its contents
+fn write_source(manifest: &Manifest, file: &str, contents: String) -> JResult<()> {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    manifest.write(file, hasher.finish(), &contents)
+}
+
+/// Hash what the code generated for every class depends on besides the class itself
+fn generator_config_hash(config: &Config, header: &Header) -> JResult<u64> {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    header.license.hash(&mut hasher);
+    header.backend.hash(&mut hasher);
+    // The output directory doesn't change what is generated, and the mappings are hashed sorted,
+    // as the iteration order of a `HashMap` differs between runs
+    let generator = Generator {
+        output_directory: None,
+        mappings: HashMap::new(),
+        ..config.generator.clone()
+    };
+    toml::to_string(&generator)?.hash(&mut hasher);
+    let mut mappings = config.generator.mappings.iter().collect::<Vec<_>>();
+    mappings.sort();
+    mappings.hash(&mut hasher);
+    // Whether a class is in the tree decides how other classes refer to it
+    let mut classes = config.classes.iter().flatten().collect::<Vec<_>>();
+    classes.sort_by(|a, b| a.0.cmp(b.0));
+    classes.hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+/// Hash the formatted class, which the generated code is derived from
+fn class_hash(class: &FormattedClassEntry, config_hash: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    config_hash.hash(&mut hasher);
+    format!("{class:?}").hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Parse configured Rust code, named `name` in the config, that is injected into generated files
+fn parse_prelude(name: &str, prelude: Option<&str>) -> JResult<TokenStream> {
+    TokenStream::from_str(prelude.unwrap_or_default())
+        .map_err(|e| anyhow!("Invalid Rust code in {name}: {e}"))
+}
 
-            let path = dir.join(format!("{}.rs", name));
-            let mut file = File::create(&path)?;
+/// The header of every generated file: the configured license, followed by where the file came from
+struct Header<'a> {
+    license: Option<&'a str>,
+    /// How the classes were read, e.g. `JVM, Java 17.0.2`
+    backend: &'a str,
+}
 
-            let tokens = generate_entry(&class);
-            let stringified = tokens.to_string();
+impl Header<'_> {
+    /// Generate the header as comment lines starting with `comment`.
+    /// `source` is the Java class the file was generated from, if it was generated from a single class
+    fn generate(&self, comment: &str, source: Option<&str>) -> String {
+        let mut header = String::new();
+        if let Some(license) = self.license {
+            for line in license.trim_end().lines() {
+                header.push_str(format!("{comment} {line}").trim_end());
+                header.push('\n');
+            }
+
+            header.push('\n');
+        }
 
-            let formatted = format_tokens(stringified)?;
-            file.write_all(formatted.as_bytes())?;
+        let mut lines = vec![format!("{GENERATED_MARKER} {}. Do not edit, changes are lost when the bindings are regenerated", env!("CARGO_PKG_VERSION"))];
+        lines.extend(source.map(|x| format!("Java class: {x}")));
+        lines.push(format!("Read with: {}", self.backend));
 
-            Result::<(), anyhow::Error>::Ok(())
-        })?;
+        for line in lines {
+            header.push_str(&format!("{comment} {line}\n"));
+        }
 
-    Ok(())
+        header.push('\n');
+        header
+    }
 }
 
 fn format_tokens(input: String) -> JResult<String> {
@@ -80,34 +227,298 @@ fn format_tokens(input: String) -> JResult<String> {
         .arg("stdout")
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
         .spawn()?;
 
-    let stdin = command.stdin.as_mut().unwrap();
+    // rustfmt only starts formatting once stdin is closed
+    let mut stdin = command.stdin.take().unwrap();
     stdin.write_all(input.as_bytes())?;
     drop(stdin);
 
     let output = command.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow!("rustfmt failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
     let stdout = output.stdout;
     let stdout = String::from_utf8(stdout)?;
     Ok(stdout)
 }
 
-fn generate_entry(class: &ClassEntry) -> TokenStream {
+/// Sort the classes and their members, so the output does not depend on the order the backend reports them in.
+/// The JVM does not specify the order of e.g. `Class#getDeclaredMethods`, and the bytecode backend uses declaration order.
+/// Enum constants and superclasses keep their order, as it is meaningful
+fn sort_tree(tree: &mut [FormattedClassEntry]) {
+    tree.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for class in tree {
+        class.methods.sort_by(|a, b| (&a.rust_name, &a.jni_signature).cmp(&(&b.rust_name, &b.jni_signature)));
+        class.constructors.sort_by(|a, b| (&a.rust_name, &a.jni_signature).cmp(&(&b.rust_name, &b.jni_signature)));
+        class.fields.sort_by(|a, b| a.rust_name.cmp(&b.rust_name));
+        class.constants.sort_by(|a, b| a.rust_name.cmp(&b.rust_name));
+        class.annotation_elements.sort_by(|a, b| a.rust_name.cmp(&b.rust_name));
+        class.interfaces.sort();
+        class.nested_classes.sort();
+        class.permitted_subclasses.sort();
+    }
+}
+
+/// Interfaces are generated as traits, with their superinterfaces as supertraits. Resolve what that requires
+/// from the interfaces in the tree before the classes are generated on their own:
+/// classes and the wrappers of interfaces implement the superinterfaces of their interfaces as well, and interfaces
+/// leave out the instance methods of their superinterfaces, as calls would be ambiguous with both traits in scope.
+/// Classes also implement the interfaces of their superclasses in the tree, as the class only lists those it declares
+fn resolve_interfaces(tree: &mut [FormattedClassEntry]) {
+    let interfaces = tree.iter()
+        .filter(|x| matches!(x.class_type, ClassType::Interface))
+        .map(|x| {
+            let methods = x.methods.iter()
+                .filter(|x| !x.is_static)
+                .map(|x| (x.java_name.clone(), x.jni_signature.clone()))
+                .collect::<HashSet<_>>();
+            (x.name.clone(), (x.interfaces.clone(), methods))
+        })
+        .collect::<HashMap<_, _>>();
+    let declared = tree.iter()
+        .filter(|x| matches!(x.class_type, ClassType::Class))
+        .map(|x| (x.name.clone(), x.interfaces.clone()))
+        .collect::<HashMap<_, _>>();
+
+    // Superinterfaces outside of the tree are not known
+    let superinterfaces = |direct: &[&String]| {
+        let mut superinterfaces = HashSet::new();
+        let mut pending = direct.iter()
+            .flat_map(|x| interfaces.get(*x))
+            .flat_map(|(x, _)| x.iter())
+            .collect::<Vec<_>>();
+        while let Some(interface) = pending.pop() {
+            if superinterfaces.insert(interface.clone()) {
+                pending.extend(interfaces.get(interface).into_iter().flat_map(|(x, _)| x.iter()));
+            }
+        }
+
+        superinterfaces
+    };
+
+    for class in tree {
+        // The superclasses start at the direct superclass, so their interfaces are added nearest first
+        let from_superclasses = class.superclasses.iter()
+            .flat_map(|x| declared.get(x))
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+        let direct = class.interfaces.iter().chain(from_superclasses.iter()).collect::<Vec<_>>();
+        let superinterfaces = superinterfaces(&direct);
+        if matches!(class.class_type, ClassType::Interface) {
+            let inherited = superinterfaces.iter()
+                .chain(class.interfaces.iter())
+                .flat_map(|x| interfaces.get(x))
+                .flat_map(|(_, methods)| methods.iter())
+                .collect::<HashSet<_>>();
+            class.methods.retain(|x| x.is_static || !inherited.contains(&(x.java_name.clone(), x.jni_signature.clone())));
+        }
+
+        if matches!(class.class_type, ClassType::Class | ClassType::Interface) {
+            for interface in from_superclasses.into_iter().chain(superinterfaces) {
+                if !class.interfaces.contains(&interface) {
+                    class.interfaces.push(interface);
+                }
+            }
+        }
+    }
+}
+
+/// Generate the `JavaException` enum, with a variant for every checked exception declared by a method or constructor in the tree
+fn generate_exceptions(tree: &[FormattedClassEntry], jni_version: JniVersion) -> TokenStream {
+    let exceptions = tree.iter()
+        .flat_map(|class| {
+            let methods = class.methods.iter().flat_map(|method| method.exceptions.iter());
+            let constructors = class.constructors.iter().flat_map(|constructor| constructor.exceptions.iter());
+            methods.chain(constructors).cloned()
+        })
+        .collect::<Vec<_>>();
+
+    let (java_names, variants): (Vec<_>, Vec<_>) = rename_exception_variants(&exceptions).into_iter()
+        .map(|(java_name, variant)| (java_name, format_ident!("{}", variant)))
+        .unzip();
+
+    let jni_names = java_names.iter()
+        .map(|x| x.replace('.', "/"))
+        .collect::<Vec<_>>();
+
+    let docs = java_names.iter()
+        .map(|x| format!(" `{x}`"))
+        .collect::<Vec<_>>();
+    let from_throwable = match jni_version {
+        JniVersion::V0_19 => generate_from_throwable(&jni_names, &variants),
+        JniVersion::V0_21 => jni21::generate_from_throwable(&jni_names, &variants),
+    };
+
+    quote! {
+        /// Checked exceptions declared by the bound Java methods and constructors.
+        /// A thrown exception maps to the most specific variant it is an instance of
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub enum JavaException {
+            #(
+                #[doc = #docs]
+                #variants,
+            )*
+            /// An exception not declared by any bound method, e.g. an unchecked exception, with its fully qualified class name
+            Other(String),
+        }
+
+        impl JavaException {
+            #from_throwable
+
+            /// Get the variant for a fully qualified exception class name, e.g. `java.io.IOException`
+            pub fn from_class_name(name: &str) -> Self {
+                match name {
+                    #(#java_names => Self::#variants,)*
+                    _ => Self::Other(name.to_string()),
+                }
+            }
+
+            /// The fully qualified class name of the exception
+            pub fn class_name(&self) -> &str {
+                match self {
+                    #(Self::#variants => #java_names,)*
+                    Self::Other(name) => name,
+                }
+            }
+        }
+    }
+}
+
+/// Generate `JavaException::from_throwable`, trying the variants' classes in the order of `jni_names`
+fn generate_from_throwable(jni_names: &[String], variants: &[Ident]) -> TokenStream {
+    let variant_count = variants.len();
+
+    quote! {
+        /// Get the variant for a thrown exception. Subclasses of a declared exception map to its variant,
+        /// e.g. a `java.io.FileNotFoundException` to `IoException` if only `java.io.IOException` is declared
+        pub fn from_throwable(env: &jni::JNIEnv<'_>, throwable: jni::objects::JThrowable<'_>) -> crate::JResult<Self> {
+            let class = env.get_object_class(throwable)?;
+            let name = env.call_method(class, "getName", "()Ljava/lang/String;", &[])?.l()?;
+            let name = crate::get_string(env, name)?;
+
+            let exact = Self::from_class_name(&name);
+            if !matches!(exact, Self::Other(_)) {
+                return Ok(exact);
+            }
+
+            let candidates: [(&str, Self); #variant_count] = [#((#jni_names, Self::#variants)),*];
+            let mut best: Option<(jni::objects::JClass<'_>, Self)> = None;
+            for (jni_name, variant) in candidates {
+                // The exception class might not be on the classpath
+                let candidate = match env.find_class(jni_name) {
+                    Ok(candidate) => candidate,
+                    Err(_) => {
+                        env.exception_clear()?;
+                        continue;
+                    }
+                };
+
+                if !env.is_instance_of(throwable, candidate)? {
+                    continue;
+                }
+
+                best = match best {
+                    Some((class, _)) if !env.is_assignable_from(candidate, class)? => best,
+                    _ => Some((candidate, variant)),
+                };
+            }
+
+            Ok(best.map(|(_, variant)| variant).unwrap_or(Self::Other(name)))
+        }
+    }
+}
+
+/// Generate a `#[deprecated]` attribute if the Java element is deprecated
+fn generate_deprecated(deprecation: &Option<Deprecation>) -> TokenStream {
+    let deprecation = match deprecation {
+        Some(deprecation) => deprecation,
+        None => return quote! {},
+    };
+
+    let note = deprecation.for_removal.then_some("Scheduled for removal in Java");
+    match (&deprecation.since, note) {
+        (Some(since), Some(note)) => quote! { #[deprecated(since = #since, note = #note)] },
+        (Some(since), None) => quote! { #[deprecated(since = #since)] },
+        (None, Some(note)) => quote! { #[deprecated(note = #note)] },
+        (None, None) => quote! { #[deprecated] },
+    }
+}
+
+/// Generate the doc comment lines for a formatted Javadoc comment, followed by an empty line to separate it
+/// from the generated documentation
+fn generate_javadoc(javadoc: &[String]) -> TokenStream {
+    if javadoc.is_empty() {
+        return quote! {};
+    }
+
+    quote! {
+        #(#[doc = #javadoc])*
+        #[doc = ""]
+    }
+}
+
+fn generate_entry(class: &FormattedClassEntry, config: &Config) -> TokenStream {
+    let type_parameters = generate_type_parameters(class, config);
     let (class_tokens, class_ident) = match class.class_type {
-        ClassType::Class => generate_class(class),
-        ClassType::Interface => generate_interface(class),
-        ClassType::Annotation => return quote! {},
+        ClassType::Class => generate_class(class, config),
+        // Interface methods are generated as part of the trait
+        ClassType::Interface => return generate_interface(class, config).0,
+        ClassType::Annotation => return generate_annotation(class, config).0,
+        // Enum constants are fully described by the generated Rust enum
+        ClassType::Enum => return generate_enum(class).0,
     };
 
+    // Closeable classes get a consuming `close` instead
+    let closeable = is_closeable(class);
     let methods = class.methods.iter()
-        .map(generate_method)
+        .filter(|x| !(closeable && is_close_method(x)))
+        .map(|x| generate_method(x, config))
         .collect::<Vec<_>>();
 
+    // Only inner classes need an enclosing instance, other classes are constructed through `new`
+    let constructors = match (class.is_abstract, class.is_inner_class) {
+        (true, _) => Vec::new(),
+        (false, true) => generate_inner_constructors(class, config),
+        (false, false) => generate_constructors(class, config),
+    };
+    let new_unchecked = generate_new_unchecked(class);
+    let instance = generate_instance_accessor(class, config);
+    let builder = builder::generate_builder(class, config);
+
     quote! {
         #class_tokens
 
-        impl<'a> #class_ident<'a> {
+        impl<'a, #(#type_parameters),*> #class_ident<'a, #(#type_parameters),*> {
+            #(#constructors)*
+            #new_unchecked
+            #instance
             #(#methods)*
         }
+
+        #builder
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn license_header() {
+        let header = Header {
+            license: Some("Copyright (c) Foo\n\nLicensed under the MIT license\n"),
+            backend: "bytecode, without a JVM",
+        };
+
+        let expected = format!("# Copyright (c) Foo\n#\n# Licensed under the MIT license\n\n\
+            # {GENERATED_MARKER} {}. Do not edit, changes are lost when the bindings are regenerated\n\
+            # Java class: com.foo.Bar$Baz\n\
+            # Read with: bytecode, without a JVM\n\n", env!("CARGO_PKG_VERSION"));
+        assert_eq!(expected, header.generate("#", Some("com.foo.Bar$Baz")));
+    }
+}
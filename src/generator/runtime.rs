@@ -0,0 +1,1401 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use crate::config::{BigDecimalType, BigIntegerType, Generator, JavaTime, JniVersion};
+
+/// The cargo feature of the generated crate enabling the monitor methods of `JavaObject`,
+/// which are rarely needed and easily misused
+pub const OBJECT_MONITOR_FEATURE: &str = "object-monitor";
+
+/// The optional dependency of the generated crate, and thus its feature, enabling the `_async` variants of
+/// methods returning futures
+pub const ASYNC_FEATURE: &str = "tokio";
+
+/// Generate `runtime.rs`, defining the items the bindings refer to through `crate::`:
+/// the traits implemented by every bound type, the error type, and helpers for calling into Java
+pub fn generate_runtime(config: &Generator) -> TokenStream {
+    let array_elements = generate_array_elements();
+    let object_types = generate_object_types(config.pure_jni);
+    let implicit_env = if config.implicit_env {
+        generate_implicit_env()
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        use std::fmt;
+
+        #object_types
+        #implicit_env
+
+        /// The result of a call into Java
+        pub type JResult<T> = Result<T, Error>;
+
+        /// An error calling into Java
+        #[derive(Debug)]
+        pub enum Error {
+            /// The Java code threw an exception. It is cleared, so the JNIEnv can be used again
+            Exception {
+                exception: crate::JavaException,
+                /// The fully qualified class name of the thrown exception, which might be a subclass of `exception`
+                class_name: String,
+                message: Option<String>,
+            },
+            /// JNI itself failed, e.g. because a class or method could not be found
+            Jni(jni::errors::Error),
+            /// A value could not be converted between Java and Rust, e.g. because it is out of the range of the Rust type
+            Conversion(String),
+        }
+
+        impl fmt::Display for Error {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    Self::Exception { class_name, message: Some(message), .. } => write!(f, "{}: {}", class_name, message),
+                    Self::Exception { class_name, message: None, .. } => write!(f, "{}", class_name),
+                    Self::Jni(e) => write!(f, "JNI error: {}", e),
+                    Self::Conversion(message) => write!(f, "Conversion error: {}", message),
+                }
+            }
+        }
+
+        impl std::error::Error for Error {
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                match self {
+                    Self::Jni(e) => Some(e),
+                    Self::Exception { .. } | Self::Conversion(_) => None,
+                }
+            }
+        }
+
+        impl From<jni::errors::Error> for Error {
+            fn from(e: jni::errors::Error) -> Self {
+                Self::Jni(e)
+            }
+        }
+
+        /// A type bound to a Java class
+        pub trait ClassName {
+            /// The name of the class in the notation of JNI, e.g. `java/lang/String`
+            fn class_name() -> &'static str;
+
+            /// The class, looked up on first use
+            fn class_ref() -> &'static ClassRef;
+        }
+
+        /// A type that wraps a Java object
+        pub trait FromRaw<'a> {
+            /// Wrap an object, which must be an instance of the class the type is bound to
+            fn from_raw(env: &'a jni::JNIEnv<'a>, obj: Object<'a>) -> Self;
+        }
+
+        /// A type that wraps a Java object, through which the traits of Java interfaces call into Java
+        pub trait JavaObject<'a> {
+            /// The JNI environment the object belongs to
+            fn env(&self) -> &'a jni::JNIEnv<'a>;
+
+            /// The underlying Java object
+            fn obj(&self) -> &Object<'a>;
+
+            /// Whether the object is an instance of the class `T` is bound to, like Java's `instanceof`
+            fn is_instance_of<T: ClassName>(&self) -> JResult<bool>
+            where
+                Self: Sized,
+            {
+                let env = self.env();
+                catch_exception(env, env.is_instance_of(self.obj().inner, T::class_name()))
+            }
+
+            /// Downcast to a more specific type, e.g. a subclass or an implemented interface's class.
+            /// Returns `None` if the object is not an instance of its class
+            fn try_cast<T: ClassName + FromRaw<'a>>(&self) -> JResult<Option<T>>
+            where
+                Self: Sized,
+            {
+                if !self.is_instance_of::<T>()? {
+                    return Ok(None);
+                }
+
+                let env = self.env();
+                Ok(Some(wrap(env, self.obj().inner)?))
+            }
+
+            /// Java: `Class<?> getClass()`. The runtime class of the object
+            fn get_class(&self) -> JResult<Class<'a>> {
+                let env = self.env();
+                Ok(Class::new(env, catch_exception(env, env.get_object_class(self.obj().inner))?))
+            }
+
+            /// Java: `int hashCode()`
+            fn hash_code(&self) -> JResult<i32> {
+                let env = self.env();
+                Ok(catch_exception(env, env.call_method(self.obj().inner, "hashCode", "()I", &[]))?.i()?)
+            }
+
+            /// Java: `boolean equals(Object)`
+            fn equals(&self, other: &dyn JavaObject<'a>) -> JResult<bool> {
+                let env = self.env();
+                Ok(catch_exception(env, env.call_method(self.obj().inner, "equals", "(Ljava/lang/Object;)Z", &[other.obj().inner.into()]))?.z()?)
+            }
+
+            /// Java: `String toString()`. Not named `to_string`, as that would be ambiguous with `ToString`,
+            /// which the bound types implement through `Display`. A `null` result is returned as `"null"`
+            fn java_to_string(&self) -> JResult<String> {
+                let env = self.env();
+                let value = catch_exception(env, env.call_method(self.obj().inner, "toString", "()Ljava/lang/String;", &[]))?.l()?;
+                if value.is_null() {
+                    Ok("null".to_string())
+                } else {
+                    get_string(env, value)
+                }
+            }
+
+            /// Enter the monitor of the object until the guard is dropped, like Java's `synchronized`.
+            /// The monitor must be held for `wait` and `notify`
+            #[cfg(feature = #OBJECT_MONITOR_FEATURE)]
+            fn lock(&self) -> JResult<jni::MonitorGuard<'a>> {
+                let env = self.env();
+                catch_exception(env, env.lock_obj(self.obj().inner))
+            }
+
+            /// Java: `void wait()`
+            #[cfg(feature = #OBJECT_MONITOR_FEATURE)]
+            fn wait(&self) -> JResult<()> {
+                let env = self.env();
+                catch_exception(env, env.call_method(self.obj().inner, "wait", "()V", &[]))?;
+                Ok(())
+            }
+
+            /// Java: `void wait(long)`. Java waits indefinitely for a timeout of zero, so it is rounded up to a millisecond
+            #[cfg(feature = #OBJECT_MONITOR_FEATURE)]
+            fn wait_timeout(&self, timeout: std::time::Duration) -> JResult<()> {
+                let env = self.env();
+                let millis = i64::try_from(timeout.as_millis()).unwrap_or(i64::MAX).max(1);
+                catch_exception(env, env.call_method(self.obj().inner, "wait", "(J)V", &[millis.into()]))?;
+                Ok(())
+            }
+
+            /// Java: `void notify()`
+            #[cfg(feature = #OBJECT_MONITOR_FEATURE)]
+            fn notify(&self) -> JResult<()> {
+                let env = self.env();
+                catch_exception(env, env.call_method(self.obj().inner, "notify", "()V", &[]))?;
+                Ok(())
+            }
+
+            /// Java: `void notifyAll()`
+            #[cfg(feature = #OBJECT_MONITOR_FEATURE)]
+            fn notify_all(&self) -> JResult<()> {
+                let env = self.env();
+                catch_exception(env, env.call_method(self.obj().inner, "notifyAll", "()V", &[]))?;
+                Ok(())
+            }
+        }
+
+        /// Objects of classes without bindings or a mapping are passed as the untyped `Object`
+        impl<'a> ClassName for Object<'a> {
+            fn class_name() -> &'static str {
+                "java/lang/Object"
+            }
+
+            fn class_ref() -> &'static ClassRef {
+                static CLASS: ClassRef = ClassRef::new("java/lang/Object");
+                &CLASS
+            }
+        }
+
+        impl<'a> FromRaw<'a> for Object<'a> {
+            fn from_raw(_env: &'a jni::JNIEnv<'a>, obj: Object<'a>) -> Self {
+                obj
+            }
+        }
+
+        impl<'a> JavaObject<'a> for Object<'a> {
+            fn env(&self) -> &'a jni::JNIEnv<'a> {
+                self.env
+            }
+
+            fn obj(&self) -> &Object<'a> {
+                self
+            }
+        }
+
+        /// Create a local reference to the object of a global one, wrapped as `T`. For the `attach` methods of the global handles
+        pub fn attach_global<'a, T: ClassName + FromRaw<'a>>(env: &'a jni::JNIEnv<'a>, global: &jni::objects::GlobalRef) -> JResult<T> {
+            // The new reference is valid for as long as the env, not just the borrow of the global one
+            let global = jni::objects::JObject::from(global.as_obj().into_inner());
+            let obj = catch_exception(env, env.new_local_ref::<jni::objects::JObject>(global))?;
+            wrap(env, obj)
+        }
+
+        /// Wrap an object, which must be an instance of the class `T` is bound to
+        pub fn wrap<'a, T: ClassName + FromRaw<'a>>(env: &'a jni::JNIEnv<'a>, obj: jni::objects::JObject<'a>) -> JResult<T> {
+            Ok(T::from_raw(env, Object::new(env, obj, T::class_ref().get(env)?)))
+        }
+
+        /// A Rust type converted from and to objects of a Java class
+        pub trait JavaConvert<'a>: Sized {
+            /// Convert a Java object, which must not be `null`
+            fn from_java(env: &'a jni::JNIEnv<'a>, obj: jni::objects::JObject<'a>) -> JResult<Self>;
+
+            /// Create the Java object for the value
+            fn to_java(&self, env: &'a jni::JNIEnv<'a>) -> JResult<jni::objects::JObject<'a>>;
+        }
+
+        /// A primitive copied from and to Java arrays as a whole, with the `Get/Set<Type>ArrayRegion` functions
+        pub trait ArrayElement: Sized {
+            /// Create a Java array holding a copy of the values
+            fn new_array<'a>(env: &'a jni::JNIEnv<'a>, values: &[Self]) -> JResult<jni::objects::JObject<'a>>;
+
+            /// Copy the elements of a Java array, which must not be `null`
+            fn array_to_vec(env: &jni::JNIEnv<'_>, array: jni::objects::JObject<'_>) -> JResult<Vec<Self>>;
+        }
+
+        #array_elements
+
+        /// Copy the values into a new Java array of their primitive type
+        pub fn new_primitive_array<'a, T: ArrayElement>(env: &'a jni::JNIEnv<'a>, values: impl AsRef<[T]>) -> JResult<jni::objects::JObject<'a>> {
+            T::new_array(env, values.as_ref())
+        }
+
+        /// Copy the converted elements into a new Java array of objects.
+        /// `element_class` is the class of the elements in the notation of JNI, e.g. `java/lang/String` or `[F`
+        pub fn new_object_array<'a, T>(
+            env: &'a jni::JNIEnv<'a>,
+            element_class: &str,
+            elements: impl IntoIterator<Item = T>,
+            convert: impl Fn(T) -> JResult<jni::objects::JObject<'a>>,
+        ) -> JResult<jni::objects::JObject<'a>> {
+            // The length must be known up front
+            let elements = elements.into_iter()
+                .map(convert)
+                .collect::<JResult<Vec<_>>>()?;
+            let array = catch_exception(env, env.new_object_array(array_length(&elements)?, element_class, jni::objects::JObject::null()))?;
+            for (idx, element) in elements.into_iter().enumerate() {
+                catch_exception(env, env.set_object_array_element(array, idx as jni::sys::jsize, element))?;
+            }
+
+            Ok(array.into())
+        }
+
+        /// Convert the elements of a Java array of objects, which must not be `null`
+        pub fn object_array_to_vec<'a, T>(
+            env: &'a jni::JNIEnv<'a>,
+            array: jni::objects::JObject<'a>,
+            convert: impl Fn(jni::objects::JObject<'a>) -> JResult<T>,
+        ) -> JResult<Vec<T>> {
+            let length = catch_exception(env, env.get_array_length(array.into_inner()))?;
+            (0..length)
+                .map(|idx| convert(catch_exception(env, env.get_object_array_element(array.into_inner(), idx))?))
+                .collect()
+        }
+
+        /// The length of a Java array with the values
+        fn array_length<T>(values: &[T]) -> JResult<jni::sys::jsize> {
+            jni::sys::jsize::try_from(values.len())
+                .map_err(|_| Error::Conversion(format!("{} elements do not fit in a Java array", values.len())))
+        }
+
+        /// Turn the result of a JNI call into a `JResult`. If the call threw,
+        /// the pending exception is cleared and returned as `Error::Exception`.
+        /// Otherwise the JVM aborts on the next JNI call
+        pub fn catch_exception<T>(env: &jni::JNIEnv<'_>, result: jni::errors::Result<T>) -> JResult<T> {
+            match result {
+                Ok(value) => Ok(value),
+                // Failing JNI functions leave an exception pending as well,
+                // e.g. a `NoSuchMethodError` if a method cannot be found
+                Err(e) if !env.exception_check()? => Err(Error::Jni(e)),
+                Err(_) => {
+                    let throwable = env.exception_occurred()?;
+                    env.exception_clear()?;
+                    Err(exception_error(env, throwable)?)
+                },
+            }
+        }
+
+        /// The `Error::Exception` describing a throwable
+        fn exception_error(env: &jni::JNIEnv<'_>, throwable: jni::objects::JThrowable<'_>) -> JResult<Error> {
+            let class = env.get_object_class(throwable)?;
+            let name = env.call_method(class, "getName", "()Ljava/lang/String;", &[])?.l()?;
+            let message = env.call_method(throwable, "getMessage", "()Ljava/lang/String;", &[])?.l()?;
+
+            Ok(Error::Exception {
+                exception: crate::JavaException::from_throwable(env, throwable)?,
+                class_name: get_string(env, name)?,
+                message: if message.is_null() {
+                    None
+                } else {
+                    Some(get_string(env, message)?)
+                },
+            })
+        }
+
+        /// Run `f`, converting arguments and calling into Java, in a new frame of local references. The references created
+        /// in it are deleted afterwards, so they do not accumulate when calling in a loop. A returned object is kept,
+        /// as a new reference in the enclosing frame
+        pub fn call_in_frame<'a>(env: &'a jni::JNIEnv<'a>, f: impl FnOnce() -> JResult<jni::objects::JValue<'a>>) -> JResult<jni::objects::JValue<'a>> {
+            catch_exception(env, env.push_local_frame(LOCAL_FRAME_CAPACITY))?;
+            let result = f();
+            let kept = match result {
+                Ok(jni::objects::JValue::Object(obj)) => obj,
+                _ => jni::objects::JObject::null(),
+            };
+
+            let kept = env.pop_local_frame(kept)?;
+            match result {
+                Ok(jni::objects::JValue::Object(_)) => Ok(jni::objects::JValue::Object(kept)),
+                result => result,
+            }
+        }
+
+        /// Run `f` in a new frame of local references, which are deleted afterwards.
+        /// For conversions to Rust values which do not hold references to Java objects
+        pub fn in_local_frame<'a, T>(env: &'a jni::JNIEnv<'a>, f: impl FnOnce() -> JResult<T>) -> JResult<T> {
+            catch_exception(env, env.push_local_frame(LOCAL_FRAME_CAPACITY))?;
+            let result = f();
+            env.pop_local_frame(jni::objects::JObject::null())?;
+            result
+        }
+
+        /// The number of local references a frame is created for. The JVM grows frames if more are needed
+        const LOCAL_FRAME_CAPACITY: i32 = 16;
+
+        /// Look up a class by its name in the notation of JNI, e.g. `java/lang/String`
+        pub fn class_for_name<'a>(env: &'a jni::JNIEnv<'a>, name: &str) -> JResult<Class<'a>> {
+            catch_exception(env, Class::for_name(env, name))
+        }
+
+        /// A class looked up by `FindClass` on first use, for the statics of the generated code.
+        /// The global reference is never deleted, which keeps the class loaded
+        pub struct ClassRef {
+            name: &'static str,
+            class: once_cell::sync::OnceCell<jni::objects::GlobalRef>,
+        }
+
+        impl ClassRef {
+            /// `name` is in the notation of JNI, e.g. `java/lang/String`
+            pub const fn new(name: &'static str) -> Self {
+                Self {
+                    name,
+                    class: once_cell::sync::OnceCell::new(),
+                }
+            }
+
+            /// Get the class, looking it up the first time
+            pub fn get<'a>(&self, env: &'a jni::JNIEnv<'a>) -> JResult<Class<'a>> {
+                let class = self.class.get_or_try_init(|| -> JResult<jni::objects::GlobalRef> {
+                    let class = catch_exception(env, env.find_class(self.name))?;
+                    let global = catch_exception(env, env.new_global_ref(class))?;
+                    env.delete_local_ref(class.into())?;
+                    Ok(global)
+                })?;
+
+                // The reference is valid for as long as the env, as it is never deleted
+                Ok(Class::new(env, jni::objects::JClass::from(class.as_obj().into_inner())))
+            }
+        }
+
+        /// The ID of a method or constructor, looked up on the first call and cached, for the statics of the generated methods.
+        /// IDs are looked up on the declaring class, and stay valid as the class is kept loaded
+        pub struct MethodId {
+            class: ClassRef,
+            name: &'static str,
+            signature: &'static str,
+            id: once_cell::sync::OnceCell<RawMethodId>,
+        }
+
+        /// A `jmethodID`, which unlike `JMethodID` is not bound to a `JNIEnv`
+        struct RawMethodId(jni::sys::jmethodID);
+
+        // Method IDs are valid in every thread
+        unsafe impl Send for RawMethodId {}
+        unsafe impl Sync for RawMethodId {}
+
+        impl MethodId {
+            /// `class` is in the notation of JNI, e.g. `java/lang/String`. The name of constructors is `<init>`
+            pub const fn new(class: &'static str, name: &'static str, signature: &'static str) -> Self {
+                Self {
+                    class: ClassRef::new(class),
+                    name,
+                    signature,
+                    id: once_cell::sync::OnceCell::new(),
+                }
+            }
+
+            fn get<'a>(&self, env: &'a jni::JNIEnv<'a>, is_static: bool) -> JResult<jni::sys::jmethodID> {
+                let id = self.id.get_or_try_init(|| -> JResult<RawMethodId> {
+                    let class = self.class.get(env)?.class;
+                    let id = if is_static {
+                        catch_exception(env, env.get_static_method_id(class, self.name, self.signature))?.into_inner()
+                    } else {
+                        catch_exception(env, env.get_method_id(class, self.name, self.signature))?.into_inner()
+                    };
+                    Ok(RawMethodId(id))
+                })?;
+
+                Ok(id.0)
+            }
+
+            /// Call the instance method on `obj`, which must be an instance of the class.
+            /// `ret` is the kind of the return type, the class of objects is not used
+            pub fn call<'a>(&self, env: &'a jni::JNIEnv<'a>, obj: jni::objects::JObject<'a>, ret: jni::signature::JavaType, args: &[jni::objects::JValue]) -> JResult<jni::objects::JValue<'a>> {
+                // The JVM does not check this
+                if obj.is_null() {
+                    return Err(jni::errors::Error::NullPtr("Calling a method on null").into());
+                }
+
+                let id = jni::objects::JMethodID::from(self.get(env, false)?);
+                catch_exception(env, env.call_method_unchecked(obj, id, ret, args))
+            }
+
+            /// Call the static method
+            pub fn call_static<'a>(&self, env: &'a jni::JNIEnv<'a>, ret: jni::signature::JavaType, args: &[jni::objects::JValue]) -> JResult<jni::objects::JValue<'a>> {
+                let id = jni::objects::JStaticMethodID::from(self.get(env, true)?);
+                catch_exception(env, env.call_static_method_unchecked(self.class.get(env)?.class, id, ret, args))
+            }
+
+            /// Create an instance of the class through the constructor
+            pub fn new_object<'a>(&self, env: &'a jni::JNIEnv<'a>, args: &[jni::objects::JValue]) -> JResult<jni::objects::JObject<'a>> {
+                let id = jni::objects::JMethodID::from(self.get(env, false)?);
+                catch_exception(env, env.new_object_unchecked(self.class.get(env)?.class, id, args))
+            }
+        }
+
+        /// Copy the converted elements into a new `java.util.ArrayList`
+        pub fn new_list<'a, T>(
+            env: &'a jni::JNIEnv<'a>,
+            elements: impl IntoIterator<Item = T>,
+            convert: impl Fn(T) -> JResult<jni::objects::JObject<'a>>,
+        ) -> JResult<jni::objects::JObject<'a>> {
+            let list = catch_exception(env, env.new_object("java/util/ArrayList", "()V", &[]))?;
+            for element in elements {
+                let element = convert(element)?;
+                catch_exception(env, env.call_method(list, "add", "(Ljava/lang/Object;)Z", &[element.into()]))?;
+            }
+
+            Ok(list)
+        }
+
+        /// Convert the elements of a `java.util.List`, in the order of its iterator
+        pub fn list_to_vec<'a, T>(
+            env: &'a jni::JNIEnv<'a>,
+            list: jni::objects::JObject<'a>,
+            convert: impl Fn(jni::objects::JObject<'a>) -> JResult<T>,
+        ) -> JResult<Vec<T>> {
+            let iterator = catch_exception(env, env.call_method(list, "iterator", "()Ljava/util/Iterator;", &[]))?.l()?;
+            let mut elements = Vec::new();
+            while catch_exception(env, env.call_method(iterator, "hasNext", "()Z", &[]))?.z()? {
+                let element = catch_exception(env, env.call_method(iterator, "next", "()Ljava/lang/Object;", &[]))?.l()?;
+                elements.push(convert(element)?);
+            }
+
+            Ok(elements)
+        }
+
+        /// Copy the converted entries into a new `java.util.HashMap`
+        pub fn new_map<'a, K, V>(
+            env: &'a jni::JNIEnv<'a>,
+            entries: impl IntoIterator<Item = (K, V)>,
+            convert_key: impl Fn(K) -> JResult<jni::objects::JObject<'a>>,
+            convert_value: impl Fn(V) -> JResult<jni::objects::JObject<'a>>,
+        ) -> JResult<jni::objects::JObject<'a>> {
+            let map = catch_exception(env, env.new_object("java/util/HashMap", "()V", &[]))?;
+            for (key, value) in entries {
+                let key = convert_key(key)?;
+                let value = convert_value(value)?;
+                catch_exception(env, env.call_method(map, "put", "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;", &[key.into(), value.into()]))?;
+            }
+
+            Ok(map)
+        }
+
+        /// Convert the entries of a `java.util.Map`, iterating over its `entrySet`
+        pub fn map_to_hash_map<'a, K: std::hash::Hash + Eq, V>(
+            env: &'a jni::JNIEnv<'a>,
+            map: jni::objects::JObject<'a>,
+            convert_key: impl Fn(jni::objects::JObject<'a>) -> JResult<K>,
+            convert_value: impl Fn(jni::objects::JObject<'a>) -> JResult<V>,
+        ) -> JResult<std::collections::HashMap<K, V>> {
+            let entries = catch_exception(env, env.call_method(map, "entrySet", "()Ljava/util/Set;", &[]))?.l()?;
+            let iterator = catch_exception(env, env.call_method(entries, "iterator", "()Ljava/util/Iterator;", &[]))?.l()?;
+            let mut converted = std::collections::HashMap::new();
+            while catch_exception(env, env.call_method(iterator, "hasNext", "()Z", &[]))?.z()? {
+                let entry = catch_exception(env, env.call_method(iterator, "next", "()Ljava/lang/Object;", &[]))?.l()?;
+                let key = catch_exception(env, env.call_method(entry, "getKey", "()Ljava/lang/Object;", &[]))?.l()?;
+                let value = catch_exception(env, env.call_method(entry, "getValue", "()Ljava/lang/Object;", &[]))?.l()?;
+                converted.insert(convert_key(key)?, convert_value(value)?);
+            }
+
+            Ok(converted)
+        }
+
+        /// Iterates over a `java.util.Iterator` through `hasNext` and `next`, wrapping the elements as `T`.
+        /// The elements are erased to `Object`, so they are checked to be instances of `T`'s class.
+        /// Iteration ends after the first error
+        pub struct JavaIter<'a, T> {
+            env: &'a jni::JNIEnv<'a>,
+            iterator: jni::objects::JObject<'a>,
+            class: Class<'a>,
+            done: bool,
+            _marker: std::marker::PhantomData<T>,
+        }
+
+        impl<'a, T: ClassName + FromRaw<'a>> JavaIter<'a, T> {
+            /// Iterate over `iterator`, which must be a `java.util.Iterator`
+            pub fn new(env: &'a jni::JNIEnv<'a>, iterator: jni::objects::JObject<'a>) -> JResult<Self> {
+                Ok(Self {
+                    env,
+                    iterator,
+                    class: T::class_ref().get(env)?,
+                    done: false,
+                    _marker: std::marker::PhantomData,
+                })
+            }
+
+            fn next_element(&self) -> JResult<Option<T>> {
+                let env = self.env;
+                if !catch_exception(env, env.call_method(self.iterator, "hasNext", "()Z", &[]))?.z()? {
+                    return Ok(None);
+                }
+
+                let element = catch_exception(env, env.call_method(self.iterator, "next", "()Ljava/lang/Object;", &[]))?.l()?;
+                if element.is_null() {
+                    return Err(Error::Conversion("The iterator returned null".to_string()));
+                }
+
+                if !catch_exception(env, env.is_instance_of(element, T::class_name()))? {
+                    return Err(Error::Conversion(format!("The iterator returned an element which is not a {}", T::class_name())));
+                }
+
+                Ok(Some(T::from_raw(env, Object::new(env, element, self.class.clone()))))
+            }
+        }
+
+        impl<'a, T: ClassName + FromRaw<'a>> Iterator for JavaIter<'a, T> {
+            type Item = JResult<T>;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                if self.done {
+                    return None;
+                }
+
+                let element = self.next_element().transpose();
+                self.done = !matches!(element, Some(Ok(_)));
+                element
+            }
+        }
+
+        impl<'a, T: ClassName + FromRaw<'a>> std::iter::FusedIterator for JavaIter<'a, T> {}
+
+        /// Call `obj.close()`, for the consuming `close` methods of the `java.lang.AutoCloseable` implementors
+        pub fn close(env: &jni::JNIEnv<'_>, obj: jni::objects::JObject<'_>) -> JResult<()> {
+            catch_exception(env, env.call_method(obj, "close", "()V", &[]))?;
+            Ok(())
+        }
+
+        /// Closes the wrapped `java.lang.AutoCloseable` when dropped. `Drop` cannot return errors,
+        /// so failures are logged. Call `close` to handle them instead
+        pub struct CloseGuard<'a, T: JavaObject<'a>> {
+            inner: Option<T>,
+            _marker: std::marker::PhantomData<&'a ()>,
+        }
+
+        impl<'a, T: JavaObject<'a>> CloseGuard<'a, T> {
+            pub fn new(inner: T) -> Self {
+                Self {
+                    inner: Some(inner),
+                    _marker: std::marker::PhantomData,
+                }
+            }
+
+            /// Close the object now, returning the error if it fails
+            pub fn close(mut self) -> JResult<()> {
+                let inner = self.inner.take().expect("The object is only taken when the guard is consumed");
+                close(inner.env(), inner.obj().inner)
+            }
+
+            /// Take the object out of the guard, without closing it
+            pub fn into_inner(mut self) -> T {
+                self.inner.take().expect("The object is only taken when the guard is consumed")
+            }
+        }
+
+        impl<'a, T: JavaObject<'a>> std::ops::Deref for CloseGuard<'a, T> {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                self.inner.as_ref().expect("The object is only taken when the guard is consumed")
+            }
+        }
+
+        impl<'a, T: JavaObject<'a>> Drop for CloseGuard<'a, T> {
+            fn drop(&mut self) {
+                if let Some(inner) = self.inner.take() {
+                    if let Err(e) = close(inner.env(), inner.obj().inner) {
+                        log::error!("Closing a {} failed: {}", std::any::type_name::<T>(), e);
+                    }
+                }
+            }
+        }
+
+        /// A Rust implementation of a Java interface, called by the `jrsgen.NativeProxy` the interface is implemented through.
+        /// Called with the name and JNI signature of the invoked method, and the arguments with primitives boxed.
+        /// Returns the result, boxed as well, or `null` for `void` methods
+        pub type ProxyHandler = Box<
+            dyn for<'a> Fn(&'a jni::JNIEnv<'a>, &str, &str, Vec<jni::objects::JObject<'a>>) -> JResult<jni::objects::JObject<'a>>
+                + Send
+                + Sync
+        >;
+
+        /// Create an instance of the interface `class_name`, in the notation of JNI, calling into `handler`.
+        /// Requires the `jrsgen.NativeProxy` helper on the classpath, and its natives registered through `register_natives`
+        pub fn new_proxy<'a>(env: &'a jni::JNIEnv<'a>, class_name: &str, handler: ProxyHandler) -> JResult<jni::objects::JObject<'a>> {
+            let class = jni::objects::JClass::from(class_for_name(env, class_name)?);
+            let handle = Box::into_raw(Box::new(handler)) as jni::sys::jlong;
+            let proxy = catch_exception(env, env.call_static_method(
+                "jrsgen/NativeProxy",
+                "create",
+                "(Ljava/lang/Class;J)Ljava/lang/Object;",
+                &[jni::objects::JValue::Object(class.into()), handle.into()],
+            ));
+
+            match proxy {
+                Ok(proxy) => Ok(proxy.l()?),
+                Err(e) => {
+                    // The proxy did not take ownership
+                    drop(unsafe { Box::from_raw(handle as *mut ProxyHandler) });
+                    Err(e)
+                },
+            }
+        }
+
+        /// Register the natives of `jrsgen.NativeProxy`, through which Java calls the Rust implementations of interfaces.
+        /// Call this once after starting the JVM, or use `jni_on_load!` if the crate is loaded by Java as a library
+        pub fn register_natives(env: &jni::JNIEnv<'_>) -> JResult<()> {
+            let natives = [
+                jni::NativeMethod {
+                    name: "dispatch".into(),
+                    sig: "(JLjava/lang/String;Ljava/lang/String;[Ljava/lang/Object;)Ljava/lang/Object;".into(),
+                    fn_ptr: proxy_dispatch as *mut std::ffi::c_void,
+                },
+                jni::NativeMethod {
+                    name: "release".into(),
+                    sig: "(J)V".into(),
+                    fn_ptr: proxy_release as *mut std::ffi::c_void,
+                },
+            ];
+
+            catch_exception(env, env.register_native_methods("jrsgen/NativeProxy", &natives))
+        }
+
+        /// Define `JNI_OnLoad`, registering the natives of the interface implementations when Java loads the crate
+        /// this is invoked in as a library. Requires `jni` as a dependency of that crate
+        #[macro_export]
+        macro_rules! jni_on_load {
+            () => {
+                #[no_mangle]
+                pub extern "system" fn JNI_OnLoad(vm: jni::JavaVM, _reserved: *mut std::ffi::c_void) -> jni::sys::jint {
+                    match vm.get_env().map_err($crate::Error::from).and_then(|env| $crate::register_natives(&env)) {
+                        Ok(()) => jni::sys::JNI_VERSION_1_8,
+                        Err(_) => jni::sys::JNI_ERR,
+                    }
+                }
+            };
+        }
+
+        /// `jrsgen.NativeProxy#dispatch`. Errors and panics are thrown as Java exceptions
+        extern "system" fn proxy_dispatch(
+            env: jni::JNIEnv<'_>,
+            _class: jni::objects::JClass<'_>,
+            handle: jni::sys::jlong,
+            name: jni::objects::JString<'_>,
+            descriptor: jni::objects::JString<'_>,
+            args: jni::sys::jobjectArray,
+        ) -> jni::sys::jobject {
+            // Owned by the proxy, which keeps it alive during the call
+            let handler = unsafe { &*(handle as *const ProxyHandler) };
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                let name = get_string(&env, name.into())?;
+                let descriptor = get_string(&env, descriptor.into())?;
+                let args = object_array_to_vec(&env, args.into(), Ok)?;
+                handler(&env, &name, &descriptor, args)
+            }));
+
+            let error = match result {
+                Ok(Ok(value)) => return value.into_inner(),
+                Ok(Err(Error::Exception { class_name, message, .. })) => {
+                    // Rethrow exceptions from calls back into Java as the same class, if it can be constructed from a message
+                    let message = message.unwrap_or_default();
+                    if env.throw_new(class_name.replace('.', "/"), &message).is_ok() {
+                        return std::ptr::null_mut();
+                    }
+
+                    let _ = env.exception_clear();
+                    format!("{}: {}", class_name, message)
+                },
+                Ok(Err(e)) => e.to_string(),
+                Err(panic) => match panic.downcast_ref::<&str>() {
+                    Some(message) => format!("Rust panicked: {}", message),
+                    None => match panic.downcast_ref::<String>() {
+                        Some(message) => format!("Rust panicked: {}", message),
+                        None => "Rust panicked".to_string(),
+                    },
+                },
+            };
+
+            let _ = env.throw_new("java/lang/RuntimeException", error);
+            std::ptr::null_mut()
+        }
+
+        /// `jrsgen.NativeProxy#release`, called when the proxy is collected
+        extern "system" fn proxy_release(_env: jni::JNIEnv<'_>, _class: jni::objects::JClass<'_>, handle: jni::sys::jlong) {
+            let _ = std::panic::catch_unwind(|| drop(unsafe { Box::from_raw(handle as *mut ProxyHandler) }));
+        }
+
+        /// Completes with the result of a `java.util.concurrent.CompletionStage`, such as a `CompletableFuture`,
+        /// or of a Guava `ListenableFuture`, without blocking a thread on `get()`. The result is delivered on the thread
+        /// completing the future, so it is a global reference, `None` for `null`. Exceptions completing the future are
+        /// returned as `Error::Exception`. Requires the natives of `jrsgen.NativeProxy`, see `register_natives`
+        #[cfg(feature = #ASYNC_FEATURE)]
+        pub struct JavaFuture {
+            receiver: tokio::sync::oneshot::Receiver<JResult<Option<jni::objects::GlobalRef>>>,
+        }
+
+        #[cfg(feature = #ASYNC_FEATURE)]
+        impl JavaFuture {
+            /// Register a completion callback on `future`, which must be a `CompletionStage` or `ListenableFuture`
+            pub fn new<'a>(env: &'a jni::JNIEnv<'a>, future: jni::objects::JObject<'a>) -> JResult<Self> {
+                if future.is_null() {
+                    return Err(Error::Conversion("The future is null".to_string()));
+                }
+
+                let (sender, receiver) = tokio::sync::oneshot::channel();
+                let sender = std::sync::Mutex::new(Some(sender));
+                // Only the first completion is sent, the callback is called once anyway
+                let complete = move |env: &jni::JNIEnv<'_>, value: jni::objects::JObject<'_>, throwable: jni::objects::JObject<'_>| {
+                    let result = future_result(env, value, throwable);
+                    if let Some(sender) = sender.lock().unwrap_or_else(|e| e.into_inner()).take() {
+                        let _ = sender.send(result);
+                    }
+                };
+
+                if catch_exception(env, env.is_instance_of(future, "java/util/concurrent/CompletionStage"))? {
+                    // `BiConsumer#accept(value, throwable)`
+                    let handler: ProxyHandler = Box::new(move |env, _, _, args| {
+                        complete(env, args[0], args[1]);
+                        Ok(jni::objects::JObject::null())
+                    });
+                    let callback = new_proxy(env, "java/util/function/BiConsumer", handler)?;
+                    catch_exception(env, env.call_method(
+                        future,
+                        "whenComplete",
+                        "(Ljava/util/function/BiConsumer;)Ljava/util/concurrent/CompletionStage;",
+                        &[callback.into()],
+                    ))?;
+                } else {
+                    // `FutureCallback#onSuccess(value)` or `FutureCallback#onFailure(throwable)`
+                    let handler: ProxyHandler = Box::new(move |env, name, _, args| {
+                        match name {
+                            "onSuccess" => complete(env, args[0], jni::objects::JObject::null()),
+                            _ => complete(env, jni::objects::JObject::null(), args[0]),
+                        }
+                        Ok(jni::objects::JObject::null())
+                    });
+                    let callback = new_proxy(env, "com/google/common/util/concurrent/FutureCallback", handler)?;
+                    let executor = catch_exception(env, env.call_static_method(
+                        "com/google/common/util/concurrent/MoreExecutors",
+                        "directExecutor",
+                        "()Ljava/util/concurrent/Executor;",
+                        &[],
+                    ))?.l()?;
+                    catch_exception(env, env.call_static_method(
+                        "com/google/common/util/concurrent/Futures",
+                        "addCallback",
+                        "(Lcom/google/common/util/concurrent/ListenableFuture;Lcom/google/common/util/concurrent/FutureCallback;Ljava/util/concurrent/Executor;)V",
+                        &[future.into(), callback.into(), executor.into()],
+                    ))?;
+                }
+
+                Ok(Self { receiver })
+            }
+        }
+
+        #[cfg(feature = #ASYNC_FEATURE)]
+        impl std::future::Future for JavaFuture {
+            type Output = JResult<Option<jni::objects::GlobalRef>>;
+
+            fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Self::Output> {
+                std::pin::Pin::new(&mut self.receiver).poll(cx)
+                    .map(|x| x.unwrap_or_else(|_| Err(Error::Conversion("The future was dropped without completing".to_string()))))
+            }
+        }
+
+        /// The result of a completed future. `CompletionStage`s report the exceptions of earlier stages wrapped in
+        /// a `java.util.concurrent.CompletionException`, which is unwrapped
+        #[cfg(feature = #ASYNC_FEATURE)]
+        fn future_result(env: &jni::JNIEnv<'_>, value: jni::objects::JObject<'_>, throwable: jni::objects::JObject<'_>) -> JResult<Option<jni::objects::GlobalRef>> {
+            if throwable.is_null() {
+                return if value.is_null() {
+                    Ok(None)
+                } else {
+                    Ok(Some(env.new_global_ref(value)?))
+                };
+            }
+
+            let mut throwable = throwable;
+            if env.is_instance_of(throwable, "java/util/concurrent/CompletionException")? {
+                let cause = env.call_method(throwable, "getCause", "()Ljava/lang/Throwable;", &[])?.l()?;
+                if !cause.is_null() {
+                    throwable = cause;
+                }
+            }
+
+            Err(exception_error(env, throwable.into())?)
+        }
+
+        /// Unbox a primitive argument of an interface implementation through its getter, e.g. `intValue`
+        pub fn unbox<'a>(env: &'a jni::JNIEnv<'a>, obj: jni::objects::JObject<'a>, getter: &str, signature: &str) -> JResult<jni::objects::JValue<'a>> {
+            catch_exception(env, env.call_method(obj, getter, signature, &[]))
+        }
+
+        /// Box a primitive result of an interface implementation through its wrapper's `valueOf`.
+        /// `class_name` is the wrapper in the notation of JNI, e.g. `java/lang/Integer`
+        pub fn box_primitive<'a>(env: &'a jni::JNIEnv<'a>, class_name: &str, signature: &str, value: jni::objects::JValue<'a>) -> JResult<jni::objects::JObject<'a>> {
+            Ok(catch_exception(env, env.call_static_method(class_name, "valueOf", signature, &[value]))?.l()?)
+        }
+
+        /// Create a `java.util.Optional`, or one of its primitive variants, through its static `of` or `empty`.
+        /// `signature` is the signature of `of`
+        pub fn new_optional<'a, T>(
+            env: &'a jni::JNIEnv<'a>,
+            class_name: &str,
+            signature: &str,
+            value: Option<T>,
+            convert: impl Fn(T) -> JResult<jni::objects::JValue<'a>>,
+        ) -> JResult<jni::objects::JObject<'a>> {
+            let optional = match value {
+                Some(value) => catch_exception(env, env.call_static_method(class_name, "of", signature, &[convert(value)?]))?,
+                None => catch_exception(env, env.call_static_method(class_name, "empty", format!("()L{class_name};"), &[]))?,
+            };
+
+            Ok(optional.l()?)
+        }
+
+        /// Convert the value of a `java.util.Optional`, or one of its primitive variants, obtained through `getter`.
+        /// `null` is treated like an empty optional
+        pub fn optional_to_option<'a, T>(
+            env: &'a jni::JNIEnv<'a>,
+            optional: jni::objects::JObject<'a>,
+            getter: &str,
+            signature: &str,
+            convert: impl Fn(jni::objects::JValue<'a>) -> JResult<T>,
+        ) -> JResult<Option<T>> {
+            if optional.is_null() || !catch_exception(env, env.call_method(optional, "isPresent", "()Z", &[]))?.z()? {
+                return Ok(None);
+            }
+
+            let value = catch_exception(env, env.call_method(optional, getter, signature, &[]))?;
+            Ok(Some(convert(value)?))
+        }
+
+        /// Create a `java.math.BigDecimal` or `BigInteger` from its string representation
+        pub fn new_big_number<'a>(env: &'a jni::JNIEnv<'a>, class_name: &str, value: &str) -> JResult<jni::objects::JObject<'a>> {
+            let value = catch_exception(env, env.new_string(value))?;
+            catch_exception(env, env.new_object(class_name, "(Ljava/lang/String;)V", &[jni::objects::JValue::Object(value.into())]))
+        }
+
+        /// Parse the string representation of a `java.math.BigDecimal` or `BigInteger`, obtained through `getter`
+        pub fn parse_big_number<'a, T>(env: &'a jni::JNIEnv<'a>, obj: jni::objects::JObject<'a>, getter: &str) -> JResult<T>
+        where
+            T: std::str::FromStr,
+            T::Err: fmt::Display,
+        {
+            let value = catch_exception(env, env.call_method(obj, getter, "()Ljava/lang/String;", &[]))?.l()?;
+            let value = get_string(env, value)?;
+            value.parse().map_err(|e| Error::Conversion(format!("{}: {}", value, e)))
+        }
+
+        /// Write the result of the object's `toString()`, for the `Display` impls of the bound types.
+        /// Formatting cannot fail with a message, so if the call fails the error is written instead
+        pub fn fmt_to_string(env: &jni::JNIEnv<'_>, obj: jni::objects::JObject<'_>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let value = catch_exception(env, env.call_method(obj, "toString", "()Ljava/lang/String;", &[]))
+                .and_then(|x| Ok(x.l()?))
+                .and_then(|x| if x.is_null() { Ok("null".to_string()) } else { get_string(env, x) });
+            match value {
+                Ok(value) => f.write_str(&value),
+                Err(e) => write!(f, "<toString() failed: {}>", e),
+            }
+        }
+
+        /// Write the runtime class and identity hash code of the object, for the `Debug` impls of the bound types
+        pub fn fmt_identity(env: &jni::JNIEnv<'_>, obj: jni::objects::JObject<'_>, name: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let identity = || -> JResult<(String, i32)> {
+                let class = catch_exception(env, env.call_method(obj, "getClass", "()Ljava/lang/Class;", &[]))?.l()?;
+                let class_name = catch_exception(env, env.call_method(class, "getName", "()Ljava/lang/String;", &[]))?.l()?;
+                let hash = catch_exception(env, env.call_static_method("java/lang/System", "identityHashCode", "(Ljava/lang/Object;)I", &[obj.into()]))?.i()?;
+                Ok((get_string(env, class_name)?, hash))
+            };
+
+            match identity() {
+                Ok((class_name, hash)) => f.debug_struct(name)
+                    .field("class", &class_name)
+                    .field("identity_hash", &format_args!("{:#x}", hash))
+                    .finish(),
+                Err(e) => f.debug_struct(name)
+                    .field("error", &e)
+                    .finish(),
+            }
+        }
+
+        /// Call `a.equals(b)`, for the `PartialEq` impls of the bound types. Panics if the call fails
+        pub fn java_equals(env: &jni::JNIEnv<'_>, a: jni::objects::JObject<'_>, b: jni::objects::JObject<'_>) -> bool {
+            catch_exception(env, env.call_method(a, "equals", "(Ljava/lang/Object;)Z", &[b.into()]))
+                .and_then(|x| Ok(x.z()?))
+                .unwrap_or_else(|e| panic!("Calling equals() failed: {}", e))
+        }
+
+        /// Call `obj.hashCode()`, for the `Hash` impls of the bound types. Panics if the call fails
+        pub fn java_hash_code(env: &jni::JNIEnv<'_>, obj: jni::objects::JObject<'_>) -> i32 {
+            catch_exception(env, env.call_method(obj, "hashCode", "()I", &[]))
+                .and_then(|x| Ok(x.i()?))
+                .unwrap_or_else(|e| panic!("Calling hashCode() failed: {}", e))
+        }
+
+        /// Create a new local reference to the object, for the `Clone` impls of the bound types. Panics if it fails
+        pub fn clone_local_ref<'a>(env: &'a jni::JNIEnv<'a>, obj: &Object<'a>) -> Object<'a> {
+            let inner = catch_exception(env, env.new_local_ref::<jni::objects::JObject>(obj.inner))
+                .unwrap_or_else(|e| panic!("Creating a local reference failed: {}", e));
+            Object::new(env, inner, obj.class.clone())
+        }
+
+        /// Call `a.compareTo(b)`, for the `Ord` impls of the bound types. Panics if the call fails
+        pub fn java_compare(env: &jni::JNIEnv<'_>, a: jni::objects::JObject<'_>, b: jni::objects::JObject<'_>) -> std::cmp::Ordering {
+            catch_exception(env, env.call_method(a, "compareTo", "(Ljava/lang/Object;)I", &[b.into()]))
+                .and_then(|x| Ok(x.i()?))
+                .map(|x| x.cmp(&0))
+                .unwrap_or_else(|e| panic!("Calling compareTo() failed: {}", e))
+        }
+
+        /// Convert a `java.lang.String` to a Rust string
+        pub fn get_string(env: &jni::JNIEnv<'_>, obj: jni::objects::JObject<'_>) -> JResult<String> {
+            Ok(env.get_string(obj.into())?.into())
+        }
+    }
+}
+
+/// The `Object` and `Class` wrappers the bindings are built on: those of `ejni`, or with `pure_jni` minimal
+/// equivalents with the same fields and constructors, so the rest of the generated code is the same
+fn generate_object_types(pure_jni: bool) -> TokenStream {
+    if !pure_jni {
+        return quote! {
+            pub use ejni::{Class, Object};
+        };
+    }
+
+    quote! {
+        /// A Java object together with its class
+        #[derive(Clone)]
+        pub struct Object<'a> {
+            pub inner: jni::objects::JObject<'a>,
+            pub class: Class<'a>,
+            pub env: &'a jni::JNIEnv<'a>,
+        }
+
+        impl<'a> Object<'a> {
+            /// `obj` must be an instance of `class`
+            pub fn new(env: &'a jni::JNIEnv<'a>, obj: jni::objects::JObject<'a>, class: Class<'a>) -> Self {
+                Self {
+                    inner: obj,
+                    class,
+                    env,
+                }
+            }
+        }
+
+        impl<'a> From<Object<'a>> for jni::objects::JValue<'a> {
+            fn from(obj: Object<'a>) -> Self {
+                jni::objects::JValue::Object(obj.inner)
+            }
+        }
+
+        impl<'a> From<&Object<'a>> for jni::objects::JValue<'a> {
+            fn from(obj: &Object<'a>) -> Self {
+                jni::objects::JValue::Object(obj.inner)
+            }
+        }
+
+        /// A `java.lang.Class`
+        #[derive(Clone)]
+        pub struct Class<'a> {
+            pub class: jni::objects::JClass<'a>,
+            env: &'a jni::JNIEnv<'a>,
+        }
+
+        impl<'a> Class<'a> {
+            pub fn new(env: &'a jni::JNIEnv<'a>, class: jni::objects::JClass<'a>) -> Self {
+                Self {
+                    class,
+                    env,
+                }
+            }
+
+            /// Find a class by its name, e.g. `java.lang.String` or `java/lang/String`
+            pub fn for_name(env: &'a jni::JNIEnv<'a>, name: &str) -> jni::errors::Result<Self> {
+                Ok(Self::new(env, env.find_class(name.replace('.', "/"))?))
+            }
+
+            /// The name of the class, e.g. `java.lang.String`
+            pub fn get_name(&self) -> jni::errors::Result<String> {
+                let name = self.env.call_method(self.class, "getName", "()Ljava/lang/String;", &[])?.l()?;
+                Ok(self.env.get_string(name.into())?.into())
+            }
+        }
+
+        impl<'a> fmt::Debug for Class<'a> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{:?}", self.get_name())
+            }
+        }
+
+        impl<'a> From<Class<'a>> for jni::objects::JClass<'a> {
+            fn from(class: Class<'a>) -> Self {
+                class.class
+            }
+        }
+    }
+}
+
+/// The JavaVM and the JNIEnv of the current thread, used by the bindings generated with `implicit_env`
+fn generate_implicit_env() -> TokenStream {
+    quote! {
+        static JAVA_VM: once_cell::sync::OnceCell<jni::JavaVM> = once_cell::sync::OnceCell::new();
+
+        thread_local! {
+            /// The JNIEnv of the current thread. It is allocated once per thread, and overwritten if the thread is attached again
+            static ENV: std::cell::Cell<Option<*mut jni::JNIEnv<'static>>> = const { std::cell::Cell::new(None) };
+        }
+
+        /// Set the JavaVM constructors and static methods attach to, e.g. in `JNI_OnLoad`.
+        /// Must be called before they are used. Returns `vm` back if it was already set
+        ///
+        /// # Safety
+        ///
+        /// The objects created without a JNIEnv are not tied to an attachment of their thread, see `env`.
+        /// A thread must not be detached while it uses them: it must not be attached by an `AttachGuard`
+        /// of `JavaVM::attach_current_thread` that is dropped before them, nor be detached with `JavaVM::detach_current_thread`.
+        /// Threads attached by `env` itself, and threads the JVM calls native methods on, are fine
+        pub unsafe fn set_java_vm(vm: jni::JavaVM) -> Result<(), jni::JavaVM> {
+            JAVA_VM.set(vm)
+        }
+
+        /// The JavaVM passed to `set_java_vm`
+        pub fn java_vm() -> Option<&'static jni::JavaVM> {
+            JAVA_VM.get()
+        }
+
+        /// The JNIEnv of the current thread, which is attached permanently if it is not attached yet.
+        /// It is only valid while the thread stays attached, as required by `set_java_vm`.
+        /// Objects are created as local references, which are only deleted when the thread detaches,
+        /// or when the frame of a Java call into native code ends. Create many in a `JNIEnv::with_local_frame`.
+        ///
+        /// # Panics
+        ///
+        /// If `set_java_vm` was not called
+        pub fn env() -> JResult<&'static jni::JNIEnv<'static>> {
+            let vm = JAVA_VM.get().expect("set_java_vm must be called before the bindings are used without a JNIEnv");
+            let env = vm.attach_current_thread_permanently()?;
+            ENV.with(|cached| {
+                let env = match cached.get() {
+                    // A thread gets a new JNIEnv if it is detached and attached again. The caller of `set_java_vm`
+                    // guarantees that the previous one is not used anymore, so it can be overwritten
+                    Some(cached) => {
+                        if unsafe { (*cached).get_native_interface() } != env.get_native_interface() {
+                            unsafe { cached.write(env) };
+                        }
+                        cached
+                    },
+                    None => {
+                        let allocated = Box::into_raw(Box::new(env));
+                        cached.set(Some(allocated));
+                        allocated
+                    }
+                };
+
+                Ok(unsafe { &*env })
+            })
+        }
+    }
+}
+
+/// Implement `ArrayElement` for the Rust types of the primitives. Bytes are unsigned in Rust,
+/// JNI provides copies from and to `u8` slices for them. Booleans are copied through `jboolean`
+fn generate_array_elements() -> TokenStream {
+    let elements = [
+        ("bool", "boolean"),
+        ("u16", "char"),
+        ("i16", "short"),
+        ("i32", "int"),
+        ("i64", "long"),
+        ("f32", "float"),
+        ("f64", "double"),
+    ];
+
+    let impls = elements.iter()
+        .map(|(rust_type, java_type)| {
+            let rust_type = format_ident!("{}", rust_type);
+            let new_array = format_ident!("new_{}_array", java_type);
+            let set_region = format_ident!("set_{}_array_region", java_type);
+            let get_region = format_ident!("get_{}_array_region", java_type);
+            let (to_jni, from_jni) = if rust_type == "bool" {
+                (
+                    quote! { let values = values.iter().map(|&x| x as jni::sys::jboolean).collect::<Vec<_>>(); },
+                    quote! { .into_iter().map(|x| x != 0).collect() },
+                )
+            } else {
+                (quote! {}, quote! {})
+            };
+
+            quote! {
+                impl ArrayElement for #rust_type {
+                    fn new_array<'a>(env: &'a jni::JNIEnv<'a>, values: &[Self]) -> JResult<jni::objects::JObject<'a>> {
+                        #to_jni
+                        let array = catch_exception(env, env.#new_array(array_length(&values)?))?;
+                        catch_exception(env, env.#set_region(array, 0, &values))?;
+                        Ok(array.into())
+                    }
+
+                    fn array_to_vec(env: &jni::JNIEnv<'_>, array: jni::objects::JObject<'_>) -> JResult<Vec<Self>> {
+                        let length = catch_exception(env, env.get_array_length(array.into_inner()))?;
+                        let mut values = vec![Default::default(); length as usize];
+                        catch_exception(env, env.#get_region(array.into_inner(), 0, &mut values))?;
+                        Ok(values #from_jni)
+                    }
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    quote! {
+        impl ArrayElement for u8 {
+            fn new_array<'a>(env: &'a jni::JNIEnv<'a>, values: &[Self]) -> JResult<jni::objects::JObject<'a>> {
+                Ok(catch_exception(env, env.byte_array_from_slice(values))?.into())
+            }
+
+            fn array_to_vec(env: &jni::JNIEnv<'_>, array: jni::objects::JObject<'_>) -> JResult<Vec<Self>> {
+                catch_exception(env, env.convert_byte_array(array.into_inner()))
+            }
+        }
+
+        #(#impls)*
+    }
+}
+
+/// The lines of the dependencies the conversions need, in the `[dependencies]` section of the manifest
+pub fn dependencies(config: &Generator) -> Vec<String> {
+    let mut dependencies = Vec::new();
+    if let Some((name, version)) = java_time_crate(config.java_time) {
+        dependencies.push(format!("{name} = {{ version = \"{version}\", optional = true }}"));
+    }
+
+    match config.big_decimal {
+        BigDecimalType::RustDecimal => dependencies.push("rust_decimal = \"1\"".to_string()),
+        BigDecimalType::BigDecimal => dependencies.push("bigdecimal = \"0.4\"".to_string()),
+        BigDecimalType::String | BigDecimalType::Raw => {},
+    }
+
+    if config.big_integer == BigIntegerType::NumBigint {
+        dependencies.push("num-bigint = \"0.4\"".to_string());
+    }
+
+    // There are no `_async` variants with jni 0.21
+    if config.jni_version == JniVersion::V0_19 {
+        dependencies.push(format!("{ASYNC_FEATURE} = {{ version = \"1\", features = [\"sync\"], optional = true }}"));
+    }
+
+    dependencies
+}
+
+/// The name and version of the time crate `java.time` types are converted to. The name is also the name of
+/// the cargo feature enabling the conversions in the generated crate
+pub fn java_time_crate(java_time: JavaTime) -> Option<(&'static str, &'static str)> {
+    match java_time {
+        JavaTime::Chrono => Some(("chrono", "0.4.38")),
+        JavaTime::Time => Some(("time", "0.3")),
+        JavaTime::Raw => None,
+    }
+}
+
+/// Generate `java_time.rs`, implementing `JavaConvert` for the types of the time crate.
+/// Dates and times are converted through their components rather than strings, as e.g. `LocalTime#toString`
+/// omits seconds that are zero
+pub fn generate_java_time(java_time: JavaTime) -> Option<TokenStream> {
+    let conversions = match java_time {
+        JavaTime::Chrono => generate_chrono(),
+        JavaTime::Time => generate_time(),
+        JavaTime::Raw => return None,
+    };
+
+    Some(quote! {
+        use jni::objects::{JObject, JValue};
+        use jni::JNIEnv;
+        use crate::{catch_exception, Error, JResult, JavaConvert};
+
+        fn call<'a>(env: &'a JNIEnv<'a>, obj: JObject<'a>, name: &str, signature: &str) -> JResult<JValue<'a>> {
+            catch_exception(env, env.call_method(obj, name, signature, &[]))
+        }
+
+        fn call_static<'a>(env: &'a JNIEnv<'a>, class: &str, name: &str, signature: &str, arguments: &[JValue<'a>]) -> JResult<JObject<'a>> {
+            Ok(catch_exception(env, env.call_static_method(class, name, signature, arguments))?.l()?)
+        }
+
+        fn out_of_range(class: &str) -> Error {
+            Error::Conversion(format!("{} is out of the range of the Rust type", class))
+        }
+
+        #conversions
+    })
+}
+
+fn generate_chrono() -> TokenStream {
+    quote! {
+        use chrono::{Datelike, Timelike};
+
+        impl<'a> JavaConvert<'a> for chrono::DateTime<chrono::Utc> {
+            fn from_java(env: &'a JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let seconds = call(env, obj, "getEpochSecond", "()J")?.j()?;
+                let nanos = call(env, obj, "getNano", "()I")?.i()?;
+                chrono::DateTime::from_timestamp(seconds, nanos as u32).ok_or_else(|| out_of_range("java.time.Instant"))
+            }
+
+            fn to_java(&self, env: &'a JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let nanos = i64::from(self.timestamp_subsec_nanos());
+                call_static(env, "java/time/Instant", "ofEpochSecond", "(JJ)Ljava/time/Instant;", &[self.timestamp().into(), nanos.into()])
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for chrono::NaiveDate {
+            fn from_java(env: &'a JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let year = call(env, obj, "getYear", "()I")?.i()?;
+                let month = call(env, obj, "getMonthValue", "()I")?.i()?;
+                let day = call(env, obj, "getDayOfMonth", "()I")?.i()?;
+                chrono::NaiveDate::from_ymd_opt(year, month as u32, day as u32).ok_or_else(|| out_of_range("java.time.LocalDate"))
+            }
+
+            fn to_java(&self, env: &'a JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let arguments = [self.year().into(), (self.month() as i32).into(), (self.day() as i32).into()];
+                call_static(env, "java/time/LocalDate", "of", "(III)Ljava/time/LocalDate;", &arguments)
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for chrono::NaiveTime {
+            fn from_java(env: &'a JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let nanos = call(env, obj, "toNanoOfDay", "()J")?.j()?;
+                chrono::NaiveTime::from_num_seconds_from_midnight_opt((nanos / 1_000_000_000) as u32, (nanos % 1_000_000_000) as u32)
+                    .ok_or_else(|| out_of_range("java.time.LocalTime"))
+            }
+
+            fn to_java(&self, env: &'a JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let nanos = i64::from(self.num_seconds_from_midnight()) * 1_000_000_000 + i64::from(self.nanosecond());
+                call_static(env, "java/time/LocalTime", "ofNanoOfDay", "(J)Ljava/time/LocalTime;", &[nanos.into()])
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for chrono::NaiveDateTime {
+            fn from_java(env: &'a JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let date = call(env, obj, "toLocalDate", "()Ljava/time/LocalDate;")?.l()?;
+                let time = call(env, obj, "toLocalTime", "()Ljava/time/LocalTime;")?.l()?;
+                Ok(chrono::NaiveDateTime::new(chrono::NaiveDate::from_java(env, date)?, chrono::NaiveTime::from_java(env, time)?))
+            }
+
+            fn to_java(&self, env: &'a JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let arguments = [self.date().to_java(env)?.into(), self.time().to_java(env)?.into()];
+                call_static(env, "java/time/LocalDateTime", "of", "(Ljava/time/LocalDate;Ljava/time/LocalTime;)Ljava/time/LocalDateTime;", &arguments)
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for chrono::TimeDelta {
+            fn from_java(env: &'a JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let seconds = call(env, obj, "getSeconds", "()J")?.j()?;
+                let nanos = call(env, obj, "getNano", "()I")?.i()?;
+                chrono::TimeDelta::new(seconds, nanos as u32).ok_or_else(|| out_of_range("java.time.Duration"))
+            }
+
+            fn to_java(&self, env: &'a JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let nanos = i64::from(self.subsec_nanos());
+                call_static(env, "java/time/Duration", "ofSeconds", "(JJ)Ljava/time/Duration;", &[self.num_seconds().into(), nanos.into()])
+            }
+        }
+    }
+}
+
+fn generate_time() -> TokenStream {
+    quote! {
+        impl<'a> JavaConvert<'a> for time::OffsetDateTime {
+            fn from_java(env: &'a JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let seconds = call(env, obj, "getEpochSecond", "()J")?.j()?;
+                let nanos = call(env, obj, "getNano", "()I")?.i()?;
+                time::OffsetDateTime::from_unix_timestamp_nanos(i128::from(seconds) * 1_000_000_000 + i128::from(nanos))
+                    .map_err(|_| out_of_range("java.time.Instant"))
+            }
+
+            fn to_java(&self, env: &'a JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let nanos = i64::from(self.nanosecond());
+                call_static(env, "java/time/Instant", "ofEpochSecond", "(JJ)Ljava/time/Instant;", &[self.unix_timestamp().into(), nanos.into()])
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for time::Date {
+            fn from_java(env: &'a JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let year = call(env, obj, "getYear", "()I")?.i()?;
+                let month = call(env, obj, "getMonthValue", "()I")?.i()?;
+                let day = call(env, obj, "getDayOfMonth", "()I")?.i()?;
+                let month = time::Month::try_from(month as u8).map_err(|_| out_of_range("java.time.LocalDate"))?;
+                time::Date::from_calendar_date(year, month, day as u8).map_err(|_| out_of_range("java.time.LocalDate"))
+            }
+
+            fn to_java(&self, env: &'a JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let arguments = [self.year().into(), i32::from(u8::from(self.month())).into(), i32::from(self.day()).into()];
+                call_static(env, "java/time/LocalDate", "of", "(III)Ljava/time/LocalDate;", &arguments)
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for time::Time {
+            fn from_java(env: &'a JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let nanos = call(env, obj, "toNanoOfDay", "()J")?.j()?;
+                let seconds = nanos / 1_000_000_000;
+                time::Time::from_hms_nano((seconds / 3600) as u8, (seconds / 60 % 60) as u8, (seconds % 60) as u8, (nanos % 1_000_000_000) as u32)
+                    .map_err(|_| out_of_range("java.time.LocalTime"))
+            }
+
+            fn to_java(&self, env: &'a JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let seconds = i64::from(self.hour()) * 3600 + i64::from(self.minute()) * 60 + i64::from(self.second());
+                let nanos = seconds * 1_000_000_000 + i64::from(self.nanosecond());
+                call_static(env, "java/time/LocalTime", "ofNanoOfDay", "(J)Ljava/time/LocalTime;", &[nanos.into()])
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for time::PrimitiveDateTime {
+            fn from_java(env: &'a JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let date = call(env, obj, "toLocalDate", "()Ljava/time/LocalDate;")?.l()?;
+                let time = call(env, obj, "toLocalTime", "()Ljava/time/LocalTime;")?.l()?;
+                Ok(time::PrimitiveDateTime::new(time::Date::from_java(env, date)?, time::Time::from_java(env, time)?))
+            }
+
+            fn to_java(&self, env: &'a JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let arguments = [self.date().to_java(env)?.into(), self.time().to_java(env)?.into()];
+                call_static(env, "java/time/LocalDateTime", "of", "(Ljava/time/LocalDate;Ljava/time/LocalTime;)Ljava/time/LocalDateTime;", &arguments)
+            }
+        }
+
+        impl<'a> JavaConvert<'a> for time::Duration {
+            fn from_java(env: &'a JNIEnv<'a>, obj: JObject<'a>) -> JResult<Self> {
+                let seconds = call(env, obj, "getSeconds", "()J")?.j()?;
+                let nanos = call(env, obj, "getNano", "()I")?.i()?;
+                Ok(time::Duration::new(seconds, nanos))
+            }
+
+            fn to_java(&self, env: &'a JNIEnv<'a>) -> JResult<JObject<'a>> {
+                let nanos = i64::from(self.subsec_nanoseconds());
+                call_static(env, "java/time/Duration", "ofSeconds", "(JJ)Ljava/time/Duration;", &[self.whole_seconds().into(), nanos.into()])
+            }
+        }
+    }
+}
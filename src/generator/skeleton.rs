@@ -0,0 +1,303 @@
+//! The files that make the generated bindings a crate: the manifest, the crate root,
+//! and the `mod.rs` files declaring the modules of the bindings tree
+
+use std::collections::{BTreeMap, BTreeSet};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use crate::config::{Generator, JniVersion};
+use crate::formatter::FormattedClassEntry;
+use crate::generator::runtime::OBJECT_MONITOR_FEATURE;
+
+/// The versions of the dependencies of the generated crate, those jrsgen itself is built against
+const JNI_VERSION: &str = "0.19.0";
+const JNI_0_21_VERSION: &str = "0.21.1";
+const EJNI_VERSION: &str = "0.1.0";
+const LOG_VERSION: &str = "0.4.16";
+const ONCE_CELL_VERSION: &str = "1.10.0";
+
+/// The modules of the bindings tree, keyed by their path relative to the `bindings` module, e.g. `["com", "foo"]`
+#[derive(Debug, Default)]
+pub struct ModuleTree {
+    modules: BTreeMap<Vec<String>, Module>,
+}
+
+#[derive(Debug, Default)]
+struct Module {
+    submodules: BTreeSet<String>,
+    /// The names of the files holding the classes in the module, without `.rs`
+    classes: BTreeSet<String>,
+}
+
+impl ModuleTree {
+    /// Add a class, given its formatted name, e.g. `com.foo.bar_p.Baz`
+    pub fn add_class(&mut self, formatted_name: &str) {
+        let mut path = formatted_name.split('.')
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let class = path.pop().unwrap();
+
+        for depth in 0..path.len() {
+            self.modules.entry(path[..depth].to_vec())
+                .or_default()
+                .submodules
+                .insert(path[depth].clone());
+        }
+
+        self.modules.entry(path)
+            .or_default()
+            .classes
+            .insert(class);
+    }
+
+    /// Generate the `mod.rs` of every module, paired with the path of the module.
+    /// Classes are included in the module of their package, so free functions and constants are next to the type
+    pub fn generate(&self, features: &Features, prelude: &TokenStream) -> Vec<(&[String], TokenStream)> {
+        let mut modules = self.modules.iter()
+            .map(|(path, module)| {
+                let cfgs = module.submodules.iter()
+                    .map(|x| features.gate(path, x).map(|feature| quote! { #[cfg(feature = #feature)] }));
+                let submodules = module.submodules.iter().map(|x| format_ident!("{}", x));
+                let files = module.classes.iter().map(|x| format!("{x}.rs"));
+
+                let tokens = quote! {
+                    #prelude
+                    #(#cfgs pub mod #submodules;)*
+                    #(include!(#files);)*
+                };
+                (path.as_slice(), tokens)
+            })
+            .collect::<Vec<_>>();
+
+        // The bindings module exists even if there are no classes
+        if modules.is_empty() {
+            modules.push((&[], prelude.clone()));
+        }
+
+        modules
+    }
+}
+
+/// Cargo features gating the top-level packages of the bindings, i.e. the packages directly below the package
+/// all classes have in common. E.g. `kernel` and `layout` for iText, whose classes are all in `com.itextpdf`.
+/// All features are enabled by default
+#[derive(Debug, Default)]
+pub struct Features {
+    /// The module the gated packages are in, e.g. `["com", "itextpdf"]`
+    root: Vec<String>,
+    /// The features, with the features they depend on
+    features: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Features {
+    pub fn new(tree: &[FormattedClassEntry], config: &Generator) -> Self {
+        // The package of a class is the start of its formatted name, e.g. `com.foo` of `com.foo.bar_p.Baz` for `com.foo.Bar$Baz`
+        let packages = tree.iter()
+            .map(|class| {
+                let depth = class.java_name.matches('.').count();
+                let package = class.name.split('.').take(depth).map(str::to_string).collect();
+                (class.name.as_str(), package)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let references = tree.iter()
+            .map(|class| (class.name.as_str(), class.referenced_classes(config)))
+            .collect::<Vec<_>>();
+
+        Self::from_packages(&packages, &references)
+    }
+
+    /// Determine the features from the package of every class, as module path, and the classes each class refers to
+    fn from_packages(packages: &BTreeMap<&str, Vec<String>>, references: &[(&str, Vec<String>)]) -> Self {
+        let root = packages.values()
+            .map(Vec::as_slice)
+            .reduce(|prefix, package| {
+                let common = prefix.iter().zip(package).take_while(|(a, b)| a == b).count();
+                &prefix[..common]
+            })
+            .unwrap_or_default()
+            .to_vec();
+
+        // `None` for classes in the root package, and for classes outside of the tree
+        let feature = |class: &str| packages.get(class).and_then(|x| x.get(root.len())).cloned();
+
+        let mut features: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        // Packages used by classes in the root package, which are always compiled
+        let mut ungated = BTreeSet::new();
+        for (class, referenced) in references {
+            let dependencies = referenced.iter().filter_map(|x| feature(x));
+            match feature(class) {
+                Some(own) => {
+                    let dependencies = dependencies.filter(|x| *x != own).collect::<Vec<_>>();
+                    features.entry(own).or_default().extend(dependencies);
+                },
+                None => ungated.extend(dependencies),
+            }
+        }
+
+        // Neither can what the ungated packages depend on be gated
+        let mut pending = ungated.iter().cloned().collect::<Vec<_>>();
+        while let Some(package) = pending.pop() {
+            for dependency in features.get(&package).into_iter().flatten() {
+                if ungated.insert(dependency.clone()) {
+                    pending.push(dependency.clone());
+                }
+            }
+        }
+
+        features.retain(|x, _| !ungated.contains(x));
+        for dependencies in features.values_mut() {
+            dependencies.retain(|x| !ungated.contains(x));
+        }
+
+        Self { root, features }
+    }
+
+    /// The feature gating `submodule` of the module at `path`, if any
+    fn gate(&self, path: &[String], submodule: &str) -> Option<&str> {
+        (path == self.root.as_slice())
+            .then(|| self.features.get_key_value(submodule))
+            .flatten()
+            .map(|(feature, _)| feature.as_str())
+    }
+
+    /// The `[features]` section of the manifest: the packages, enabled by default, and the opt-in runtime features
+    fn manifest_section(&self) -> String {
+        let mut section = "\n[features]\n".to_string();
+        if !self.features.is_empty() {
+            section.push_str(&format!("default = {}\n", toml_list(self.features.keys())));
+            for (feature, dependencies) in &self.features {
+                section.push_str(&format!("{feature} = {}\n", toml_list(dependencies)));
+            }
+        }
+
+        section.push_str(&format!("{OBJECT_MONITOR_FEATURE} = []\n"));
+        section
+    }
+}
+
+/// Format strings as a TOML array, e.g. `["a", "b"]`
+fn toml_list<'a>(items: impl IntoIterator<Item = &'a String>) -> String {
+    let quoted = items.into_iter()
+        .map(|x| format!("\"{x}\""))
+        .collect::<Vec<_>>();
+    format!("[{}]", quoted.join(", "))
+}
+
+/// Generate the `Cargo.toml` of the crate
+/// `dependencies` are the lines of the dependencies of the conversions, in addition to those of the runtime:
+/// jni, ejni unless `pure_jni` is set, log and once_cell, or only jni and once_cell for jni 0.21
+pub fn generate_manifest(config: &Generator, features: &Features, dependencies: &[String]) -> String {
+    let crate_name = &config.crate_name;
+    let runtime = match (config.jni_version, config.pure_jni) {
+        (JniVersion::V0_19, false) => format!("jni = \"{JNI_VERSION}\"\nejni = \"{EJNI_VERSION}\"\nlog = \"{LOG_VERSION}\"\n"),
+        (JniVersion::V0_19, true) => format!("jni = \"{JNI_VERSION}\"\nlog = \"{LOG_VERSION}\"\n"),
+        (JniVersion::V0_21, _) => format!("jni = \"{JNI_0_21_VERSION}\"\n"),
+    };
+    let dependencies = dependencies.iter()
+        .map(|x| format!("{x}\n"))
+        .collect::<String>();
+
+    format!(r#"[package]
+name = "{crate_name}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+{runtime}once_cell = "{ONCE_CELL_VERSION}"
+{dependencies}{}"#, features.manifest_section())
+}
+
+/// Generate `lib.rs`, with the configured prelude after the crate attributes
+/// `time_feature` is the feature enabling the conversions of `java.time` types, if they are converted
+pub fn generate_lib(prelude: &TokenStream, time_feature: Option<&str>) -> TokenStream {
+    let java_time = match time_feature {
+        Some(feature) => quote! {
+            #[cfg(feature = #feature)]
+            mod java_time;
+        },
+        None => quote! {},
+    };
+
+    quote! {
+        // Java names do not follow the Rust naming conventions,
+        // and types without methods never read the JNIEnv they hold
+        #![allow(non_snake_case, non_camel_case_types, non_upper_case_globals, dead_code)]
+        #prelude
+
+        pub mod bindings;
+        pub mod exceptions;
+        mod runtime;
+        #java_time
+
+        pub use exceptions::JavaException;
+        pub use runtime::*;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn module_tree() {
+        let mut tree = ModuleTree::default();
+        tree.add_class("com.foo.Bar");
+        tree.add_class("com.foo.bar_p.Baz");
+        tree.add_class("Unnamed");
+
+        let prelude = quote! { use crate::Mapped; };
+        let modules = tree.generate(&Features::default(), &prelude).into_iter()
+            .map(|(path, tokens)| (path.join("."), tokens.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![
+            ("".to_string(), r#"use crate :: Mapped ; pub mod com ; include ! ("Unnamed.rs") ;"#.to_string()),
+            ("com".to_string(), "use crate :: Mapped ; pub mod foo ;".to_string()),
+            ("com.foo".to_string(), r#"use crate :: Mapped ; pub mod bar_p ; include ! ("Bar.rs") ;"#.to_string()),
+            ("com.foo.bar_p".to_string(), r#"use crate :: Mapped ; include ! ("Baz.rs") ;"#.to_string()),
+        ], modules);
+    }
+
+    #[test]
+    fn features() {
+        let packages = BTreeMap::from([
+            ("com.foo.Root", vec!["com".to_string(), "foo".to_string()]),
+            ("com.foo.io.Stream", vec!["com".to_string(), "foo".to_string(), "io".to_string()]),
+            ("com.foo.util.Util", vec!["com".to_string(), "foo".to_string(), "util".to_string()]),
+            ("com.foo.kernel.Document", vec!["com".to_string(), "foo".to_string(), "kernel".to_string()]),
+            ("com.foo.kernel.document_p.Page", vec!["com".to_string(), "foo".to_string(), "kernel".to_string()]),
+            ("com.foo.layout.Table", vec!["com".to_string(), "foo".to_string(), "layout".to_string()]),
+        ]);
+        let references = vec![
+            ("com.foo.Root", vec!["com.foo.util.Util".to_string()]),
+            ("com.foo.kernel.Document", vec!["com.foo.io.Stream".to_string(), "java.lang.Object".to_string()]),
+            ("com.foo.kernel.document_p.Page", vec!["com.foo.kernel.Document".to_string()]),
+            ("com.foo.layout.Table", vec!["com.foo.kernel.document_p.Page".to_string(), "com.foo.util.Util".to_string()]),
+            ("com.foo.io.Stream", Vec::new()),
+            ("com.foo.util.Util", Vec::new()),
+        ];
+
+        let features = Features::from_packages(&packages, &references);
+        let root = ["com".to_string(), "foo".to_string()];
+        assert_eq!(Some("kernel"), features.gate(&root, "kernel"));
+        // Used by a class in the root package
+        assert_eq!(None, features.gate(&root, "util"));
+        assert_eq!(None, features.gate(&root[..1], "foo"));
+
+        assert_eq!("\n[features]\ndefault = [\"io\", \"kernel\", \"layout\"]\nio = []\nkernel = [\"io\"]\nlayout = [\"kernel\"]\nobject-monitor = []\n", features.manifest_section());
+    }
+
+    #[test]
+    fn manifest_runtime_dependencies() {
+        let dependencies = |config: &Generator| generate_manifest(config, &Features::default(), &[]).lines()
+            .filter(|line| line.contains(" = \"") && !line.starts_with("name") && !line.starts_with("version") && !line.starts_with("edition"))
+            .map(|line| line.split(' ').next().unwrap().to_string())
+            .collect::<Vec<_>>();
+
+        let mut config = Generator::default();
+        assert_eq!(vec!["jni", "ejni", "log", "once_cell"], dependencies(&config));
+        config.pure_jni = true;
+        assert_eq!(vec!["jni", "log", "once_cell"], dependencies(&config));
+        config.jni_version = JniVersion::V0_21;
+        assert_eq!(vec!["jni", "once_cell"], dependencies(&config));
+    }
+}
@@ -0,0 +1,24 @@
+//! The Java helpers in `java-dependencies`, compiled by the build script.
+//! They are needed by the generated code, e.g. `jrsgen.AnnotationProxy`, and by the JVM backend of the parser,
+//! which describes classes with `jrsgen.ClassDescriber`
+
+use std::fs;
+use std::path::Path;
+use crate::JResult;
+
+include!(concat!(env!("OUT_DIR"), "/java_helpers.rs"));
+
+/// Write the compiled helpers to `directory`, as class files in their package directories,
+/// e.g. `jrsgen/AnnotationProxy.class`. The directory can then be put on the classpath
+pub fn write_to(directory: &Path) -> JResult<()> {
+    for (name, bytes) in JAVA_HELPERS {
+        let path = directory.join(format!("{name}.class"));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, bytes)?;
+    }
+
+    Ok(())
+}
@@ -1,21 +1,103 @@
-use log::{debug, trace};
-use clap::Parser;
-use parser::class_tree;
-use crate::formatter::FormattedClassEntry;
+use std::path::{Path, PathBuf};
+use log::{debug, trace, warn};
+use clap::{ArgEnum, Parser};
+use parser::{bytecode, class_tree, classpath, libjvm};
+use crate::cache::TreeCache;
+use crate::config::{Config, JniVersion};
+use crate::parser::javadoc::Javadoc;
 use crate::parser::jvm::Jvm;
+use crate::resolver::maven::{Coordinate, Resolver};
 
+mod cache;
 mod parser;
-//mod generator;
+mod generator;
 mod config;
 mod formatter;
+mod java_helpers;
+mod resolver;
 
 pub type JResult<T> = std::result::Result<T, anyhow::Error>;
 
+/// The directory the bindings crate is generated in if none is configured
+const OUTPUT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/output");
+
+#[derive(ArgEnum, Clone, Debug)]
+enum Backend {
+    /// Inspect the classes through reflection in an embedded JVM
+    Jvm,
+    /// Read the class files directly, without a JVM
+    Bytecode,
+}
+
+#[derive(ArgEnum, Clone, Debug)]
+enum JniTarget {
+    #[clap(name = "0.19")]
+    V0_19,
+    #[clap(name = "0.21")]
+    V0_21,
+}
+
 #[derive(Parser, Debug)]
 #[clap(author, version)]
 struct Args {
+    /// How to read the classes
+    #[clap(long, arg_enum, default_value = "jvm")]
+    backend: Backend,
+    /// Jars to generate bindings for. Jars ending in `-sources.jar` are not loaded,
+    /// but used to add the Javadoc to the generated code. `dir/*` adds every jar in `dir`.
+    /// Android libraries (`.aar`) and the SDK's `android.jar` can be used as well.
+    /// Multiple paths can be separated by the platform's path separator, like with `java -classpath`
+    #[clap(short, long)]
+    classpath: Vec<String>,
+    /// Maven artifacts to generate bindings for, as `group:artifact:version`.
+    /// They are downloaded and added to the classpath
+    #[clap(long)]
+    maven: Vec<String>,
+    /// Also add the dependencies of the Maven artifacts to the classpath.
+    /// Overrides the config
+    #[clap(long)]
+    transitive: bool,
+    /// A `pom.xml` or `gradle.lockfile`, or a directory containing one.
+    /// The dependencies of the project and its compiled classes are added to the classpath
+    #[clap(long)]
+    project: Option<String>,
+    /// Package prefixes to generate bindings for, e.g. `com.itextpdf.`.
+    /// Overrides the roots in the config
+    #[clap(short, long = "root")]
+    roots: Vec<String>,
+    /// Only include classes matching this regular expression. Added to the patterns in the config
+    #[clap(long)]
+    include: Vec<String>,
+    /// Exclude classes matching this regular expression. Added to the patterns in the config
+    #[clap(long)]
+    exclude: Vec<String>,
+    /// Include public methods inherited from superclasses and interfaces.
+    /// Overrides the config
+    #[clap(long)]
+    include_inherited: bool,
+    /// Include classes outside of the roots that are referenced by the signatures of included classes.
+    /// Overrides the config
+    #[clap(long)]
+    include_referenced: bool,
+    /// Only generate bindings for this class and the classes reachable from the signatures of the entry points.
+    /// Added to the entry points in the config
+    #[clap(long = "entry-point")]
+    entry_points: Vec<String>,
+    /// Always inspect the classes, rather than using the class tree cached by a previous run
+    #[clap(long)]
+    no_cache: bool,
+    /// An option to pass to the JVM, e.g. `--jvm-opt=-Xmx4g`. Added to the options in the config
+    #[clap(long = "jvm-opt", allow_hyphen_values = true)]
+    jvm_options: Vec<String>,
+    /// The Java home of the JDK to reflect with. Overrides the config and `$JAVA_HOME`
+    #[clap(long)]
+    java_home: Option<String>,
+    /// The directory to generate the bindings crate in. Overrides the config
     #[clap(short, long)]
-    classpath: Vec<String>
+    output: Option<String>,
+    /// The version of the `jni` crate to generate code for. Overrides the config
+    #[clap(long, arg_enum)]
+    jni_version: Option<JniTarget>,
 }
 
 fn main() {
@@ -23,23 +105,126 @@ fn main() {
     debug!("Parsing arguments");
     let args = Args::parse();
 
-    debug!("Creating JVM");
-    let jvm = Jvm::new(&args.classpath).expect("Creating JVM");
-    let env = jvm.attach_current_thread().expect("Attaching thread");
+    debug!("Reading config");
+    let mut config = Config::new().expect("Reading config");
+    if args.include_inherited {
+        config.parser.include_inherited_methods = true;
+    }
+
+    if args.include_referenced {
+        config.parser.include_referenced = true;
+    }
+
+    if !args.roots.is_empty() {
+        config.parser.roots = args.roots;
+    }
+
+    config.parser.include.extend(args.include);
+    config.parser.exclude.extend(args.exclude);
+    config.parser.entry_points.extend(args.entry_points);
+    config.jvm.options.extend(args.jvm_options);
+    if args.java_home.is_some() {
+        config.jvm.java_home = args.java_home;
+    }
+
+    if args.output.is_some() {
+        config.generator.output_directory = args.output;
+    }
+
+    if args.transitive {
+        config.maven.transitive = true;
+    }
+
+    match args.jni_version {
+        Some(JniTarget::V0_19) => config.generator.jni_version = JniVersion::V0_19,
+        Some(JniTarget::V0_21) => config.generator.jni_version = JniVersion::V0_21,
+        None => {},
+    }
 
-    debug!("Building class tree");
-    let class_tree = class_tree::build(&env, "com.itextpdf.".into()).expect("Failed to build tree");
+    let classpath = classpath::split_entries(&args.classpath);
+    let mut classpath = classpath::expand_wildcards(&classpath).expect("Expanding classpath wildcards");
+    if !args.maven.is_empty() {
+        debug!("Resolving Maven artifacts");
+        let coordinates = args.maven.iter()
+            .map(|x| Coordinate::parse(x))
+            .collect::<JResult<Vec<_>>>()
+            .expect("Parsing Maven coordinates");
+
+        let mut resolver = Resolver::new(&config.maven).expect("Creating Maven resolver");
+        let jars = resolver.resolve(&coordinates, config.maven.transitive).expect("Resolving Maven artifacts");
+        classpath.extend(jars.into_iter().map(|x| x.to_string_lossy().to_string()));
+    }
+
+    if let Some(project) = &args.project {
+        debug!("Resolving project classpath");
+        let jars = resolver::project_classpath(Path::new(project), &config.maven).expect("Resolving project classpath");
+        classpath.extend(jars.into_iter().map(|x| x.to_string_lossy().to_string()));
+    }
+
+    let aar_directory = cache::cache_directory().expect("Locating cache directory").join("aar");
+    let classpath = classpath::extract_archives(&classpath, &aar_directory).expect("Extracting Android libraries");
+
+    let (sources, classpath): (Vec<_>, Vec<_>) = classpath.into_iter()
+        .partition(|x| x.ends_with("-sources.jar"));
+
+    let cache = if args.no_cache {
+        None
+    } else {
+        Some(TreeCache::new(&format!("{:?}", args.backend), &classpath, &config.parser).expect("Hashing classpath"))
+    };
+
+    let mut class_tree = match cache.as_ref().and_then(TreeCache::load) {
+        Some(class_tree) => class_tree,
+        None => {
+            debug!("Building class tree");
+            let class_tree = match args.backend {
+                Backend::Jvm => {
+                    debug!("Creating JVM");
+                    let jvm = Jvm::new(&config.jvm).expect("Creating JVM");
+                    class_tree::build(&jvm, &classpath, &config.parser.roots, &config.parser).expect("Failed to build tree")
+                },
+                Backend::Bytecode => bytecode::build(&classpath, &config.parser.roots, &config.parser).expect("Failed to build tree"),
+            };
+
+            if let Some(cache) = &cache {
+                if let Err(e) = cache.store(&class_tree) {
+                    warn!("Failed to cache the class tree: {}", e);
+                }
+            }
+
+            class_tree
+        }
+    };
+
+    debug!("Reading Javadoc");
+    let mut javadoc = Javadoc::default();
+    for sources_jar in &sources {
+        javadoc.extend(Javadoc::from_sources_jar(sources_jar).expect("Reading sources jar"));
+    }
+    javadoc.apply(&mut class_tree);
 
     trace!("Built tree:");
     trace!("{:#?}", class_tree);
 
     debug!("Formatting");
-    let formatted = class_tree.into_iter()
-        .map(FormattedClassEntry::from)
-        .collect::<Vec<_>>();
+    let formatted = formatter::format_classes(class_tree, &config.generator);
 
     trace!("{:#?}", formatted);
 
+    let output_dir = PathBuf::from(config.generator.output_directory.as_deref().unwrap_or(OUTPUT_DIR));
+
+    debug!("Writing name mapping");
+    formatter::write_names(&formatted, &output_dir.join("names.json")).expect("Writing name mapping");
+
+    let backend = match args.backend {
+        Backend::Jvm => {
+            let version = libjvm::find_java_home(config.jvm.java_home.as_deref()).ok()
+                .and_then(|x| libjvm::java_version(&x));
+            format!("JVM, Java {}", version.as_deref().unwrap_or("of unknown version"))
+        },
+        Backend::Bytecode => "bytecode, without a JVM".to_string(),
+    };
+
     debug!("Generating code");
-    //generator::generate(class_tree).expect("Failed to generate code");
+    generator::generate(formatted, &config, &output_dir, &backend).expect("Failed to generate code");
 }
\ No newline at end of file
@@ -0,0 +1,606 @@
+//! A parser backend reading the class metadata directly from class files, rather than through reflection.
+//! It does not need a JVM, and never loads or initializes the classes it describes.
+//!
+//! Classes outside the classpath, like those of the JDK, are unknown to this backend.
+//! Superclass chains stop at the first such class, and inherited methods are only collected from the classpath
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use anyhow::Context;
+use log::{debug, trace};
+use crate::class_tree::{deduplicate_overrides, find_functional_method, find_instance_field, is_scala_object, link_nested_classes, AnnotationElement, ClassEntry, ClassType, ConstantEntry, ConstructorEntry, Deprecation, FieldEntry, MethodEntry, Nullability, Visibility};
+use crate::config::Parser;
+use crate::JResult;
+use crate::parser::classfile::*;
+use crate::parser::filter::ClassFilter;
+use crate::parser::{jar, kotlin};
+
+/// Build the class tree of all classes in the jars in `classpath` that are in one of the packages in `roots`.
+/// This is the bytecode counterpart of [class_tree::build](crate::class_tree::build)
+pub fn build(classpath: &[String], roots: &[String], config: &Parser) -> JResult<Vec<ClassEntry>> {
+    let filter = ClassFilter::new(config)?;
+
+    let mut classes = HashMap::new();
+    let mut names = Vec::new();
+    for path in classpath {
+        debug!("Reading classes from {}", path);
+        for (name, bytes) in jar::read_classes(Path::new(path))? {
+            // Like a classloader, the first class on the classpath wins
+            if classes.contains_key(&name) {
+                continue;
+            }
+
+            let class = ClassFile::parse(&bytes).with_context(|| format!("Parsing class file of {name}"))?;
+            names.push(name.clone());
+            classes.insert(name, class);
+        }
+    }
+
+    let mut pending = filter.select(names, roots);
+    let mut known = pending.iter().cloned().collect::<HashSet<_>>();
+    let available = classes.keys().cloned().collect::<HashSet<_>>();
+    trace!("Selected {} classes from packages {:?} or the entry points", pending.len(), roots);
+
+    let mut class_entries = Vec::new();
+    while !pending.is_empty() {
+        let explored = class_entries.len();
+        for name in &pending {
+            let entry = class_entry(&classes[name], &classes, config).with_context(|| format!("Analyzing class {name}"))?;
+            class_entries.extend(entry);
+        }
+
+        pending = filter.referenced_classes(&class_entries[explored..], &available, &mut known, roots);
+        trace!("Found {} referenced classes", pending.len());
+    }
+
+    link_nested_classes(&mut class_entries);
+    Ok(class_entries)
+}
+
+fn class_entry(class: &ClassFile, classes: &HashMap<String, ClassFile>, config: &Parser) -> JResult<Option<ClassEntry>> {
+    let name = class.name.clone();
+    trace!("Exploring class {}", name);
+
+    // Nested classes have their modifiers as declared in the source in the InnerClasses attribute
+    let inner_classes = class.inner_classes()?;
+    let own_entry = inner_classes.iter().find(|x| x.name == class.name);
+    let access_flags = own_entry.map(|x| x.access_flags).unwrap_or(class.access_flags);
+
+    // Local and anonymous classes only have the class declaring them in the EnclosingMethod attribute
+    let enclosing_class = match own_entry.and_then(|x| x.outer_name.clone()) {
+        Some(outer_name) => Some(outer_name),
+        None => class.enclosing_method_class()?,
+    };
+    let simple_name = match own_entry {
+        Some(entry) => entry.simple_name.clone().unwrap_or_default(),
+        None => name.rsplit('.').next().unwrap_or_default().to_string(),
+    };
+
+    let visibility = Visibility::from_access_flags(access_flags);
+    if !visibility.is_included(config) {
+        trace!("Skipping class {} with visibility {:?}", name, visibility);
+        return Ok(None);
+    }
+
+    if class.access_flags & ACC_SYNTHETIC != 0 {
+        trace!("Skipping synthetic class {}", name);
+        return Ok(None);
+    }
+
+    // Enum constants with a body are subclasses of the enum, marked as enum as well
+    let class_type = if access_flags & ACC_ANNOTATION != 0 {
+        ClassType::Annotation
+    } else if access_flags & ACC_INTERFACE != 0 {
+        ClassType::Interface
+    } else if access_flags & ACC_ENUM != 0 && class.super_class.as_deref() == Some("java.lang.Enum") {
+        ClassType::Enum
+    } else {
+        ClassType::Class
+    };
+    let is_interface = access_flags & ACC_INTERFACE != 0;
+
+    let is_abstract = access_flags & ACC_ABSTRACT != 0;
+    let deprecation = deprecation(class, &class.attributes)?;
+
+    let methods = get_methods(class, classes, config)?;
+    trace!("Found {} methods for {}", methods.len(), name);
+
+    let enum_constants = match class_type {
+        ClassType::Enum => class.fields.iter()
+            .filter(|x| x.access_flags & ACC_ENUM != 0)
+            .map(|x| x.name.clone())
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let annotation_elements = match class_type {
+        ClassType::Annotation => get_annotation_elements(class)?,
+        _ => Vec::new(),
+    };
+
+    let signature = class.signature(&class.attributes)?
+        .map(|x| parse_class_signature(&x))
+        .transpose()?;
+    let type_parameters = signature.as_ref()
+        .map(|x| x.type_parameters.clone())
+        .unwrap_or_default();
+    // Interfaces have no superclass, even though the class file lists java.lang.Object
+    let generic_superclass = signature
+        .map(|x| x.superclass)
+        .filter(|x| !is_interface && x.contains('<'));
+
+    let superclasses = if is_interface {
+        Vec::new()
+    } else {
+        get_superclasses(class, classes)
+    };
+
+    let permitted_subclasses = class.class_list(&class.attributes, "PermittedSubclasses")?;
+    let is_inner_class = own_entry
+        .map(|x| x.outer_name.is_some() && x.access_flags & ACC_STATIC == 0)
+        .unwrap_or(false);
+
+    let constructors = class.methods.iter()
+        .filter(|x| x.name == "<init>" && x.access_flags & ACC_SYNTHETIC == 0)
+        .filter(|x| visibility_of(x).is_included(config))
+        .map(|x| constructor_entry(class, x))
+        .collect::<JResult<Vec<_>>>()?;
+
+    let kotlin_metadata = kotlin_metadata(class)?.filter(|x| x.kind == kotlin::KIND_CLASS);
+    let static_fields = if kotlin_metadata.is_some() || is_scala_object(&name) {
+        static_fields(class)?
+    } else {
+        Vec::new()
+    };
+
+    let instance_field = find_instance_field(&name, &static_fields);
+    let (companion_field, properties) = match &kotlin_metadata {
+        Some(metadata) => (kotlin::companion_field(&name, &static_fields), kotlin::properties(&methods, metadata)),
+        None => (None, Vec::new()),
+    };
+
+    let constants = constants(class, config)?;
+    let fields = fields(class, &constants, config)?;
+
+    let functional_method = match class_type {
+        ClassType::Interface => functional_method(class, classes)?,
+        _ => None,
+    };
+
+    Ok(Some(ClassEntry {
+        name,
+        simple_name,
+        enclosing_class,
+        nested_classes: Vec::new(),
+        class_type,
+        visibility,
+        is_abstract,
+        deprecation,
+        methods,
+        interfaces: class.interfaces.clone(),
+        enum_constants,
+        type_parameters,
+        generic_superclass,
+        superclasses,
+        permitted_subclasses,
+        annotation_elements,
+        is_inner_class,
+        constructors,
+        instance_field,
+        companion_field,
+        properties,
+        constants,
+        fields,
+        functional_method,
+        javadoc: None,
+    }))
+}
+
+fn visibility_of(member: &Member) -> Visibility {
+    Visibility::from_access_flags(member.access_flags)
+}
+
+/// Whether a method is part of the API, i.e. not an initializer, compiler generated or a bridge method
+fn is_api_method(method: &Member) -> bool {
+    !method.name.starts_with('<') && method.access_flags & (ACC_SYNTHETIC | ACC_BRIDGE) == 0
+        && !kotlin::is_default_overload(&method.name)
+}
+
+/// Get the names and type names of the static fields declared by a class
+fn static_fields(class: &ClassFile) -> JResult<Vec<(String, String)>> {
+    class.fields.iter()
+        .filter(|x| x.access_flags & ACC_STATIC != 0)
+        .map(|x| Ok((x.name.clone(), type_name(&parse_field_descriptor(&x.descriptor)?))))
+        .collect()
+}
+
+/// Read the `@kotlin.Metadata` annotation of a class
+fn kotlin_metadata(class: &ClassFile) -> JResult<Option<kotlin::Metadata>> {
+    let annotation = class.annotations(&class.attributes)?
+        .into_iter()
+        .find(|x| x.type_name == kotlin::METADATA_ANNOTATION);
+
+    let annotation = match annotation {
+        Some(annotation) => annotation,
+        None => return Ok(None),
+    };
+
+    // `k` defaults to a class if it is not present
+    let mut metadata = kotlin::Metadata {
+        kind: kotlin::KIND_CLASS,
+        strings: Vec::new(),
+    };
+
+    for (name, value) in annotation.values {
+        match (name.as_str(), value) {
+            ("k", ElementValue::Int(kind)) => metadata.kind = kind,
+            ("d2", ElementValue::Array(strings)) => metadata.strings = strings.into_iter()
+                .filter_map(|x| match x {
+                    ElementValue::String(x) => Some(x),
+                    _ => None,
+                })
+                .collect(),
+            _ => {},
+        }
+    }
+
+    Ok(Some(metadata))
+}
+
+/// Get the methods of `class`. If `include_inherited_methods` is enabled this includes the public methods
+/// inherited from superclasses and interfaces on the classpath, where overridden methods are included only once
+fn get_methods(class: &ClassFile, classes: &HashMap<String, ClassFile>, config: &Parser) -> JResult<Vec<MethodEntry>> {
+    if !config.include_inherited_methods {
+        return class.methods.iter()
+            .filter(|x| is_api_method(x) && visibility_of(x).is_included(config))
+            .map(|x| method_entry(class, x))
+            .collect();
+    }
+
+    // Like `Class.getMethods()`: the public methods of the class and its superclasses,
+    // and the public instance methods of all superinterfaces
+    let mut methods = Vec::new();
+    let mut interfaces = class.interfaces.clone();
+    let mut current = Some(class);
+    while let Some(superclass) = current {
+        for method in superclass.methods.iter().filter(|x| is_api_method(x) && x.access_flags & ACC_PUBLIC != 0) {
+            methods.push(method_entry(superclass, method)?);
+        }
+
+        interfaces.extend(superclass.interfaces.iter().cloned());
+        current = superclass.super_class.as_ref().and_then(|x| classes.get(x));
+    }
+
+    let mut seen = HashSet::new();
+    while let Some(interface) = interfaces.pop() {
+        if !seen.insert(interface.clone()) {
+            continue;
+        }
+
+        if let Some(interface) = classes.get(&interface) {
+            let inherited = interface.methods.iter()
+                .filter(|x| is_api_method(x) && x.access_flags & ACC_PUBLIC != 0 && x.access_flags & ACC_STATIC == 0);
+            for method in inherited {
+                methods.push(method_entry(interface, method)?);
+            }
+
+            interfaces.extend(interface.interfaces.iter().cloned());
+        }
+    }
+
+    Ok(deduplicate_overrides(methods, &class.name))
+}
+
+/// Get the compile-time constants declared by a class, i.e. the `static final` fields with a `ConstantValue` attribute
+fn constants(class: &ClassFile, config: &Parser) -> JResult<Vec<ConstantEntry>> {
+    let mut constants = Vec::new();
+    let fields = class.fields.iter()
+        .filter(|x| x.access_flags & (ACC_STATIC | ACC_FINAL) == ACC_STATIC | ACC_FINAL)
+        .filter(|x| visibility_of(x).is_included(config));
+
+    for field in fields {
+        if let Some(value) = class.constant_value(field)? {
+            constants.push(ConstantEntry {
+                name: field.name.clone(),
+                value,
+                deprecation: deprecation(class, &field.attributes)?,
+            });
+        }
+    }
+
+    Ok(constants)
+}
+
+/// Get the fields declared by a class, other than its enum constants and `constants`
+fn fields(class: &ClassFile, constants: &[ConstantEntry], config: &Parser) -> JResult<Vec<FieldEntry>> {
+    class.fields.iter()
+        .filter(|x| x.access_flags & (ACC_SYNTHETIC | ACC_ENUM) == 0 && visibility_of(x).is_included(config))
+        .filter(|x| !constants.iter().any(|constant| constant.name == x.name))
+        .map(|field| {
+            let field_type = parse_field_descriptor(&field.descriptor)?;
+            let generic_type = match class.signature(&field.attributes)? {
+                Some(signature) => parse_field_signature(&signature)?,
+                None => type_name(&field_type),
+            };
+
+            Ok(FieldEntry {
+                name: field.name.clone(),
+                is_static: field.access_flags & ACC_STATIC != 0,
+                is_final: field.access_flags & ACC_FINAL != 0,
+                visibility: visibility_of(field),
+                field_type,
+                generic_type,
+                deprecation: deprecation(class, &field.attributes)?,
+            })
+        })
+        .collect()
+}
+
+/// Get the single abstract method of an interface, from the methods of the interface and its superinterfaces.
+/// If a superinterface is not on the classpath its methods are unknown, in which case the interface
+/// is only considered functional if it is annotated with `@FunctionalInterface`
+fn functional_method(class: &ClassFile, classes: &HashMap<String, ClassFile>) -> JResult<Option<MethodEntry>> {
+    let mut methods = Vec::new();
+    let mut is_complete = true;
+    let mut interfaces = vec![class];
+    let mut seen = HashSet::new();
+    while let Some(interface) = interfaces.pop() {
+        if !seen.insert(&interface.name) {
+            continue;
+        }
+
+        for method in interface.methods.iter().filter(|x| is_api_method(x) && x.access_flags & ACC_STATIC == 0) {
+            methods.push(method_entry(interface, method)?);
+        }
+
+        for name in &interface.interfaces {
+            match classes.get(name) {
+                Some(superinterface) => interfaces.push(superinterface),
+                None => is_complete = false,
+            }
+        }
+    }
+
+    let is_annotated = class.annotations(&class.attributes)?
+        .iter()
+        .any(|x| x.type_name == "java.lang.FunctionalInterface");
+    if !is_complete && !is_annotated {
+        return Ok(None);
+    }
+
+    Ok(find_functional_method(methods, &class.name))
+}
+
+fn method_entry(class: &ClassFile, method: &Member) -> JResult<MethodEntry> {
+    trace!("Analyzing method {}", method.name);
+
+    let (arguments, return_type) = parse_method_descriptor(&method.descriptor)?;
+    let signature = class.signature(&method.attributes)?
+        .map(|x| parse_method_signature(&x))
+        .transpose()?;
+
+    let (type_parameters, generic_arguments, generic_return_type) = match signature {
+        Some(signature) => (signature.type_parameters, signature.arguments, signature.return_type),
+        None => (Vec::new(), arguments.iter().map(type_name).collect(), return_type.as_ref().map(type_name)),
+    };
+
+    let is_interface = class.access_flags & ACC_INTERFACE != 0;
+    let return_nullability = match return_type {
+        Some(_) => return_nullability(class, method)?,
+        None => Nullability::Unknown,
+    };
+
+    Ok(MethodEntry {
+        name: method.name.clone(),
+        is_static: method.access_flags & ACC_STATIC != 0,
+        visibility: visibility_of(method),
+        is_abstract: method.access_flags & ACC_ABSTRACT != 0,
+        // Like `Method.isDefault()`
+        is_default: is_interface && method.access_flags & (ACC_ABSTRACT | ACC_PUBLIC | ACC_STATIC) == ACC_PUBLIC,
+        deprecation: deprecation(class, &method.attributes)?,
+        argument_names: argument_names(class, method, arguments.len())?,
+        argument_nullability: argument_nullability(class, method, arguments.len())?,
+        arguments,
+        return_type,
+        declaring_class: class.name.clone(),
+        type_parameters,
+        generic_arguments,
+        generic_return_type,
+        exceptions: class.class_list(&method.attributes, "Exceptions")?,
+        return_nullability,
+        javadoc: None,
+    })
+}
+
+fn constructor_entry(class: &ClassFile, constructor: &Member) -> JResult<ConstructorEntry> {
+    let (arguments, _) = parse_method_descriptor(&constructor.descriptor)?;
+    let generic_arguments = match class.signature(&constructor.attributes)? {
+        Some(signature) => parse_method_signature(&signature)?.arguments,
+        None => arguments.iter().map(type_name).collect(),
+    };
+
+    Ok(ConstructorEntry {
+        visibility: visibility_of(constructor),
+        deprecation: deprecation(class, &constructor.attributes)?,
+        argument_names: argument_names(class, constructor, arguments.len())?,
+        argument_nullability: argument_nullability(class, constructor, arguments.len())?,
+        arguments,
+        generic_arguments,
+        exceptions: class.class_list(&constructor.attributes, "Exceptions")?,
+    })
+}
+
+/// Get the argument names from the `MethodParameters` attribute, `None` for every argument if it is not present
+fn argument_names(class: &ClassFile, method: &Member, argument_count: usize) -> JResult<Vec<Option<String>>> {
+    let mut names = class.parameter_names(&method.attributes)?;
+    names.resize(argument_count, None);
+    Ok(names)
+}
+
+/// Get the nullability of the arguments from their declaration and type-use annotations
+fn argument_nullability(class: &ClassFile, method: &Member, argument_count: usize) -> JResult<Vec<Nullability>> {
+    let mut annotation_names = class.parameter_annotations(&method.attributes)?
+        .into_iter()
+        .map(|x| x.into_iter().map(|x| x.type_name).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    // Annotation information is reported for the trailing arguments
+    while annotation_names.len() < argument_count {
+        annotation_names.insert(0, Vec::new());
+    }
+
+    for type_annotation in class.type_annotations(&method.attributes)? {
+        if let (TypeAnnotationTarget::Parameter(idx), false) = (type_annotation.target, type_annotation.is_nested) {
+            if let Some(names) = annotation_names.get_mut(idx as usize) {
+                names.push(type_annotation.annotation.type_name);
+            }
+        }
+    }
+
+    Ok(annotation_names.iter()
+        .take(argument_count)
+        .map(|x| Nullability::from_annotation_names(x))
+        .collect())
+}
+
+/// Get the nullability of the return value from the method's declaration and type-use annotations
+fn return_nullability(class: &ClassFile, method: &Member) -> JResult<Nullability> {
+    let mut names = class.annotations(&method.attributes)?
+        .into_iter()
+        .map(|x| x.type_name)
+        .collect::<Vec<_>>();
+
+    names.extend(class.type_annotations(&method.attributes)?
+        .into_iter()
+        .filter(|x| x.target == TypeAnnotationTarget::Return && !x.is_nested)
+        .map(|x| x.annotation.type_name));
+
+    Ok(Nullability::from_annotation_names(&names))
+}
+
+/// Read the `@Deprecated` annotation of a class or member. Falls back to the `Deprecated` attribute,
+/// which is the only deprecation information in classes compiled by old compilers
+fn deprecation(class: &ClassFile, attributes: &[Attribute]) -> JResult<Option<Deprecation>> {
+    let annotation = class.annotations(attributes)?
+        .into_iter()
+        .find(|x| x.type_name == "java.lang.Deprecated");
+
+    let annotation = match annotation {
+        Some(annotation) => annotation,
+        None if attributes.iter().any(|x| x.name == "Deprecated") => return Ok(Some(Deprecation {
+            since: None,
+            for_removal: false,
+        })),
+        None => return Ok(None),
+    };
+
+    let mut deprecation = Deprecation {
+        since: None,
+        for_removal: false,
+    };
+
+    for (name, value) in annotation.values {
+        match (name.as_str(), value) {
+            ("since", ElementValue::String(since)) if !since.is_empty() => deprecation.since = Some(since),
+            ("forRemoval", ElementValue::Boolean(for_removal)) => deprecation.for_removal = for_removal,
+            _ => {},
+        }
+    }
+
+    Ok(Some(deprecation))
+}
+
+fn get_annotation_elements(class: &ClassFile) -> JResult<Vec<AnnotationElement>> {
+    class.methods.iter()
+        .filter(|x| is_api_method(x))
+        .map(|method| {
+            let (_, element_type) = parse_method_descriptor(&method.descriptor)?;
+            let element_type = element_type.ok_or_else(|| anyhow::anyhow!("Annotation element {} returns void", method.name))?;
+            let default_value = class.annotation_default(&method.attributes)?
+                .map(|x| format_element_value(&x));
+
+            Ok(AnnotationElement {
+                name: method.name.clone(),
+                element_type,
+                default_value,
+            })
+        })
+        .collect()
+}
+
+/// Format an annotation element value like `String.valueOf` formats the value returned by reflection.
+/// Arrays are formatted as their elements in braces, as Java would only print their identity
+fn format_element_value(value: &ElementValue) -> String {
+    match value {
+        ElementValue::Boolean(value) => value.to_string(),
+        ElementValue::Byte(value) => value.to_string(),
+        ElementValue::Char(value) => String::from_utf16_lossy(&[*value]),
+        ElementValue::Short(value) => value.to_string(),
+        ElementValue::Int(value) => value.to_string(),
+        ElementValue::Long(value) => value.to_string(),
+        // Debug formatting keeps the fraction, like Java does, e.g. `1.0` rather than `1`
+        ElementValue::Float(value) => format!("{value:?}"),
+        ElementValue::Double(value) => format!("{value:?}"),
+        ElementValue::String(value) => value.clone(),
+        ElementValue::Enum(_, name) => name.clone(),
+        ElementValue::Class(name) => format!("class {name}"),
+        ElementValue::Annotation(annotation) => {
+            let values = annotation.values.iter()
+                .map(|(name, value)| format!("{name}={}", format_element_value(value)))
+                .collect::<Vec<_>>();
+            format!("@{}({})", annotation.type_name, values.join(", "))
+        },
+        ElementValue::Array(values) => {
+            let values = values.iter()
+                .map(format_element_value)
+                .collect::<Vec<_>>();
+            format!("{{{}}}", values.join(", "))
+        },
+    }
+}
+
+/// Walk the superclass chain through the classpath. Classes outside of the classpath,
+/// e.g. those of the JDK, end the chain, after which only `java.lang.Object` is appended
+fn get_superclasses(class: &ClassFile, classes: &HashMap<String, ClassFile>) -> Vec<String> {
+    let mut superclasses = Vec::new();
+    let mut current = class.super_class.clone();
+    while let Some(name) = current {
+        let superclass = classes.get(&name);
+        current = superclass.and_then(|x| x.super_class.clone());
+        superclasses.push(name);
+
+        if superclass.is_none() {
+            break;
+        }
+    }
+
+    if !superclasses.is_empty() && superclasses.last().map(|x| x.as_str()) != Some("java.lang.Object") {
+        superclasses.push("java.lang.Object".to_string());
+    }
+
+    superclasses
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn element_value() {
+        let value = ElementValue::Array(vec![
+            ElementValue::Float(1.0),
+            ElementValue::Char('x' as u16),
+            ElementValue::Enum("java.lang.annotation.ElementType".to_string(), "METHOD".to_string()),
+        ]);
+
+        assert_eq!("{1.0, x, METHOD}", format_element_value(&value));
+        assert_eq!("class java.lang.String[]", format_element_value(&ElementValue::Class("java.lang.String[]".to_string())));
+    }
+
+    #[test]
+    fn access_flags() {
+        assert_eq!(Visibility::Public, Visibility::from_access_flags(ACC_PUBLIC | ACC_STATIC));
+        assert_eq!(Visibility::Protected, Visibility::from_access_flags(ACC_PROTECTED));
+        assert_eq!(Visibility::PackagePrivate, Visibility::from_access_flags(ACC_ABSTRACT));
+    }
+}
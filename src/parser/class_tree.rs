@@ -1,161 +1,571 @@
+use std::collections::{HashMap, HashSet};
 use ejni::{Class, JavaString, Object};
-use jni::JNIEnv;
-use jni::objects::JValue;
-use log::trace;
+use jni::{JavaVM, JNIEnv};
+use jni::objects::JObject;
+use log::{trace, warn};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::config::Parser;
 use crate::JResult;
-use crate::parser::guava::ClassPath;
+use crate::parser::filter::ClassFilter;
+use crate::parser::classfile::{self, ClassFile, ACC_ABSTRACT, ACC_FINAL, ACC_PRIVATE, ACC_PROTECTED, ACC_PUBLIC, ACC_STATIC};
+use crate::parser::classpath::ClassPath;
+use crate::parser::description::{ClassDescription, FieldDescription};
+use crate::parser::kotlin::{self, Property};
 
-#[derive(Debug)]
+/// The number of local references reserved for exploring a single class.
+/// The JVM grows the frame when more are needed
+const LOCAL_FRAME_CAPACITY: i32 = 256;
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ClassEntry {
     pub name: String,
+    /// The name as declared in the source, e.g. `Baz` for `com.foo.Bar$Baz`. Empty for anonymous classes
+    pub simple_name: String,
+    /// The class this class is declared in, e.g. `com.foo.Bar` for `com.foo.Bar$Baz`. `None` for top-level classes
+    pub enclosing_class: Option<String>,
+    /// The classes in the tree declared in this class, in the order they appear in the tree
+    pub nested_classes: Vec<String>,
     pub class_type: ClassType,
+    pub visibility: Visibility,
+    /// Whether the class is abstract. Always true for interfaces
+    pub is_abstract: bool,
+    /// Present if the class is annotated with `@Deprecated`
+    pub deprecation: Option<Deprecation>,
     pub methods: Vec<MethodEntry>,
     pub interfaces: Vec<String>,
+    /// The names of the enum constants, in declaration order.
+    /// Empty if the class is not an enum
+    pub enum_constants: Vec<String>,
+    /// The type parameters declared by the class, e.g. `T` for `List<T>`
+    pub type_parameters: Vec<TypeParameter>,
+    /// The superclass including its type arguments, e.g. `java.util.AbstractList<E>`.
+    /// Only present if the superclass is parameterized
+    pub generic_superclass: Option<String>,
+    /// The superclass chain, starting at the direct superclass and ending at `java.lang.Object`.
+    /// Empty for interfaces and `java.lang.Object` itself
+    pub superclasses: Vec<String>,
+    /// The subclasses a sealed class or interface permits. Empty if it is not sealed
+    pub permitted_subclasses: Vec<String>,
+    /// The elements of an annotation type. Empty if the class is not an annotation
+    pub annotation_elements: Vec<AnnotationElement>,
+    /// Whether the class is a non-static member class, requiring an instance
+    /// of the enclosing class to be instantiated
+    pub is_inner_class: bool,
+    pub constructors: Vec<ConstructorEntry>,
+    /// The static field holding the only instance of the class, e.g. `INSTANCE` of a Kotlin `object`
+    pub instance_field: Option<String>,
+    /// The static field holding the companion object of a Kotlin class, e.g. `Companion`.
+    /// Its type is the nested class of the same name
+    pub companion_field: Option<String>,
+    /// The properties of a Kotlin class. Their getters and setters are part of `methods` as well
+    pub properties: Vec<Property>,
+    /// The compile-time constants declared by the class, like the `public static final` fields of an interface
+    pub constants: Vec<ConstantEntry>,
+    /// The fields declared by the class, other than its enum constants and compile-time constants
+    pub fields: Vec<FieldEntry>,
+    /// The single abstract method of a functional interface, which a lambda would implement.
+    /// `None` if the class is not a functional interface
+    pub functional_method: Option<MethodEntry>,
+    /// The Javadoc of the class, if a sources jar was provided
+    pub javadoc: Option<String>,
 }
 
-#[derive(Debug)]
+/// A field accessed through JNI, as opposed to a compile-time constant
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldEntry {
+    pub name: String,
+    pub is_static: bool,
+    pub is_final: bool,
+    pub visibility: Visibility,
+    pub field_type: ArgumentType,
+    /// The type including its type arguments, e.g. `java.util.List<java.lang.String>`
+    pub generic_type: String,
+    /// Present if the field is annotated with `@Deprecated`
+    pub deprecation: Option<Deprecation>,
+}
+
+/// A `static final` field of a primitive type or `String`, initialized with a constant expression
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstantEntry {
+    pub name: String,
+    pub value: ConstantValue,
+    /// Present if the field is annotated with `@Deprecated`
+    pub deprecation: Option<Deprecation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ConstantValue {
+    Boolean(bool),
+    Byte(i8),
+    Char(u16),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotationElement {
+    pub name: String,
+    pub element_type: ArgumentType,
+    /// The default value, formatted by `String.valueOf`. `None` if the element has no default
+    pub default_value: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deprecation {
+    /// The version in which the element became deprecated, if specified
+    pub since: Option<String>,
+    /// Whether the element is scheduled to be removed
+    pub for_removal: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Nullability {
+    /// No nullability annotation is present
+    Unknown,
+    /// Annotated as possibly being `null`
+    Nullable,
+    /// Annotated as never being `null`
+    NonNull,
+}
+
+/// Annotations marking an element as possibly `null`
+const NULLABLE_ANNOTATIONS: &[&str] = &[
+    "javax.annotation.Nullable",
+    "javax.annotation.CheckForNull",
+    "jakarta.annotation.Nullable",
+    "org.jetbrains.annotations.Nullable",
+    "org.checkerframework.checker.nullness.qual.Nullable",
+    "org.checkerframework.checker.nullness.compatqual.NullableDecl",
+    "org.jspecify.annotations.Nullable",
+    "edu.umd.cs.findbugs.annotations.Nullable",
+    "androidx.annotation.Nullable",
+    "android.support.annotation.Nullable",
+];
+
+/// Annotations marking an element as never being `null`
+const NON_NULL_ANNOTATIONS: &[&str] = &[
+    "javax.annotation.Nonnull",
+    "jakarta.annotation.Nonnull",
+    "org.jetbrains.annotations.NotNull",
+    "org.checkerframework.checker.nullness.qual.NonNull",
+    "org.jspecify.annotations.NonNull",
+    "edu.umd.cs.findbugs.annotations.NonNull",
+    "androidx.annotation.NonNull",
+    "android.support.annotation.NonNull",
+    "lombok.NonNull",
+];
+
+impl Nullability {
+    /// Determine the nullability from the fully qualified names of the annotations on an element.
+    /// If both kinds of annotations are present, `Nullable` wins
+    pub fn from_annotation_names<S: AsRef<str>>(names: &[S]) -> Self {
+        let has_any = |annotations: &[&str]| names.iter().any(|x| annotations.contains(&x.as_ref()));
+
+        if has_any(NULLABLE_ANNOTATIONS) {
+            Self::Nullable
+        } else if has_any(NON_NULL_ANNOTATIONS) {
+            Self::NonNull
+        } else {
+            Self::Unknown
+        }
+    }
+
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypeParameter {
+    /// The name of the type variable, e.g. `T`
+    pub name: String,
+    /// The upper bounds of the type variable, e.g. `java.lang.Comparable<T>`.
+    /// The implicit `java.lang.Object` bound is omitted
+    pub bounds: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ClassType {
     Class,
     Interface,
-    Annotation
+    Annotation,
+    Enum,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Visibility {
+    Public,
+    Protected,
+    PackagePrivate,
+    Private,
+}
+
+impl Visibility {
+    /// Get the visibility from access flags, or the equivalent modifiers returned by `getModifiers()`
+    pub fn from_access_flags(access_flags: u16) -> Self {
+        if access_flags & ACC_PUBLIC != 0 {
+            Self::Public
+        } else if access_flags & ACC_PROTECTED != 0 {
+            Self::Protected
+        } else if access_flags & ACC_PRIVATE != 0 {
+            Self::Private
+        } else {
+            Self::PackagePrivate
+        }
+    }
+
+    /// Whether members with this visibility should be included in the tree
+    pub fn is_included(&self, config: &Parser) -> bool {
+        match self {
+            Self::Public => true,
+            Self::Protected => config.include_protected,
+            Self::PackagePrivate | Self::Private => false,
+        }
+    }
 }
 
 impl ClassType {
-    fn new(env: &JNIEnv<'_>, class: &Class<'_>) -> JResult<Self> {
-        let is_interface = env.call_method(class.class.into_inner(), "isInterface", "()Z", &[])?.z()?;
-        let is_annotation = env.call_method(class.class.into_inner(), "isAnnotation", "()Z", &[])?.z()?;
-
-        if is_interface {
-            Ok(Self::Interface)
-        } else if is_annotation {
-            Ok(Self::Annotation)
+    fn new(description: &ClassDescription) -> Self {
+        // Annotation types are interfaces as well
+        if description.is_annotation {
+            Self::Annotation
+        } else if description.is_interface {
+            Self::Interface
+        } else if description.is_enum {
+            Self::Enum
         } else {
-            Ok(Self::Class)
+            Self::Class
         }
     }
 }
 
-pub fn build(env: &JNIEnv<'_>, root: String) -> JResult<Vec<ClassEntry>> {
-    let classpath = ClassPath::new(env)?;
-    let classes = classpath.get_all_classes()?;
-    let classes = classes.into_iter()
-        .map(|x| Ok((x.get_name()?, x)))
-        .collect::<JResult<Vec<_>>>()?
-        .into_iter()
-        .filter(|(x, _)| x.starts_with(&root))
-        .map(|(_, x)| x)
-        .collect::<Vec<_>>();
+/// Build the class tree of all classes in the jars in `classpath` that are in one of the packages in `roots`, e.g. `com.itextpdf.`.
+/// Classes matching multiple, overlapping roots, or found multiple times on the classpath, are included once.
+///
+/// The classes are explored in parallel, each worker thread attaching itself to `vm`
+pub fn build(vm: &JavaVM, classpath: &[String], roots: &[String], config: &Parser) -> JResult<Vec<ClassEntry>> {
+    let filter = ClassFilter::new(config)?;
+    let classpath = ClassPath::new(&*vm.attach_current_thread()?, classpath)?;
+    let names = classpath.get_all_class_names()?;
+    let available = names.iter().cloned().collect::<HashSet<_>>();
 
-    trace!("Found {} classes as subclass of package {}", classes.len(), root);
-
-    let class_entries = classes.into_iter()
-        .map(|class| {
-            let name = class.get_name()?;
-            trace!("Exploring class {}", name);
-            let class_type = ClassType::new(env, &class)?;
-
-            let methods = get_methods(env, &class)?;
-            trace!("Found {} methods for {}", methods.len(), name);
-
-            let interfaces = env.call_method(class.class.into_inner(), "getInterfaces", "()[Ljava/lang/Class;", &[])?.l()?;
-            let len = env.get_array_length(interfaces.into_inner())?;
-            let interfaces = (0..len).into_iter()
-                .map(|idx| Ok(env.get_object_array_element(interfaces.into_inner(), idx)?))
-                .collect::<JResult<Vec<_>>>()?
-                .into_iter()
-                .map(|object| Ok(env.call_method(object, "getName", "()Ljava/lang/String;", &[])?.l()?))
-                .collect::<JResult<Vec<_>>>()?
-                .into_iter()
-                .map(|string_object| Ok(JavaString::new(env, Object::new(env, string_object, Class::String(env)?)).into_rust()?))
-                .collect::<JResult<Vec<_>>>()?;
+    // Filter by name before loading, to avoid loading classes that are not generated
+    let mut pending = filter.select(names, roots);
+    let mut known = pending.iter().cloned().collect::<HashSet<_>>();
+    trace!("Selected {} classes from packages {:?} or the entry points", pending.len(), roots);
 
-            Ok(ClassEntry {
-                name,
-                class_type,
-                methods,
-                interfaces,
+    let mut skipped = Vec::new();
+    let mut class_entries = Vec::new();
+    while !pending.is_empty() {
+        let explored = class_entries.len();
+        let results = pending.par_iter()
+            .map(|name| {
+                // Worker threads stay attached, so they do not have to attach again for every class
+                let env = vm.attach_current_thread_permanently()?;
+                explore_class(&env, &classpath, name, config)
             })
-        })
-        .collect::<JResult<Vec<_>>>()?;
+            .collect::<JResult<Vec<_>>>()?;
+
+        for result in results {
+            match result {
+                Ok(entry) => class_entries.extend(entry),
+                Err(skipped_class) => skipped.push(skipped_class),
+            }
+        }
+
+        pending = filter.referenced_classes(&class_entries[explored..], &available, &mut known, roots);
+        trace!("Found {} referenced classes", pending.len());
+    }
+
+    link_nested_classes(&mut class_entries);
+
+    if !skipped.is_empty() {
+        warn!("Skipped {} classes that could not be loaded:", skipped.len());
+        for class in &skipped {
+            warn!("    {}: {}", class.name, class.reason);
+        }
+    }
 
     Ok(class_entries)
 }
 
+/// A class that was left out of the tree because the JVM failed to load or link it
 #[derive(Debug)]
-pub struct MethodEntry {
+pub struct SkippedClass {
     pub name: String,
-    pub is_static: bool,
-    pub arguments: Vec<ArgumentType>,
-    pub return_type: Option<ArgumentType>,
-    pub declaring_class: String,
+    /// The description of the thrown error, e.g. `java.lang.NoClassDefFoundError: com/foo/Bar`
+    pub reason: String,
 }
 
-impl MethodEntry {
-    pub fn new(env: &JNIEnv<'_>, method: Object<'_>) -> JResult<Self> {
-        let name = env.call_method(method.inner, "getName", "()Ljava/lang/String;", &[])?.l()?;
-        let name = JavaString::new(env, Object::new(env, name, Class::String(env)?)).into_rust()?;
+/// Load and explore a single class. Returns the class entry, which is `None` if the class is not included,
+/// or the reason the class was skipped if it could not be loaded
+fn explore_class<'a>(env: &'a JNIEnv<'a>, classpath: &ClassPath, name: &str, config: &Parser) -> JResult<Result<Option<ClassEntry>, SkippedClass>> {
+    // Local references are only freed when a thread detaches, which the worker threads never do.
+    // Free them after every class instead
+    env.push_local_frame(LOCAL_FRAME_CAPACITY)?;
 
-        trace!("Analyzing method {}", name);
+    let result = match skip_load_failure(env, name, classpath.load_class(env, name)) {
+        Ok(Ok(class)) => skip_load_failure(env, name, class_entry(env, classpath, class, config)),
+        Ok(Err(skipped)) => Ok(Err(skipped)),
+        Err(e) => Err(e),
+    };
 
-        let modifiers = env.call_method(method.inner, "getModifiers", "()I", &[])?.i()?;
-        let is_static = env.call_static_method("java/lang/reflect/Modifier", "isStatic", "(I)Z", &[JValue::Int(modifiers)])?.z()?;
+    env.pop_local_frame(JObject::null())?;
+    result
+}
 
-        let parameter_classes_array = env.call_method(method.inner, "getParameterTypes", "()[Ljava/lang/Class;", &[])?.l()?;
-        let len = env.get_array_length(parameter_classes_array.into_inner())?;
-        let arguments = (0..len).into_iter()
-            .map(|idx| Ok(env.get_object_array_element(parameter_classes_array.into_inner(), idx)?))
-            .collect::<JResult<Vec<_>>>()?
-            .into_iter()
-            .map(|object| {
-                let class_name = env.call_method(object, "getName", "()Ljava/lang/String;", &[])?.l()?;
-                let class_name = JavaString::new(env, Object::new(env, class_name, Class::String(env)?)).into_rust()?;
-                let argument = ArgumentType::new(&env, class_name)?;
-                Ok(argument)
-            })
-            .collect::<JResult<Vec<_>>>()?;
+/// If `result` failed because loading the class threw a `LinkageError`, such as `NoClassDefFoundError`
+/// or `ExceptionInInitializerError`, clear the exception and return the reason the class is skipped.
+/// Other errors are returned as-is
+fn skip_load_failure<T>(env: &JNIEnv<'_>, name: &str, result: JResult<T>) -> JResult<Result<T, SkippedClass>> {
+    let error = match result {
+        Ok(value) => return Ok(Ok(value)),
+        Err(error) => error,
+    };
 
-        trace!("Found {} arguments for method {}", arguments.len(), name);
-
-        let declaring_class = env.call_method(method.inner, "getDeclaringClass", "()Ljava/lang/Class;", &[])?.l()?;
-        let declaring_class = env.call_method(declaring_class, "getName", "()Ljava/lang/String;", &[])?.l()?;
-        let declaring_class = JavaString::new(env, Object::new(env, declaring_class, Class::String(env)?)).into_rust()?;
-
-        let return_type = env.call_method(method.inner, "getReturnType", "()Ljava/lang/Class;", &[])?.l()?;
-        let ret_name = env.call_method(return_type, "getName", "()Ljava/lang/String;", &[])?.l()?;
-        let ret_name = JavaString::new(env, Object::new(env, ret_name, Class::String(env)?)).into_rust()?;
-
-        let return_type = match ret_name.as_str() {
-            "boolean" => Some(ArgumentType::Boolean),
-            "int" => Some(ArgumentType::Int),
-            "float" => Some(ArgumentType::Float),
-            "short" => Some(ArgumentType::Short),
-            "double" => Some(ArgumentType::Double),
-            "byte" => Some(ArgumentType::Byte),
-            "long" => Some(ArgumentType::Long),
-            "char" => Some(ArgumentType::Char),
-            "void" => None,
-            _ => {
-                let class = Class::for_name(env, &ret_name)?;
-                let is_array = env.call_method(class.class.into_inner(), "isArray", "()Z", &[])?.z()?;
-                if is_array {
-                    Some(ArgumentType::Array(Box::new(ArgumentType::Object(ret_name))))
-                } else {
-                    Some(ArgumentType::Object(ret_name))
-                }
+    if !env.exception_check()? {
+        return Err(error);
+    }
+
+    // The exception must be cleared before any other JNI call is made
+    let throwable = env.exception_occurred()?;
+    env.exception_clear()?;
+
+    if !env.is_instance_of(throwable, "java/lang/LinkageError")? {
+        env.throw(throwable)?;
+        return Err(error);
+    }
+
+    let reason = env.call_method(throwable, "toString", "()Ljava/lang/String;", &[])?.l()?;
+    let reason = JavaString::new(env, Object::new(env, reason, Class::String(env)?)).into_rust()?;
+    trace!("Skipping class {} that failed to load: {}", name, reason);
+
+    Ok(Err(SkippedClass {
+        name: name.to_string(),
+        reason,
+    }))
+}
+
+fn class_entry<'a>(env: &'a JNIEnv<'a>, classpath: &ClassPath, class: Class<'a>, config: &Parser) -> JResult<Option<ClassEntry>> {
+    let name = class.get_name()?;
+    trace!("Exploring class {}", name);
+
+    let modifiers = env.call_method(class.class.into_inner(), "getModifiers", "()I", &[])?.i()? as u16;
+    let visibility = Visibility::from_access_flags(modifiers);
+    if !visibility.is_included(config) {
+        trace!("Skipping class {} with visibility {:?}", name, visibility);
+        return Ok(None);
+    }
+
+    let is_synthetic = env.call_method(class.class.into_inner(), "isSynthetic", "()Z", &[])?.z()?;
+    if is_synthetic {
+        trace!("Skipping synthetic class {}", name);
+        return Ok(None);
+    }
+
+    // Everything else is gathered in a single call, rather than a call for every member
+    let description = ClassDescription::new(env, classpath, &class, config)?;
+
+    let simple_name = description.simple_name.clone()
+        .unwrap_or_else(|| name.rsplit(['.', '$']).next().unwrap_or_default().to_string());
+    let class_type = ClassType::new(&description);
+    let is_abstract = modifiers & ACC_ABSTRACT != 0;
+    let is_inner_class = description.is_member_class && modifiers & ACC_STATIC == 0;
+
+    let methods = description.methods.into_iter()
+        .map(|x| x.into_entry())
+        .collect::<JResult<Vec<_>>>()?;
+    let methods = if config.include_inherited_methods {
+        deduplicate_overrides(methods, &name)
+    } else {
+        methods
+    };
+    trace!("Found {} methods for {}", methods.len(), name);
+
+    let enum_constants = description.enum_constants;
+    trace!("Found {} enum constants for {}", enum_constants.len(), name);
+
+    let annotation_elements = description.annotation_elements.into_iter()
+        .map(|x| x.into_entry())
+        .collect::<JResult<Vec<_>>>()?;
+
+    let constructors = description.constructors.into_iter()
+        .map(|x| x.into_entry())
+        .collect::<JResult<Vec<_>>>()?;
+    trace!("Found {} constructors for {}", constructors.len(), name);
+
+    let kotlin_metadata = description.kotlin_metadata.filter(|x| x.kind == kotlin::KIND_CLASS);
+    let static_fields = if kotlin_metadata.is_some() || is_scala_object(&name) {
+        description.fields.iter()
+            .filter(|x| x.modifiers & ACC_STATIC != 0)
+            .map(|x| (x.name.clone(), x.field_type.clone()))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let instance_field = find_instance_field(&name, &static_fields);
+    let (companion_field, properties) = match &kotlin_metadata {
+        Some(metadata) => (kotlin::companion_field(&name, &static_fields), kotlin::properties(&methods, metadata)),
+        None => (None, Vec::new()),
+    };
+
+    let constants = get_constants(env, classpath, &description.fields, &name, config)?;
+    trace!("Found {} constants for {}", constants.len(), name);
+
+    let fields = description.fields.into_iter()
+        .filter(|x| !x.is_synthetic && !x.is_enum_constant && Visibility::from_access_flags(x.modifiers).is_included(config))
+        .filter(|x| !constants.iter().any(|constant| constant.name == x.name))
+        .map(|x| x.into_entry())
+        .collect::<JResult<Vec<_>>>()?;
+    trace!("Found {} fields for {}", fields.len(), name);
+
+    let functional_method = match class_type {
+        ClassType::Interface => {
+            let methods = description.interface_methods.into_iter()
+                .map(|x| x.into_entry())
+                .collect::<JResult<Vec<_>>>()?;
+            find_functional_method(methods, &name)
+        },
+        _ => None,
+    };
+
+    Ok(Some(ClassEntry {
+        name,
+        simple_name,
+        enclosing_class: description.enclosing_class,
+        nested_classes: Vec::new(),
+        class_type,
+        visibility,
+        is_abstract,
+        deprecation: description.deprecation,
+        methods,
+        interfaces: description.interfaces,
+        enum_constants,
+        type_parameters: description.type_parameters,
+        generic_superclass: description.generic_superclass,
+        superclasses: description.superclasses,
+        permitted_subclasses: description.permitted_subclasses,
+        annotation_elements,
+        is_inner_class,
+        constructors,
+        instance_field,
+        companion_field,
+        properties,
+        constants,
+        fields,
+        functional_method,
+        javadoc: None,
+    }))
+}
+
+impl ClassEntry {
+    /// The names of the classes referenced by the signatures of the class, its methods, its constructors and its fields.
+    /// Names are taken from generic types as well, so this can include type variables and keywords, like `T` and `extends`
+    pub fn referenced_types(&self) -> Vec<String> {
+        let mut types = Vec::new();
+        types.extend(self.superclasses.iter().cloned());
+        types.extend(self.interfaces.iter().cloned());
+
+        let mut generic_types = Vec::new();
+        generic_types.extend(self.generic_superclass.iter());
+        for type_parameter in &self.type_parameters {
+            generic_types.extend(type_parameter.bounds.iter());
+        }
+
+        for method in &self.methods {
+            method.arguments.iter()
+                .chain(method.return_type.iter())
+                .for_each(|x| types.extend(x.class_name()));
+            generic_types.extend(method.generic_arguments.iter());
+            generic_types.extend(method.generic_return_type.iter());
+            for type_parameter in &method.type_parameters {
+                generic_types.extend(type_parameter.bounds.iter());
             }
-        };
+        }
 
-        Ok(Self {
-            name,
-            is_static,
-            arguments,
-            return_type,
-            declaring_class,
-        })
+        for constructor in &self.constructors {
+            constructor.arguments.iter().for_each(|x| types.extend(x.class_name()));
+            generic_types.extend(constructor.generic_arguments.iter());
+        }
+
+        for field in &self.fields {
+            types.extend(field.field_type.class_name());
+            generic_types.push(&field.generic_type);
+        }
+
+        for element in &self.annotation_elements {
+            types.extend(element.element_type.class_name());
+        }
+
+        // E.g. `java.util.Map<java.lang.String, ? extends com.foo.Bar>`
+        for generic_type in generic_types {
+            types.extend(generic_type
+                .split(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '$' | '.')))
+                .filter(|x| !x.is_empty())
+                .map(str::to_string));
+        }
+
+        types
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MethodEntry {
+    pub name: String,
+    pub is_static: bool,
+    pub visibility: Visibility,
+    /// Whether the method is abstract, i.e. it has no implementation in the declaring class
+    pub is_abstract: bool,
+    /// Whether the method is a default method of an interface
+    pub is_default: bool,
+    /// Present if the method is annotated with `@Deprecated`
+    pub deprecation: Option<Deprecation>,
+    pub arguments: Vec<ArgumentType>,
+    /// The names of the arguments, in the same order as `arguments`.
+    /// Only present if the class was compiled with `-parameters`
+    pub argument_names: Vec<Option<String>>,
+    pub return_type: Option<ArgumentType>,
+    pub declaring_class: String,
+    /// The type parameters declared by the method itself, e.g. `T` for `<T> T foo()`
+    pub type_parameters: Vec<TypeParameter>,
+    /// The arguments including their type arguments, e.g. `java.util.List<java.lang.String>`
+    pub generic_arguments: Vec<String>,
+    /// The return type including type arguments. `None` if the method returns `void`
+    pub generic_return_type: Option<String>,
+    /// The exceptions the method declares to throw, e.g. `java.io.IOException`
+    pub exceptions: Vec<String>,
+    /// The nullability of each argument, in the same order as `arguments`
+    pub argument_nullability: Vec<Nullability>,
+    /// The nullability of the return value. `Unknown` if the method returns `void`
+    pub return_nullability: Nullability,
+    /// The Javadoc of the method, if a sources jar was provided
+    pub javadoc: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConstructorEntry {
+    pub visibility: Visibility,
+    /// Present if the constructor is annotated with `@Deprecated`
+    pub deprecation: Option<Deprecation>,
+    /// The arguments as passed through JNI. For inner classes the first
+    /// argument is the implicit instance of the enclosing class
+    pub arguments: Vec<ArgumentType>,
+    /// The names of the arguments, in the same order as `arguments`.
+    /// Only present if the class was compiled with `-parameters`
+    pub argument_names: Vec<Option<String>>,
+    /// The arguments including their type arguments, e.g. `java.util.List<java.lang.String>`.
+    /// Whether this includes the implicit enclosing instance depends on the compiler
+    pub generic_arguments: Vec<String>,
+    /// The exceptions the constructor declares to throw, e.g. `java.io.IOException`
+    pub exceptions: Vec<String>,
+    /// The nullability of each argument, in the same order as `arguments`
+    pub argument_nullability: Vec<Nullability>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ArgumentType {
     Boolean,
     Byte,
@@ -165,61 +575,331 @@ pub enum ArgumentType {
     Long,
     Float,
     Double,
+    /// An object, with its fully qualified class name, e.g. `java.lang.String`
     Object(String),
+    /// An array of the element type
     Array(Box<ArgumentType>),
 }
 
 impl ArgumentType {
-    pub fn from_signature_type(i: &str) -> Option<Self> {
-        match i {
-            "B" => Some(Self::Byte),
-            "Z" => Some(Self::Boolean),
-            "J" => Some(Self::Long),
-            "I" => Some(Self::Int),
-            "F" => Some(Self::Float),
-            "D" => Some(Self::Double),
-            "S" => Some(Self::Short),
-            "C" => Some(Self::Char),
-            _ => None
-        }
-    }
-
-    pub fn new(env: &JNIEnv<'_>, name: String) -> JResult<ArgumentType> {
-        match name.as_str() {
-            "boolean" => Ok(Self::Boolean),
-            "byte" => Ok(Self::Byte),
-            "char" => Ok(Self::Char),
-            "short" => Ok(Self::Short),
-            "int" => Ok(Self::Int),
-            "long" => Ok(Self::Long),
-            "float" => Ok(Self::Float),
-            "double" => Ok(Self::Double),
-            _ => {
-                let class = Class::for_name(env, &name)?;
-                let is_array = env.call_method(class.class.into_inner(), "isArray", "()Z", &[])?.z()?;
-                if is_array {
-                    let this = Self::from_signature_type(&name).unwrap_or(ArgumentType::Object(name));
-                    Ok(Self::Array(Box::new(this)))
-                } else {
-                    Ok(Self::Object(name))
+    /// The fully qualified name of the class, or of the element class for arrays.
+    /// `None` for primitives
+    pub fn class_name(&self) -> Option<String> {
+        match self {
+            Self::Object(name) => Some(name.clone()),
+            Self::Array(element) => element.class_name(),
+            _ => None,
+        }
+    }
+
+    /// Resolve the type from the name returned by `Class.getName()`, e.g. `int`, `java.lang.String` or `[[F` for `float[][]`
+    pub fn from_class_name(name: &str) -> JResult<Self> {
+        // Array classes are named by their descriptor, with dots rather than slashes
+        if name.starts_with('[') {
+            return classfile::parse_field_descriptor(&name.replace('.', "/"));
+        }
+
+        Ok(match name {
+            "boolean" => Self::Boolean,
+            "byte" => Self::Byte,
+            "char" => Self::Char,
+            "short" => Self::Short,
+            "int" => Self::Int,
+            "long" => Self::Long,
+            "float" => Self::Float,
+            "double" => Self::Double,
+            _ => Self::Object(name.to_string()),
+        })
+    }
+}
+
+/// The static field holding the instance of a Scala `object`
+pub const SCALA_MODULE_FIELD: &str = "MODULE$";
+
+/// Whether a class is the class of a Scala `object`, which is named after the object with a `$` appended, e.g. `com.foo.Bar$`
+pub fn is_scala_object(class_name: &str) -> bool {
+    class_name.ends_with('$')
+}
+
+/// Find the static field holding the only instance of a singleton, i.e. `INSTANCE` of a Kotlin `object` or `MODULE$`
+/// of a Scala `object`, with the type of the class itself. `fields` are the names and types of the static fields
+pub fn find_instance_field(class_name: &str, fields: &[(String, String)]) -> Option<String> {
+    fields.iter()
+        .find(|(name, field_type)| [kotlin::INSTANCE_FIELD, SCALA_MODULE_FIELD].contains(&name.as_str()) && field_type == class_name)
+        .map(|(name, _)| name.clone())
+}
+
+/// Remove overridden methods, keeping one method per name and argument list. The method declared
+/// by `class_name` itself is preferred, after that a concrete method is preferred over an abstract one
+pub fn deduplicate_overrides(methods: Vec<MethodEntry>, class_name: &str) -> Vec<MethodEntry> {
+    let mut deduplicated: Vec<MethodEntry> = Vec::with_capacity(methods.len());
+    for method in methods {
+        let existing = deduplicated.iter_mut()
+            .find(|x| x.name == method.name && ArgumentType::to_jni_signature(&x.arguments) == ArgumentType::to_jni_signature(&method.arguments));
+
+        match existing {
+            Some(existing) => {
+                let priority = |x: &MethodEntry| (x.declaring_class == class_name, !x.is_abstract);
+                if priority(&method) > priority(existing) {
+                    *existing = method;
                 }
-            }
+            },
+            None => deduplicated.push(method),
         }
     }
+
+    deduplicated
 }
 
-fn get_methods(env: &JNIEnv<'_>, class: &Class<'_>) -> JResult<Vec<MethodEntry>> {
-    let methods = env.call_method(class.class.into_inner(), "getDeclaredMethods", "()[Ljava/lang/reflect/Method;", &[])?.l()?;
-    let len = env.get_array_length(methods.into_inner())?;
-    let methods = (0..len).into_iter()
-        .map(|idx| Ok(env.get_object_array_element(methods.into_inner(), idx)?))
-        .collect::<JResult<Vec<_>>>()?
-        .into_iter()
-        .map(|object| Ok(Object::new(env, object, Class::Method(env)?)))
-        .collect::<JResult<Vec<_>>>()?
-        .into_iter()
-        .map(|object| Ok(MethodEntry::new(env, object)?))
-        .collect::<JResult<Vec<_>>>()?;
+/// Fill in the `nested_classes` of the classes in the tree from the `enclosing_class` of their nested classes
+pub fn link_nested_classes(tree: &mut [ClassEntry]) {
+    let positions = tree.iter()
+        .enumerate()
+        .map(|(idx, class)| (class.name.clone(), idx))
+        .collect::<HashMap<_, _>>();
+
+    for idx in 0..tree.len() {
+        let enclosing = tree[idx].enclosing_class.as_ref().and_then(|x| positions.get(x));
+        if let Some(&enclosing) = enclosing {
+            let name = tree[idx].name.clone();
+            tree[enclosing].nested_classes.push(name);
+        }
+    }
+}
+
+/// Find the single abstract method of a functional interface. `methods` are the instance methods of the interface
+/// and its superinterfaces. Abstract methods overridden by a default method do not count, nor do the public methods
+/// of `java.lang.Object`, which every implementation inherits
+pub fn find_functional_method(methods: Vec<MethodEntry>, class_name: &str) -> Option<MethodEntry> {
+    let is_object_method = |x: &MethodEntry| matches!(
+        (x.name.as_str(), ArgumentType::to_jni_signature(&x.arguments).as_str()),
+        ("equals", "Ljava/lang/Object;") | ("hashCode", "") | ("toString", "")
+    );
+
+    let mut abstract_methods = deduplicate_overrides(methods, class_name).into_iter()
+        .filter(|x| x.is_abstract && !x.is_static && !is_object_method(x));
+
+    match (abstract_methods.next(), abstract_methods.next()) {
+        (Some(method), None) => Some(method),
+        _ => None,
+    }
+}
+
+/// Get the compile-time constants declared by a class. Reflection would initialize the class to read their values,
+/// so the values are read from the class file instead
+fn get_constants(env: &JNIEnv<'_>, classpath: &ClassPath, fields: &[FieldDescription], class_name: &str, config: &Parser) -> JResult<Vec<ConstantEntry>> {
+    let candidates = fields.iter()
+        .filter(|x| x.modifiers & (ACC_STATIC | ACC_FINAL) == ACC_STATIC | ACC_FINAL && Visibility::from_access_flags(x.modifiers).is_included(config))
+        .collect::<Vec<_>>();
+
+    // Avoid reading the class file of the many classes without constants
+    if candidates.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let bytes = match classpath.read_class_file(env, class_name)? {
+        Some(bytes) => bytes,
+        None => return Ok(Vec::new()),
+    };
+    let class_file = ClassFile::parse(&bytes)?;
+
+    let mut constants = Vec::new();
+    for candidate in candidates {
+        let field = match class_file.fields.iter().find(|x| x.name == candidate.name) {
+            Some(field) => field,
+            None => continue,
+        };
+
+        if let Some(value) = class_file.constant_value(field)? {
+            constants.push(ConstantEntry {
+                name: candidate.name.clone(),
+                value,
+                deprecation: candidate.deprecation.clone(),
+            });
+        }
+    }
+
+    Ok(constants)
+}
 
-    Ok(methods)
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nullability_from_annotations() {
+        assert_eq!(Nullability::Nullable, Nullability::from_annotation_names(&["javax.annotation.Nullable"]));
+        assert_eq!(Nullability::NonNull, Nullability::from_annotation_names(&["org.jetbrains.annotations.NotNull", "java.lang.Deprecated"]));
+        assert_eq!(Nullability::Nullable, Nullability::from_annotation_names(&["lombok.NonNull", "org.jspecify.annotations.Nullable"]));
+        assert_eq!(Nullability::Unknown, Nullability::from_annotation_names(&["java.lang.Deprecated"]));
+        assert_eq!(Nullability::Unknown, Nullability::from_annotation_names::<&str>(&[]));
+    }
+
+    fn method(name: &str, declaring_class: &str, arguments: Vec<ArgumentType>, is_abstract: bool) -> MethodEntry {
+        MethodEntry {
+            name: name.to_string(),
+            is_static: false,
+            visibility: Visibility::Public,
+            is_abstract,
+            is_default: false,
+            deprecation: None,
+            argument_names: vec![None; arguments.len()],
+            argument_nullability: vec![Nullability::Unknown; arguments.len()],
+            arguments,
+            return_type: None,
+            declaring_class: declaring_class.to_string(),
+            type_parameters: Vec::new(),
+            generic_arguments: Vec::new(),
+            generic_return_type: None,
+            exceptions: Vec::new(),
+            return_nullability: Nullability::Unknown,
+            javadoc: None,
+        }
+    }
+
+    #[test]
+    fn nested_classes() {
+        let class = |name: &str, enclosing_class: Option<&str>| ClassEntry {
+            name: name.to_string(),
+            simple_name: name.rsplit(['.', '$']).next().unwrap().to_string(),
+            enclosing_class: enclosing_class.map(str::to_string),
+            nested_classes: Vec::new(),
+            class_type: ClassType::Class,
+            visibility: Visibility::Public,
+            is_abstract: false,
+            deprecation: None,
+            methods: Vec::new(),
+            interfaces: Vec::new(),
+            enum_constants: Vec::new(),
+            type_parameters: Vec::new(),
+            generic_superclass: None,
+            superclasses: Vec::new(),
+            permitted_subclasses: Vec::new(),
+            annotation_elements: Vec::new(),
+            is_inner_class: false,
+            constructors: Vec::new(),
+            instance_field: None,
+            companion_field: None,
+            properties: Vec::new(),
+            constants: Vec::new(),
+            fields: Vec::new(),
+            functional_method: None,
+            javadoc: None,
+        };
+
+        let mut tree = vec![
+            class("com.foo.Bar$Baz", Some("com.foo.Bar")),
+            class("com.foo.Bar", None),
+            class("com.foo.Bar$Baz$Quix", Some("com.foo.Bar$Baz")),
+            class("com.foo.Other$Nested", Some("com.foo.Other")),
+        ];
+        link_nested_classes(&mut tree);
+
+        assert_eq!(vec!["com.foo.Bar$Baz$Quix"], tree[0].nested_classes);
+        assert_eq!(vec!["com.foo.Bar$Baz"], tree[1].nested_classes);
+        assert!(tree[2].nested_classes.is_empty());
+    }
+
+    #[test]
+    fn functional_method() {
+        let mut equals = method("equals", "com.foo.Predicate", vec![ArgumentType::Object("java.lang.Object".to_string())], true);
+        equals.return_type = Some(ArgumentType::Boolean);
+        let methods = vec![
+            method("test", "com.foo.Predicate", vec![ArgumentType::Object("java.lang.Object".to_string())], true),
+            equals,
+            method("negate", "com.foo.Predicate", vec![], false),
+        ];
+        assert_eq!("test", find_functional_method(methods, "com.foo.Predicate").unwrap().name);
+
+        // A default method in the subinterface implements `run`
+        let methods = vec![
+            method("run", "com.foo.Task", vec![], true),
+            method("run", "com.foo.Job", vec![], false),
+            method("cancel", "com.foo.Job", vec![], true),
+        ];
+        assert_eq!("cancel", find_functional_method(methods, "com.foo.Job").unwrap().name);
+
+        let methods = vec![
+            method("run", "com.foo.Task", vec![], true),
+            method("cancel", "com.foo.Task", vec![], true),
+        ];
+        assert!(find_functional_method(methods, "com.foo.Task").is_none());
+    }
+
+    #[test]
+    fn instance_field() {
+        let fields = [
+            ("MODULE$".to_string(), "com.foo.Bar$".to_string()),
+            ("INSTANCE".to_string(), "com.foo.Other".to_string()),
+        ];
+        assert_eq!(Some("MODULE$".to_string()), find_instance_field("com.foo.Bar$", &fields));
+        assert_eq!(None, find_instance_field("com.foo.Bar", &fields));
+        assert!(is_scala_object("com.foo.Bar$"));
+    }
+
+    #[test]
+    fn referenced_types() {
+        let mut get = method("get", "com.foo.Bar", vec![ArgumentType::Array(Box::new(ArgumentType::Object("com.foo.Key".to_string()))), ArgumentType::Int], false);
+        get.return_type = Some(ArgumentType::Object("java.util.Map".to_string()));
+        get.generic_return_type = Some("java.util.Map<java.lang.String, ? extends org.slf4j.Logger>".to_string());
+
+        let class = ClassEntry {
+            name: "com.foo.Bar".to_string(),
+            simple_name: "Bar".to_string(),
+            enclosing_class: None,
+            nested_classes: Vec::new(),
+            class_type: ClassType::Class,
+            visibility: Visibility::Public,
+            is_abstract: false,
+            deprecation: None,
+            methods: vec![get],
+            interfaces: vec!["com.foo.Api".to_string()],
+            enum_constants: Vec::new(),
+            type_parameters: Vec::new(),
+            generic_superclass: None,
+            superclasses: vec!["java.lang.Object".to_string()],
+            permitted_subclasses: Vec::new(),
+            annotation_elements: Vec::new(),
+            is_inner_class: false,
+            constructors: Vec::new(),
+            instance_field: None,
+            companion_field: None,
+            properties: Vec::new(),
+            constants: Vec::new(),
+            fields: Vec::new(),
+            functional_method: None,
+            javadoc: None,
+        };
+
+        let types = class.referenced_types();
+        for name in ["java.lang.Object", "com.foo.Api", "com.foo.Key", "java.util.Map", "java.lang.String", "org.slf4j.Logger"] {
+            assert!(types.iter().any(|x| x == name), "{} is not referenced", name);
+        }
+    }
+
+    #[test]
+    fn deduplicate_overrides_prefers_own_class() {
+        let methods = vec![
+            method("toString", "java.lang.Object", vec![], false),
+            method("toString", "com.foo.Bar", vec![], false),
+            method("add", "com.foo.Bar", vec![ArgumentType::Int], false),
+            method("add", "com.foo.Bar", vec![ArgumentType::Float], false),
+        ];
+        let output = deduplicate_overrides(methods, "com.foo.Bar");
+
+        assert_eq!(3, output.len());
+        assert_eq!("com.foo.Bar", output[0].declaring_class);
+    }
+
+    #[test]
+    fn deduplicate_overrides_prefers_concrete() {
+        let methods = vec![
+            method("size", "com.foo.Collection", vec![], true),
+            method("size", "com.foo.AbstractCollection", vec![], false),
+        ];
+        let output = deduplicate_overrides(methods, "com.foo.List");
+
+        assert_eq!(1, output.len());
+        assert_eq!("com.foo.AbstractCollection", output[0].declaring_class);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,944 @@
+//! A parser for the Java class file format, covering the parts needed to describe the API of a class:
+//! the constant pool, members, and the attributes holding generic signatures, exceptions,
+//! parameter names, annotations and nesting information
+
+use anyhow::anyhow;
+use crate::class_tree::{ArgumentType, ConstantValue, TypeParameter};
+use crate::JResult;
+
+pub const ACC_PUBLIC: u16 = 0x0001;
+pub const ACC_PRIVATE: u16 = 0x0002;
+pub const ACC_PROTECTED: u16 = 0x0004;
+pub const ACC_STATIC: u16 = 0x0008;
+pub const ACC_FINAL: u16 = 0x0010;
+pub const ACC_BRIDGE: u16 = 0x0040;
+pub const ACC_INTERFACE: u16 = 0x0200;
+pub const ACC_ABSTRACT: u16 = 0x0400;
+pub const ACC_SYNTHETIC: u16 = 0x1000;
+pub const ACC_ANNOTATION: u16 = 0x2000;
+pub const ACC_ENUM: u16 = 0x4000;
+
+const MAGIC: u32 = 0xCAFEBABE;
+
+#[derive(Debug)]
+pub struct ClassFile {
+    constant_pool: Vec<Constant>,
+    pub access_flags: u16,
+    /// The binary name of the class, e.g. `com.foo.Bar$Baz`
+    pub name: String,
+    /// The binary name of the superclass. `None` for `java.lang.Object`
+    pub super_class: Option<String>,
+    pub interfaces: Vec<String>,
+    pub fields: Vec<Member>,
+    pub methods: Vec<Member>,
+    pub attributes: Vec<Attribute>,
+}
+
+/// A field or method
+#[derive(Debug)]
+pub struct Member {
+    pub access_flags: u16,
+    pub name: String,
+    pub descriptor: String,
+    pub attributes: Vec<Attribute>,
+}
+
+#[derive(Debug)]
+pub struct Attribute {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+enum Constant {
+    Utf8(String),
+    Integer(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    Class(u16),
+    String(u16),
+    /// A constant not needed to describe the API, e.g. a method reference
+    Other,
+}
+
+/// An entry of the `InnerClasses` attribute
+#[derive(Debug)]
+pub struct InnerClass {
+    /// The binary name of the nested class
+    pub name: String,
+    /// The binary name of the enclosing class. `None` for local and anonymous classes
+    pub outer_name: Option<String>,
+    /// The simple name as declared in the source. `None` for anonymous classes
+    pub simple_name: Option<String>,
+    /// The access flags as declared in the source, these include e.g. `ACC_STATIC` and `ACC_PRIVATE`
+    pub access_flags: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    /// The binary name of the annotation type, e.g. `java.lang.Deprecated`
+    pub type_name: String,
+    pub values: Vec<(String, ElementValue)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ElementValue {
+    Boolean(bool),
+    Byte(i8),
+    Char(u16),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    /// An enum constant, with the binary name of the enum and the name of the constant
+    Enum(#[allow(dead_code)] String, String),
+    /// A class literal, with the Java name of the type, e.g. `int` or `java.lang.String[]`
+    Class(String),
+    Annotation(Annotation),
+    Array(Vec<ElementValue>),
+}
+
+/// Where a type annotation is located, only the locations needed to determine nullability are distinguished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeAnnotationTarget {
+    /// The return type of a method
+    Return,
+    /// The type of the formal parameter with the index
+    Parameter(u8),
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeAnnotation {
+    pub target: TypeAnnotationTarget,
+    /// Whether the annotation is on a nested type, like a type argument or array component,
+    /// rather than on the annotated type itself
+    pub is_nested: bool,
+    pub annotation: Annotation,
+}
+
+impl ClassFile {
+    pub fn parse(bytes: &[u8]) -> JResult<Self> {
+        let mut reader = Reader::new(bytes);
+        if reader.u32()? != MAGIC {
+            return Err(anyhow!("Not a class file"));
+        }
+
+        // Minor and major version
+        reader.skip(4)?;
+
+        let constant_pool = parse_constant_pool(&mut reader)?;
+        let access_flags = reader.u16()?;
+        let this_class = reader.u16()?;
+        let super_class = reader.u16()?;
+
+        let mut this = Self {
+            constant_pool,
+            access_flags,
+            name: String::new(),
+            super_class: None,
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes: Vec::new(),
+        };
+
+        this.name = this.class_name(this_class)?;
+        this.super_class = match super_class {
+            0 => None,
+            idx => Some(this.class_name(idx)?),
+        };
+
+        let interface_count = reader.u16()?;
+        this.interfaces = (0..interface_count)
+            .map(|_| this.class_name(reader.u16()?))
+            .collect::<JResult<Vec<_>>>()?;
+
+        this.fields = this.parse_members(&mut reader)?;
+        this.methods = this.parse_members(&mut reader)?;
+        this.attributes = this.parse_attributes(&mut reader)?;
+
+        Ok(this)
+    }
+
+    fn parse_members(&self, reader: &mut Reader<'_>) -> JResult<Vec<Member>> {
+        let count = reader.u16()?;
+        (0..count)
+            .map(|_| {
+                let access_flags = reader.u16()?;
+                let name = self.utf8(reader.u16()?)?.to_string();
+                let descriptor = self.utf8(reader.u16()?)?.to_string();
+                let attributes = self.parse_attributes(reader)?;
+
+                Ok(Member {
+                    access_flags,
+                    name,
+                    descriptor,
+                    attributes,
+                })
+            })
+            .collect()
+    }
+
+    fn parse_attributes(&self, reader: &mut Reader<'_>) -> JResult<Vec<Attribute>> {
+        let count = reader.u16()?;
+        (0..count)
+            .map(|_| {
+                let name = self.utf8(reader.u16()?)?.to_string();
+                let length = reader.u32()? as usize;
+                let data = reader.bytes(length)?.to_vec();
+
+                Ok(Attribute {
+                    name,
+                    data,
+                })
+            })
+            .collect()
+    }
+
+    fn constant(&self, idx: u16) -> JResult<&Constant> {
+        self.constant_pool.get(idx as usize)
+            .ok_or_else(|| anyhow!("Constant pool index {idx} out of bounds in {}", self.name))
+    }
+
+    fn utf8(&self, idx: u16) -> JResult<&str> {
+        match self.constant(idx)? {
+            Constant::Utf8(value) => Ok(value),
+            constant => Err(anyhow!("Expected a Utf8 constant at index {idx}, found {constant:?}")),
+        }
+    }
+
+    /// The binary name of a class constant, e.g. `com.foo.Bar$Baz`
+    fn class_name(&self, idx: u16) -> JResult<String> {
+        match self.constant(idx)? {
+            Constant::Class(name_idx) => Ok(self.utf8(*name_idx)?.replace('/', ".")),
+            constant => Err(anyhow!("Expected a Class constant at index {idx}, found {constant:?}")),
+        }
+    }
+
+    /// The class attribute with the name, if present
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        find_attribute(&self.attributes, name)
+    }
+
+    /// The value of the `Signature` attribute in `attributes`, if present
+    pub fn signature(&self, attributes: &[Attribute]) -> JResult<Option<String>> {
+        match find_attribute(attributes, "Signature") {
+            Some(attribute) => {
+                let idx = Reader::new(&attribute.data).u16()?;
+                Ok(Some(self.utf8(idx)?.to_string()))
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// The value of the `ConstantValue` attribute of a field, as the type of the field.
+    /// `None` if the field is not a compile-time constant
+    pub fn constant_value(&self, field: &Member) -> JResult<Option<ConstantValue>> {
+        let attribute = match find_attribute(&field.attributes, "ConstantValue") {
+            Some(attribute) => attribute,
+            None => return Ok(None),
+        };
+
+        let idx = Reader::new(&attribute.data).u16()?;
+        let value = match (field.descriptor.as_str(), self.constant(idx)?) {
+            ("Z", Constant::Integer(value)) => ConstantValue::Boolean(*value != 0),
+            ("B", Constant::Integer(value)) => ConstantValue::Byte(*value as i8),
+            ("C", Constant::Integer(value)) => ConstantValue::Char(*value as u16),
+            ("S", Constant::Integer(value)) => ConstantValue::Short(*value as i16),
+            ("I", Constant::Integer(value)) => ConstantValue::Int(*value),
+            ("J", Constant::Long(value)) => ConstantValue::Long(*value),
+            ("F", Constant::Float(value)) => ConstantValue::Float(*value),
+            ("D", Constant::Double(value)) => ConstantValue::Double(*value),
+            ("Ljava/lang/String;", Constant::String(idx)) => ConstantValue::String(self.utf8(*idx)?.to_string()),
+            (descriptor, constant) => return Err(anyhow!("Unexpected constant {constant:?} for field {} of type {descriptor} in {}", field.name, self.name)),
+        };
+
+        Ok(Some(value))
+    }
+
+    /// The binary names of the classes listed in an attribute consisting of a list of class constants,
+    /// like `Exceptions` and `PermittedSubclasses`. Empty if the attribute is not present
+    pub fn class_list(&self, attributes: &[Attribute], name: &str) -> JResult<Vec<String>> {
+        let attribute = match find_attribute(attributes, name) {
+            Some(attribute) => attribute,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut reader = Reader::new(&attribute.data);
+        let count = reader.u16()?;
+        (0..count)
+            .map(|_| self.class_name(reader.u16()?))
+            .collect()
+    }
+
+    /// The parameter names from the `MethodParameters` attribute.
+    /// Empty if the attribute is not present, i.e. if the class was not compiled with `-parameters`
+    pub fn parameter_names(&self, attributes: &[Attribute]) -> JResult<Vec<Option<String>>> {
+        let attribute = match find_attribute(attributes, "MethodParameters") {
+            Some(attribute) => attribute,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut reader = Reader::new(&attribute.data);
+        let count = reader.u8()?;
+        (0..count)
+            .map(|_| {
+                let name_idx = reader.u16()?;
+                // Access flags
+                reader.skip(2)?;
+
+                match name_idx {
+                    0 => Ok(None),
+                    idx => Ok(Some(self.utf8(idx)?.to_string())),
+                }
+            })
+            .collect()
+    }
+
+    /// The entries of the `InnerClasses` attribute
+    pub fn inner_classes(&self) -> JResult<Vec<InnerClass>> {
+        let attribute = match self.attribute("InnerClasses") {
+            Some(attribute) => attribute,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut reader = Reader::new(&attribute.data);
+        let count = reader.u16()?;
+        (0..count)
+            .map(|_| {
+                let name = self.class_name(reader.u16()?)?;
+                let outer_name = match reader.u16()? {
+                    0 => None,
+                    idx => Some(self.class_name(idx)?),
+                };
+                let simple_name = match reader.u16()? {
+                    0 => None,
+                    idx => Some(self.utf8(idx)?.to_string()),
+                };
+                let access_flags = reader.u16()?;
+
+                Ok(InnerClass {
+                    name,
+                    outer_name,
+                    simple_name,
+                    access_flags,
+                })
+            })
+            .collect()
+    }
+
+    /// The binary name of the class declaring a local or anonymous class, from the `EnclosingMethod` attribute
+    pub fn enclosing_method_class(&self) -> JResult<Option<String>> {
+        match self.attribute("EnclosingMethod") {
+            Some(attribute) => Ok(Some(self.class_name(Reader::new(&attribute.data).u16()?)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The annotations in the `RuntimeVisibleAnnotations` and `RuntimeInvisibleAnnotations` attributes.
+    /// Annotations with `CLASS` retention, which many nullability annotations have, are in the latter
+    pub fn annotations(&self, attributes: &[Attribute]) -> JResult<Vec<Annotation>> {
+        let mut annotations = Vec::new();
+        for name in ["RuntimeVisibleAnnotations", "RuntimeInvisibleAnnotations"] {
+            if let Some(attribute) = find_attribute(attributes, name) {
+                let mut reader = Reader::new(&attribute.data);
+                let count = reader.u16()?;
+                for _ in 0..count {
+                    annotations.push(self.parse_annotation(&mut reader)?);
+                }
+            }
+        }
+
+        Ok(annotations)
+    }
+
+    /// The annotations of each parameter, from the `RuntimeVisibleParameterAnnotations` and
+    /// `RuntimeInvisibleParameterAnnotations` attributes. These may not include implicit parameters
+    pub fn parameter_annotations(&self, attributes: &[Attribute]) -> JResult<Vec<Vec<Annotation>>> {
+        let mut parameters: Vec<Vec<Annotation>> = Vec::new();
+        for name in ["RuntimeVisibleParameterAnnotations", "RuntimeInvisibleParameterAnnotations"] {
+            if let Some(attribute) = find_attribute(attributes, name) {
+                let mut reader = Reader::new(&attribute.data);
+                let count = reader.u8()? as usize;
+                if parameters.len() < count {
+                    parameters.resize(count, Vec::new());
+                }
+
+                for parameter in parameters.iter_mut().take(count) {
+                    let annotation_count = reader.u16()?;
+                    for _ in 0..annotation_count {
+                        parameter.push(self.parse_annotation(&mut reader)?);
+                    }
+                }
+            }
+        }
+
+        Ok(parameters)
+    }
+
+    /// The annotations in the `RuntimeVisibleTypeAnnotations` and `RuntimeInvisibleTypeAnnotations` attributes
+    pub fn type_annotations(&self, attributes: &[Attribute]) -> JResult<Vec<TypeAnnotation>> {
+        let mut annotations = Vec::new();
+        for name in ["RuntimeVisibleTypeAnnotations", "RuntimeInvisibleTypeAnnotations"] {
+            if let Some(attribute) = find_attribute(attributes, name) {
+                let mut reader = Reader::new(&attribute.data);
+                let count = reader.u16()?;
+                for _ in 0..count {
+                    annotations.push(self.parse_type_annotation(&mut reader)?);
+                }
+            }
+        }
+
+        Ok(annotations)
+    }
+
+    /// The value of the `AnnotationDefault` attribute of an annotation element, if present
+    pub fn annotation_default(&self, attributes: &[Attribute]) -> JResult<Option<ElementValue>> {
+        match find_attribute(attributes, "AnnotationDefault") {
+            Some(attribute) => Ok(Some(self.parse_element_value(&mut Reader::new(&attribute.data))?)),
+            None => Ok(None),
+        }
+    }
+
+    fn parse_annotation(&self, reader: &mut Reader<'_>) -> JResult<Annotation> {
+        let type_name = descriptor_type_name(self.utf8(reader.u16()?)?)?;
+        let count = reader.u16()?;
+        let values = (0..count)
+            .map(|_| {
+                let name = self.utf8(reader.u16()?)?.to_string();
+                let value = self.parse_element_value(reader)?;
+                Ok((name, value))
+            })
+            .collect::<JResult<Vec<_>>>()?;
+
+        Ok(Annotation {
+            type_name,
+            values,
+        })
+    }
+
+    fn parse_type_annotation(&self, reader: &mut Reader<'_>) -> JResult<TypeAnnotation> {
+        let target_type = reader.u8()?;
+        let target = match target_type {
+            0x14 => TypeAnnotationTarget::Return,
+            0x16 => TypeAnnotationTarget::Parameter(reader.u8()?),
+            _ => {
+                // Skip the target_info of the other target types
+                match target_type {
+                    0x00 | 0x01 => reader.skip(1)?,
+                    0x10 | 0x11 | 0x12 | 0x17 | 0x42..=0x46 => reader.skip(2)?,
+                    0x13 | 0x15 => {},
+                    0x40 | 0x41 => {
+                        let length = reader.u16()? as usize;
+                        reader.skip(length * 6)?;
+                    },
+                    0x47..=0x4B => reader.skip(3)?,
+                    _ => return Err(anyhow!("Unknown type annotation target type {target_type:#x} in {}", self.name)),
+                }
+
+                TypeAnnotationTarget::Other
+            },
+        };
+
+        let path_length = reader.u8()? as usize;
+        reader.skip(path_length * 2)?;
+        let annotation = self.parse_annotation(reader)?;
+
+        Ok(TypeAnnotation {
+            target,
+            is_nested: path_length > 0,
+            annotation,
+        })
+    }
+
+    fn parse_element_value(&self, reader: &mut Reader<'_>) -> JResult<ElementValue> {
+        let tag = reader.u8()?;
+        let value = match tag {
+            b'B' | b'C' | b'I' | b'S' | b'Z' => {
+                let value = match self.constant(reader.u16()?)? {
+                    Constant::Integer(value) => *value,
+                    constant => return Err(anyhow!("Expected an Integer constant, found {constant:?}")),
+                };
+
+                match tag {
+                    b'B' => ElementValue::Byte(value as i8),
+                    b'C' => ElementValue::Char(value as u16),
+                    b'S' => ElementValue::Short(value as i16),
+                    b'Z' => ElementValue::Boolean(value != 0),
+                    _ => ElementValue::Int(value),
+                }
+            },
+            b'J' | b'F' | b'D' | b's' => match (tag, self.constant(reader.u16()?)?) {
+                (b'J', Constant::Long(value)) => ElementValue::Long(*value),
+                (b'F', Constant::Float(value)) => ElementValue::Float(*value),
+                (b'D', Constant::Double(value)) => ElementValue::Double(*value),
+                (b's', Constant::Utf8(value)) => ElementValue::String(value.clone()),
+                (_, constant) => return Err(anyhow!("Unexpected constant {constant:?} for element value tag {}", tag as char)),
+            },
+            b'e' => {
+                let type_name = descriptor_type_name(self.utf8(reader.u16()?)?)?;
+                let name = self.utf8(reader.u16()?)?.to_string();
+                ElementValue::Enum(type_name, name)
+            },
+            b'c' => {
+                let descriptor = self.utf8(reader.u16()?)?;
+                let type_name = match descriptor {
+                    "V" => "void".to_string(),
+                    descriptor => descriptor_type_name(descriptor)?,
+                };
+
+                ElementValue::Class(type_name)
+            },
+            b'@' => ElementValue::Annotation(self.parse_annotation(reader)?),
+            b'[' => {
+                let count = reader.u16()?;
+                let values = (0..count)
+                    .map(|_| self.parse_element_value(reader))
+                    .collect::<JResult<Vec<_>>>()?;
+                ElementValue::Array(values)
+            },
+            _ => return Err(anyhow!("Unknown element value tag {} in {}", tag as char, self.name)),
+        };
+
+        Ok(value)
+    }
+}
+
+fn find_attribute<'a>(attributes: &'a [Attribute], name: &str) -> Option<&'a Attribute> {
+    attributes.iter().find(|x| x.name == name)
+}
+
+fn parse_constant_pool(reader: &mut Reader<'_>) -> JResult<Vec<Constant>> {
+    let count = reader.u16()? as usize;
+    // Index 0 is unused
+    let mut constants = vec![Constant::Other];
+    while constants.len() < count {
+        let tag = reader.u8()?;
+        let constant = match tag {
+            1 => {
+                let length = reader.u16()? as usize;
+                Constant::Utf8(decode_modified_utf8(reader.bytes(length)?))
+            },
+            3 => Constant::Integer(reader.u32()? as i32),
+            4 => Constant::Float(f32::from_bits(reader.u32()?)),
+            5 => Constant::Long(reader.u64()? as i64),
+            6 => Constant::Double(f64::from_bits(reader.u64()?)),
+            7 => Constant::Class(reader.u16()?),
+            8 => Constant::String(reader.u16()?),
+            // Module and Package
+            19 | 20 => {
+                reader.skip(2)?;
+                Constant::Other
+            },
+            // MethodType
+            16 => {
+                reader.skip(2)?;
+                Constant::Other
+            },
+            // MethodHandle
+            15 => {
+                reader.skip(3)?;
+                Constant::Other
+            },
+            // Field-, Method- and InterfaceMethodref, NameAndType, Dynamic and InvokeDynamic
+            9 | 10 | 11 | 12 | 17 | 18 => {
+                reader.skip(4)?;
+                Constant::Other
+            },
+            _ => return Err(anyhow!("Unknown constant pool tag {tag}")),
+        };
+
+        // Longs and doubles take up two entries
+        let is_wide = matches!(constant, Constant::Long(_) | Constant::Double(_));
+        constants.push(constant);
+        if is_wide {
+            constants.push(Constant::Other);
+        }
+    }
+
+    Ok(constants)
+}
+
+/// Decode the modified UTF-8 used by class files. It encodes `\0` in two bytes,
+/// and supplementary characters as surrogate pairs of three bytes each
+fn decode_modified_utf8(bytes: &[u8]) -> String {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let byte = bytes[idx] as u16;
+        let continuation = |offset: usize| bytes.get(idx + offset).map(|x| (*x & 0x3F) as u16).unwrap_or(0);
+        if byte & 0x80 == 0 {
+            units.push(byte);
+            idx += 1;
+        } else if byte & 0xE0 == 0xC0 {
+            units.push(((byte & 0x1F) << 6) | continuation(1));
+            idx += 2;
+        } else {
+            units.push(((byte & 0x0F) << 12) | (continuation(1) << 6) | continuation(2));
+            idx += 3;
+        }
+    }
+
+    String::from_utf16_lossy(&units)
+}
+
+/// Parse a field descriptor, e.g. `[Ljava/lang/String;`
+pub fn parse_field_descriptor(descriptor: &str) -> JResult<ArgumentType> {
+    let mut chars = descriptor.chars().peekable();
+    let argument_type = parse_descriptor_type(&mut chars)?;
+    match chars.next() {
+        None => Ok(argument_type),
+        Some(_) => Err(anyhow!("Trailing characters in field descriptor {descriptor}")),
+    }
+}
+
+/// Parse a method descriptor, e.g. `(I[J)Ljava/lang/String;`, to its argument types and return type.
+/// The return type is `None` for `void`
+pub fn parse_method_descriptor(descriptor: &str) -> JResult<(Vec<ArgumentType>, Option<ArgumentType>)> {
+    let mut chars = descriptor.chars().peekable();
+    if chars.next() != Some('(') {
+        return Err(anyhow!("Invalid method descriptor {descriptor}"));
+    }
+
+    let mut arguments = Vec::new();
+    while chars.peek() != Some(&')') {
+        arguments.push(parse_descriptor_type(&mut chars)?);
+    }
+    chars.next();
+
+    let return_type = match chars.peek() {
+        Some('V') => None,
+        _ => Some(parse_descriptor_type(&mut chars)?),
+    };
+
+    Ok((arguments, return_type))
+}
+
+fn parse_descriptor_type(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> JResult<ArgumentType> {
+    let argument_type = match chars.next() {
+        Some('Z') => ArgumentType::Boolean,
+        Some('B') => ArgumentType::Byte,
+        Some('C') => ArgumentType::Char,
+        Some('S') => ArgumentType::Short,
+        Some('I') => ArgumentType::Int,
+        Some('J') => ArgumentType::Long,
+        Some('F') => ArgumentType::Float,
+        Some('D') => ArgumentType::Double,
+        Some('[') => ArgumentType::Array(Box::new(parse_descriptor_type(chars)?)),
+        Some('L') => {
+            let name = chars.by_ref()
+                .take_while(|x| *x != ';')
+                .collect::<String>();
+            ArgumentType::Object(name.replace('/', "."))
+        },
+        c => return Err(anyhow!("Invalid descriptor type {c:?}")),
+    };
+
+    Ok(argument_type)
+}
+
+/// The Java name of a type, as returned by `Type.getTypeName()` for a class, e.g. `int[]` or `com.foo.Bar$Baz`
+pub fn type_name(argument_type: &ArgumentType) -> String {
+    match argument_type {
+        ArgumentType::Boolean => "boolean".into(),
+        ArgumentType::Byte => "byte".into(),
+        ArgumentType::Char => "char".into(),
+        ArgumentType::Short => "short".into(),
+        ArgumentType::Int => "int".into(),
+        ArgumentType::Long => "long".into(),
+        ArgumentType::Float => "float".into(),
+        ArgumentType::Double => "double".into(),
+        ArgumentType::Object(name) => name.clone(),
+        ArgumentType::Array(argument_type) => format!("{}[]", type_name(argument_type)),
+    }
+}
+
+/// The Java name of the type of a field descriptor
+fn descriptor_type_name(descriptor: &str) -> JResult<String> {
+    Ok(type_name(&parse_field_descriptor(descriptor)?))
+}
+
+/// A parsed generic class signature
+#[derive(Debug)]
+pub struct ClassSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    /// The superclass in Java notation, e.g. `java.util.AbstractList<E>`
+    pub superclass: String,
+}
+
+/// A parsed generic method signature, types are in Java notation
+#[derive(Debug)]
+pub struct MethodSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub arguments: Vec<String>,
+    /// `None` for `void`
+    pub return_type: Option<String>,
+}
+
+/// Parse a generic class signature, e.g. `<E:Ljava/lang/Object;>Ljava/util/AbstractList<TE;>;`
+pub fn parse_class_signature(signature: &str) -> JResult<ClassSignature> {
+    let mut parser = SignatureParser::new(signature);
+    let type_parameters = parser.type_parameters()?;
+    let superclass = parser.reference_type()?;
+
+    Ok(ClassSignature {
+        type_parameters,
+        superclass,
+    })
+}
+
+/// Parse a generic method signature, e.g. `<T:Ljava/lang/Object;>(Ljava/util/List<TT;>;)TT;`
+pub fn parse_method_signature(signature: &str) -> JResult<MethodSignature> {
+    let mut parser = SignatureParser::new(signature);
+    let type_parameters = parser.type_parameters()?;
+
+    parser.expect('(')?;
+    let mut arguments = Vec::new();
+    while parser.peek() != Some(')') {
+        arguments.push(parser.java_type()?);
+    }
+    parser.expect(')')?;
+
+    let return_type = match parser.peek() {
+        Some('V') => None,
+        _ => Some(parser.java_type()?),
+    };
+
+    Ok(MethodSignature {
+        type_parameters,
+        arguments,
+        return_type,
+    })
+}
+
+/// Parse a generic field signature, e.g. `Ljava/util/List<TT;>;`, to the type in Java notation
+pub fn parse_field_signature(signature: &str) -> JResult<String> {
+    SignatureParser::new(signature).java_type()
+}
+
+/// Parses generic signatures, producing type names in the notation of `Type.getTypeName()`
+struct SignatureParser<'a> {
+    signature: &'a str,
+    position: usize,
+}
+
+impl<'a> SignatureParser<'a> {
+    fn new(signature: &'a str) -> Self {
+        Self { signature, position: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.signature[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> JResult<()> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            c => Err(anyhow!("Expected '{expected}' at {} in signature {}, found {c:?}", self.position, self.signature)),
+        }
+    }
+
+    /// Read an identifier, up to the first character in `terminators`
+    fn identifier(&mut self, terminators: &[char]) -> &'a str {
+        let rest = &self.signature[self.position..];
+        let end = rest.find(terminators).unwrap_or(rest.len());
+        self.position += end;
+        &rest[..end]
+    }
+
+    fn type_parameters(&mut self) -> JResult<Vec<TypeParameter>> {
+        if self.peek() != Some('<') {
+            return Ok(Vec::new());
+        }
+        self.next();
+
+        let mut type_parameters = Vec::new();
+        while self.peek() != Some('>') {
+            let name = self.identifier(&[':']).to_string();
+            let mut bounds = Vec::new();
+            // The class bound may be empty if there are only interface bounds
+            while self.peek() == Some(':') {
+                self.next();
+                if !matches!(self.peek(), Some(':') | Some('>')) {
+                    bounds.push(self.reference_type()?);
+                }
+            }
+
+            bounds.retain(|x| x != "java.lang.Object");
+            type_parameters.push(TypeParameter {
+                name,
+                bounds,
+            });
+        }
+        self.next();
+
+        Ok(type_parameters)
+    }
+
+    fn java_type(&mut self) -> JResult<String> {
+        let primitive = match self.peek() {
+            Some('Z') => "boolean",
+            Some('B') => "byte",
+            Some('C') => "char",
+            Some('S') => "short",
+            Some('I') => "int",
+            Some('J') => "long",
+            Some('F') => "float",
+            Some('D') => "double",
+            _ => return self.reference_type(),
+        };
+
+        self.next();
+        Ok(primitive.to_string())
+    }
+
+    fn reference_type(&mut self) -> JResult<String> {
+        match self.next() {
+            Some('L') => self.class_type(),
+            Some('T') => {
+                let name = self.identifier(&[';']).to_string();
+                self.expect(';')?;
+                Ok(name)
+            },
+            Some('[') => Ok(format!("{}[]", self.java_type()?)),
+            c => Err(anyhow!("Invalid reference type {c:?} in signature {}", self.signature)),
+        }
+    }
+
+    /// Parse a class type signature after the leading `L`. Nested classes are separated by `$`,
+    /// if the enclosing class has type arguments these are included, e.g. `com.foo.Outer<T>$Inner`
+    fn class_type(&mut self) -> JResult<String> {
+        let mut name = self.identifier(&['<', '.', ';']).replace('/', ".");
+        loop {
+            if self.peek() == Some('<') {
+                self.next();
+                let mut arguments = Vec::new();
+                while self.peek() != Some('>') {
+                    let argument = match self.peek() {
+                        Some('*') => {
+                            self.next();
+                            "?".to_string()
+                        },
+                        Some('+') => {
+                            self.next();
+                            format!("? extends {}", self.reference_type()?)
+                        },
+                        Some('-') => {
+                            self.next();
+                            format!("? super {}", self.reference_type()?)
+                        },
+                        _ => self.reference_type()?,
+                    };
+                    arguments.push(argument);
+                }
+                self.next();
+
+                name = format!("{name}<{}>", arguments.join(", "));
+            }
+
+            match self.next() {
+                Some(';') => return Ok(name),
+                Some('.') => {
+                    let inner = self.identifier(&['<', '.', ';']);
+                    name = format!("{name}${inner}");
+                },
+                c => return Err(anyhow!("Unexpected {c:?} in class type of signature {}", self.signature)),
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn bytes(&mut self, length: usize) -> JResult<&'a [u8]> {
+        let bytes = self.bytes.get(self.position..self.position + length)
+            .ok_or_else(|| anyhow!("Unexpected end of class file at offset {}", self.position))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, length: usize) -> JResult<()> {
+        self.bytes(length).map(|_| ())
+    }
+
+    fn u8(&mut self) -> JResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> JResult<u16> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> JResult<u32> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> JResult<u64> {
+        Ok(u64::from_be_bytes(self.bytes(8)?.try_into()?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn method_descriptor() {
+        let (arguments, return_type) = parse_method_descriptor("(I[[JLjava/lang/String;)[Lcom/foo/Bar$Baz;").unwrap();
+        assert_eq!("I[[JLjava/lang/String;", ArgumentType::to_jni_signature(&arguments));
+        assert_eq!("com.foo.Bar$Baz[]", type_name(&return_type.unwrap()));
+
+        let (arguments, return_type) = parse_method_descriptor("()V").unwrap();
+        assert!(arguments.is_empty());
+        assert!(return_type.is_none());
+    }
+
+    #[test]
+    fn class_signature() {
+        let signature = parse_class_signature("<K:Ljava/lang/Object;V::Ljava/lang/Comparable<TV;>;>Ljava/util/AbstractMap<TK;TV;>;Ljava/io/Serializable;").unwrap();
+        assert_eq!("java.util.AbstractMap<K, V>", signature.superclass);
+        assert_eq!("K", signature.type_parameters[0].name);
+        assert!(signature.type_parameters[0].bounds.is_empty());
+        assert_eq!(vec!["java.lang.Comparable<V>".to_string()], signature.type_parameters[1].bounds);
+    }
+
+    #[test]
+    fn method_signature() {
+        let signature = parse_method_signature("<T:Ljava/lang/Number;>(Ljava/util/List<+TT;>;[TT;Ljava/util/Map<*-Ljava/lang/String;>;Lcom/foo/Outer<TT;>.Inner<[I>;Z)V^Ljava/io/IOException;").unwrap();
+        assert_eq!(vec!["java.lang.Number".to_string()], signature.type_parameters[0].bounds);
+        assert_eq!(
+            vec!["java.util.List<? extends T>", "T[]", "java.util.Map<?, ? super java.lang.String>", "com.foo.Outer<T>$Inner<int[]>", "boolean"],
+            signature.arguments
+        );
+        assert!(signature.return_type.is_none());
+    }
+
+    #[test]
+    fn field_signature() {
+        assert_eq!("java.util.Map<java.lang.String, T[]>", parse_field_signature("Ljava/util/Map<Ljava/lang/String;[TT;>;").unwrap());
+        assert_eq!("T", parse_field_signature("TT;").unwrap());
+    }
+
+    #[test]
+    fn modified_utf8() {
+        assert_eq!("a\0é€😀", decode_modified_utf8(&[0x61, 0xC0, 0x80, 0xC3, 0xA9, 0xE2, 0x82, 0xAC, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]));
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use anyhow::anyhow;
+use ejni::Class;
+use jni::JNIEnv;
+use jni::objects::{GlobalRef, JClass, JObject, JValue};
+use log::trace;
+use crate::{java_helpers, JResult};
+use crate::parser::jar;
+
+/// The Java helper describing classes, see [crate::parser::description]
+const DESCRIBER_CLASS: &str = "jrsgen/ClassDescriber";
+
+/// The jars to generate bindings for, loaded by their own classloader.
+/// The classloader is a global reference, so classes can be loaded from any attached thread
+pub struct ClassPath {
+    /// The jars and directories the classes are loaded from
+    paths: Vec<String>,
+    /// The classloader the classes are loaded with. It only has access to the JDK and the provided jars,
+    /// not to jrsgen's own dependencies on the system classpath
+    classloader: GlobalRef,
+    /// `jrsgen.ClassDescriber`, defined in the classloader
+    describer: GlobalRef,
+}
+
+impl ClassPath {
+    /// Create a ClassPath over the jars in `classpath`, loaded by their own `URLClassLoader`
+    pub fn new(env: &JNIEnv<'_>, classpath: &[String]) -> JResult<Self> {
+        let classloader = Self::create_classloader(env, classpath)?;
+        let describer = Self::define_describer(env, classloader)?;
+
+        Ok(Self {
+            paths: classpath.to_vec(),
+            classloader: env.new_global_ref(classloader)?,
+            describer: env.new_global_ref(describer)?,
+        })
+    }
+
+    /// Define `jrsgen.ClassDescriber` in `classloader`. It is embedded in the binary, rather than being loaded from a jar
+    fn define_describer<'a>(env: &JNIEnv<'a>, classloader: JObject<'a>) -> JResult<JClass<'a>> {
+        let (_, bytes) = java_helpers::JAVA_HELPERS.iter()
+            .find(|(name, _)| *name == DESCRIBER_CLASS)
+            .ok_or_else(|| anyhow!("jrsgen was built without its Java helpers, which the JVM backend requires. Build jrsgen with a JDK installed, or use the bytecode backend"))?;
+
+        Ok(env.define_class(DESCRIBER_CLASS, classloader, bytes)?)
+    }
+
+    /// Create a `URLClassLoader` over the jars in `classpath`. Its parent is the platform classloader,
+    /// so the classes cannot see jrsgen's own dependencies. On Java 8 the parent is the bootstrap classloader instead
+    fn create_classloader<'a>(env: &JNIEnv<'a>, classpath: &[String]) -> JResult<JObject<'a>> {
+        let urls = env.new_object_array(classpath.len() as i32, "java/net/URL", JObject::null())?;
+        for (idx, path) in classpath.iter().enumerate() {
+            trace!("Adding {} to the classloader", path);
+            let path = env.new_string(path)?;
+            let file = env.new_object("java/io/File", "(Ljava/lang/String;)V", &[JValue::Object(path.into())])?;
+            let uri = env.call_method(file, "toURI", "()Ljava/net/URI;", &[])?.l()?;
+            let url = env.call_method(uri, "toURL", "()Ljava/net/URL;", &[])?.l()?;
+            env.set_object_array_element(urls, idx as i32, url)?;
+        }
+
+        let parent = if env.get_static_method_id("java/lang/ClassLoader", "getPlatformClassLoader", "()Ljava/lang/ClassLoader;").is_ok() {
+            env.call_static_method("java/lang/ClassLoader", "getPlatformClassLoader", "()Ljava/lang/ClassLoader;", &[])?.l()?
+        } else {
+            // Clear the pending NoSuchMethodError
+            env.exception_clear()?;
+            JObject::null()
+        };
+
+        let classloader = env.new_object("java/net/URLClassLoader", "([Ljava/net/URL;Ljava/lang/ClassLoader;)V", &[JValue::Object(urls.into()), JValue::Object(parent)])?;
+        Ok(classloader)
+    }
+
+    /// Get the binary names of all classes on the classpath, e.g. `com.foo.Bar$Baz`, without loading them
+    pub fn get_all_class_names(&self) -> JResult<Vec<String>> {
+        let mut names = Vec::new();
+        for path in &self.paths {
+            let classes = jar::list_classes(Path::new(path))?;
+            trace!("Found {} classes in {}", classes.len(), path);
+            names.extend(classes);
+        }
+
+        Ok(names)
+    }
+
+    /// Load a class by its binary name through the classpath's classloader.
+    /// The class is not initialized, so its static initializer is never run
+    pub fn load_class<'a>(&self, env: &'a JNIEnv<'a>, name: &str) -> JResult<Class<'a>> {
+        let name = env.new_string(name)?;
+        let class = env.call_static_method("java/lang/Class", "forName", "(Ljava/lang/String;ZLjava/lang/ClassLoader;)Ljava/lang/Class;", &[JValue::Object(name.into()), JValue::Bool(0), JValue::Object(self.classloader.as_obj())])?.l()?;
+        Ok(Class::new(env, JClass::from(class)))
+    }
+
+    /// Describe a class as JSON through `jrsgen.ClassDescriber`, see [crate::parser::description::ClassDescription]
+    pub fn describe_class(&self, env: &JNIEnv<'_>, class: &Class<'_>, include_inherited_methods: bool, include_protected: bool) -> JResult<String> {
+        let describer = JClass::from(self.describer.as_obj());
+        let json = env.call_static_method(describer, "describe", "(Ljava/lang/Class;ZZ)Ljava/lang/String;", &[
+            JValue::Object(class.class.into()),
+            JValue::Bool(include_inherited_methods as u8),
+            JValue::Bool(include_protected as u8),
+        ])?.l()?;
+
+        Ok(env.get_string(json.into())?.into())
+    }
+
+    /// Read the class file of a class through the classpath's classloader, without loading the class.
+    /// Returns `None` if the classloader cannot find it
+    pub fn read_class_file(&self, env: &JNIEnv<'_>, name: &str) -> JResult<Option<Vec<u8>>> {
+        let resource = env.new_string(format!("{}.class", name.replace('.', "/")))?;
+        let stream = env.call_method(self.classloader.as_obj(), "getResourceAsStream", "(Ljava/lang/String;)Ljava/io/InputStream;", &[JValue::Object(resource.into())])?.l()?;
+        if stream.is_null() {
+            return Ok(None);
+        }
+
+        // InputStream.readAllBytes is not available on Java 8
+        let buffer = env.new_byte_array(READ_BUFFER_SIZE)?;
+        let mut chunk = vec![0; READ_BUFFER_SIZE as usize];
+        let mut bytes = Vec::new();
+        loop {
+            let read = env.call_method(stream, "read", "([B)I", &[JValue::Object(JObject::from(buffer))])?.i()?;
+            if read < 0 {
+                break;
+            }
+
+            env.get_byte_array_region(buffer, 0, &mut chunk[..read as usize])?;
+            bytes.extend(chunk[..read as usize].iter().map(|x| *x as u8));
+        }
+
+        env.call_method(stream, "close", "()V", &[])?;
+        Ok(Some(bytes))
+    }
+}
+
+/// The size of the buffer class files are read in chunks of
+const READ_BUFFER_SIZE: i32 = 8192;
+
+/// Split entries holding multiple paths, separated by the platform's path separator
+/// (`:`, or `;` on Windows), like the value of `-classpath`
+pub fn split_entries(classpath: &[String]) -> Vec<String> {
+    classpath.iter()
+        .flat_map(std::env::split_paths)
+        .filter(|x| !x.as_os_str().is_empty())
+        .map(|x| x.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Expand entries ending in `*` to every jar in that directory, like the JVM does for `-classpath`.
+/// The expansion is not recursive, and only files ending in `.jar` or `.JAR` are included, sorted by name.
+/// Other entries are kept as-is
+pub fn expand_wildcards(classpath: &[String]) -> JResult<Vec<String>> {
+    let mut expanded = Vec::new();
+    for entry in classpath {
+        let directory = match entry.strip_suffix('*') {
+            Some(directory) if directory.is_empty() || directory.ends_with(['/', std::path::MAIN_SEPARATOR]) => directory,
+            _ => {
+                expanded.push(entry.clone());
+                continue;
+            }
+        };
+
+        let directory = if directory.is_empty() { "." } else { directory };
+        let mut jars = fs::read_dir(directory)?
+            .map(|x| Ok(x?.path()))
+            .collect::<JResult<Vec<_>>>()?
+            .into_iter()
+            .filter(|x| x.is_file())
+            .filter(|x| x.extension().map(|x| x == "jar" || x == "JAR").unwrap_or(false))
+            .map(|x| x.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        jars.sort();
+
+        trace!("Expanded {} to {} jars", entry, jars.len());
+        expanded.extend(jars);
+    }
+
+    Ok(expanded)
+}
+
+/// Replace Android libraries (`.aar`) by the jars inside them, `classes.jar` and any jars in `libs/`.
+/// They are extracted into `directory`, under the hash of the library's contents.
+/// Dex files cannot be read and have to be converted to a jar first, e.g. with `d8` or `dex2jar`.
+/// Other entries, including the `android.jar` of the SDK, are kept as-is
+pub fn extract_archives(classpath: &[String], directory: &Path) -> JResult<Vec<String>> {
+    let mut extracted = Vec::new();
+    for entry in classpath {
+        let extension = Path::new(entry).extension()
+            .map(|x| x.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        match extension.as_str() {
+            "aar" => {
+                let bytes = fs::read(entry)?;
+                let mut hasher = DefaultHasher::new();
+                bytes.hash(&mut hasher);
+                let target = directory.join(format!("{:016x}", hasher.finish()));
+
+                let mut jars = jar::read_files(Path::new(entry), ".jar")?.into_iter()
+                    .filter(|(name, _)| name == "classes.jar" || (name.starts_with("libs/") && !name[5..].contains('/')))
+                    .collect::<Vec<_>>();
+                // classes.jar sorts before libs/, so the library's own classes come first
+                jars.sort_by(|a, b| a.0.cmp(&b.0));
+
+                trace!("Extracting {} jars from {} to {}", jars.len(), entry, target.display());
+                for (name, contents) in jars {
+                    let path = target.join(&name);
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+
+                    fs::write(&path, contents)?;
+                    extracted.push(path.to_string_lossy().to_string());
+                }
+            },
+            "dex" | "apk" => return Err(anyhow!("Cannot read the dex bytecode in {}, convert it to a jar first, e.g. with d8 or dex2jar", entry)),
+            _ => extracted.push(entry.clone()),
+        }
+    }
+
+    Ok(extracted)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.jar", "a.JAR", "c.zip", "d.class"] {
+            fs::write(dir.path().join(name), []).unwrap();
+        }
+        fs::create_dir(dir.path().join("nested.jar")).unwrap();
+
+        let wildcard = format!("{}/*", dir.path().display());
+        let expanded = expand_wildcards(&[wildcard, "foo.jar".to_string()]).unwrap();
+        let names = expanded.iter()
+            .map(|x| Path::new(x).file_name().unwrap().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec!["a.JAR", "b.jar", "foo.jar"], names);
+    }
+
+    #[test]
+    fn split() {
+        let joined = std::env::join_paths(["a.jar", "lib/b.jar"]).unwrap().to_string_lossy().to_string();
+        let split = split_entries(&[joined, "c.jar".to_string()]);
+        assert_eq!(vec!["a.jar", "lib/b.jar", "c.jar"], split);
+    }
+
+    #[test]
+    fn dex_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let kept = extract_archives(&["android.jar".to_string(), "classes".to_string()], dir.path()).unwrap();
+        assert_eq!(vec!["android.jar", "classes"], kept);
+        assert!(extract_archives(&["classes.dex".to_string()], dir.path()).is_err());
+    }
+}
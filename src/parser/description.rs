@@ -0,0 +1,233 @@
+//! The description of a class produced by `jrsgen.ClassDescriber`, which gathers everything the class tree needs
+//! through reflection in a single call through JNI. Types are described by their `Class.getName()`, e.g. `int` or `[Ljava.lang.String;`
+
+use ejni::Class;
+use jni::JNIEnv;
+use serde::Deserialize;
+use crate::config::Parser;
+use crate::JResult;
+use crate::parser::class_tree::{AnnotationElement, ArgumentType, ConstructorEntry, Deprecation, FieldEntry, MethodEntry, Nullability, TypeParameter, Visibility};
+use crate::parser::classfile::{ACC_ABSTRACT, ACC_FINAL, ACC_STATIC};
+use crate::parser::classpath::ClassPath;
+use crate::parser::kotlin;
+
+#[derive(Debug, Deserialize)]
+pub struct ClassDescription {
+    /// `None` if the JVM cannot determine it, like Java 8 for the nested classes of Scala
+    pub simple_name: Option<String>,
+    pub enclosing_class: Option<String>,
+    pub is_member_class: bool,
+    pub is_interface: bool,
+    pub is_annotation: bool,
+    pub is_enum: bool,
+    pub deprecation: Option<Deprecation>,
+    /// Only present if the Kotlin standard library is on the classpath
+    pub kotlin_metadata: Option<kotlin::Metadata>,
+    pub interfaces: Vec<String>,
+    pub type_parameters: Vec<TypeParameter>,
+    /// Only present if the superclass has type arguments
+    pub generic_superclass: Option<String>,
+    /// The `getSuperclass()` chain, starting at the direct superclass
+    pub superclasses: Vec<String>,
+    /// Empty on JVMs older than Java 17
+    pub permitted_subclasses: Vec<String>,
+    /// All fields declared by the class
+    pub fields: Vec<FieldDescription>,
+    /// The names of the constants of an enum in the order of their ordinals. Empty for other classes
+    pub enum_constants: Vec<String>,
+    /// The methods to include in the tree. Inherited methods are included if `include_inherited_methods` is enabled
+    pub methods: Vec<MethodDescription>,
+    /// All public methods of an interface, including those of its superinterfaces. Empty for other classes
+    pub interface_methods: Vec<MethodDescription>,
+    pub constructors: Vec<ConstructorDescription>,
+    /// The elements of an annotation type. Empty for other classes
+    pub annotation_elements: Vec<AnnotationElementDescription>,
+}
+
+impl ClassDescription {
+    /// Describe a class. Only its members with a visibility included by `config` are described
+    pub fn new(env: &JNIEnv<'_>, classpath: &ClassPath, class: &Class<'_>, config: &Parser) -> JResult<Self> {
+        let json = classpath.describe_class(env, class, config.include_inherited_methods, config.include_protected)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FieldDescription {
+    pub name: String,
+    pub modifiers: u16,
+    pub is_synthetic: bool,
+    pub field_type: String,
+    pub generic_type: String,
+    pub is_enum_constant: bool,
+    pub deprecation: Option<Deprecation>,
+}
+
+impl FieldDescription {
+    pub fn into_entry(self) -> JResult<FieldEntry> {
+        Ok(FieldEntry {
+            name: self.name,
+            is_static: self.modifiers & ACC_STATIC != 0,
+            is_final: self.modifiers & ACC_FINAL != 0,
+            visibility: Visibility::from_access_flags(self.modifiers),
+            field_type: ArgumentType::from_class_name(&self.field_type)?,
+            generic_type: self.generic_type,
+            deprecation: self.deprecation,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MethodDescription {
+    pub name: String,
+    pub modifiers: u16,
+    pub is_default: bool,
+    pub deprecation: Option<Deprecation>,
+    pub arguments: Vec<String>,
+    pub argument_names: Vec<Option<String>>,
+    pub generic_arguments: Vec<String>,
+    pub exceptions: Vec<String>,
+    /// The names of the declaration and type-use annotations of each argument
+    pub argument_annotations: Vec<Vec<String>>,
+    /// `void` if the method does not return anything
+    pub return_type: String,
+    pub declaring_class: String,
+    pub type_parameters: Vec<TypeParameter>,
+    pub generic_return_type: String,
+    /// The names of the declaration annotations of the method and the type-use annotations of its return type
+    pub annotations: Vec<String>,
+}
+
+impl MethodDescription {
+    pub fn into_entry(self) -> JResult<MethodEntry> {
+        let arguments = argument_types(&self.arguments)?;
+        let argument_nullability = argument_nullability(&self.argument_annotations, arguments.len());
+
+        let (return_type, generic_return_type, return_nullability) = match self.return_type.as_str() {
+            "void" => (None, None, Nullability::Unknown),
+            name => (
+                Some(ArgumentType::from_class_name(name)?),
+                Some(self.generic_return_type),
+                Nullability::from_annotation_names(&self.annotations),
+            ),
+        };
+
+        Ok(MethodEntry {
+            name: self.name,
+            is_static: self.modifiers & ACC_STATIC != 0,
+            visibility: Visibility::from_access_flags(self.modifiers),
+            is_abstract: self.modifiers & ACC_ABSTRACT != 0,
+            is_default: self.is_default,
+            deprecation: self.deprecation,
+            arguments,
+            argument_names: self.argument_names,
+            return_type,
+            declaring_class: self.declaring_class,
+            type_parameters: self.type_parameters,
+            generic_arguments: self.generic_arguments,
+            generic_return_type,
+            exceptions: self.exceptions,
+            argument_nullability,
+            return_nullability,
+            javadoc: None,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConstructorDescription {
+    pub modifiers: u16,
+    pub deprecation: Option<Deprecation>,
+    pub arguments: Vec<String>,
+    pub argument_names: Vec<Option<String>>,
+    pub generic_arguments: Vec<String>,
+    pub exceptions: Vec<String>,
+    /// The names of the declaration and type-use annotations of each argument
+    pub argument_annotations: Vec<Vec<String>>,
+}
+
+impl ConstructorDescription {
+    pub fn into_entry(self) -> JResult<ConstructorEntry> {
+        let arguments = argument_types(&self.arguments)?;
+        let argument_nullability = argument_nullability(&self.argument_annotations, arguments.len());
+
+        Ok(ConstructorEntry {
+            visibility: Visibility::from_access_flags(self.modifiers),
+            deprecation: self.deprecation,
+            arguments,
+            argument_names: self.argument_names,
+            generic_arguments: self.generic_arguments,
+            exceptions: self.exceptions,
+            argument_nullability,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnnotationElementDescription {
+    pub name: String,
+    pub element_type: String,
+    /// The default value formatted by `String.valueOf`
+    pub default_value: Option<String>,
+}
+
+impl AnnotationElementDescription {
+    pub fn into_entry(self) -> JResult<AnnotationElement> {
+        Ok(AnnotationElement {
+            name: self.name,
+            element_type: ArgumentType::from_class_name(&self.element_type)?,
+            default_value: self.default_value,
+        })
+    }
+}
+
+fn argument_types(names: &[String]) -> JResult<Vec<ArgumentType>> {
+    names.iter()
+        .map(|x| ArgumentType::from_class_name(x))
+        .collect()
+}
+
+/// Get the nullability of each of the `argument_count` arguments. Implicit arguments, like the outer instance
+/// of inner class constructors, may lack annotation information; their nullability is `Unknown`
+fn argument_nullability(annotations: &[Vec<String>], argument_count: usize) -> Vec<Nullability> {
+    // Annotation information is reported for the trailing arguments
+    let implicit_count = argument_count.saturating_sub(annotations.len());
+    std::iter::repeat_n(Nullability::Unknown, implicit_count)
+        .chain(annotations.iter().map(|x| Nullability::from_annotation_names(x)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn method() {
+        let json = r#"{
+            "name": "get", "modifiers": 1025, "is_default": false, "deprecation": {"since": "9", "for_removal": false},
+            "arguments": ["int", "[Ljava.lang.String;"], "argument_names": [null, "keys"],
+            "generic_arguments": ["int", "java.lang.String[]"], "exceptions": ["java.io.IOException"],
+            "argument_annotations": [[], ["javax.annotation.Nullable"]],
+            "return_type": "java.util.List", "declaring_class": "com.foo.Bar",
+            "type_parameters": [{"name": "T", "bounds": ["java.lang.Comparable<T>"]}],
+            "generic_return_type": "java.util.List<T>", "annotations": ["org.jetbrains.annotations.NotNull"]
+        }"#;
+
+        let method = serde_json::from_str::<MethodDescription>(json).unwrap().into_entry().unwrap();
+        assert_eq!("get", method.name);
+        assert_eq!(Visibility::Public, method.visibility);
+        assert!(method.is_abstract && !method.is_static);
+        assert_eq!(Some("9".to_string()), method.deprecation.and_then(|x| x.since));
+        assert_eq!(vec![ArgumentType::Int, ArgumentType::Array(Box::new(ArgumentType::Object("java.lang.String".to_string())))], method.arguments);
+        assert_eq!(vec![Nullability::Unknown, Nullability::Nullable], method.argument_nullability);
+        assert_eq!(Some(ArgumentType::Object("java.util.List".to_string())), method.return_type);
+        assert_eq!(Some("java.util.List<T>".to_string()), method.generic_return_type);
+        assert_eq!(Nullability::NonNull, method.return_nullability);
+    }
+
+    #[test]
+    fn implicit_arguments() {
+        let nullability = argument_nullability(&[vec!["lombok.NonNull".to_string()]], 2);
+        assert_eq!(vec![Nullability::Unknown, Nullability::NonNull], nullability);
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashSet;
+use log::warn;
+use regex::Regex;
+use crate::config::Parser;
+use crate::JResult;
+use crate::parser::class_tree::ClassEntry;
+
+/// Filters classes by their fully qualified name, using the `include` and `exclude` patterns of the config.
+/// Also decides which classes are selected through the signatures of other classes
+#[derive(Debug)]
+pub struct ClassFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+    entry_points: Vec<String>,
+    include_referenced: bool,
+}
+
+impl ClassFilter {
+    /// Compile the patterns of the config.
+    ///
+    /// # Errors
+    ///
+    /// If a pattern is not a valid regular expression
+    pub fn new(config: &Parser) -> JResult<Self> {
+        let compile = |patterns: &[String]| patterns.iter()
+            .map(|x| Ok(Regex::new(x)?))
+            .collect::<JResult<Vec<_>>>();
+
+        Ok(Self {
+            include: compile(&config.include)?,
+            exclude: compile(&config.exclude)?,
+            entry_points: config.entry_points.clone(),
+            include_referenced: config.include_referenced,
+        })
+    }
+
+    /// Whether the class with the fully qualified name `name` should be included
+    pub fn is_included(&self, name: &str) -> bool {
+        let included = self.include.is_empty() || self.include.iter().any(|x| x.is_match(name));
+        included && !self.exclude.iter().any(|x| x.is_match(name))
+    }
+
+    /// Select the classes in one of the packages in `roots` that are included by the filter.
+    /// Classes matching multiple roots, or occurring multiple times in `names`, are selected once.
+    /// If entry points are configured, only those are selected instead
+    pub fn select(&self, names: Vec<String>, roots: &[String]) -> Vec<String> {
+        let mut seen = HashSet::new();
+        if !self.entry_points.is_empty() {
+            let names = names.into_iter().collect::<HashSet<_>>();
+            return self.entry_points.iter()
+                .filter(|x| {
+                    let found = names.contains(*x);
+                    if !found {
+                        warn!("Entry point {} is not on the classpath", x);
+                    }
+                    found
+                })
+                .filter(|x| seen.insert((*x).clone()))
+                .cloned()
+                .collect();
+        }
+
+        names.into_iter()
+            .filter(|x| roots.iter().any(|root| x.starts_with(root)))
+            .filter(|x| self.is_included(x))
+            .filter(|x| seen.insert(x.clone()))
+            .collect()
+    }
+
+    /// Select the classes referenced by the signatures of `entries` that are on the classpath, i.e. in `available`,
+    /// and included by the filter. Classes in `known` are skipped, selected classes are added to it.
+    /// `available` only holds the classes in the jars, so classes of the JDK are not followed,
+    /// even though the JVM backend could load them. The generator passes them as `crate::Object`.
+    ///
+    /// References outside of `roots` are only followed with `include_referenced`.
+    /// Without it, references are only followed if entry points are configured
+    pub fn referenced_classes(&self, entries: &[ClassEntry], available: &HashSet<String>, known: &mut HashSet<String>, roots: &[String]) -> Vec<String> {
+        if !self.include_referenced && self.entry_points.is_empty() {
+            return Vec::new();
+        }
+
+        entries.iter()
+            .flat_map(|x| x.referenced_types())
+            .filter(|x| available.contains(x))
+            .filter(|x| self.include_referenced || roots.iter().any(|root| x.starts_with(root)))
+            .filter(|x| self.is_included(x))
+            .filter(|x| known.insert(x.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn filter() {
+        let config = Parser {
+            include: vec![r"^com\.foo\.".to_string()],
+            exclude: vec![r"\.internal\.".to_string(), r"\$Impl$".to_string()],
+            ..Parser::default()
+        };
+        let filter = ClassFilter::new(&config).unwrap();
+
+        assert!(filter.is_included("com.foo.Bar"));
+        assert!(filter.is_included("com.foo.Bar$Baz"));
+        assert!(!filter.is_included("com.bar.Foo"));
+        assert!(!filter.is_included("com.foo.internal.Bar"));
+        assert!(!filter.is_included("com.foo.Bar$Impl"));
+    }
+
+    #[test]
+    fn select() {
+        let filter = ClassFilter::new(&Parser::default()).unwrap();
+        let names = vec!["com.foo.Bar".to_string(), "com.bar.Foo".to_string(), "com.foo.Bar".to_string(), "org.Baz".to_string()];
+        let roots = vec!["com.foo.".to_string(), "com.".to_string()];
+
+        assert_eq!(vec!["com.foo.Bar".to_string(), "com.bar.Foo".to_string()], filter.select(names, &roots));
+    }
+
+    #[test]
+    fn select_entry_points() {
+        let config = Parser {
+            entry_points: vec!["com.foo.Bar".to_string(), "com.foo.Missing".to_string()],
+            exclude: vec![r"Bar".to_string()],
+            ..Parser::default()
+        };
+        let filter = ClassFilter::new(&config).unwrap();
+        let names = vec!["com.foo.Bar".to_string(), "com.foo.Baz".to_string()];
+
+        assert_eq!(vec!["com.foo.Bar".to_string()], filter.select(names, &["com.".to_string()]));
+    }
+
+    #[test]
+    fn filter_empty() {
+        let filter = ClassFilter::new(&Parser::default()).unwrap();
+        assert!(filter.is_included("com.foo.Bar"));
+    }
+}
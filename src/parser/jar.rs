@@ -0,0 +1,152 @@
+//! Reads the classes in a jar, or a directory of class files.
+//! Listing the classes only needs the entry names in the central directory, so nothing is decompressed
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use anyhow::Context;
+use zip::ZipArchive;
+use crate::JResult;
+
+/// List the binary names of the classes in a jar or directory, e.g. `com.foo.Bar$Baz`.
+/// `module-info` and classes under `META-INF`, like multi-release versions, are skipped
+pub fn list_classes(path: &Path) -> JResult<Vec<String>> {
+    let names = if path.is_dir() {
+        list_directory(path, path)?
+    } else {
+        open(path)?.file_names()
+            .map(str::to_string)
+            .collect()
+    };
+
+    let classes = names.into_iter()
+        .filter_map(|x| class_name(&x))
+        .collect();
+
+    Ok(classes)
+}
+
+/// Read the classes in a jar or directory. Returns the binary name and the class file contents of every class,
+/// skipping the same classes as [list_classes]
+pub fn read_classes(path: &Path) -> JResult<Vec<(String, Vec<u8>)>> {
+    let classes = read_files(path, ".class")?.into_iter()
+        .filter_map(|(name, contents)| class_name(&name).map(|name| (name, contents)))
+        .collect();
+
+    Ok(classes)
+}
+
+/// Read the files in a jar or directory with a name ending in `suffix`.
+/// Returns the path of every file within the jar, separated by `/`, and its contents
+pub fn read_files(path: &Path, suffix: &str) -> JResult<Vec<(String, Vec<u8>)>> {
+    if path.is_dir() {
+        return list_directory(path, path)?
+            .into_iter()
+            .filter(|x| x.ends_with(suffix))
+            .map(|x| {
+                let contents = fs::read(path.join(&x))?;
+                Ok((x, contents))
+            })
+            .collect();
+    }
+
+    let mut archive = open(path)?;
+    let mut files = Vec::new();
+    for idx in 0..archive.len() {
+        let mut file = archive.by_index(idx)?;
+        if !file.is_file() || !file.name().ends_with(suffix) {
+            continue;
+        }
+
+        let mut contents = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut contents).with_context(|| format!("Reading {} from {}", file.name(), path.display()))?;
+        files.push((file.name().to_string(), contents));
+    }
+
+    Ok(files)
+}
+
+/// Convert the path of a class file to the binary name of the class.
+/// Returns `None` if the file is not a class, or one that should be skipped
+fn class_name(path: &str) -> Option<String> {
+    if path.starts_with("META-INF/") {
+        return None;
+    }
+
+    let name = path.strip_suffix(".class")?.replace('/', ".");
+    if name.ends_with("module-info") || name.ends_with("package-info") {
+        return None;
+    }
+
+    Some(name)
+}
+
+/// List the files in a directory recursively, relative to `root` and separated by `/` like jar entries
+fn list_directory(root: &Path, path: &Path) -> JResult<Vec<String>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?.path();
+        if entry.is_dir() {
+            entries.extend(list_directory(root, &entry)?);
+        } else if let Ok(relative) = entry.strip_prefix(root) {
+            let components = relative.components()
+                .map(|x| x.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>();
+            entries.push(components.join("/"));
+        }
+    }
+
+    Ok(entries)
+}
+
+fn open(path: &Path) -> JResult<ZipArchive<File>> {
+    ZipArchive::new(File::open(path)?).with_context(|| format!("Reading {}", path.display()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+    use super::*;
+
+    /// Write a jar with the named entries, compressed like `jar` does, and return the contents of the classes
+    fn read_jar(names: &[&str]) -> Vec<(String, Vec<u8>)> {
+        let jar = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = ZipWriter::new(jar.reopen().unwrap());
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for name in names {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(name.repeat(10).as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        assert_eq!(vec!["com.foo.Bar".to_string(), "com.foo.Bar$Baz".to_string()], list_classes(jar.path()).unwrap());
+        read_classes(jar.path()).unwrap()
+    }
+
+    #[test]
+    fn entries() {
+        let classes = read_jar(&["META-INF/MANIFEST.MF", "com/foo/Bar.class", "com/foo/Bar$Baz.class"]);
+        assert_eq!(vec![
+            ("com.foo.Bar".to_string(), "com/foo/Bar.class".repeat(10).into_bytes()),
+            ("com.foo.Bar$Baz".to_string(), "com/foo/Bar$Baz.class".repeat(10).into_bytes()),
+        ], classes);
+    }
+
+    #[test]
+    fn classes() {
+        assert_eq!(Some("com.foo.Bar$Baz".to_string()), class_name("com/foo/Bar$Baz.class"));
+        assert_eq!(None, class_name("META-INF/versions/9/com/foo/Bar.class"));
+        assert_eq!(None, class_name("com/foo/package-info.class"));
+        assert_eq!(None, class_name("module-info.class"));
+        assert_eq!(None, class_name("com/foo/Bar.java"));
+    }
+
+    #[test]
+    fn not_a_zip() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(file.path(), b"not a zip archive, but long enough").unwrap();
+        assert!(list_classes(file.path()).is_err());
+    }
+}
@@ -0,0 +1,437 @@
+use std::collections::HashMap;
+use std::path::Path;
+use log::{debug, trace};
+use crate::class_tree::{ArgumentType, ClassEntry};
+use crate::JResult;
+use crate::parser::jar;
+
+/// Javadoc comments extracted from Java sources, as written in the source without the comment delimiters
+#[derive(Debug, Default)]
+pub struct Javadoc {
+    /// Class Javadoc by fully qualified binary class name, e.g. `com.foo.Bar$Baz`
+    classes: HashMap<String, String>,
+    /// Method Javadoc by fully qualified class name and method name
+    methods: HashMap<(String, String), Vec<MethodJavadoc>>,
+}
+
+#[derive(Debug)]
+struct MethodJavadoc {
+    /// The simple names of the argument types as written in the source, without type arguments.
+    /// Arrays and varargs end with `[]`
+    argument_types: Vec<String>,
+    doc: String,
+}
+
+impl Javadoc {
+    /// Read the Javadoc of all `.java` files in a sources jar
+    pub fn from_sources_jar(path: &str) -> JResult<Self> {
+        debug!("Reading sources jar {}", path);
+
+        let mut javadoc = Self::default();
+        for (name, source) in jar::read_files(Path::new(path), ".java")? {
+            trace!("Extracting Javadoc from {}", name);
+            javadoc.add_source(&String::from_utf8_lossy(&source));
+        }
+
+        Ok(javadoc)
+    }
+
+    /// Merge the Javadoc of `other` into this one
+    pub fn extend(&mut self, other: Self) {
+        self.classes.extend(other.classes);
+        for (key, methods) in other.methods {
+            self.methods.entry(key).or_default().extend(methods);
+        }
+    }
+
+    /// Attach the Javadoc to the classes and methods in `tree`.
+    /// Classes and methods tagged `@hide`, like the internal API in the Android sources, are removed
+    pub fn apply(&self, tree: &mut Vec<ClassEntry>) {
+        tree.retain(|class| match self.classes.get(&class.name) {
+            Some(doc) if is_hidden(doc) => {
+                trace!("Skipping hidden class {}", class.name);
+                false
+            },
+            _ => true,
+        });
+
+        for class in tree {
+            class.javadoc = self.classes.get(&class.name).cloned();
+            for method in &mut class.methods {
+                method.javadoc = self.find_method(&method.declaring_class, &method.name, &method.arguments)
+                    .map(|x| x.to_string());
+            }
+
+            class.methods.retain(|x| !x.javadoc.as_deref().map(is_hidden).unwrap_or(false));
+        }
+    }
+
+    /// Find the Javadoc of a method. Overloads are matched on the simple names of their argument types.
+    /// If that fails, e.g. because of erased type variables, the only overload with the same number of arguments is used
+    fn find_method(&self, class: &str, name: &str, arguments: &[ArgumentType]) -> Option<&str> {
+        let overloads = self.methods.get(&(class.to_string(), name.to_string()))?;
+        let argument_types = arguments.iter()
+            .map(simple_type_name)
+            .collect::<Vec<_>>();
+
+        if let Some(overload) = overloads.iter().find(|x| x.argument_types == argument_types) {
+            return Some(&overload.doc);
+        }
+
+        let mut same_count = overloads.iter().filter(|x| x.argument_types.len() == arguments.len());
+        match (same_count.next(), same_count.next()) {
+            (Some(overload), None) => Some(&overload.doc),
+            _ => None,
+        }
+    }
+
+    /// Extract the Javadoc of the classes and methods declared in a single source file
+    fn add_source(&mut self, source: &str) {
+        let mut scanner = SourceScanner::default();
+        scanner.scan(source);
+
+        self.classes.extend(scanner.classes);
+        for (class, name, method) in scanner.methods {
+            self.methods.entry((class, name)).or_default().push(method);
+        }
+    }
+}
+
+/// The simple name of a type as it would be written in Java source, e.g. `Baz[]` for `com.foo.Bar$Baz[]`
+/// Whether a Javadoc comment has the `@hide` block tag
+fn is_hidden(doc: &str) -> bool {
+    doc.lines()
+        .map(|x| x.trim_start().trim_start_matches('*').trim_start())
+        .any(|x| x == "@hide" || x.starts_with("@hide "))
+}
+
+fn simple_type_name(argument_type: &ArgumentType) -> String {
+    match argument_type {
+        ArgumentType::Boolean => "boolean".into(),
+        ArgumentType::Byte => "byte".into(),
+        ArgumentType::Char => "char".into(),
+        ArgumentType::Short => "short".into(),
+        ArgumentType::Int => "int".into(),
+        ArgumentType::Long => "long".into(),
+        ArgumentType::Float => "float".into(),
+        ArgumentType::Double => "double".into(),
+        ArgumentType::Object(class_fq) => class_fq.rsplit(['.', '$']).next().unwrap().to_string(),
+        ArgumentType::Array(argument_type) => format!("{}[]", simple_type_name(argument_type)),
+    }
+}
+
+/// Finds Javadoc comments in Java source and the declarations they belong to.
+/// This is not a full Java parser, it only tracks braces, comments and literals to find declarations in class bodies
+#[derive(Default)]
+struct SourceScanner {
+    package: Option<String>,
+    /// The classes enclosing the current position, with the brace depth of their body
+    enclosing: Vec<(usize, String)>,
+    depth: usize,
+    /// The source of the current declaration, without comments
+    declaration: String,
+    pending_doc: Option<String>,
+    classes: HashMap<String, String>,
+    methods: Vec<(String, String, MethodJavadoc)>,
+}
+
+impl SourceScanner {
+    fn scan(&mut self, source: &str) {
+        let chars = source.chars().collect::<Vec<_>>();
+        let mut idx = 0;
+        while idx < chars.len() {
+            let rest = &chars[idx..];
+            match rest {
+                ['/', '*', '*', '/', ..] => idx += 4,
+                ['/', '*', '*', ..] => {
+                    let end = find(&chars, idx + 3, &['*', '/']);
+                    self.pending_doc = Some(chars[idx + 3..end].iter().collect());
+                    self.declaration.clear();
+                    idx = end + 2;
+                },
+                ['/', '*', ..] => idx = find(&chars, idx + 2, &['*', '/']) + 2,
+                ['/', '/', ..] => idx = find(&chars, idx, &['\n']),
+                ['"', '"', '"', ..] => {
+                    let end = find(&chars, idx + 3, &['"', '"', '"']);
+                    self.declaration.push_str("\"\"");
+                    idx = end + 3;
+                },
+                ['"', ..] | ['\'', ..] => {
+                    let quote = chars[idx];
+                    idx += 1;
+                    while idx < chars.len() && chars[idx] != quote {
+                        if chars[idx] == '\\' {
+                            idx += 1;
+                        }
+                        idx += 1;
+                    }
+
+                    self.declaration.push(quote);
+                    self.declaration.push(quote);
+                    idx += 1;
+                },
+                ['{', ..] => {
+                    self.end_declaration(true);
+                    self.depth += 1;
+                    idx += 1;
+                },
+                [';', ..] => {
+                    self.end_declaration(false);
+                    idx += 1;
+                },
+                ['}', ..] => {
+                    if matches!(self.enclosing.last(), Some((depth, _)) if *depth == self.depth) {
+                        self.enclosing.pop();
+                    }
+
+                    self.depth = self.depth.saturating_sub(1);
+                    self.declaration.clear();
+                    self.pending_doc = None;
+                    idx += 1;
+                },
+                [c, ..] => {
+                    self.declaration.push(*c);
+                    idx += 1;
+                },
+                [] => unreachable!(),
+            }
+        }
+    }
+
+    /// Handle the end of a declaration, either by the opening brace of its body or by a semicolon
+    fn end_declaration(&mut self, opens_body: bool) {
+        let declaration = strip_annotations(&std::mem::take(&mut self.declaration));
+        let doc = self.pending_doc.take();
+
+        // Only declarations directly in a class body, or at the top level, are of interest
+        let in_class_body = match self.enclosing.last() {
+            Some((depth, _)) => *depth == self.depth,
+            None => true,
+        };
+        if !in_class_body {
+            return;
+        }
+
+        let words = declaration.split_whitespace().collect::<Vec<_>>();
+        if self.enclosing.is_empty() && words.first() == Some(&"package") {
+            self.package = words.get(1).map(|x| x.to_string());
+            return;
+        }
+
+        if opens_body {
+            let class_name = words.iter()
+                .position(|x| matches!(*x, "class" | "interface" | "enum" | "record" | "@interface"))
+                .and_then(|idx| words.get(idx + 1))
+                .map(|x| x.split(['<', '(']).next().unwrap());
+
+            if let Some(class_name) = class_name {
+                let name = match (self.enclosing.last(), &self.package) {
+                    (Some((_, outer)), _) => format!("{outer}${class_name}"),
+                    (None, Some(package)) => format!("{package}.{class_name}"),
+                    (None, None) => class_name.to_string(),
+                };
+
+                if let Some(doc) = doc {
+                    self.classes.insert(name.clone(), doc);
+                }
+
+                self.enclosing.push((self.depth + 1, name));
+                return;
+            }
+        }
+
+        if let (Some(doc), Some((_, class))) = (doc, self.enclosing.last()) {
+            if let Some((name, argument_types)) = parse_method_declaration(&declaration) {
+                self.methods.push((class.clone(), name, MethodJavadoc { argument_types, doc }));
+            }
+        }
+    }
+}
+
+/// Find the index of `pattern` at or after `from`. Returns the length of `chars` if it is not found
+fn find(chars: &[char], from: usize, pattern: &[char]) -> usize {
+    (from..chars.len())
+        .find(|&idx| chars[idx..].starts_with(pattern))
+        .unwrap_or(chars.len())
+}
+
+/// Remove annotations, including their arguments, from a declaration. `@interface` is kept
+fn strip_annotations(declaration: &str) -> String {
+    let chars = declaration.chars().collect::<Vec<_>>();
+    let mut stripped = String::with_capacity(declaration.len());
+    let mut idx = 0;
+    while idx < chars.len() {
+        if chars[idx] != '@' || chars[idx + 1..].starts_with(&['i', 'n', 't', 'e', 'r', 'f', 'a', 'c', 'e']) {
+            stripped.push(chars[idx]);
+            idx += 1;
+            continue;
+        }
+
+        idx += 1;
+        while idx < chars.len() && (chars[idx].is_alphanumeric() || matches!(chars[idx], '_' | '$' | '.')) {
+            idx += 1;
+        }
+
+        let next = (idx..chars.len()).find(|&idx| !chars[idx].is_whitespace());
+        if let Some(next) = next.filter(|&next| chars[next] == '(') {
+            let mut depth = 0;
+            idx = next;
+            while idx < chars.len() {
+                match chars[idx] {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {},
+                }
+
+                idx += 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+        }
+
+        stripped.push(' ');
+    }
+
+    stripped
+}
+
+/// Parse a method declaration to its name and the simple names of its argument types.
+/// Returns `None` if the declaration is not a method, e.g. a field
+fn parse_method_declaration(declaration: &str) -> Option<(String, Vec<String>)> {
+    let open = declaration.find('(')?;
+    let close = declaration.rfind(')')?;
+    let head = &declaration[..open];
+    if head.contains('=') {
+        return None;
+    }
+
+    let name = head.split_whitespace().last()?;
+    if !name.chars().all(|x| x.is_alphanumeric() || x == '_' || x == '$') {
+        return None;
+    }
+
+    let mut arguments = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in declaration[open + 1..close].chars() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                arguments.push(std::mem::take(&mut current));
+                continue;
+            },
+            _ if depth > 0 => continue,
+            _ => {},
+        }
+
+        if depth == 0 && c != '>' {
+            current.push(c);
+        }
+    }
+
+    if !current.trim().is_empty() {
+        arguments.push(current);
+    }
+
+    let argument_types = arguments.iter()
+        .map(|argument| {
+            let argument = argument.replace("...", "[] ").replace('[', " [").replace(']', "] ");
+            let words = argument.split_whitespace()
+                .filter(|x| *x != "final")
+                .collect::<Vec<_>>();
+
+            // The last word is the argument name, array brackets may be on either side of it
+            let name_idx = words.iter()
+                .rposition(|x| *x != "[]")
+                .unwrap_or(0);
+            let type_name = words[..name_idx].iter()
+                .chain(words[name_idx + 1..].iter())
+                .copied()
+                .collect::<String>();
+
+            match type_name.split_once('[') {
+                Some((class, dimensions)) => format!("{}[{}", class.rsplit('.').next().unwrap(), dimensions),
+                None => type_name.rsplit('.').next().unwrap().to_string(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Some((name.to_string(), argument_types))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOURCE: &str = r#"
+        package com.foo;
+
+        import java.util.List;
+
+        /**
+         * A bar.
+         */
+        @Deprecated
+        public class Bar<T> {
+            /** The name, "}" braces in strings are ignored */
+            private String name = "}";
+
+            /**
+             * Set the name.
+             */
+            public void setName(final String name) {
+                Runnable r = new Runnable() {
+                    /** Not a method of Bar */
+                    public void run() {}
+                };
+            }
+
+            /** Add items */
+            @SuppressWarnings("unchecked")
+            public <E extends List<T>> void add(int[] indices, java.util.Map<String, E> map, String... rest) {}
+
+            /** A nested class */
+            public static class Baz {
+                /** Get the value */
+                abstract int getValue();
+            }
+        }
+    "#;
+
+    #[test]
+    fn extracts_classes() {
+        let mut javadoc = Javadoc::default();
+        javadoc.add_source(SOURCE);
+
+        assert_eq!(Some("\n         * A bar.\n         "), javadoc.classes.get("com.foo.Bar").map(|x| x.as_str()));
+        assert_eq!(Some(" A nested class "), javadoc.classes.get("com.foo.Bar$Baz").map(|x| x.as_str()));
+    }
+
+    #[test]
+    fn extracts_methods() {
+        let mut javadoc = Javadoc::default();
+        javadoc.add_source(SOURCE);
+
+        let string = ArgumentType::Object("java.lang.String".to_string());
+        assert!(javadoc.find_method("com.foo.Bar", "setName", std::slice::from_ref(&string)).unwrap().contains("Set the name."));
+        assert!(javadoc.find_method("com.foo.Bar", "run", &[]).is_none());
+        assert_eq!(Some(" Get the value "), javadoc.find_method("com.foo.Bar$Baz", "getValue", &[]));
+
+        let arguments = [
+            ArgumentType::Array(Box::new(ArgumentType::Int)),
+            ArgumentType::Object("java.util.Map".to_string()),
+            ArgumentType::Array(Box::new(string)),
+        ];
+        assert_eq!(Some(" Add items "), javadoc.find_method("com.foo.Bar", "add", &arguments));
+    }
+
+    #[test]
+    fn hidden() {
+        assert!(is_hidden(" @hide "));
+        assert!(is_hidden("\n     * Internal.\n     *\n     * @hide\n     "));
+        assert!(!is_hidden(" Whether to @hide the view "));
+        assert!(!is_hidden(" @hidden "));
+    }
+}
@@ -1,18 +1,16 @@
-use std::{fs, io};
-use std::io::Write;
 use std::ops::Deref;
-use std::path::PathBuf;
-use jni::{InitArgsBuilder, JavaVM, JNIVersion};
+use std::ffi::{c_char, c_void, CString};
+use std::ptr;
+use anyhow::anyhow;
+use jni::JavaVM;
+use jni::sys::{jint, JavaVMInitArgs, JavaVMOption, JNI_FALSE, JNI_OK, JNI_VERSION_1_8};
 use log::{debug, trace};
+use crate::config;
 use crate::JResult;
+use crate::parser::libjvm;
 
 pub struct Jvm(JavaVM);
 
-/// java-dependencies.jar
-const JAVA_DEPENDENCIES: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/target/java-dependencies.jar"));
-
-
-
 impl Deref for Jvm {
     type Target = JavaVM;
 
@@ -22,41 +20,46 @@ impl Deref for Jvm {
 }
 
 impl Jvm {
-    fn write_java_dependencies_to_disk() -> io::Result<PathBuf> {
-        let tmp = tempfile::tempdir()?;
-        let path = tmp.path().to_path_buf();
-
-        let jarpath = path.join("dependencies.jar");
-        let mut f = fs::File::create(&jarpath)?;
-        f.write_all(JAVA_DEPENDENCIES)?;
-
-        // Prevent the jar from being deleted
-        Box::leak(Box::new(tmp));
+    /// Launch the JVM of the configured Java home, or the one found by [find_java_home](libjvm::find_java_home),
+    /// with the configured options, e.g. `-Xmx4g`. Nothing is put on the classpath,
+    /// the jars to generate bindings for are loaded separately by [ClassPath](crate::parser::classpath::ClassPath)
+    pub fn new(config: &config::Jvm) -> JResult<Self> {
+        let java_home = libjvm::find_java_home(config.java_home.as_deref())?;
+        debug!("Using Java home {}", java_home.display());
+        let create_java_vm = libjvm::load(&libjvm::find_libjvm(&java_home)?)?;
 
-        Ok(PathBuf::from(jarpath))
-    }
+        let options = config.options.iter()
+            .map(|x| {
+                trace!("Using JVM option: {}", x);
+                Ok(CString::new(x.as_str())?)
+            })
+            .collect::<JResult<Vec<_>>>()?;
 
-    pub fn new<S: AsRef<str>>(classpath: &[S]) -> JResult<Self> {
-        let mut classpath = classpath.iter()
-            .map(|x| x.as_ref())
+        let mut raw_options = options.iter()
+            .map(|x| JavaVMOption {
+                optionString: x.as_ptr() as *mut c_char,
+                extraInfo: ptr::null_mut(),
+            })
             .collect::<Vec<_>>();
 
-        // Add our own java dependencies to the classpath
-        let path = Self::write_java_dependencies_to_disk()?;
-        let path = path.to_string_lossy();
-        classpath.push(&path);
-
-        let classpath = classpath.join(":");
-        trace!("Using classpath: {}", classpath);
-
-        let args = InitArgsBuilder::new()
-            .version(JNIVersion::V8)
-            // .option("-Xcheck:jni")
-            .option(&format!("-Djava.class.path={}", classpath));
+        let mut args = JavaVMInitArgs {
+            version: JNI_VERSION_1_8,
+            nOptions: raw_options.len() as jint,
+            options: raw_options.as_mut_ptr(),
+            ignoreUnrecognized: JNI_FALSE,
+        };
 
         debug!("Launching JVM");
-        let args = args.build()?;
-        let vm = JavaVM::new(args)?;
+        let mut vm = ptr::null_mut();
+        let mut env = ptr::null_mut();
+        // SAFETY: The options outlive the call, the JVM copies what it needs
+        let result = unsafe { create_java_vm(&mut vm, &mut env, &mut args as *mut JavaVMInitArgs as *mut c_void) };
+        if result != JNI_OK {
+            return Err(anyhow!("Failed to launch the JVM, JNI_CreateJavaVM returned {}", result));
+        }
+
+        // SAFETY: The pointer was just created by JNI_CreateJavaVM
+        let vm = unsafe { JavaVM::from_raw(vm)? };
         Ok(Self(vm))
     }
-}
\ No newline at end of file
+}
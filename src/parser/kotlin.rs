@@ -0,0 +1,155 @@
+//! Recognizes Kotlin constructs in compiled classes. Kotlin classes are annotated with `@kotlin.Metadata`,
+//! whose `d2` holds the names of the properties and functions declared in the class
+
+use serde::{Deserialize, Serialize};
+use crate::class_tree::MethodEntry;
+
+/// The annotation the Kotlin compiler adds to every class it generates
+pub const METADATA_ANNOTATION: &str = "kotlin.Metadata";
+/// The value of `Metadata.k` for a class, as opposed to e.g. a file facade holding top-level functions
+pub const KIND_CLASS: i32 = 1;
+/// The static field holding the instance of an `object`
+pub const INSTANCE_FIELD: &str = "INSTANCE";
+
+/// The contents of a `@kotlin.Metadata` annotation relevant to the class tree
+#[derive(Debug, Default, Deserialize)]
+pub struct Metadata {
+    /// The kind of the class, `k`
+    pub kind: i32,
+    /// The strings referenced by the metadata, `d2`. This includes the names of the properties
+    pub strings: Vec<String>,
+}
+
+/// A Kotlin property, accessed through its getter and setter methods
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Property {
+    /// The name of the property in Kotlin, e.g. `name` for `getName`
+    pub name: String,
+    pub getter: String,
+    /// `None` for a `val`
+    pub setter: Option<String>,
+}
+
+/// Whether a method is the overload Kotlin generates to fill in default arguments, e.g. `foo$default`.
+/// These take a bit mask of the provided arguments, and are not meant to be called directly
+pub fn is_default_overload(name: &str) -> bool {
+    name.ends_with("$default")
+}
+
+/// Find the static field holding the companion object, e.g. `Companion`. The field has the type of the
+/// nested class named after it, e.g. `com.foo.Bar$Companion`. `fields` are the names and types of the static fields
+pub fn companion_field(class_name: &str, fields: &[(String, String)]) -> Option<String> {
+    fields.iter()
+        .find(|(name, field_type)| *field_type == format!("{class_name}${name}"))
+        .map(|(name, _)| name.clone())
+}
+
+/// Pair the getters and setters of the properties declared by a class. Only methods of which the property name
+/// appears in the metadata are considered, so methods that merely look like getters are left alone
+pub fn properties(methods: &[MethodEntry], metadata: &Metadata) -> Vec<Property> {
+    let is_property = |name: &str| metadata.strings.iter().any(|x| x == name);
+
+    methods.iter()
+        .filter(|x| !x.is_static && x.arguments.is_empty() && x.return_type.is_some())
+        .filter_map(|getter| {
+            // `isFoo` keeps its name as property name, `getFoo` becomes `foo`
+            let (name, suffix) = if let Some(suffix) = getter.name.strip_prefix("is").filter(|x| starts_uppercase(x)) {
+                (getter.name.clone(), suffix)
+            } else {
+                let suffix = getter.name.strip_prefix("get").filter(|x| starts_uppercase(x))?;
+                // E.g. `getURL` for a property `URL`
+                let name = [decapitalize(suffix), suffix.to_string()].into_iter()
+                    .find(|x| is_property(x))?;
+                (name, suffix)
+            };
+
+            if !is_property(&name) {
+                return None;
+            }
+
+            let setter_name = format!("set{suffix}");
+            let setter = methods.iter()
+                .find(|x| x.name == setter_name && !x.is_static && x.return_type.is_none()
+                    && x.arguments.len() == 1 && Some(&x.arguments[0]) == getter.return_type.as_ref())
+                .map(|x| x.name.clone());
+
+            Some(Property {
+                name,
+                getter: getter.name.clone(),
+                setter,
+            })
+        })
+        .collect()
+}
+
+fn starts_uppercase(x: &str) -> bool {
+    x.chars().next().map(char::is_uppercase).unwrap_or(false)
+}
+
+fn decapitalize(x: &str) -> String {
+    let mut chars = x.chars();
+    chars.next()
+        .map(|first| first.to_lowercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::class_tree::{ArgumentType, Nullability, Visibility};
+
+    fn method(name: &str, arguments: Vec<ArgumentType>, return_type: Option<ArgumentType>) -> MethodEntry {
+        MethodEntry {
+            name: name.to_string(),
+            is_static: false,
+            visibility: Visibility::Public,
+            is_abstract: false,
+            is_default: false,
+            deprecation: None,
+            argument_names: vec![None; arguments.len()],
+            argument_nullability: vec![Nullability::Unknown; arguments.len()],
+            arguments,
+            return_type,
+            declaring_class: "com.foo.Bar".to_string(),
+            type_parameters: Vec::new(),
+            generic_arguments: Vec::new(),
+            generic_return_type: None,
+            exceptions: Vec::new(),
+            return_nullability: Nullability::Unknown,
+            javadoc: None,
+        }
+    }
+
+    #[test]
+    fn pairs_properties() {
+        let string = ArgumentType::Object("java.lang.String".to_string());
+        let methods = vec![
+            method("getName", Vec::new(), Some(string.clone())),
+            method("setName", vec![string.clone()], None),
+            method("isEnabled", Vec::new(), Some(ArgumentType::Boolean)),
+            method("getURL", Vec::new(), Some(string.clone())),
+            method("getComputed", Vec::new(), Some(string)),
+        ];
+        let metadata = Metadata {
+            kind: KIND_CLASS,
+            strings: ["Bar", "name", "isEnabled", "URL", "Lcom/foo/Bar;"].map(String::from).to_vec(),
+        };
+
+        let properties = properties(&methods, &metadata);
+        assert_eq!(vec![
+            Property { name: "name".to_string(), getter: "getName".to_string(), setter: Some("setName".to_string()) },
+            Property { name: "isEnabled".to_string(), getter: "isEnabled".to_string(), setter: None },
+            Property { name: "URL".to_string(), getter: "getURL".to_string(), setter: None },
+        ], properties);
+    }
+
+    #[test]
+    fn companion() {
+        let fields = [
+            ("INSTANCE".to_string(), "com.foo.Other".to_string()),
+            ("Factory".to_string(), "com.foo.Bar$Factory".to_string()),
+        ];
+        assert_eq!(Some("Factory".to_string()), companion_field("com.foo.Bar", &fields));
+        assert_eq!(None, companion_field("com.foo.Other", &fields));
+    }
+}
@@ -0,0 +1,225 @@
+//! Locates and loads the JVM shared library at runtime, so jrsgen does not depend on the JDK it was built against
+
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use anyhow::anyhow;
+use jni::sys::{jint, JavaVM as RawJavaVM};
+use log::{debug, trace};
+use crate::JResult;
+
+/// The signature of `JNI_CreateJavaVM`
+pub type CreateJavaVm = unsafe extern "system" fn(*mut *mut RawJavaVM, *mut *mut c_void, *mut c_void) -> jint;
+
+#[cfg(target_os = "windows")]
+const LIBJVM_NAME: &str = "jvm.dll";
+#[cfg(target_os = "macos")]
+const LIBJVM_NAME: &str = "libjvm.dylib";
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const LIBJVM_NAME: &str = "libjvm.so";
+
+/// Directories JDKs are commonly installed in. Every subdirectory is considered a Java home
+const INSTALL_DIRECTORIES: [&str; 4] = [
+    "/usr/lib/jvm",
+    "/usr/java",
+    "/Library/Java/JavaVirtualMachines",
+    "C:\\Program Files\\Java",
+];
+
+/// Find the Java home to use. In order of preference, this is `java_home`, `$JAVA_HOME`,
+/// the home of the `java` on the `PATH`, or a JDK in one of the common install directories
+pub fn find_java_home(java_home: Option<&str>) -> JResult<PathBuf> {
+    if let Some(java_home) = java_home {
+        return Ok(PathBuf::from(java_home));
+    }
+
+    if let Some(java_home) = std::env::var_os("JAVA_HOME") {
+        trace!("Using JAVA_HOME");
+        return Ok(PathBuf::from(java_home));
+    }
+
+    if let Some(java_home) = java_home_from_path() {
+        trace!("Using the Java home of the java on the PATH");
+        return Ok(java_home);
+    }
+
+    for directory in INSTALL_DIRECTORIES {
+        let mut candidates = match std::fs::read_dir(directory) {
+            Ok(entries) => entries.filter_map(|x| x.ok()).map(|x| x.path()).collect::<Vec<_>>(),
+            Err(_) => continue,
+        };
+
+        // Prefer the newest version, assuming the directories are named after their version
+        candidates.sort();
+        for candidate in candidates.into_iter().rev() {
+            // macOS bundles have their home in Contents/Home
+            let home = if candidate.join("Contents/Home").is_dir() { candidate.join("Contents/Home") } else { candidate };
+            if find_libjvm(&home).is_ok() {
+                return Ok(home);
+            }
+        }
+    }
+
+    Err(anyhow!("Could not find a Java installation. Set JAVA_HOME or use --java-home"))
+}
+
+/// Ask `java` for its home, through the `java.home` property
+fn java_home_from_path() -> Option<PathBuf> {
+    let output = Command::new("java")
+        .args(["-XshowSettings:properties", "-version"])
+        .output()
+        .ok()?;
+
+    // The settings are printed to stderr
+    String::from_utf8_lossy(&output.stderr).lines()
+        .chain(String::from_utf8_lossy(&output.stdout).lines())
+        .find_map(|x| x.trim().strip_prefix("java.home = ").map(PathBuf::from))
+}
+
+/// Find the JVM library in a Java home. Java 8 keeps it in `jre/lib/<arch>/server`,
+/// later versions in `lib/server`, or `bin/server` on Windows
+pub fn find_libjvm(java_home: &Path) -> JResult<PathBuf> {
+    let candidates = ["lib/server", "bin/server", "jre/lib/server", "jre/bin/server", "lib", "jre/lib"];
+    if let Some(path) = candidates.iter()
+        .map(|x| java_home.join(x).join(LIBJVM_NAME))
+        .find(|x| x.is_file()) {
+        return Ok(path);
+    }
+
+    search(&java_home.join("jre").join("lib"))
+        .or_else(|| search(java_home))
+        .ok_or_else(|| anyhow!("Could not find {} in {}", LIBJVM_NAME, java_home.display()))
+}
+
+/// The version of the Java installation, e.g. `17.0.2`, from the `release` file of the JDK.
+/// A Java 8 home may be the `jre` directory of the JDK, so its parent is tried as well
+pub fn java_version(java_home: &Path) -> Option<String> {
+    java_home.ancestors()
+        .take(2)
+        .find_map(|x| std::fs::read_to_string(x.join("release")).ok())?
+        .lines()
+        .find_map(|x| x.strip_prefix("JAVA_VERSION="))
+        .map(|x| x.trim_matches('"').to_string())
+}
+
+/// Search a directory recursively for the JVM library
+fn search(directory: &Path) -> Option<PathBuf> {
+    let mut entries = std::fs::read_dir(directory).ok()?
+        .filter_map(|x| x.ok())
+        .map(|x| x.path())
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    if let Some(path) = entries.iter().find(|x| x.file_name().map(|x| x == LIBJVM_NAME).unwrap_or(false)) {
+        return Some(path.clone());
+    }
+
+    entries.iter()
+        .filter(|x| x.is_dir())
+        .find_map(|x| search(x))
+}
+
+/// Load the JVM library and look up `JNI_CreateJavaVM`. The library stays loaded for the rest of the process
+pub fn load(path: &Path) -> JResult<CreateJavaVm> {
+    debug!("Loading {}", path.display());
+    let symbol = unsafe { platform::load_symbol(path, "JNI_CreateJavaVM")? };
+    // SAFETY: The symbol is JNI_CreateJavaVM, which has this signature
+    Ok(unsafe { std::mem::transmute::<*mut c_void, CreateJavaVm>(symbol) })
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::ffi::{c_void, CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    use anyhow::anyhow;
+    use crate::JResult;
+
+    pub unsafe fn load_symbol(path: &Path, name: &str) -> JResult<*mut c_void> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        let handle = libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_GLOBAL);
+        if handle.is_null() {
+            return Err(anyhow!("Failed to load the JVM library: {}", last_error()));
+        }
+
+        let name = CString::new(name)?;
+        let symbol = libc::dlsym(handle, name.as_ptr());
+        if symbol.is_null() {
+            return Err(anyhow!("Failed to find {:?} in the JVM library: {}", name, last_error()));
+        }
+
+        Ok(symbol)
+    }
+
+    unsafe fn last_error() -> String {
+        let error = libc::dlerror();
+        if error.is_null() {
+            return "unknown error".to_string();
+        }
+
+        CStr::from_ptr(error).to_string_lossy().to_string()
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::{c_void, CString};
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use anyhow::anyhow;
+    use crate::JResult;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn LoadLibraryW(name: *const u16) -> *mut c_void;
+        fn GetProcAddress(module: *mut c_void, name: *const i8) -> *mut c_void;
+    }
+
+    pub unsafe fn load_symbol(path: &Path, name: &str) -> JResult<*mut c_void> {
+        let path = path.as_os_str().encode_wide().chain(Some(0)).collect::<Vec<_>>();
+        let handle = LoadLibraryW(path.as_ptr());
+        if handle.is_null() {
+            return Err(anyhow!("Failed to load the JVM library: {}", std::io::Error::last_os_error()));
+        }
+
+        let name = CString::new(name)?;
+        let symbol = GetProcAddress(handle, name.as_ptr());
+        if symbol.is_null() {
+            return Err(anyhow!("Failed to find {:?} in the JVM library: {}", name, std::io::Error::last_os_error()));
+        }
+
+        Ok(symbol)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn libjvm_layouts() {
+        let home = tempfile::tempdir().unwrap();
+        assert!(find_libjvm(home.path()).is_err());
+
+        // The Java 8 layout
+        let server = home.path().join("jre/lib/amd64/server");
+        std::fs::create_dir_all(&server).unwrap();
+        std::fs::write(server.join(LIBJVM_NAME), []).unwrap();
+        assert_eq!(server.join(LIBJVM_NAME), find_libjvm(home.path()).unwrap());
+
+        let server = home.path().join("lib/server");
+        std::fs::create_dir_all(&server).unwrap();
+        std::fs::write(server.join(LIBJVM_NAME), []).unwrap();
+        assert_eq!(server.join(LIBJVM_NAME), find_libjvm(home.path()).unwrap());
+    }
+
+    #[test]
+    fn release_version() {
+        let home = tempfile::tempdir().unwrap();
+        assert_eq!(None, java_version(home.path()));
+
+        std::fs::write(home.path().join("release"), "IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"1.8.0_392\"\n").unwrap();
+        assert_eq!(Some("1.8.0_392".to_string()), java_version(home.path()));
+        assert_eq!(Some("1.8.0_392".to_string()), java_version(&home.path().join("jre")));
+    }
+}
@@ -1,3 +1,11 @@
+pub mod bytecode;
 pub mod class_tree;
-pub mod guava;
-pub mod jvm;
\ No newline at end of file
+pub mod classfile;
+pub mod classpath;
+pub mod description;
+pub mod filter;
+pub mod jar;
+pub mod javadoc;
+pub mod jvm;
+pub mod kotlin;
+pub mod libjvm;
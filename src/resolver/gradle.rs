@@ -0,0 +1,52 @@
+//! Reads Gradle dependency lockfiles, as written by `gradle dependencies --write-locks`
+
+use anyhow::anyhow;
+use crate::JResult;
+use crate::resolver::maven::Coordinate;
+
+/// The configurations making up the classpath of the main source set
+const CONFIGURATIONS: [&str; 2] = ["compileClasspath", "runtimeClasspath"];
+
+/// Read the coordinates locked for the compile or runtime classpath from the contents of a `gradle.lockfile`.
+/// The lockfile already contains all transitive dependencies
+pub fn read_lockfile(contents: &str) -> JResult<Vec<Coordinate>> {
+    contents.lines()
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        // Lists the configurations without dependencies
+        .filter(|x| !x.starts_with("empty="))
+        .filter_map(|line| {
+            let (coordinate, configurations) = match line.split_once('=') {
+                Some(x) => x,
+                None => return Some(Err(anyhow!("Invalid lockfile entry '{line}'"))),
+            };
+
+            configurations.split(',')
+                .any(|x| CONFIGURATIONS.contains(&x.trim()))
+                .then(|| Coordinate::parse(coordinate))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lockfile() {
+        let contents = "\
+            # This is a Gradle generated file for dependency locking.\n\
+            # Manual edits can break the build and are not advised.\n\
+            com.google.guava:guava:31.0-jre=compileClasspath,runtimeClasspath\n\
+            junit:junit:4.13.2=testCompileClasspath,testRuntimeClasspath\n\
+            org.slf4j:slf4j-api:1.7.36=runtimeClasspath\n\
+            empty=annotationProcessor\n";
+
+        let coordinates = read_lockfile(contents).unwrap();
+        assert_eq!(2, coordinates.len());
+        assert_eq!("guava", coordinates[0].artifact_id);
+        assert_eq!("1.7.36", coordinates[1].version);
+
+        assert!(read_lockfile("com.foo:bar:1.0").is_err());
+    }
+}
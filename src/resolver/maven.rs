@@ -0,0 +1,642 @@
+//! Resolves Maven coordinates to jars, downloading them and their POMs into a local cache.
+//! Downloads are verified against the `.sha1` checksum the repository publishes next to every file
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use anyhow::{anyhow, Context};
+use log::{debug, trace, warn};
+use roxmltree::{Document, Node};
+use sha1::{Digest, Sha1};
+use crate::cache::cache_directory;
+use crate::config::Maven;
+use crate::JResult;
+
+/// The maximum number of `${...}` property references replaced in a value, which bounds self-referencing properties
+const MAX_INTERPOLATIONS: usize = 16;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Coordinate {
+    pub group_id: String,
+    pub artifact_id: String,
+    pub version: String,
+    pub classifier: Option<String>,
+}
+
+impl Coordinate {
+    /// Parse a coordinate in the form `group:artifact:version` or `group:artifact:version:classifier`
+    pub fn parse(coordinate: &str) -> JResult<Self> {
+        let parts = coordinate.split(':').collect::<Vec<_>>();
+        if !(3..=4).contains(&parts.len()) || parts.iter().any(|x| x.is_empty()) {
+            return Err(anyhow!("Invalid Maven coordinate '{coordinate}', expected group:artifact:version"));
+        }
+
+        Ok(Self {
+            group_id: parts[0].to_string(),
+            artifact_id: parts[1].to_string(),
+            version: parts[2].to_string(),
+            classifier: parts.get(3).map(|x| x.to_string()),
+        })
+    }
+
+    /// The path of the artifact with the given extension, relative to the root of a repository
+    fn path(&self, extension: &str) -> String {
+        let classifier = self.classifier.as_ref()
+            .map(|x| format!("-{x}"))
+            .unwrap_or_default();
+
+        format!("{}/{}/{}/{}-{}{}.{}",
+            self.group_id.replace('.', "/"),
+            self.artifact_id,
+            self.version,
+            self.artifact_id,
+            self.version,
+            classifier,
+            extension
+        )
+    }
+
+    /// The coordinate of the POM describing this artifact
+    fn pom(&self) -> Self {
+        Self {
+            classifier: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// A coordinate queued for resolution, with its dependency type and the exclusions inherited from its dependents
+type Queued = (Coordinate, String, Vec<(String, String)>);
+
+/// A dependency as declared in a POM, after interpolating properties
+/// and applying the dependency management
+#[derive(Debug, Clone)]
+struct Dependency {
+    group_id: String,
+    artifact_id: String,
+    version: Option<String>,
+    classifier: Option<String>,
+    dependency_type: String,
+    scope: String,
+    optional: bool,
+    /// `groupId:artifactId` pairs, either of which may be `*`
+    exclusions: Vec<(String, String)>,
+}
+
+impl Dependency {
+    fn key(&self) -> (String, String) {
+        (self.group_id.clone(), self.artifact_id.clone())
+    }
+}
+
+/// A POM with its parents and imported BOMs merged in
+#[derive(Debug, Default)]
+struct Pom {
+    coordinate: Coordinate,
+    packaging: String,
+    properties: HashMap<String, String>,
+    /// The managed dependencies, by `(groupId, artifactId)`
+    managed: HashMap<(String, String), Dependency>,
+    dependencies: Vec<Dependency>,
+}
+
+pub struct Resolver<'a> {
+    config: &'a Maven,
+    cache: PathBuf,
+    poms: HashMap<Coordinate, Rc<Pom>>,
+}
+
+impl<'a> Resolver<'a> {
+    /// Create a resolver downloading into the configured cache directory,
+    /// or `$XDG_CACHE_HOME/jrsgen/maven` if none is configured
+    pub fn new(config: &'a Maven) -> JResult<Self> {
+        let cache = match &config.cache {
+            Some(cache) => PathBuf::from(cache),
+            None => cache_directory()?.join("maven"),
+        };
+
+        Ok(Self {
+            config,
+            cache,
+            poms: HashMap::new(),
+        })
+    }
+
+    /// Resolve the coordinates to the paths of their jars. If `transitive` is set, the jars of their
+    /// compile and runtime dependencies are included as well. Like Maven, the nearest version of a dependency wins
+    pub fn resolve(&mut self, coordinates: &[Coordinate], transitive: bool) -> JResult<Vec<PathBuf>> {
+        let mut seen = HashSet::new();
+        let queue = coordinates.iter()
+            .filter(|x| seen.insert((x.group_id.clone(), x.artifact_id.clone())))
+            .map(|x| (x.clone(), "jar".to_string(), Vec::new()))
+            .collect::<VecDeque<_>>();
+
+        self.resolve_queue(queue, seen, transitive)
+    }
+
+    /// Resolve the dependencies of a local Maven project, described by its `pom.xml`, to the paths of their jars.
+    /// Transitive dependencies are always included. Parent POMs are read from disk if they can be found through their `relativePath`
+    pub fn resolve_project(&mut self, path: &Path) -> JResult<Vec<PathBuf>> {
+        let pom = self.project_pom(path)?;
+
+        let mut seen = HashSet::new();
+        let queue = pom.dependencies.iter()
+            // Provided dependencies are not transitive, but they are needed to load the project's own classes
+            .filter(|x| matches!(x.scope.as_str(), "compile" | "runtime" | "provided"))
+            .filter(|x| seen.insert(x.key()))
+            .map(|x| {
+                let version = x.version.as_deref()
+                    .ok_or_else(|| anyhow!("No version for dependency {}:{}", x.group_id, x.artifact_id))?;
+
+                let coordinate = Coordinate {
+                    group_id: x.group_id.clone(),
+                    artifact_id: x.artifact_id.clone(),
+                    version: select_version(version),
+                    classifier: x.classifier.clone(),
+                };
+
+                Ok((coordinate, x.dependency_type.clone(), x.exclusions.clone()))
+            })
+            .collect::<JResult<VecDeque<_>>>()?;
+
+        self.resolve_queue(queue, seen, true)
+    }
+
+    /// Resolve the queued `(coordinate, type, exclusions)` entries breadth-first.
+    /// Dependencies whose `(groupId, artifactId)` is in `seen` are not resolved again
+    fn resolve_queue(&mut self, mut queue: VecDeque<Queued>, mut seen: HashSet<(String, String)>, transitive: bool) -> JResult<Vec<PathBuf>> {
+        let mut jars = Vec::new();
+        while let Some((coordinate, dependency_type, exclusions)) = queue.pop_front() {
+            debug!("Resolving {}:{}:{}", coordinate.group_id, coordinate.artifact_id, coordinate.version);
+            let pom = self.effective_pom(&coordinate.pom())?;
+
+            let extension = match (dependency_type.as_str(), pom.packaging.as_str()) {
+                ("pom", _) | (_, "pom") => None,
+                ("jar", _) => Some("jar"),
+                (other, _) => {
+                    warn!("Not adding {}:{} of unsupported type {} to the classpath", coordinate.group_id, coordinate.artifact_id, other);
+                    None
+                }
+            };
+
+            if let Some(extension) = extension {
+                jars.push(self.fetch(&coordinate.path(extension))?);
+            }
+
+            if !transitive {
+                continue;
+            }
+
+            for dependency in &pom.dependencies {
+                if !matches!(dependency.scope.as_str(), "compile" | "runtime") || dependency.optional {
+                    continue;
+                }
+
+                if is_excluded(&exclusions, dependency) {
+                    trace!("Excluding {}:{}", dependency.group_id, dependency.artifact_id);
+                    continue;
+                }
+
+                if !seen.insert(dependency.key()) {
+                    continue;
+                }
+
+                let version = dependency.version.as_deref()
+                    .ok_or_else(|| anyhow!("No version for dependency {}:{} of {}:{}", dependency.group_id, dependency.artifact_id, coordinate.group_id, coordinate.artifact_id))?;
+
+                let mut exclusions = exclusions.clone();
+                exclusions.extend(dependency.exclusions.iter().cloned());
+
+                let dependency_coordinate = Coordinate {
+                    group_id: dependency.group_id.clone(),
+                    artifact_id: dependency.artifact_id.clone(),
+                    version: select_version(version),
+                    classifier: dependency.classifier.clone(),
+                };
+
+                queue.push_back((dependency_coordinate, dependency.dependency_type.clone(), exclusions));
+            }
+        }
+
+        Ok(jars)
+    }
+
+    /// Get the path of a file in the local cache, downloading it from the first repository that has it.
+    /// Files in the local Maven repository, `~/.m2/repository`, are used as-is
+    fn fetch(&self, path: &str) -> JResult<PathBuf> {
+        let cached = self.cache.join(path);
+        if cached.exists() {
+            return Ok(cached);
+        }
+
+        if let Some(home) = std::env::var_os("HOME") {
+            let local = Path::new(&home).join(".m2").join("repository").join(path);
+            if local.exists() {
+                trace!("Using {:?} from the local Maven repository", local);
+                return Ok(local);
+            }
+        }
+
+        let partial = partial_path(&cached);
+        for repository in &self.config.repositories {
+            let url = format!("{}/{}", repository.trim_end_matches('/'), path);
+            debug!("Downloading {}", url);
+
+            let bytes = match download(&url) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    trace!("Could not download {}: {}", url, e);
+                    continue;
+                }
+            };
+            verify_checksum(&url, &bytes)?;
+
+            if let Some(parent) = partial.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&partial, &bytes)?;
+            fs::rename(&partial, &cached)?;
+            return Ok(cached);
+        }
+
+        Err(anyhow!("Could not find {} in any of the repositories {:?}", path, self.config.repositories))
+    }
+
+    /// Read the POM of `coordinate`, merging in its parents and imported BOMs
+    fn effective_pom(&mut self, coordinate: &Coordinate) -> JResult<Rc<Pom>> {
+        if let Some(pom) = self.poms.get(coordinate) {
+            return Ok(pom.clone());
+        }
+
+        let path = self.fetch(&coordinate.path("pom"))?;
+        let contents = fs::read_to_string(&path)?;
+        let document = Document::parse(&contents).with_context(|| format!("Parsing {}", path.display()))?;
+
+        let pom = Rc::new(self.build_pom(document.root_element(), Some(coordinate))?);
+        self.poms.insert(coordinate.clone(), pom.clone());
+        Ok(pom)
+    }
+
+    /// Read the POM of a local project. Its parent is read from disk as well,
+    /// if it can be found through `relativePath` and has the expected coordinate
+    fn project_pom(&mut self, path: &Path) -> JResult<Pom> {
+        let contents = fs::read_to_string(path).with_context(|| format!("Reading {}", path.display()))?;
+        let document = Document::parse(&contents).with_context(|| format!("Parsing {}", path.display()))?;
+        let root = document.root_element();
+
+        if let Some(parent) = root.child("parent") {
+            let coordinate = parent_coordinate(parent);
+            let relative_path = parent.child_text("relativePath").unwrap_or("../pom.xml");
+
+            let mut parent_path = path.parent().unwrap_or_else(|| Path::new(".")).join(relative_path);
+            if parent_path.is_dir() {
+                parent_path = parent_path.join("pom.xml");
+            }
+
+            if !relative_path.is_empty() && parent_path.is_file() && !self.poms.contains_key(&coordinate) {
+                let parent_pom = self.project_pom(&parent_path)?;
+                if parent_pom.coordinate == coordinate {
+                    trace!("Using parent POM {}", parent_path.display());
+                    self.poms.insert(coordinate, Rc::new(parent_pom));
+                }
+            }
+        }
+
+        self.build_pom(root, None)
+    }
+
+    /// Build the effective POM from the root element of a POM.
+    /// `coordinate` is the coordinate the POM was fetched by, if any
+    fn build_pom(&mut self, root: Node, coordinate: Option<&Coordinate>) -> JResult<Pom> {
+        let mut pom = Pom::default();
+
+        let parent = match root.child("parent") {
+            Some(parent) => {
+                let parent = parent_coordinate(parent);
+                let parent_pom = self.effective_pom(&parent)?;
+                pom.properties = parent_pom.properties.clone();
+                pom.managed = parent_pom.managed.clone();
+                pom.dependencies = parent_pom.dependencies.clone();
+                Some(parent)
+            },
+            None => None,
+        };
+
+        let group_id = root.child_text("groupId")
+            .or(parent.as_ref().map(|x| x.group_id.as_str()))
+            .or(coordinate.map(|x| x.group_id.as_str()))
+            .unwrap_or_default()
+            .to_string();
+        let version = root.child_text("version")
+            .or(parent.as_ref().map(|x| x.version.as_str()))
+            .or(coordinate.map(|x| x.version.as_str()))
+            .unwrap_or_default()
+            .to_string();
+        let artifact_id = root.child_text("artifactId").unwrap_or_default().to_string();
+
+        if let Some(properties) = root.child("properties") {
+            for property in properties.children().filter(Node::is_element) {
+                pom.properties.insert(property.tag_name().name().to_string(), property.text().unwrap_or_default().trim().to_string());
+            }
+        }
+
+        for (name, value) in [("groupId", &group_id), ("artifactId", &artifact_id), ("version", &version)] {
+            pom.properties.insert(format!("project.{name}"), value.clone());
+            pom.properties.insert(format!("pom.{name}"), value.clone());
+        }
+
+        if let Some(parent) = &parent {
+            pom.properties.insert("project.parent.groupId".to_string(), parent.group_id.clone());
+            pom.properties.insert("project.parent.version".to_string(), parent.version.clone());
+        }
+
+        pom.packaging = root.child_text("packaging").unwrap_or("jar").to_string();
+        pom.coordinate = Coordinate {
+            group_id: group_id.clone(),
+            artifact_id: artifact_id.clone(),
+            version: version.clone(),
+            classifier: None,
+        };
+
+        let managed = root.child("dependencyManagement")
+            .and_then(|x| x.child("dependencies"))
+            .map(|x| x.children_named("dependency").collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        for dependency in managed {
+            let dependency = parse_dependency(dependency, &pom.properties);
+            if dependency.scope == "import" && dependency.dependency_type == "pom" {
+                let version = dependency.version.as_deref()
+                    .ok_or_else(|| anyhow!("No version for imported BOM {}:{}", dependency.group_id, dependency.artifact_id))?;
+
+                let bom = self.effective_pom(&Coordinate {
+                    group_id: dependency.group_id.clone(),
+                    artifact_id: dependency.artifact_id.clone(),
+                    version: select_version(version),
+                    classifier: None,
+                })?;
+
+                // Dependencies managed by the POM itself take precedence over imported ones
+                for (key, managed) in &bom.managed {
+                    pom.managed.entry(key.clone()).or_insert_with(|| managed.clone());
+                }
+            } else {
+                pom.managed.insert(dependency.key(), dependency);
+            }
+        }
+
+        let dependencies = root.child("dependencies")
+            .map(|x| x.children_named("dependency").collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        for dependency in dependencies {
+            let mut dependency = parse_dependency(dependency, &pom.properties);
+            if let Some(managed) = pom.managed.get(&dependency.key()) {
+                if dependency.version.is_none() {
+                    dependency.version = managed.version.clone();
+                }
+
+                if dependency.scope.is_empty() {
+                    dependency.scope = managed.scope.clone();
+                }
+
+                dependency.exclusions.extend(managed.exclusions.iter().cloned());
+            }
+
+            if dependency.scope.is_empty() {
+                dependency.scope = "compile".to_string();
+            }
+
+            pom.dependencies.retain(|x| x.key() != dependency.key());
+            pom.dependencies.push(dependency);
+        }
+
+        Ok(pom)
+    }
+}
+
+/// The coordinate of the POM referenced by a `<parent>` element
+fn parent_coordinate(parent: Node) -> Coordinate {
+    Coordinate {
+        group_id: parent.child_text("groupId").unwrap_or_default().to_string(),
+        artifact_id: parent.child_text("artifactId").unwrap_or_default().to_string(),
+        version: parent.child_text("version").unwrap_or_default().to_string(),
+        classifier: None,
+    }
+}
+
+/// Parse a `<dependency>` element. The scope is left empty if it is not declared,
+/// so it can be taken from the dependency management
+fn parse_dependency(element: Node, properties: &HashMap<String, String>) -> Dependency {
+    let text = |name: &str| element.child_text(name).map(|x| interpolate(x, properties));
+
+    let exclusions = element.child("exclusions")
+        .map(|x| x.children_named("exclusion")
+            .map(|x| (
+                x.child_text("groupId").unwrap_or("*").to_string(),
+                x.child_text("artifactId").unwrap_or("*").to_string(),
+            ))
+            .collect())
+        .unwrap_or_default();
+
+    Dependency {
+        group_id: text("groupId").unwrap_or_default(),
+        artifact_id: text("artifactId").unwrap_or_default(),
+        version: text("version"),
+        classifier: text("classifier"),
+        dependency_type: text("type").unwrap_or_else(|| "jar".to_string()),
+        scope: text("scope").unwrap_or_default(),
+        optional: text("optional").as_deref() == Some("true"),
+        exclusions,
+    }
+}
+
+/// Lookups of the child elements of a POM element by their name
+trait PomElement<'a, 'input: 'a> {
+    /// The first child element with the given name
+    fn child(&self, name: &str) -> Option<Node<'a, 'input>>;
+
+    /// All child elements with the given name
+    fn children_named(&self, name: &'a str) -> impl Iterator<Item = Node<'a, 'input>> + 'a;
+
+    /// The trimmed text of the first child element with the given name
+    fn child_text(&self, name: &str) -> Option<&'a str>;
+}
+
+impl<'a, 'input: 'a> PomElement<'a, 'input> for Node<'a, 'input> {
+    fn child(&self, name: &str) -> Option<Node<'a, 'input>> {
+        self.children().find(|x| x.has_tag_name(name))
+    }
+
+    fn children_named(&self, name: &'a str) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+        self.children().filter(move |x| x.has_tag_name(name))
+    }
+
+    fn child_text(&self, name: &str) -> Option<&'a str> {
+        self.child(name).map(|x| x.text().unwrap_or_default().trim())
+    }
+}
+
+/// The path a file is downloaded to before it is moved to `cached`. `.part` is appended to the whole file name,
+/// as e.g. `kernel-7.2.1.pom` and `kernel-7.2.1.jar` share their stem
+fn partial_path(cached: &Path) -> PathBuf {
+    let mut partial = cached.as_os_str().to_owned();
+    partial.push(".part");
+    PathBuf::from(partial)
+}
+
+fn download(url: &str) -> JResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ureq::get(url).call()?.into_reader().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Compare the SHA-1 of a file downloaded from `url` to the checksum published as `<url>.sha1`.
+/// Files without a published checksum are used as-is
+fn verify_checksum(url: &str, bytes: &[u8]) -> JResult<()> {
+    let checksum_url = format!("{url}.sha1");
+    let published = match download(&checksum_url) {
+        Ok(published) => String::from_utf8(published).with_context(|| format!("Reading {checksum_url}"))?,
+        Err(e) => {
+            warn!("Not verifying {}, its checksum could not be downloaded: {}", url, e);
+            return Ok(());
+        }
+    };
+
+    // Some repositories append the file name to the checksum
+    let expected = published.split_whitespace().next().unwrap_or_default().to_ascii_lowercase();
+    let actual = format!("{:x}", Sha1::digest(bytes));
+    if expected != actual {
+        return Err(anyhow!("The SHA-1 of {url} is {actual}, but {expected} is published"));
+    }
+
+    Ok(())
+}
+
+/// Replace `${name}` references with the value of the property. Unknown properties are left as-is
+fn interpolate(value: &str, properties: &HashMap<String, String>) -> String {
+    let mut value = value.to_string();
+    let mut replacements = 0;
+    // References before `from` are unknown, and skipped
+    let mut from = 0;
+    while let Some(start) = value[from..].find("${").map(|x| from + x) {
+        let end = match value[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        let name = &value[start + 2..end];
+        match properties.get(name) {
+            // The replacement is scanned again, as it may refer to other properties
+            Some(replacement) if replacements < MAX_INTERPOLATIONS => {
+                value.replace_range(start..=end, &replacement.clone());
+                replacements += 1;
+            },
+            _ => from = end + 1,
+        }
+    }
+
+    value
+}
+
+/// Pick a concrete version for a version range like `[1.0,2.0)`, by taking its lower bound,
+/// or its upper bound if it has none. Plain versions are returned as-is
+fn select_version(version: &str) -> String {
+    if !version.starts_with(['[', '(']) {
+        return version.to_string();
+    }
+
+    let range = version.trim_matches(|c| matches!(c, '[' | ']' | '(' | ')'));
+    let bound = range.split(',')
+        .map(str::trim)
+        .find(|x| !x.is_empty())
+        .unwrap_or(range);
+
+    warn!("Using version {} for version range {}", bound, version);
+    bound.to_string()
+}
+
+/// Whether the dependency is excluded by one of the `groupId:artifactId` patterns
+fn is_excluded(exclusions: &[(String, String)], dependency: &Dependency) -> bool {
+    exclusions.iter().any(|(group_id, artifact_id)| {
+        (group_id == "*" || group_id == &dependency.group_id) && (artifact_id == "*" || artifact_id == &dependency.artifact_id)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn coordinate() {
+        let coordinate = Coordinate::parse("com.itextpdf:kernel:7.2.1").unwrap();
+        assert_eq!("com/itextpdf/kernel/7.2.1/kernel-7.2.1.jar", coordinate.path("jar"));
+
+        let coordinate = Coordinate::parse("com.foo:bar:1.0:sources").unwrap();
+        assert_eq!("com/foo/bar/1.0/bar-1.0-sources.jar", coordinate.path("jar"));
+        assert_eq!("com/foo/bar/1.0/bar-1.0.pom", coordinate.pom().path("pom"));
+
+        assert!(Coordinate::parse("com.foo:bar").is_err());
+        assert!(Coordinate::parse("com.foo::1.0").is_err());
+    }
+
+    #[test]
+    fn interpolation() {
+        let properties = HashMap::from([
+            ("project.version".to_string(), "1.2".to_string()),
+            ("slf4j.version".to_string(), "${project.version}.3".to_string()),
+        ]);
+
+        assert_eq!("1.2.3", interpolate("${slf4j.version}", &properties));
+        assert_eq!("v1.2-${unknown}", interpolate("v${project.version}-${unknown}", &properties));
+        assert_eq!("${unknown}-1.2.3", interpolate("${unknown}-${slf4j.version}", &properties));
+    }
+
+    #[test]
+    fn dependency() {
+        let input = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <project xmlns="http://maven.apache.org/POM/4.0.0">
+                <dependencies>
+                    <!-- <dependency><artifactId>commented</artifactId></dependency> -->
+                    <dependency>
+                        <groupId>com.foo</groupId>
+                        <artifactId><![CDATA[bar]]></artifactId>
+                        <version>${bar.version}</version>
+                        <optional>true</optional>
+                        <exclusions>
+                            <exclusion><groupId>com.baz</groupId></exclusion>
+                        </exclusions>
+                    </dependency>
+                </dependencies>
+            </project>
+        "#;
+
+        let document = Document::parse(input).unwrap();
+        let dependencies = document.root_element().child("dependencies").unwrap().children_named("dependency").collect::<Vec<_>>();
+        assert_eq!(1, dependencies.len());
+
+        let properties = HashMap::from([("bar.version".to_string(), "1.0".to_string())]);
+        let dependency = parse_dependency(dependencies[0], &properties);
+        assert_eq!(("com.foo".to_string(), "bar".to_string()), dependency.key());
+        assert_eq!(Some("1.0"), dependency.version.as_deref());
+        assert_eq!("jar", dependency.dependency_type);
+        assert!(dependency.optional);
+        assert_eq!(vec![("com.baz".to_string(), "*".to_string())], dependency.exclusions);
+    }
+
+    #[test]
+    fn partial_download() {
+        assert_eq!(Path::new("cache/kernel-7.2.1.pom.part"), partial_path(Path::new("cache/kernel-7.2.1.pom")));
+        assert_eq!(Path::new("cache/kernel-7.2.1.jar.part"), partial_path(Path::new("cache/kernel-7.2.1.jar")));
+    }
+
+    #[test]
+    fn version_range() {
+        assert_eq!("1.0", select_version("1.0"));
+        assert_eq!("1.0", select_version("[1.0,2.0)"));
+        assert_eq!("1.5", select_version("[1.5]"));
+        assert_eq!("2.0", select_version("(,2.0]"));
+    }
+}
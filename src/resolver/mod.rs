@@ -0,0 +1,40 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::anyhow;
+use log::debug;
+use crate::config::Maven;
+use crate::JResult;
+use crate::resolver::maven::Resolver;
+
+pub mod gradle;
+pub mod maven;
+
+/// Derive the classpath of a local Java project from its `pom.xml` or `gradle.lockfile`,
+/// or from a directory containing either. The project's own compiled classes are included if they exist
+pub fn project_classpath(path: &Path, config: &Maven) -> JResult<Vec<PathBuf>> {
+    let path = if path.is_dir() {
+        ["pom.xml", "gradle.lockfile"].iter()
+            .map(|x| path.join(x))
+            .find(|x| x.is_file())
+            .ok_or_else(|| anyhow!("No pom.xml or gradle.lockfile in {}", path.display()))?
+    } else {
+        path.to_path_buf()
+    };
+
+    let directory = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut resolver = Resolver::new(config)?;
+    let (mut classpath, classes) = if path.extension().map(|x| x == "xml").unwrap_or(false) {
+        debug!("Reading Maven project {}", path.display());
+        (resolver.resolve_project(&path)?, directory.join("target").join("classes"))
+    } else {
+        debug!("Reading Gradle lockfile {}", path.display());
+        let coordinates = gradle::read_lockfile(&fs::read_to_string(&path)?)?;
+        (resolver.resolve(&coordinates, false)?, directory.join("build").join("classes").join("java").join("main"))
+    };
+
+    if classes.is_dir() {
+        classpath.insert(0, classes);
+    }
+
+    Ok(classpath)
+}
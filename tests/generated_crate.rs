@@ -0,0 +1,69 @@
+//! Generate bindings for a class of `tests/itext7.jar` and check that the generated crate builds.
+//! The dependencies of the generated crate are fetched by Cargo, unless they are cached already
+
+use std::path::Path;
+use std::process::Command;
+
+const JAR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/itext7.jar");
+
+/// Generate the bindings of `entry_point` with the bytecode backend, which does not need a JVM, and `cargo check` them.
+/// Returns the directory they were generated in, the crate is in `bindings`
+fn check_generated(entry_point: &str, jni_version: &str) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("bindings");
+
+    // jrsgen reads, and creates, `config.toml` in its working directory
+    let status = Command::new(env!("CARGO_BIN_EXE_jrsgen"))
+        .current_dir(dir.path())
+        .args(["--backend", "bytecode", "--no-cache", "--jni-version", jni_version])
+        .args(["--classpath", JAR, "--root", "com.itextpdf.", "--entry-point", entry_point])
+        .arg("--output")
+        .arg(&output)
+        .status()
+        .unwrap();
+    assert!(status.success(), "jrsgen failed for {entry_point}");
+
+    check(&output, &dir.path().join("target"));
+    dir
+}
+
+fn check(manifest_dir: &Path, target_dir: &Path) {
+    let output = Command::new(env!("CARGO"))
+        .current_dir(manifest_dir)
+        .args(["check", "--quiet"])
+        .env("CARGO_TARGET_DIR", target_dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "The generated crate does not build:\n{}", String::from_utf8_lossy(&output.stderr));
+}
+
+// `Subpath` refers to `Point`, the interface `IShape`, and classes outside of the tree like `java.lang.Object`
+#[test]
+fn subpath_jni_0_19() {
+    check_generated("com.itextpdf.kernel.geom.Subpath", "0.19");
+}
+
+#[test]
+fn subpath_jni_0_21() {
+    check_generated("com.itextpdf.kernel.geom.Subpath", "0.21");
+}
+
+// `IStructureNode` reaches enums like `PdfReader.StrictnessLevel`, which are passed by value and converted through `crate::JavaConvert`
+#[test]
+fn structure_node_jni_0_19() {
+    check_generated("com.itextpdf.kernel.pdf.tagging.IStructureNode", "0.19");
+}
+
+#[test]
+fn structure_node_jni_0_21() {
+    check_generated("com.itextpdf.kernel.pdf.tagging.IStructureNode", "0.21");
+}
+
+// `PdfWriter` is an `AutoCloseable` through its superclass `java.io.OutputStream`, which is outside of the tree
+#[test]
+fn pdf_writer_jni_0_19() {
+    let dir = check_generated("com.itextpdf.kernel.pdf.PdfWriter", "0.19");
+    let writer = std::fs::read_to_string(dir.path().join("bindings/src/bindings/com/itextpdf/kernel/pdf/PdfWriter.rs")).unwrap();
+    assert!(writer.contains("pub fn close(self)"));
+    assert!(writer.contains("pub fn close_on_drop(self)"));
+}